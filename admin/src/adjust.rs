@@ -0,0 +1,23 @@
+use ore_pool_types::AdjustBalancePayload;
+
+use crate::{client::Client, error::Error};
+
+/// credits (positive amount) or debits (negative amount) the balance of a member,
+/// recorded with the reason in the ledger the member sees.
+pub async fn adjust(client: &Client, payload: AdjustBalancePayload) -> Result<(), Error> {
+    let adjustment = client.adjust_balance(&payload).await?;
+    let kind = if adjustment.amount < 0 {
+        "debited"
+    } else {
+        "credited"
+    };
+    println!(
+        "{} {} grains to {}: {}",
+        kind,
+        adjustment.amount.unsigned_abs(),
+        adjustment.authority,
+        adjustment.reason
+    );
+    println!("adjustment id: {}", adjustment.id);
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use ore_pool_types::IssueApiKeyPayload;
+
+use crate::{client::Client, error::Error};
+
+/// issues an api key for read access to a third-party consumer, and prints it.
+/// the key is only printed once, as the pool server only stores its hash.
+pub async fn issue_api_key(client: &Client, payload: IssueApiKeyPayload) -> Result<(), Error> {
+    let api_key = client.issue_api_key(&payload).await?;
+    println!("id: {}", api_key.id);
+    println!("name: {}", api_key.name);
+    println!("rate limit: {} requests per minute", api_key.rate_limit);
+    if let Some(key) = api_key.key {
+        println!("key: {}", key);
+    }
+    Ok(())
+}
+
+/// prints the issued api keys.
+pub async fn api_keys(client: &Client) -> Result<(), Error> {
+    let api_keys = client.api_keys().await?;
+    for api_key in api_keys {
+        let status = match api_key.revoked_at {
+            Some(revoked_at) => format!("revoked at {}", revoked_at),
+            None => "active".to_string(),
+        };
+        println!(
+            "{} {} {}/min issued at {} ({})",
+            api_key.id, api_key.name, api_key.rate_limit, api_key.created_at, status,
+        );
+    }
+    Ok(())
+}
+
+/// revokes an api key.
+pub async fn revoke_api_key(client: &Client, id: i64) -> Result<(), Error> {
+    client.revoke_api_key(id).await?;
+    println!("revoked api key {}", id);
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use ore_pool_types::AuditLogQuery;
+
+use crate::{client::Client, error::Error};
+
+/// prints the recorded admin actions, most recent first.
+/// page back with the id of the oldest entry printed.
+pub async fn audit_log(client: &Client, query: AuditLogQuery) -> Result<(), Error> {
+    let entries = client.audit_log(&query).await?;
+    for entry in entries {
+        println!(
+            "{} {} {} {} ({})",
+            entry.id,
+            entry.created_at,
+            entry.actor,
+            entry.action,
+            entry.remote_addr.as_deref().unwrap_or("unknown"),
+        );
+        if let Some(old_value) = entry.old_value {
+            println!("  old: {}", old_value);
+        }
+        if let Some(new_value) = entry.new_value {
+            println!("  new: {}", new_value);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use crate::{client::Client, error::Error};
+
+/// writes a snapshot of the pool db and the round in flight to the backup path,
+/// to restore on a fresh host with the restore command.
+pub async fn backup(client: &Client, backup_path: String) -> Result<(), Error> {
+    let backup = client.backup().await?;
+    let file = std::fs::File::create(backup_path.as_str())?;
+    serde_json::to_writer(std::io::BufWriter::new(file), &backup)?;
+    println!("members: {}", backup.members.len());
+    println!("stakers: {}", backup.stakers.len());
+    println!("contributions: {}", backup.round.contributions.len());
+    println!("wrote backup to {}", backup_path);
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use ore_pool_types::{
+    AdjustBalancePayload, AdminConfig, ApiKey, AuditEntry, AuditLogQuery, Backup,
+    BalanceAdjustment, DifficultyStats, DistributionPreview, DistributionPreviewQuery,
+    IssueApiKeyPayload, Member, ReconcileReport, RegisterPayload, RestoreReport, RpcUsageReport,
+    ShareVerification, TreasuryReport, UpdateConfigPayload,
+};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error;
+
+/// client for the http api of the pool server,
+/// including the admin api guarded by the admin token.
+pub struct Client {
+    http_client: reqwest::Client,
+    /// the url of the pool server
+    pool_url: String,
+    /// the bearer token for the admin api
+    admin_token: Option<String>,
+    /// the name recorded in the audit log for admin actions
+    actor: String,
+}
+
+impl Client {
+    pub fn new(pool_url: String, admin_token: Option<String>, actor: String) -> Self {
+        let pool_url = pool_url.trim_end_matches('/').to_string();
+        Self {
+            http_client: reqwest::Client::new(),
+            pool_url,
+            admin_token,
+            actor,
+        }
+    }
+
+    /// registers the on-chain member account with the pool server db
+    pub async fn register(&self, authority: Pubkey) -> Result<Member, Error> {
+        let url = format!("{}/admin/register", self.pool_url);
+        let req = self.admin(self.http_client.post(url).json(&RegisterPayload {
+            authority,
+            solution: None,
+        }))?;
+        Self::send(req).await
+    }
+
+    /// forces the pool to submit the best solution of the current round
+    pub async fn submit(&self) -> Result<(), Error> {
+        let url = format!("{}/admin/submit", self.pool_url);
+        let req = self.admin(self.http_client.post(url))?;
+        let resp = req.send().await?;
+        Self::check(resp).await?;
+        Ok(())
+    }
+
+    pub async fn reconcile(&self) -> Result<ReconcileReport, Error> {
+        let url = format!("{}/admin/reconcile", self.pool_url);
+        let req = self.admin(self.http_client.post(url))?;
+        Self::send(req).await
+    }
+
+    pub async fn config(&self) -> Result<AdminConfig, Error> {
+        let url = format!("{}/admin/config", self.pool_url);
+        let req = self.admin(self.http_client.get(url))?;
+        Self::send(req).await
+    }
+
+    pub async fn update_config(&self, payload: &UpdateConfigPayload) -> Result<AdminConfig, Error> {
+        let url = format!("{}/admin/config", self.pool_url);
+        let req = self.admin(self.http_client.post(url).json(payload))?;
+        Self::send(req).await
+    }
+
+    pub async fn audit_log(&self, query: &AuditLogQuery) -> Result<Vec<AuditEntry>, Error> {
+        let url = format!("{}/admin/audit-log", self.pool_url);
+        let req = self.admin(self.http_client.get(url).query(query))?;
+        Self::send(req).await
+    }
+
+    pub async fn distribution_preview(
+        &self,
+        query: &DistributionPreviewQuery,
+    ) -> Result<DistributionPreview, Error> {
+        let url = format!("{}/admin/distribution/preview", self.pool_url);
+        let req = self.admin(self.http_client.get(url).query(query))?;
+        Self::send(req).await
+    }
+
+    pub async fn treasury(&self) -> Result<TreasuryReport, Error> {
+        let url = format!("{}/admin/treasury", self.pool_url);
+        let req = self.admin(self.http_client.get(url))?;
+        Self::send(req).await
+    }
+
+    pub async fn rpc_usage(&self) -> Result<RpcUsageReport, Error> {
+        let url = format!("{}/admin/rpc-usage", self.pool_url);
+        let req = self.admin(self.http_client.get(url))?;
+        Self::send(req).await
+    }
+
+    /// public, but printed for the operator to tune the min difficulty
+    pub async fn difficulty_stats(&self) -> Result<DifficultyStats, Error> {
+        let url = format!("{}/stats/difficulty", self.pool_url);
+        Self::send(self.http_client.get(url)).await
+    }
+
+    pub async fn backup(&self) -> Result<Backup, Error> {
+        let url = format!("{}/admin/backup", self.pool_url);
+        let req = self.admin(self.http_client.get(url))?;
+        Self::send(req).await
+    }
+
+    pub async fn restore(&self, backup: &Backup) -> Result<RestoreReport, Error> {
+        let url = format!("{}/admin/restore", self.pool_url);
+        let req = self.admin(self.http_client.post(url).json(backup))?;
+        Self::send(req).await
+    }
+
+    pub async fn adjust_balance(
+        &self,
+        payload: &AdjustBalancePayload,
+    ) -> Result<BalanceAdjustment, Error> {
+        let url = format!("{}/admin/adjustments", self.pool_url);
+        let req = self.admin(self.http_client.post(url).json(payload))?;
+        Self::send(req).await
+    }
+
+    pub async fn issue_api_key(&self, payload: &IssueApiKeyPayload) -> Result<ApiKey, Error> {
+        let url = format!("{}/admin/api-keys", self.pool_url);
+        let req = self.admin(self.http_client.post(url).json(payload))?;
+        Self::send(req).await
+    }
+
+    pub async fn api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        let url = format!("{}/admin/api-keys", self.pool_url);
+        let req = self.admin(self.http_client.get(url))?;
+        Self::send(req).await
+    }
+
+    pub async fn revoke_api_key(&self, id: i64) -> Result<(), Error> {
+        let url = format!("{}/admin/api-keys/{}/revoke", self.pool_url, id);
+        let req = self.admin(self.http_client.post(url))?;
+        let resp = req.send().await?;
+        Self::check(resp).await?;
+        Ok(())
+    }
+
+    /// the archived shares of the member in the round, from the public verify api
+    pub async fn verify(&self, round: &str, member: &Pubkey) -> Result<ShareVerification, Error> {
+        let url = format!("{}/verify/{}/{}", self.pool_url, round, member);
+        Self::send(self.http_client.get(url)).await
+    }
+
+    fn admin(&self, req: RequestBuilder) -> Result<RequestBuilder, Error> {
+        let admin_token = self.admin_token.as_ref().ok_or(Error::MissingAdminToken)?;
+        Ok(req
+            .bearer_auth(admin_token)
+            .header("x-admin-actor", self.actor.as_str()))
+    }
+
+    async fn send<T: DeserializeOwned>(req: RequestBuilder) -> Result<T, Error> {
+        let resp = req.send().await?;
+        let resp = Self::check(resp).await?;
+        let body = resp.json().await?;
+        Ok(body)
+    }
+
+    /// surfaces the status and body of unsuccessful responses
+    async fn check(resp: reqwest::Response) -> Result<reqwest::Response, Error> {
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let body = resp.text().await.unwrap_or_default();
+        Err(Error::PoolServer(status, body))
+    }
+}
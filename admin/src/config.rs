@@ -0,0 +1,28 @@
+use ore_pool_types::{AdminConfig, UpdateConfigPayload};
+
+use crate::{client::Client, error::Error};
+
+/// prints the runtime config of the pool server,
+/// or updates it first if any of the fields are passed.
+/// commissions apply from the next submission,
+/// and the min difficulty from the next challenge.
+pub async fn config(client: &Client, payload: UpdateConfigPayload) -> Result<(), Error> {
+    let is_update = payload.operator_commission.is_some()
+        || payload.staker_commission.is_some()
+        || payload.min_difficulty.is_some();
+    let config = match is_update {
+        true => client.update_config(&payload).await?,
+        false => client.config().await?,
+    };
+    print_config(&config);
+    Ok(())
+}
+
+fn print_config(config: &AdminConfig) {
+    println!("operator commission: {}%", config.operator_commission);
+    println!("staker commission: {}%", config.staker_commission);
+    match config.min_difficulty {
+        0 => println!("min difficulty: program min"),
+        min_difficulty => println!("min difficulty: {}", min_difficulty),
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{client::Client, error::Error};
+
+/// prints the difficulties of the shares of the current round,
+/// and the members whose shares are suspiciously uniform.
+pub async fn difficulty(client: &Client) -> Result<(), Error> {
+    let stats = client.difficulty_stats().await?;
+    println!("min difficulty: {}", stats.min_difficulty);
+    let round = &stats.round;
+    println!("shares: {}", round.shares);
+    println!("mean difficulty: {:.2}", round.mean);
+    for bucket in round.buckets.iter() {
+        println!("  {:>3}: {}", bucket.difficulty, bucket.count);
+    }
+    println!("uniform members: {}", stats.uniform_members.len());
+    for member in stats.uniform_members.iter() {
+        println!("  {}", member);
+    }
+    Ok(())
+}
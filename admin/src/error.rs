@@ -12,12 +12,32 @@ pub enum Error {
     SolanaClient(#[from] solana_client::client_error::ClientError),
     #[error("solana program")]
     SolaanProgram(#[from] solana_program::program_error::ProgramError),
+    #[error("std parse int")]
+    StdParseInt(#[from] std::num::ParseIntError),
+    #[error("std io")]
+    StdIO(#[from] std::io::Error),
+    #[error("serde json")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("reqwest")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("pool server {0}: {1}")]
+    PoolServer(reqwest::StatusCode, String),
     #[error("solana parse pubkey")]
     SolanaParsePubkey(#[from] solana_sdk::pubkey::ParsePubkeyError),
+    #[error("solana parse signature")]
+    SolanaParseSignature(#[from] solana_sdk::signature::ParseSignatureError),
     #[error("missing boost mint")]
     MissingBoostMint,
     #[error("missing pool url")]
     MissingPoolUrl,
+    #[error("missing member authority")]
+    MissingMember,
+    #[error("missing admin token")]
+    MissingAdminToken,
+    #[error("missing min claim")]
+    MissingMinClaim,
+    #[error("missing api key id")]
+    MissingApiKeyId,
     #[error("invalid command")]
     InvalidCommand,
 }
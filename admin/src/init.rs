@@ -29,7 +29,7 @@ pub async fn init(
     Ok(())
 }
 
-async fn get_or_create_pda<T: AccountDeserialize + Debug>(
+pub async fn get_or_create_pda<T: AccountDeserialize + Debug>(
     rpc_client: &RpcClient,
     keypair: &Keypair,
     pda: &Pubkey,
@@ -1,32 +1,89 @@
 use std::str::FromStr;
 
+use ore_pool_types::{
+    AdjustBalancePayload, AuditLogQuery, DistributionPreviewQuery, IssueApiKeyPayload,
+    UpdateConfigPayload,
+};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair, signer::EncodableKey,
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::{EncodableKey, Signer},
 };
 
+mod adjust;
+mod api_keys;
+mod audit_log;
+mod backup;
+mod client;
+mod config;
+mod difficulty;
 mod error;
 mod init;
 mod member_account;
+mod min_claim;
 mod open_stake;
 mod pool_account;
+mod preview;
 mod proof_account;
+mod reconcile;
+mod register_member;
+mod restore;
+mod rpc_usage;
+mod submit;
+mod treasury;
+mod verify;
 
 #[tokio::main]
 async fn main() -> Result<(), error::Error> {
     // parse resources
     let command = command()?;
-    let keypair = keypair()?;
-    let rpc_client = rpc_client()?;
     let boost_mint = boost_mint();
     let pool_url = pool_url();
+    let member = member()?;
     // run
+    // the admin api commands only need the pool url and admin token
+    match command.as_str() {
+        "submit" => return submit::submit(&client(pool_url)?).await,
+        "reconcile" => return reconcile::reconcile(&client(pool_url)?).await,
+        "config" => return config::config(&client(pool_url)?, update_config()?).await,
+        "audit-log" => return audit_log::audit_log(&client(pool_url)?, audit_log_query()?).await,
+        "treasury" => return treasury::treasury(&client(pool_url)?).await,
+        "difficulty" => return difficulty::difficulty(&client(pool_url)?).await,
+        "rpc-usage" => return rpc_usage::rpc_usage(&client(pool_url)?).await,
+        "verify" => {
+            let member = member.ok_or(error::Error::MissingMember)?;
+            return verify::verify(&client(pool_url)?, round()?.as_str(), &member).await;
+        }
+        "preview" => return preview::preview(&client(pool_url)?, preview_query()?).await,
+        "backup" => return backup::backup(&client(pool_url)?, backup_path()?).await,
+        "restore" => return restore::restore(&client(pool_url)?, backup_path()?).await,
+        "adjust" => return adjust::adjust(&client(pool_url)?, adjust_balance(member)?).await,
+        "api-keys" => return api_keys::api_keys(&client(pool_url)?).await,
+        "api-key-issue" => {
+            return api_keys::issue_api_key(&client(pool_url)?, issue_api_key()?).await
+        }
+        "api-key-revoke" => {
+            return api_keys::revoke_api_key(&client(pool_url)?, api_key_id()?).await
+        }
+        _ => {}
+    }
+    let keypair = keypair()?;
+    let rpc_client = rpc_client()?;
     match command.as_str() {
         "init" => init::init(&rpc_client, &keypair, pool_url).await,
         "open-stake" => open_stake::open_stake(&rpc_client, &keypair, boost_mint).await,
+        "min-claim" => {
+            min_claim::min_claim(&rpc_client, &keypair, optional_u64("MIN_CLAIM")?).await
+        }
         "pool-account" => pool_account::pool_account(&rpc_client, &keypair).await,
         "proof-account" => proof_account::proof_account(&rpc_client, &keypair).await,
-        "member-account" => member_account::member_account(&rpc_client, &keypair).await,
+        "member-account" => member_account::member_account(&rpc_client, &keypair, member).await,
+        "register-member" => {
+            let client = client(pool_url)?;
+            register_member::register_member(&rpc_client, &keypair, &client, member).await
+        }
         _ => Err(error::Error::InvalidCommand),
     }
 }
@@ -62,3 +119,92 @@ fn keypair() -> Result<Keypair, error::Error> {
 fn pool_url() -> Option<String> {
     std::env::var("POOL_URL").ok()
 }
+
+fn backup_path() -> Result<String, error::Error> {
+    std::env::var("BACKUP_PATH").map_err(From::from)
+}
+
+fn round() -> Result<String, error::Error> {
+    std::env::var("ROUND").map_err(From::from)
+}
+
+fn member() -> Result<Option<Pubkey>, error::Error> {
+    match std::env::var("MEMBER") {
+        Ok(member) => Ok(Some(Pubkey::from_str(member.as_str())?)),
+        // optional
+        Err(_) => Ok(None),
+    }
+}
+
+fn client(pool_url: Option<String>) -> Result<client::Client, error::Error> {
+    let pool_url = pool_url.ok_or(error::Error::MissingPoolUrl)?;
+    let admin_token = std::env::var("ADMIN_TOKEN").ok();
+    // recorded in the audit log of the server
+    let actor = std::env::var("ADMIN_ACTOR")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or("admin".to_string());
+    Ok(client::Client::new(pool_url, admin_token, actor))
+}
+
+fn audit_log_query() -> Result<AuditLogQuery, error::Error> {
+    Ok(AuditLogQuery {
+        limit: optional_u64("LIMIT")?.map(|limit| limit as i64),
+        before: optional_u64("BEFORE")?.map(|before| before as i64),
+    })
+}
+
+fn preview_query() -> Result<DistributionPreviewQuery, error::Error> {
+    Ok(DistributionPreviewQuery {
+        reward: optional_u64("REWARD")?,
+        operator_commission: optional_u64("OPERATOR_COMMISSION")?,
+        staker_commission: optional_u64("STAKER_COMMISSION")?,
+    })
+}
+
+fn update_config() -> Result<UpdateConfigPayload, error::Error> {
+    Ok(UpdateConfigPayload {
+        operator_commission: optional_u64("OPERATOR_COMMISSION")?,
+        staker_commission: optional_u64("STAKER_COMMISSION")?,
+        min_difficulty: optional_u64("MIN_DIFFICULTY")?,
+    })
+}
+
+fn issue_api_key() -> Result<IssueApiKeyPayload, error::Error> {
+    Ok(IssueApiKeyPayload {
+        name: std::env::var("NAME")?,
+        rate_limit: optional_u64("RATE_LIMIT")?.map(|limit| limit as u32),
+    })
+}
+
+/// the adjustment signed by the admin key at `ADMIN_KEYPAIR_PATH`.
+fn adjust_balance(member: Option<Pubkey>) -> Result<AdjustBalancePayload, error::Error> {
+    let keypair_path = std::env::var("ADMIN_KEYPAIR_PATH")?;
+    let keypair = Keypair::read_from_file(keypair_path.clone())
+        .map_err(|_| error::Error::KeypairRead(keypair_path))?;
+    let signed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let mut payload = AdjustBalancePayload {
+        authority: member.ok_or(error::Error::MissingMember)?,
+        amount: std::env::var("AMOUNT")?.parse()?,
+        reason: std::env::var("REASON")?,
+        signed_at,
+        signer: keypair.pubkey(),
+        signature: Signature::default(),
+    };
+    payload.signature = keypair.sign_message(payload.to_bytes().as_slice());
+    Ok(payload)
+}
+
+fn api_key_id() -> Result<i64, error::Error> {
+    let id = optional_u64("ID")?.ok_or(error::Error::MissingApiKeyId)?;
+    Ok(id as i64)
+}
+
+fn optional_u64(var: &str) -> Result<Option<u64>, error::Error> {
+    match std::env::var(var) {
+        Ok(value) => Ok(Some(value.parse()?)),
+        Err(_) => Ok(None),
+    }
+}
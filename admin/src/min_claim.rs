@@ -0,0 +1,21 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+
+use crate::error::Error;
+
+/// sets the minimum amount members may claim at once, enforced on-chain.
+pub async fn min_claim(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    min_claim: Option<u64>,
+) -> Result<(), Error> {
+    let min_claim = min_claim.ok_or(Error::MissingMinClaim)?;
+    let pubkey = keypair.pubkey();
+    let ix = ore_pool_api::sdk::set_min_claim(pubkey, min_claim);
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&pubkey));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("{:?}", sig);
+    Ok(())
+}
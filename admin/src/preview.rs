@@ -0,0 +1,26 @@
+use ore_pool_types::DistributionPreviewQuery;
+
+use crate::{client::Client, error::Error};
+
+/// prints the payout split of the current round if it were submitted now,
+/// without committing anything.
+pub async fn preview(client: &Client, query: DistributionPreviewQuery) -> Result<(), Error> {
+    let preview = client.distribution_preview(&query).await?;
+    println!("challenge: {:?}", preview.challenge);
+    println!("reward: {}", preview.reward);
+    println!(
+        "commissions: operator {}%, staker {}%",
+        preview.operator_commission, preview.staker_commission
+    );
+    println!("total score: {}", preview.total_score);
+    println!("shares: {}", preview.shares.len());
+    for share in preview.shares.iter() {
+        println!(
+            "  {} {} score {} weight {} amount {}",
+            share.member, share.worker, share.score, share.weight, share.amount
+        );
+    }
+    println!("operator: {}", preview.operator);
+    println!("dust: {}", preview.dust);
+    Ok(())
+}
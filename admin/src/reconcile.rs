@@ -0,0 +1,23 @@
+use crate::{client::Client, error::Error};
+
+/// compares the db balance of every member against its on-chain account.
+/// members whose db balance is ahead of the chain are marked as unsynced,
+/// so that the next attribution lands their balance.
+/// members missing on-chain, or ahead on-chain, are printed for manual review.
+pub async fn reconcile(client: &Client) -> Result<(), Error> {
+    let report = client.reconcile().await?;
+    println!("checked: {}", report.checked);
+    println!("resynced: {}", report.resynced.len());
+    for authority in report.resynced.iter() {
+        println!("  {}", authority);
+    }
+    println!("missing on-chain: {}", report.missing_onchain.len());
+    for authority in report.missing_onchain.iter() {
+        println!("  {}", authority);
+    }
+    println!("ahead on-chain: {}", report.ahead_onchain.len());
+    for authority in report.ahead_onchain.iter() {
+        println!("  {}", authority);
+    }
+    Ok(())
+}
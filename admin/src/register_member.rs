@@ -0,0 +1,37 @@
+use ore_pool_api::state::Member;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{client::Client, error::Error, init::get_or_create_pda};
+
+/// registers a member on behalf of its authority,
+/// for members who can't (or don't want to) pay for their own account.
+/// the operator pays to open the member account on-chain if it doesn't exist yet,
+/// then registers it with the pool server db.
+/// authorities already members of this pool are registered without a transaction.
+pub async fn register_member(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    client: &Client,
+    member: Option<Pubkey>,
+) -> Result<(), Error> {
+    let member_authority = member.ok_or(Error::MissingMember)?;
+    let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
+    let (member_pda, _) = ore_pool_api::state::member_pda(member_authority, pool_pda);
+    println!("member address: {:?}", member_pda);
+    // skip the join transaction if the authority is already a member of this pool
+    match client.register(member_authority).await {
+        Ok(db_member) => {
+            println!("already a member of this pool with id {}", db_member.id);
+            println!("{:?}", db_member);
+            return Ok(());
+        }
+        Err(Error::PoolServer(status, _)) if status == reqwest::StatusCode::NOT_FOUND => {}
+        Err(err) => return Err(err),
+    }
+    let join_ix = ore_pool_api::sdk::join(member_authority, pool_pda, keypair.pubkey());
+    get_or_create_pda::<Member>(rpc_client, keypair, &member_pda, join_ix).await?;
+    let db_member = client.register(member_authority).await?;
+    println!("{:?}", db_member);
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use ore_pool_types::Backup;
+
+use crate::{client::Client, error::Error};
+
+/// restores the backup at the backup path into the empty db of a fresh host.
+/// the round in flight is restored too if the pool is still on the same challenge,
+/// so restore before the first cutoff after starting the new host.
+pub async fn restore(client: &Client, backup_path: String) -> Result<(), Error> {
+    let file = std::fs::File::open(backup_path.as_str())?;
+    let backup: Backup = serde_json::from_reader(std::io::BufReader::new(file))?;
+    let report = client.restore(&backup).await?;
+    println!("members: {}", report.members);
+    println!("stakers: {}", report.stakers);
+    match report.round_restored {
+        true => println!("contributions: {}", report.contributions),
+        false => println!("round is stale, contributions not restored"),
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::{client::Client, error::Error};
+
+/// prints the rpc requests and estimated credits of the server today,
+/// against the daily budget if configured.
+pub async fn rpc_usage(client: &Client) -> Result<(), Error> {
+    let report = client.rpc_usage().await?;
+    println!("day start: {}", report.day_start);
+    println!("requests: {}", report.requests);
+    match report.daily_budget {
+        Some(budget) if budget > 0 => println!(
+            "credits: {} of {} ({:.1}%)",
+            report.credits,
+            budget,
+            report.credits as f64 / budget as f64 * 100.0
+        ),
+        _ => println!("credits: {}", report.credits),
+    }
+    for call in report.calls.iter() {
+        println!(
+            "  {}: {} requests, {} credits",
+            call.call, call.requests, call.credits
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use crate::{client::Client, error::Error};
+
+/// forces the pool to submit the best solution of the current round now,
+/// rather than waiting for the cutoff time.
+/// errors if no contributions have been received yet this round.
+pub async fn submit(client: &Client) -> Result<(), Error> {
+    client.submit().await?;
+    println!("submit requested");
+    Ok(())
+}
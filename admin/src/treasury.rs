@@ -0,0 +1,31 @@
+use crate::{client::Client, error::Error};
+
+/// prints the pool proof reconciled against the db balances,
+/// and the rounds where they began to drift apart.
+pub async fn treasury(client: &Client) -> Result<(), Error> {
+    let report = client.treasury().await?;
+    println!("proof balance: {}", report.proof_balance);
+    println!("proof lifetime rewards: {}", report.proof_total_rewards);
+    println!("claimed: {}", report.claimed);
+    println!("member balances: {}", report.member_balances);
+    println!("commission: {}", report.commission);
+    println!("drift: {}", report.drift);
+    if !report.is_drifting {
+        println!("ok, within rounding dust");
+    } else {
+        println!("DRIFTING");
+    }
+    if let Some(first) = report.drift_rounds.first() {
+        println!(
+            "divergence began at round {} ({})",
+            first.challenge, first.created_at
+        );
+    }
+    for round in report.drift_rounds.iter() {
+        println!(
+            "  {} {} reward {} drift {} ({:+})",
+            round.created_at, round.challenge, round.reward, round.drift, round.change
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use ore_pool_types::ShareVerification;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{client::Client, error::Error};
+
+/// re-verifies the archived shares of a member in a round,
+/// running the digest verification locally rather than trusting the verdict of the server.
+pub async fn verify(client: &Client, round: &str, member: &Pubkey) -> Result<(), Error> {
+    let report = client.verify(round, member).await?;
+    let shares = report.shares.into_iter().map(|s| s.share).collect();
+    let local = ShareVerification::new(&report.challenge.to_bytes(), shares);
+    for share in local.shares.iter() {
+        let difficulty = match share.difficulty {
+            Some(difficulty) => difficulty.to_string(),
+            None => "invalid digest".to_string(),
+        };
+        println!(
+            "  {} difficulty {} score {} (credited {})",
+            share.share.worker, difficulty, share.verified_score, share.share.score
+        );
+    }
+    println!("round: {}", local.challenge);
+    println!("credited score: {}", local.reported_score);
+    println!("verified score: {}", local.verified_score);
+    if local.is_valid {
+        println!("ok, every share is real work and credited its score");
+    } else {
+        println!("INVALID");
+    }
+    if local.is_valid != report.is_valid {
+        println!("the server reported the round as {}", report.is_valid);
+    }
+    Ok(())
+}
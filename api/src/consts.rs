@@ -4,5 +4,8 @@ pub const MEMBER: &[u8] = b"member";
 /// The seed of the pool account PDA.
 pub const POOL: &[u8] = b"pool";
 
+/// The seed of the pool config account PDA.
+pub const POOL_CONFIG: &[u8] = b"pool_config";
+
 /// The seed of the share account PDA.
 pub const SHARE: &[u8] = b"share";
@@ -16,6 +16,7 @@ pub enum PoolInstruction {
     Launch = 102,
     OpenStake = 103,
     Submit = 104,
+    SetMinClaim = 105,
 }
 
 #[repr(C)]
@@ -59,6 +60,12 @@ pub struct Join {
     pub member_bump: u8,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetMinClaim {
+    pub min_claim: [u8; 8],
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct Stake {
@@ -86,6 +93,7 @@ instruction!(PoolInstruction, Launch);
 instruction!(PoolInstruction, OpenShare);
 instruction!(PoolInstruction, OpenStake);
 instruction!(PoolInstruction, Join);
+instruction!(PoolInstruction, SetMinClaim);
 instruction!(PoolInstruction, Stake);
 instruction!(PoolInstruction, Submit);
 instruction!(PoolInstruction, Unstake);
@@ -5,7 +5,7 @@ use steel::*;
 use crate::{
     error::ApiError,
     instruction::*,
-    state::{member_pda, pool_pda, pool_proof_pda, share_pda},
+    state::{member_pda, pool_config_pda, pool_pda, pool_proof_pda, share_pda},
 };
 
 /// Builds a launch instruction.
@@ -62,6 +62,7 @@ pub fn claim(
 ) -> Instruction {
     let (member_pda, _) = member_pda(signer, pool_pda);
     let (pool_proof_pda, _) = pool_proof_pda(pool_pda);
+    let (config_pda, _) = pool_config_pda(pool_pda);
     Instruction {
         program_id: crate::ID,
         accounts: vec![
@@ -74,6 +75,7 @@ pub fn claim(
             AccountMeta::new(TREASURY_TOKENS_ADDRESS, false),
             AccountMeta::new_readonly(ore_api::ID, false),
             AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(config_pda, false),
         ],
         data: Claim {
             amount: amount.to_le_bytes(),
@@ -276,6 +278,25 @@ pub fn open_stake(signer: Pubkey, mint: Pubkey) -> Instruction {
     }
 }
 
+/// Builds a set min claim instruction.
+pub fn set_min_claim(signer: Pubkey, min_claim: u64) -> Instruction {
+    let (pool_pda, _) = pool_pda(signer);
+    let (config_pda, _) = pool_config_pda(pool_pda);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(config_pda, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: SetMinClaim {
+            min_claim: min_claim.to_le_bytes(),
+        }
+        .to_bytes(),
+    }
+}
+
 fn url_to_bytes(input: &str) -> Result<[u8; 128], ApiError> {
     let bytes = input.as_bytes();
     let len = bytes.len();
@@ -1,9 +1,11 @@
 mod member;
 mod pool;
+mod pool_config;
 mod share;
 
 pub use member::*;
 pub use pool::*;
+pub use pool_config::*;
 pub use share::*;
 
 use steel::*;
@@ -16,12 +18,17 @@ pub enum AccountDiscriminator {
     Member = 100,
     Pool = 101,
     Share = 102,
+    PoolConfig = 103,
 }
 
 pub fn pool_pda(authority: Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[POOL, authority.as_ref()], &crate::id())
 }
 
+pub fn pool_config_pda(pool: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_CONFIG, pool.as_ref()], &crate::id())
+}
+
 pub fn pool_proof_pda(pool: Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[ore_api::consts::PROOF, pool.as_ref()], &ore_api::id())
 }
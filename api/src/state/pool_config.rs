@@ -0,0 +1,19 @@
+use steel::*;
+
+use super::AccountDiscriminator;
+
+/// PoolConfig holds the operator settings of a pool, apart from the pool account,
+/// so that settings are added without resizing the pools already launched.
+/// Pools that never changed a setting have no config account.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct PoolConfig {
+    /// The pool this config is associated with.
+    pub pool: Pubkey,
+
+    /// The minimum amount a member may claim at once, zero for no minimum.
+    /// A member may always claim its whole balance.
+    pub min_claim: u64,
+}
+
+account!(AccountDiscriminator, PoolConfig);
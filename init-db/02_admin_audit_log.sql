@@ -0,0 +1,16 @@
+-- create admin audit log table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'admin_audit_log') THEN
+        CREATE TABLE admin_audit_log (
+          id BIGSERIAL PRIMARY KEY,
+          actor VARCHAR NOT NULL, -- the name the admin cli identified as
+          remote_addr VARCHAR, -- the address the admin request came from
+          action VARCHAR NOT NULL, -- the admin action, e.g. update_config
+          old_value VARCHAR, -- json of the value before the action, if any
+          new_value VARCHAR, -- json of the value after the action, if any
+          created_at BIGINT NOT NULL -- unix timestamp
+        );
+    END IF;
+END
+$$;
@@ -0,0 +1,64 @@
+-- create notification prefs table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'notification_prefs') THEN
+        CREATE TABLE notification_prefs (
+          authority VARCHAR PRIMARY KEY, -- the authority of the member
+          channel VARCHAR NOT NULL, -- none, webhook, or email
+          target VARCHAR, -- the webhook url or email address
+          updated_at BIGINT NOT NULL -- unix timestamp
+        );
+    END IF;
+END
+$$;
+
+-- create member earnings table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'member_earnings') THEN
+        CREATE TABLE member_earnings (
+          id BIGSERIAL PRIMARY KEY,
+          address VARCHAR NOT NULL, -- address of member account
+          amount BIGINT NOT NULL, -- the balance increment of the round
+          created_at BIGINT NOT NULL -- unix timestamp
+        );
+        CREATE INDEX member_earnings_created_at ON member_earnings (created_at);
+    END IF;
+END
+$$;
+
+-- create member claims table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'member_claims') THEN
+        CREATE TABLE member_claims (
+          signature VARCHAR PRIMARY KEY, -- the confirmed claim transaction
+          authority VARCHAR NOT NULL, -- the authority of the member
+          amount BIGINT NOT NULL,
+          created_at BIGINT NOT NULL -- unix timestamp
+        );
+        CREATE INDEX member_claims_created_at ON member_claims (created_at);
+    END IF;
+END
+$$;
+
+-- create notification outbox table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'notification_outbox') THEN
+        CREATE TABLE notification_outbox (
+          id BIGSERIAL PRIMARY KEY,
+          key VARCHAR NOT NULL, -- dedupes notifications, e.g. digest:<authority>:<day>
+          authority VARCHAR NOT NULL, -- the authority of the member
+          channel VARCHAR NOT NULL, -- webhook or email
+          target VARCHAR NOT NULL, -- the webhook url or email address
+          payload VARCHAR NOT NULL, -- json of the notification
+          attempts INT NOT NULL DEFAULT 0,
+          last_error VARCHAR,
+          created_at BIGINT NOT NULL, -- unix timestamp
+          sent_at BIGINT, -- unix timestamp, null until delivered
+          CONSTRAINT unique_outbox_key UNIQUE (key)
+        );
+    END IF;
+END
+$$;
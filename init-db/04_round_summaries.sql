@@ -0,0 +1,19 @@
+-- create round summaries table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'round_summaries') THEN
+        CREATE TABLE round_summaries (
+          challenge VARCHAR PRIMARY KEY, -- base58 of the round challenge
+          last_hash_at BIGINT NOT NULL, -- the last hash at of the pool during the round
+          shares BIGINT NOT NULL, -- the number of accepted shares
+          total_score BIGINT NOT NULL,
+          min_difficulty INT, -- of the accepted shares, null if none
+          max_difficulty INT,
+          mean_difficulty DOUBLE PRECISION NOT NULL,
+          histogram VARCHAR NOT NULL, -- json of the difficulty buckets
+          reward BIGINT NOT NULL, -- the base reward of the round
+          created_at BIGINT NOT NULL -- unix timestamp
+        );
+    END IF;
+END
+$$;
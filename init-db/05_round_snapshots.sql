@@ -0,0 +1,3 @@
+-- snapshot the proof and db balances of each round, for the treasury report
+ALTER TABLE round_summaries ADD COLUMN IF NOT EXISTS proof_total_rewards BIGINT; -- lifetime rewards of the pool proof after the round, null if unknown
+ALTER TABLE round_summaries ADD COLUMN IF NOT EXISTS db_total_balance BIGINT; -- sum of member balances in the db after the round
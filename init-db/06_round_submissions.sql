@@ -0,0 +1,15 @@
+-- create round submissions table, to never submit or distribute a round twice across restarts
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'round_submissions') THEN
+        CREATE TABLE round_submissions (
+          challenge VARCHAR PRIMARY KEY, -- base58 of the round challenge
+          last_hash_at BIGINT NOT NULL, -- the last hash at of the pool during the round
+          claimed_at BIGINT NOT NULL, -- unix timestamp the submission was started at
+          signature VARCHAR, -- of the landed submission, null until confirmed
+          submitted_at BIGINT, -- unix timestamp the submission was confirmed at
+          distributed_at BIGINT -- unix timestamp the rewards were written to member balances
+        );
+    END IF;
+END
+$$;
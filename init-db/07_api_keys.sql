@@ -0,0 +1,15 @@
+-- create api keys table, for third-party read access
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'api_keys') THEN
+        CREATE TABLE api_keys (
+          id BIGSERIAL PRIMARY KEY,
+          name VARCHAR NOT NULL, -- who the key is issued to
+          key_hash VARCHAR NOT NULL UNIQUE, -- hex of the sha3-256 of the key, the key itself isn't stored
+          rate_limit INT NOT NULL, -- max requests per minute
+          created_at BIGINT NOT NULL, -- unix timestamp
+          revoked_at BIGINT -- unix timestamp, null while the key is valid
+        );
+    END IF;
+END
+$$;
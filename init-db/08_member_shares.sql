@@ -0,0 +1,18 @@
+-- create member shares table, the shares of each member per round for the member hashrate
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'member_shares') THEN
+        CREATE TABLE member_shares (
+          challenge VARCHAR NOT NULL, -- base58 of the round challenge
+          authority VARCHAR NOT NULL, -- the authority of the member
+          accepted BIGINT NOT NULL, -- the number of accepted shares
+          rejected BIGINT NOT NULL, -- the number of rejected shares
+          score BIGINT NOT NULL, -- the total difficulty score of the accepted shares
+          created_at BIGINT NOT NULL, -- unix timestamp the round was submitted at
+          PRIMARY KEY (challenge, authority)
+        );
+        CREATE INDEX member_shares_authority ON member_shares (authority, created_at);
+        CREATE INDEX member_shares_created_at ON member_shares (created_at);
+    END IF;
+END
+$$;
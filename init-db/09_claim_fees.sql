@@ -0,0 +1,2 @@
+-- add the fee deducted from claims paid for by the operator
+ALTER TABLE member_claims ADD COLUMN IF NOT EXISTS fee BIGINT NOT NULL DEFAULT 0; -- in grains, zero if paid for by the member
@@ -0,0 +1,20 @@
+-- create member adjustments table, the manual balance adjustments applied by the operator
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'member_adjustments') THEN
+        CREATE TABLE member_adjustments (
+          id BIGSERIAL PRIMARY KEY,
+          address VARCHAR NOT NULL, -- the member pda
+          authority VARCHAR NOT NULL, -- the authority of the member
+          amount BIGINT NOT NULL, -- in grains, positive for a credit, negative for a debit
+          reason VARCHAR NOT NULL, -- why the balance was adjusted, shown to the member
+          actor VARCHAR NOT NULL, -- the admin who applied it
+          created_at BIGINT NOT NULL -- unix timestamp
+        );
+        CREATE INDEX member_adjustments_authority ON member_adjustments (authority, created_at);
+    END IF;
+END
+$$;
+-- the debits not yet recovered from the earnings of the member,
+-- as the on-chain balance only ever increments
+ALTER TABLE members ADD COLUMN IF NOT EXISTS debit BIGINT NOT NULL DEFAULT 0; -- in grains
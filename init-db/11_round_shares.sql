@@ -0,0 +1,17 @@
+-- create round shares table, the accepted solutions of each round for independent verification
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'round_shares') THEN
+        CREATE TABLE round_shares (
+          challenge VARCHAR NOT NULL, -- base58 of the round challenge
+          authority VARCHAR NOT NULL, -- the authority of the member
+          worker VARCHAR NOT NULL, -- the named worker of the member
+          digest BYTEA NOT NULL, -- the 16 byte drillx digest of the solution
+          nonce BYTEA NOT NULL, -- the 8 byte little-endian nonce of the solution
+          score BIGINT NOT NULL, -- the difficulty score credited
+          created_at BIGINT NOT NULL, -- unix timestamp the round was submitted at
+          PRIMARY KEY (challenge, authority, worker)
+        );
+    END IF;
+END
+$$;
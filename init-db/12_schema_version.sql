@@ -0,0 +1,9 @@
+-- create schema version table, checked by the server on startup against the version it expects
+CREATE TABLE IF NOT EXISTS schema_version (
+  version INT PRIMARY KEY, -- the number of the migration
+  applied_at BIGINT NOT NULL -- unix timestamp
+);
+-- each migration from here on records its number
+INSERT INTO schema_version (version, applied_at)
+VALUES (12, EXTRACT(EPOCH FROM NOW())::BIGINT)
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,21 @@
+-- create round payouts table, the miner rewards paid to each member per round for the payout statements
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'round_payouts') THEN
+        CREATE TABLE round_payouts (
+          challenge VARCHAR NOT NULL, -- base58 of the round challenge
+          authority VARCHAR NOT NULL, -- the authority of the member
+          score BIGINT NOT NULL, -- the weighted score of the member
+          amount BIGINT NOT NULL, -- the miner rewards paid to the member, net of the debit recovered
+          total_score BIGINT NOT NULL, -- the total weighted score of the round
+          total_amount BIGINT NOT NULL, -- the miner rewards of the round
+          created_at BIGINT NOT NULL, -- unix timestamp the round was submitted at
+          PRIMARY KEY (challenge, authority)
+        );
+        CREATE INDEX round_payouts_authority ON round_payouts (authority, created_at);
+    END IF;
+END
+$$;
+INSERT INTO schema_version (version, applied_at)
+VALUES (13, EXTRACT(EPOCH FROM NOW())::BIGINT)
+ON CONFLICT (version) DO NOTHING;
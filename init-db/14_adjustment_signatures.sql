@@ -0,0 +1,7 @@
+-- the admin signatures of the manual balance adjustments, each applied once
+ALTER TABLE member_adjustments ADD COLUMN IF NOT EXISTS signer VARCHAR; -- the admin key that signed it
+ALTER TABLE member_adjustments ADD COLUMN IF NOT EXISTS signature VARCHAR; -- base58 of the admin signature
+CREATE UNIQUE INDEX IF NOT EXISTS member_adjustments_signature ON member_adjustments (signature);
+INSERT INTO schema_version (version, applied_at)
+VALUES (14, EXTRACT(EPOCH FROM NOW())::BIGINT)
+ON CONFLICT (version) DO NOTHING;
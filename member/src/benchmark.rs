@@ -0,0 +1,48 @@
+use std::{
+    sync::atomic::AtomicU64,
+    time::{Duration, Instant},
+};
+
+use crate::{client::Client, config::Config, error::Error, mine};
+
+/// The number of seconds the benchmark runs for if no duration is passed.
+const DEFAULT_DURATION: u64 = 60;
+
+/// Hashes locally for a fixed duration across the configured threads
+/// and reports the sustained hashrate against the pool's min difficulty,
+/// without contributing anything to the pool.
+pub async fn benchmark(config: &Config, duration: Option<u64>) -> Result<(), Error> {
+    let client = Client::new(config.pool_urls());
+    let member_challenge = client.challenge().await?;
+    let challenge = member_challenge.challenge;
+    let min_difficulty = challenge.min_difficulty as u32;
+    let duration = duration.unwrap_or(DEFAULT_DURATION);
+    let threads = config.threads;
+    println!("benchmarking {} threads for {} seconds", threads, duration);
+    let timer = Instant::now();
+    let deadline = timer + Duration::from_secs(duration);
+    let counters: Vec<AtomicU64> = (0..threads.max(1)).map(|_| AtomicU64::new(0)).collect();
+    let best = tokio::task::spawn_blocking(move || {
+        mine::find_best(challenge.challenge, 0..u64::MAX, &counters, deadline)
+    })
+    .await?;
+    let elapsed = timer.elapsed().as_secs_f64();
+    let hashrate = best.hashes as f64 / elapsed;
+    println!("hashes: {}", best.hashes);
+    println!("hashrate: {:.2} H/s", hashrate);
+    if let Some((_, difficulty)) = best.solution {
+        println!("best difficulty: {}", difficulty);
+    }
+    println!("pool min difficulty: {}", min_difficulty);
+    println!(
+        "expected shares per minute: {:.4}",
+        expected_shares_per_minute(hashrate, min_difficulty)
+    );
+    Ok(())
+}
+
+/// The rate of hashes at or above the difficulty,
+/// each hash meeting it with probability 2^-difficulty.
+fn expected_shares_per_minute(hashrate: f64, difficulty: u32) -> f64 {
+    hashrate * 60.0 / 2f64.powi(difficulty as i32)
+}
@@ -0,0 +1,402 @@
+use std::{collections::HashSet, ops::Range, sync::Arc, time::Duration};
+
+use drillx::Solution;
+use ore_pool_types::{hex, ContributePayload};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{signature::Keypair, signer::Signer};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+    sync::{broadcast, Mutex},
+};
+
+use crate::{client::Client, config::Config, error::Error, mine};
+
+/// The address the bridge listens on if none is passed.
+const DEFAULT_LISTEN: &str = "0.0.0.0:3333";
+
+/// The number of slices the member nonce range is split into,
+/// one per connected miner, and so the max connected miners.
+const NUM_SLOTS: u64 = 256;
+
+/// A unit of work pushed to external miners.
+#[derive(Clone, Copy, Debug)]
+struct Job {
+    /// Increments with every new pool challenge.
+    id: u64,
+
+    /// The pool challenge.
+    challenge: [u8; 32],
+
+    /// The start of the member nonce range.
+    nonce_start: u64,
+
+    /// The end of the member nonce range.
+    nonce_end: u64,
+
+    /// The minimum difficulty the pool accepts.
+    min_difficulty: u64,
+
+    /// The seconds until the bridge contributes its best share to the pool.
+    seconds: u64,
+}
+
+impl Job {
+    /// the slice of the member nonce range assigned to a connection.
+    fn nonces(&self, slot: u64) -> Range<u64> {
+        let unit = (self.nonce_end - self.nonce_start).saturating_div(NUM_SLOTS);
+        let start = self.nonce_start.saturating_add(unit.saturating_mul(slot));
+        start..start.saturating_add(unit)
+    }
+
+    fn notify(&self, slot: u64) -> Notification {
+        let nonces = self.nonces(slot);
+        Notification {
+            id: Value::Null,
+            method: "mining.notify".to_string(),
+            params: vec![
+                json!(self.id.to_string()),
+                json!(hex::encode(&self.challenge)),
+                json!(nonces.start),
+                json!(nonces.end),
+                json!(self.min_difficulty),
+                json!(self.seconds),
+            ],
+        }
+    }
+}
+
+/// The state shared by all connections.
+#[derive(Default)]
+struct Bridge {
+    /// The current job.
+    job: Option<Job>,
+
+    /// The best share submitted by any miner for the current job.
+    best: Option<(Solution, u32)>,
+
+    /// The slots of the connected miners.
+    slots: HashSet<u64>,
+}
+
+impl Bridge {
+    /// Assigns the lowest free slot to a new connection, none if every slot is taken.
+    fn take_slot(&mut self) -> Option<u64> {
+        let slot = (0..NUM_SLOTS).find(|slot| !self.slots.contains(slot))?;
+        self.slots.insert(slot);
+        Some(slot)
+    }
+
+    /// Frees the slot of a closed connection, for the next one.
+    fn free_slot(&mut self, slot: u64) {
+        self.slots.remove(&slot);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    id: Value,
+    result: Value,
+    error: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Notification {
+    id: Value,
+    method: String,
+    params: Vec<Value>,
+}
+
+/// Speaks a stratum-like json-rpc job protocol to external mining software
+/// and forwards the best share of each round to the pool as this member.
+///
+/// Messages are newline delimited json.
+/// - `mining.subscribe` returns the slot of the connection.
+/// - `mining.authorize` with params `[worker]` starts pushing jobs.
+/// - `mining.notify` pushes `[job_id, challenge_hex, nonce_start, nonce_end, min_difficulty, seconds]`.
+/// - `mining.submit` with params `[worker, job_id, nonce, digest_hex]` submits a share.
+pub async fn bridge(config: &Config, listen: Option<String>) -> Result<(), Error> {
+    let client = Client::new(config.pool_urls());
+    let signer = config.keypair()?;
+    let member = mine::get_or_register(&client, signer.pubkey()).await?;
+    println!("member: {:?}", member);
+    let listen = listen.unwrap_or(DEFAULT_LISTEN.to_string());
+    let listener = TcpListener::bind(listen.as_str()).await?;
+    println!("bridge listening on {}", listen);
+    let bridge = Arc::new(Mutex::new(Bridge::default()));
+    let (jobs_tx, _) = broadcast::channel::<Job>(16);
+    // accept connections
+    tokio::task::spawn({
+        let bridge = bridge.clone();
+        let jobs_tx = jobs_tx.clone();
+        async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        // each connection mines its own slice of the nonce range
+                        let Some(slot) = bridge.lock().await.take_slot() else {
+                            println!("miner refused, all {} slots taken: {}", NUM_SLOTS, addr);
+                            continue;
+                        };
+                        println!("miner connected: {} (slot {})", addr, slot);
+                        let bridge = bridge.clone();
+                        let jobs_rx = jobs_tx.subscribe();
+                        tokio::task::spawn(async move {
+                            if let Err(err) = serve(stream, &bridge, slot, jobs_rx).await {
+                                println!("{:?}", err);
+                            }
+                            bridge.lock().await.free_slot(slot);
+                            println!("miner disconnected: {}", addr);
+                        });
+                    }
+                    Err(err) => println!("{:?}", err),
+                }
+            }
+        }
+    });
+    // translate pool challenges into jobs
+    run_jobs(
+        &client,
+        &signer,
+        member.id as u64,
+        config.worker.as_str(),
+        &bridge,
+        &jobs_tx,
+    )
+    .await
+}
+
+async fn run_jobs(
+    client: &Client,
+    signer: &Keypair,
+    member_id: u64,
+    worker: &str,
+    bridge: &Mutex<Bridge>,
+    jobs_tx: &broadcast::Sender<Job>,
+) -> Result<(), Error> {
+    let mut last_challenge = [0; 32];
+    let mut job_id = 0;
+    loop {
+        // wait for the next challenge
+        let member_challenge = match client.challenge().await {
+            Ok(member_challenge) => member_challenge,
+            Err(err) => {
+                println!("{:?}", err);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let challenge = member_challenge.challenge;
+        if challenge.challenge == last_challenge {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+        last_challenge = challenge.challenge;
+        // publish job
+        job_id += 1;
+        let nonces = mine::nonce_range(member_id, member_challenge.num_total_members);
+        let seconds = challenge
+            .cutoff_time
+            .saturating_sub(member_challenge.buffer);
+        let job = Job {
+            id: job_id,
+            challenge: challenge.challenge,
+            nonce_start: nonces.start,
+            nonce_end: nonces.end,
+            min_difficulty: challenge.min_difficulty,
+            seconds,
+        };
+        {
+            let mut bridge = bridge.lock().await;
+            bridge.job = Some(job);
+            bridge.best = None;
+        }
+        // errors only if no miners are connected
+        let _ = jobs_tx.send(job);
+        // collect shares until the submission window opens
+        tokio::time::sleep(Duration::from_secs(seconds)).await;
+        let best = bridge.lock().await.best.take();
+        let Some((solution, difficulty)) = best else {
+            println!("no shares for job {}", job_id);
+            continue;
+        };
+        println!("job {} best difficulty: {}", job_id, difficulty);
+        let payload = ContributePayload {
+            authority: signer.pubkey(),
+            solution,
+            signature: signer.sign_message(&solution.to_bytes()),
+            worker: Some(worker.to_string()),
+        };
+        if let Err(err) = client.contribute(&payload).await {
+            println!("{:?}", err);
+        }
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    bridge: &Mutex<Bridge>,
+    slot: u64,
+    mut jobs_rx: broadcast::Receiver<Job>,
+) -> Result<(), Error> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut authorized = false;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                let request = match serde_json::from_str::<Request>(line.as_str()) {
+                    Ok(request) => request,
+                    Err(err) => {
+                        let response = error(Value::Null, err.to_string());
+                        write_message(&mut write, &response).await?;
+                        continue;
+                    }
+                };
+                let response = handle(&request, bridge, slot).await;
+                write_message(&mut write, &response).await?;
+                // push the current job right after authorizing
+                if request.method == "mining.authorize" && !authorized {
+                    authorized = true;
+                    let job = bridge.lock().await.job;
+                    if let Some(job) = job {
+                        write_message(&mut write, &job.notify(slot)).await?;
+                    }
+                }
+            }
+            job = jobs_rx.recv() => match job {
+                Ok(job) if authorized => write_message(&mut write, &job.notify(slot)).await?,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn handle(request: &Request, bridge: &Mutex<Bridge>, slot: u64) -> Response {
+    let id = request.id.clone();
+    match request.method.as_str() {
+        "mining.subscribe" => ok(id, json!(slot)),
+        "mining.authorize" => {
+            let worker = request.params.first().and_then(Value::as_str);
+            println!("miner authorized: {}", worker.unwrap_or("unknown"));
+            ok(id, json!(true))
+        }
+        "mining.submit" => match submit(&request.params, bridge, slot).await {
+            Ok(()) => ok(id, json!(true)),
+            Err(reason) => error(id, reason),
+        },
+        _ => error(id, "unknown method".to_string()),
+    }
+}
+
+/// validates a share against the current job
+/// and keeps it if it is the best seen so far.
+async fn submit(params: &[Value], bridge: &Mutex<Bridge>, slot: u64) -> Result<(), String> {
+    let [_worker, job_id, nonce, digest] = params else {
+        return Err("expected params [worker, job_id, nonce, digest]".to_string());
+    };
+    let job_id: u64 = job_id
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or("invalid job id")?;
+    let nonce = nonce.as_u64().ok_or("invalid nonce")?;
+    let digest: [u8; 16] = digest
+        .as_str()
+        .and_then(hex::decode)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("invalid digest")?;
+    let mut bridge = bridge.lock().await;
+    let job = bridge.job.ok_or("no job yet")?;
+    if job.id != job_id {
+        return Err("stale job".to_string());
+    }
+    if !job.nonces(slot).contains(&nonce) {
+        return Err("nonce out of range".to_string());
+    }
+    let solution = Solution::new(digest, nonce.to_le_bytes());
+    if !solution.is_valid(&job.challenge) {
+        return Err("invalid digest".to_string());
+    }
+    let difficulty = solution.to_hash().difficulty();
+    if difficulty < job.min_difficulty as u32 {
+        return Err("below min difficulty".to_string());
+    }
+    match bridge.best {
+        Some((_, best)) if best >= difficulty => {}
+        _ => bridge.best = Some((solution, difficulty)),
+    }
+    Ok(())
+}
+
+fn ok(id: Value, result: Value) -> Response {
+    Response {
+        id,
+        result,
+        error: Value::Null,
+    }
+}
+
+fn error(id: Value, reason: String) -> Response {
+    Response {
+        id,
+        result: Value::Null,
+        error: json!(reason),
+    }
+}
+
+async fn write_message<T: Serialize>(write: &mut OwnedWriteHalf, message: &T) -> Result<(), Error> {
+    let mut bytes = serde_json::to_vec(message)?;
+    bytes.push(b'\n');
+    write.write_all(bytes.as_slice()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assigns_free_slots() {
+        let mut bridge = Bridge::default();
+        for slot in 0..NUM_SLOTS {
+            assert_eq!(bridge.take_slot(), Some(slot));
+        }
+        // full
+        assert_eq!(bridge.take_slot(), None);
+        // the slot of a closed connection is reused, never a live one
+        bridge.free_slot(7);
+        assert_eq!(bridge.take_slot(), Some(7));
+        assert_eq!(bridge.take_slot(), None);
+    }
+
+    #[test]
+    fn splits_disjoint_nonce_ranges() {
+        let job = Job {
+            id: 1,
+            challenge: [0; 32],
+            nonce_start: 1_000,
+            nonce_end: 1_000 + NUM_SLOTS * 10,
+            min_difficulty: 0,
+            seconds: 0,
+        };
+        let ranges: Vec<Range<u64>> = (0..NUM_SLOTS).map(|slot| job.nonces(slot)).collect();
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(ranges[0].start, job.nonce_start);
+        assert!(ranges[NUM_SLOTS as usize - 1].end <= job.nonce_end);
+    }
+}
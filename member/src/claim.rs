@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use ore_api::consts::MINT_ADDRESS;
+use ore_pool_api::state::{member_pda, pool_config_pda, Member, PoolConfig};
+use ore_pool_types::{ClaimPayload, ClaimQuote};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, hash::Hash,
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::Transaction,
+};
+use steel::AccountDeserialize;
+
+use crate::{client::Client, config::Config, error::Error};
+
+/// The seconds between auto-claim sweeps, weekly.
+const AUTO_CLAIM_INTERVAL: u64 = 7 * 24 * 60 * 60;
+
+/// The compute unit limit of a claim transaction.
+const CU_LIMIT: u32 = 100_000;
+
+/// The compute unit price of a claim transaction, in micro lamports.
+const CU_PRICE: u64 = 10_000;
+
+/// The SOL balance below which the member may not cover its own claim,
+/// the transaction fee and the rent of a new token account, in lamports.
+const MIN_CLAIM_LAMPORTS: u64 = 2_100_000;
+
+/// Claims the whole member balance to the member's ORE token account,
+/// relayed by the pool server.
+/// Members without the SOL to pay for the claim have the operator pay for it,
+/// if the pool offers it, net of the fee quoted by the pool.
+pub async fn claim(config: &Config) -> Result<(), Error> {
+    let claimer = Claimer::new(config)?;
+    match claimer.claim_above(0).await? {
+        Some(amount) => println!("claimed {} grains", amount),
+        None => println!("nothing to claim"),
+    }
+    Ok(())
+}
+
+/// Sweeps the member balance weekly, whenever it is at or above the claim threshold,
+/// which defaults to the pool minimum.
+pub async fn auto_claim(config: &Config) -> Result<(), Error> {
+    let claimer = Claimer::new(config)?;
+    let threshold = match config.claim_threshold {
+        Some(threshold) => threshold,
+        None => claimer.get_min_claim().await?,
+    };
+    println!(
+        "auto-claiming balances of at least {} grains every {} days",
+        threshold,
+        AUTO_CLAIM_INTERVAL / (24 * 60 * 60)
+    );
+    loop {
+        match claimer.claim_above(threshold).await {
+            Ok(Some(amount)) => println!("claimed {} grains", amount),
+            Ok(None) => println!("balance below threshold, skipping"),
+            Err(err) => println!("claim failed: {:?}", err),
+        }
+        tokio::time::sleep(Duration::from_secs(AUTO_CLAIM_INTERVAL)).await;
+    }
+}
+
+struct Claimer {
+    client: Client,
+    rpc_client: RpcClient,
+    signer: Keypair,
+}
+
+impl Claimer {
+    fn new(config: &Config) -> Result<Self, Error> {
+        let rpc_url = config.rpc_url.clone().ok_or(Error::MissingRpcUrl)?;
+        Ok(Self {
+            client: Client::new(config.pool_urls()),
+            rpc_client: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            signer: config.keypair()?,
+        })
+    }
+
+    /// Claims the whole balance if it is at or above the threshold,
+    /// returning the amount claimed.
+    /// The whole balance may be claimed even if below the pool minimum.
+    async fn claim_above(&self, threshold: u64) -> Result<Option<u64>, Error> {
+        let authority = self.signer.pubkey();
+        let pool_address = self.client.pool_address().await?;
+        let member = self.get_member(&pool_address.address).await?;
+        if member.balance == 0 || member.balance < threshold {
+            return Ok(None);
+        }
+        let claim_ix = ore_pool_api::sdk::claim(
+            authority,
+            beneficiary(&authority),
+            pool_address.address,
+            pool_address.bump,
+            member.balance,
+        );
+        let hash = self.rpc_client.get_latest_blockhash().await?;
+        let transaction = match self.fee_quote(member.balance).await? {
+            Some(quote) => {
+                println!(
+                    "operator paying the claim, fee {} grains, net {} grains",
+                    quote.fee, quote.net_amount
+                );
+                self.fee_claim_transaction(claim_ix, &quote, hash)?
+            }
+            None => {
+                let ixs = [
+                    ComputeBudgetInstruction::set_compute_unit_limit(CU_LIMIT),
+                    ComputeBudgetInstruction::set_compute_unit_price(CU_PRICE),
+                    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                        &authority,
+                        &authority,
+                        &MINT_ADDRESS,
+                        &spl_token::ID,
+                    ),
+                    claim_ix,
+                ];
+                Transaction::new_signed_with_payer(&ixs, Some(&authority), &[&self.signer], hash)
+            }
+        };
+        let receipt = self
+            .client
+            .claim(&ClaimPayload {
+                authority,
+                transaction,
+            })
+            .await?;
+        println!("{}", receipt.signature);
+        Ok(Some(receipt.amount - receipt.fee))
+    }
+
+    /// The quote of the operator paying for the claim,
+    /// none if the member can pay for it or the pool doesn't offer it.
+    async fn fee_quote(&self, amount: u64) -> Result<Option<ClaimQuote>, Error> {
+        let authority = self.signer.pubkey();
+        let lamports = self.rpc_client.get_balance(&authority).await?;
+        if lamports >= MIN_CLAIM_LAMPORTS {
+            return Ok(None);
+        }
+        match self.client.claim_quote(&authority, amount).await {
+            Ok(quote) if lamports < quote.network_fee => Ok(Some(quote)),
+            Ok(_) => Ok(None),
+            Err(err) => {
+                println!("operator-paid claim unavailable: {:?}", err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// The claim paid for by the operator, transferring the quoted fee to the operator,
+    /// signed by the member only for the operator to sign as fee payer.
+    fn fee_claim_transaction(
+        &self,
+        claim_ix: Instruction,
+        quote: &ClaimQuote,
+        hash: Hash,
+    ) -> Result<Transaction, Error> {
+        let authority = self.signer.pubkey();
+        let ixs = [
+            ComputeBudgetInstruction::set_compute_unit_limit(quote.compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(quote.compute_unit_price),
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &quote.fee_payer,
+                &authority,
+                &MINT_ADDRESS,
+                &spl_token::ID,
+            ),
+            claim_ix,
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &beneficiary(&authority),
+                &quote.fee_account,
+                &authority,
+                &[],
+                quote.fee,
+            )?,
+        ];
+        let mut transaction = Transaction::new_with_payer(&ixs, Some(&quote.fee_payer));
+        transaction.try_partial_sign(&[&self.signer], hash)?;
+        Ok(transaction)
+    }
+
+    /// The pool minimum, zero if the pool never set one and so has no config account.
+    async fn get_min_claim(&self) -> Result<u64, Error> {
+        let pool_address = self.client.pool_address().await?;
+        let (config_address, _) = pool_config_pda(pool_address.address);
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(&config_address, self.rpc_client.commitment())
+            .await?;
+        match account.value {
+            Some(account) => Ok(PoolConfig::try_from_bytes(account.data.as_slice())?.min_claim),
+            None => Ok(0),
+        }
+    }
+
+    async fn get_member(&self, pool_address: &Pubkey) -> Result<Member, Error> {
+        let (member_address, _) = member_pda(self.signer.pubkey(), *pool_address);
+        let data = self.rpc_client.get_account_data(&member_address).await?;
+        let member = Member::try_from_bytes(data.as_slice())?;
+        Ok(*member)
+    }
+}
+
+fn beneficiary(authority: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(authority, &MINT_ADDRESS)
+}
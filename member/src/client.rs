@@ -0,0 +1,288 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use drillx::Solution;
+use ore_pool_types::{
+    ClaimPayload, ClaimQuote, ClaimQuoteQuery, ClaimReceipt, ContributePayload, Member,
+    MemberChallenge, NotificationPrefs, NotificationPrefsPayload, PoolAddress, RegisterChallenge,
+    RegisterPayload, SignedPayoutStatement, WorkerStats,
+};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error;
+
+/// the max retries of a contribution the pool asks to retry later
+const MAX_CONTRIBUTE_RETRIES: usize = 3;
+
+/// the max seconds to wait before retrying a contribution
+const MAX_RETRY_AFTER_SECONDS: u64 = 5;
+
+/// the time to connect to a pool url before failing over to the next
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// client for the http api of a pool server,
+/// failing over between the instances of the pool
+pub struct Client {
+    http_client: reqwest::Client,
+    /// the urls of the pool instances, the primary first
+    pool_urls: Vec<String>,
+    /// the index of the url requests are sent to first
+    active: AtomicUsize,
+}
+
+impl Client {
+    pub fn new(pool_urls: Vec<String>) -> Self {
+        let pool_urls = pool_urls
+            .into_iter()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .collect();
+        Self {
+            http_client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            pool_urls,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// the url requests are currently sent to,
+    /// which changes when the client fails over
+    pub fn pool_url(&self) -> &str {
+        let active = self.active.load(Ordering::Relaxed);
+        self.pool_urls
+            .get(active)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    /// sends the request to the active url,
+    /// failing over to the next url while the pool is unreachable or on standby.
+    /// the url that answers becomes the active url.
+    async fn send(&self, req: impl Fn(&str) -> RequestBuilder) -> Result<Response, Error> {
+        let active = self.active.load(Ordering::Relaxed);
+        let count = self.pool_urls.len();
+        let mut last = Err(Error::MissingPoolUrl);
+        for i in 0..count {
+            let index = (active + i) % count;
+            let res = req(self.pool_urls[index].as_str()).send().await;
+            let failover = match &res {
+                Ok(resp) => is_unavailable(resp),
+                Err(err) => err.is_connect() || err.is_timeout(),
+            };
+            if !failover {
+                self.active.store(index, Ordering::Relaxed);
+                return res.map_err(From::from);
+            }
+            last = res.map_err(From::from);
+        }
+        last
+    }
+
+    pub async fn challenge(&self) -> Result<MemberChallenge, Error> {
+        let resp = self
+            .send(|pool_url| self.http_client.get(format!("{}/challenge", pool_url)))
+            .await?;
+        let challenge = resp.error_for_status()?.json().await?;
+        Ok(challenge)
+    }
+
+    /// follows the challenge stream,
+    /// calling f with each challenge until the pool closes the stream.
+    pub async fn follow_challenges(&self, mut f: impl FnMut(MemberChallenge)) -> Result<(), Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/challenge-stream", pool_url))
+            })
+            .await?;
+        let mut resp = resp.error_for_status()?;
+        // events are separated by a blank line
+        let mut buffer = String::new();
+        while let Some(chunk) = resp.chunk().await? {
+            buffer.push_str(String::from_utf8_lossy(&chunk).as_ref());
+            while let Some(index) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..index + 2).collect();
+                for line in event.lines() {
+                    if let Some(data) = line.strip_prefix("data: ") {
+                        f(serde_json::from_str(data)?);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn member(&self, authority: &Pubkey) -> Result<Member, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/member/{}", pool_url, authority))
+            })
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(Error::MemberNotRegistered);
+        }
+        let member = resp.error_for_status()?.json().await?;
+        Ok(member)
+    }
+
+    pub async fn workers(&self, authority: &Pubkey) -> Result<Vec<WorkerStats>, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/member/{}/workers", pool_url, authority))
+            })
+            .await?;
+        let workers = resp.error_for_status()?.json().await?;
+        Ok(workers)
+    }
+
+    /// the signed payout statements of the member in the most recent rounds
+    pub async fn payouts(&self, authority: &Pubkey) -> Result<Vec<SignedPayoutStatement>, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/member/{}/payouts", pool_url, authority))
+            })
+            .await?;
+        let payouts = resp.error_for_status()?.json().await?;
+        Ok(payouts)
+    }
+
+    pub async fn pool_address(&self) -> Result<PoolAddress, Error> {
+        let resp = self
+            .send(|pool_url| self.http_client.get(format!("{}/pool-address", pool_url)))
+            .await?;
+        let pool_address = resp.error_for_status()?.json().await?;
+        Ok(pool_address)
+    }
+
+    /// relays a signed claim through the pool server
+    pub async fn claim(&self, payload: &ClaimPayload) -> Result<ClaimReceipt, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .post(format!("{}/claim", pool_url))
+                    .json(payload)
+            })
+            .await?;
+        let receipt = resp.error_for_status()?.json().await?;
+        Ok(receipt)
+    }
+
+    /// the fees of claiming the amount with the pool operator paying the transaction fee,
+    /// errors if the pool doesn't offer it or the amount doesn't cover the fee
+    pub async fn claim_quote(&self, authority: &Pubkey, amount: u64) -> Result<ClaimQuote, Error> {
+        let query = ClaimQuoteQuery {
+            authority: authority.to_string(),
+            amount,
+        };
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/claim/quote", pool_url))
+                    .query(&query)
+            })
+            .await?;
+        let quote = resp.error_for_status()?.json().await?;
+        Ok(quote)
+    }
+
+    /// stores the signed notification preferences of the member
+    pub async fn notifications(
+        &self,
+        payload: &NotificationPrefsPayload,
+    ) -> Result<NotificationPrefs, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .post(format!("{}/notifications", pool_url))
+                    .json(payload)
+            })
+            .await?;
+        let prefs = resp.error_for_status()?.json().await?;
+        Ok(prefs)
+    }
+
+    /// the proof-of-work challenge to solve before registering
+    pub async fn register_challenge(&self, authority: &Pubkey) -> Result<RegisterChallenge, Error> {
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .get(format!("{}/register/challenge/{}", pool_url, authority))
+            })
+            .await?;
+        let challenge = resp.error_for_status()?.json().await?;
+        Ok(challenge)
+    }
+
+    /// registers the on-chain member account with the pool server db
+    pub async fn register(
+        &self,
+        authority: Pubkey,
+        solution: Option<Solution>,
+    ) -> Result<Member, Error> {
+        let payload = RegisterPayload {
+            authority,
+            solution,
+        };
+        let resp = self
+            .send(|pool_url| {
+                self.http_client
+                    .post(format!("{}/register", pool_url))
+                    .json(&payload)
+            })
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(Error::MemberNotRegistered);
+        }
+        let member = resp.error_for_status()?.json().await?;
+        Ok(member)
+    }
+
+    /// retries if the pool is busy and asks to retry after a while
+    pub async fn contribute(&self, payload: &ContributePayload) -> Result<(), Error> {
+        let mut retries = 0;
+        loop {
+            let resp = self
+                .send(|pool_url| {
+                    self.http_client
+                        .post(format!("{}/contribute", pool_url))
+                        .json(payload)
+                })
+                .await?;
+            let retry_after = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            match (resp.status(), retry_after) {
+                (StatusCode::SERVICE_UNAVAILABLE, Some(seconds))
+                    if retries < MAX_CONTRIBUTE_RETRIES =>
+                {
+                    retries += 1;
+                    let seconds = seconds.min(MAX_RETRY_AFTER_SECONDS);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(seconds)).await;
+                }
+                _ => {
+                    resp.error_for_status()?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// whether the instance can't serve the request and another may,
+/// a standby or a failing instance.
+/// a pool that is busy asks to retry after a while instead.
+fn is_unavailable(resp: &Response) -> bool {
+    let status = resp.status();
+    status.is_server_error()
+        && !(status == StatusCode::SERVICE_UNAVAILABLE
+            && resp.headers().contains_key(reqwest::header::RETRY_AFTER))
+}
@@ -0,0 +1,428 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+use solana_sdk::signature::{EncodableKey, Keypair};
+
+use crate::error::Error;
+
+/// The config file location, relative to the home directory.
+const CONFIG_PATH: &str = ".config/ore-pool/member.toml";
+
+/// The keypair used if neither the config file nor the command line specify one.
+const DEFAULT_KEYPAIR_PATH: &str = "~/.config/solana/id.json";
+
+/// The worker name used if neither the config file nor the command line specify one.
+const DEFAULT_WORKER: &str = "default";
+
+/// The minutes the pool may be unreachable before mining solo, if an rpc url is set.
+const DEFAULT_SOLO_AFTER: u64 = 5;
+
+/// The member config file.
+///
+/// Top-level settings apply to every profile.
+/// Named profiles override them field by field,
+/// so one machine can mine to multiple pools or with multiple authorities.
+///
+/// ```toml
+/// default_profile = "main"
+/// threads = 8
+///
+/// [profiles.main]
+/// pool_url = "https://pool.example.com"
+/// keypair_path = "~/.config/solana/id.json"
+/// # instances of the same pool to fail over to, in order
+/// mirror_urls = ["https://pool-2.example.com"]
+///
+/// [profiles.backup]
+/// pool_url = "https://pool.other.com"
+/// keypair_path = "~/.config/solana/other.json"
+/// worker = "rig-2"
+///
+/// # mine solo with the member's own proof if the pool is down for 10 minutes
+/// rpc_url = "https://api.mainnet-beta.solana.com"
+/// solo_after = 10
+///
+/// # with auto-claim, sweep the balance weekly once it reaches 1 ORE
+/// claim_threshold = 100000000000
+///
+/// # discover pools with `pools list`
+/// directory_url = "https://directory.example.com"
+///
+/// # run several named workers from one invocation
+/// [profiles.farm]
+/// pool_url = "https://pool.example.com"
+/// workers = [{ name = "gpu-a", threads = 4 }, { name = "gpu-b" }]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct ConfigFile {
+    /// The profile used if none is passed on the command line.
+    pub default_profile: Option<String>,
+
+    /// The settings shared by all profiles.
+    #[serde(flatten)]
+    pub base: Profile,
+
+    /// The named profiles.
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// A set of member settings, any of which may be left unset.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Profile {
+    /// The url of the pool server.
+    pub pool_url: Option<String>,
+
+    /// The urls of other instances of the same pool, failed over to in order.
+    pub mirror_urls: Option<Vec<String>>,
+
+    /// The path to the member authority keypair.
+    pub keypair_path: Option<String>,
+
+    /// The number of threads to mine with.
+    pub threads: Option<u64>,
+
+    /// The name of this machine as reported to the pool.
+    pub worker: Option<String>,
+
+    /// The named workers to run, instead of the single worker above.
+    pub workers: Option<Vec<WorkerProfile>>,
+
+    /// The solana rpc url used to mine solo while the pool is unreachable.
+    pub rpc_url: Option<String>,
+
+    /// The minutes the pool may be unreachable before mining solo.
+    pub solo_after: Option<u64>,
+
+    /// The min balance auto-claim sweeps, in grains.
+    pub claim_threshold: Option<u64>,
+
+    /// The url of the pool directory to discover pools from.
+    pub directory_url: Option<String>,
+}
+
+/// A named worker in the config file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WorkerProfile {
+    /// The name of the worker as reported to the pool.
+    pub name: String,
+
+    /// The number of threads to mine with,
+    /// defaults to the threads of the profile.
+    pub threads: Option<u64>,
+}
+
+/// The parsed command line.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// The positional arguments, e.g. `mine`.
+    pub command: Vec<String>,
+
+    /// Overrides the default config file location.
+    pub config_path: Option<String>,
+
+    /// The named profile to load from the config file.
+    pub profile: Option<String>,
+
+    /// Settings passed as flags, which take precedence over the config file.
+    pub overrides: Profile,
+
+    /// The number of seconds to run the benchmark for.
+    pub duration: Option<u64>,
+
+    /// The address the bridge listens on.
+    pub listen: Option<String>,
+}
+
+/// The resolved member settings.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The name of the profile these settings were loaded from.
+    pub profile: Option<String>,
+
+    /// The url of the pool server.
+    pub pool_url: String,
+
+    /// The urls of other instances of the same pool, failed over to in order.
+    pub mirror_urls: Vec<String>,
+
+    /// The path to the member authority keypair.
+    pub keypair_path: PathBuf,
+
+    /// The number of threads to mine with.
+    pub threads: u64,
+
+    /// The name of this machine as reported to the pool.
+    pub worker: String,
+
+    /// The named workers to run.
+    pub workers: Vec<Worker>,
+
+    /// The solana rpc url used to mine solo while the pool is unreachable,
+    /// solo mining is disabled if unset.
+    pub rpc_url: Option<String>,
+
+    /// The minutes the pool may be unreachable before mining solo.
+    pub solo_after: u64,
+
+    /// The min balance auto-claim sweeps, in grains,
+    /// defaults to the pool min claim.
+    pub claim_threshold: Option<u64>,
+}
+
+/// A resolved named worker.
+#[derive(Clone, Debug)]
+pub struct Worker {
+    /// The name of the worker as reported to the pool.
+    pub name: String,
+
+    /// The number of threads to mine with.
+    pub threads: u64,
+}
+
+impl Profile {
+    /// Returns self with every field that is set in other replaced.
+    fn merge(self, other: &Profile) -> Profile {
+        Profile {
+            pool_url: other.pool_url.clone().or(self.pool_url),
+            mirror_urls: other.mirror_urls.clone().or(self.mirror_urls),
+            keypair_path: other.keypair_path.clone().or(self.keypair_path),
+            threads: other.threads.or(self.threads),
+            worker: other.worker.clone().or(self.worker),
+            workers: other.workers.clone().or(self.workers),
+            rpc_url: other.rpc_url.clone().or(self.rpc_url),
+            solo_after: other.solo_after.or(self.solo_after),
+            claim_threshold: other.claim_threshold.or(self.claim_threshold),
+            directory_url: other.directory_url.clone().or(self.directory_url),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Reads the config file at the provided path,
+    /// or the default location if none is provided.
+    /// A missing file at the default location is treated as empty.
+    pub fn read(path: Option<&str>) -> Result<Self, Error> {
+        let (path, required) = match path {
+            Some(path) => (expand_home(path), true),
+            None => match home_dir() {
+                Some(home) => (home.join(CONFIG_PATH), false),
+                None => return Ok(Self::default()),
+            },
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(string) => Self::parse(string.as_str()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {
+                Ok(Self::default())
+            }
+            Err(_) => Err(Error::ConfigRead(path.display().to_string())),
+        }
+    }
+
+    pub fn parse(string: &str) -> Result<Self, Error> {
+        toml::from_str(string).map_err(From::from)
+    }
+
+    /// Resolves the settings of the named profile on top of the shared settings.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Profile, Error> {
+        let base = self.base.clone();
+        match profile {
+            Some(name) => {
+                let profile = self
+                    .profiles
+                    .get(name)
+                    .ok_or(Error::MissingProfile(name.to_string()))?;
+                Ok(base.merge(profile))
+            }
+            None => Ok(base),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Error> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                parsed.command.push(arg);
+                continue;
+            }
+            let value = args.next().ok_or(Error::MissingArgument(arg.clone()))?;
+            let overrides = &mut parsed.overrides;
+            match arg.as_str() {
+                "--config" => parsed.config_path = Some(value),
+                "--profile" => parsed.profile = Some(value),
+                "--pool-url" => overrides.pool_url = Some(value),
+                "--mirror-url" => overrides
+                    .mirror_urls
+                    .get_or_insert_with(Vec::new)
+                    .push(value),
+                "--keypair" => overrides.keypair_path = Some(value),
+                "--threads" => overrides.threads = Some(value.parse()?),
+                "--worker" => overrides.worker = Some(value),
+                "--rpc-url" => overrides.rpc_url = Some(value),
+                "--solo-after" => overrides.solo_after = Some(value.parse()?),
+                "--claim-threshold" => overrides.claim_threshold = Some(value.parse()?),
+                "--directory-url" => overrides.directory_url = Some(value),
+                "--duration" => parsed.duration = Some(value.parse()?),
+                "--listen" => parsed.listen = Some(value),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl Config {
+    pub fn load(args: &Args) -> Result<Self, Error> {
+        let file = ConfigFile::read(args.config_path.as_deref())?;
+        let profile = args.profile.clone().or(file.default_profile.clone());
+        let settings = file.resolve(profile.as_deref())?.merge(&args.overrides);
+        let pool_url = settings.pool_url.ok_or(Error::MissingPoolUrl)?;
+        let keypair_path = settings
+            .keypair_path
+            .unwrap_or(DEFAULT_KEYPAIR_PATH.to_string());
+        let keypair_path = expand_home(keypair_path.as_str());
+        let threads = settings.threads.unwrap_or_else(default_threads);
+        let worker = settings.worker.unwrap_or(DEFAULT_WORKER.to_string());
+        let workers = match settings.workers {
+            Some(workers) => workers
+                .into_iter()
+                .map(|w| Worker {
+                    name: w.name,
+                    threads: w.threads.unwrap_or(threads),
+                })
+                .collect(),
+            None => vec![Worker {
+                name: worker.clone(),
+                threads,
+            }],
+        };
+        Ok(Config {
+            profile,
+            pool_url,
+            mirror_urls: settings.mirror_urls.unwrap_or_default(),
+            keypair_path,
+            threads,
+            worker,
+            workers,
+            rpc_url: settings.rpc_url,
+            solo_after: settings.solo_after.unwrap_or(DEFAULT_SOLO_AFTER),
+            claim_threshold: settings.claim_threshold,
+        })
+    }
+
+    /// The pool url followed by its mirrors, in failover order.
+    pub fn pool_urls(&self) -> Vec<String> {
+        std::iter::once(self.pool_url.clone())
+            .chain(self.mirror_urls.iter().cloned())
+            .collect()
+    }
+
+    /// The number of threads across all workers.
+    pub fn total_threads(&self) -> u64 {
+        self.workers.iter().map(|w| w.threads.max(1)).sum()
+    }
+
+    /// Reads the member authority keypair,
+    /// which not every command requires.
+    pub fn keypair(&self) -> Result<Keypair, Error> {
+        let keypair_path = &self.keypair_path;
+        Keypair::read_from_file(keypair_path)
+            .map_err(|_| Error::KeypairRead(keypair_path.display().to_string()))
+    }
+}
+
+/// Resolves the pool directory url alone,
+/// as discovering pools doesn't need the rest of the settings.
+pub fn directory_url(args: &Args) -> Result<String, Error> {
+    let file = ConfigFile::read(args.config_path.as_deref())?;
+    let profile = args.profile.clone().or(file.default_profile.clone());
+    let settings = file.resolve(profile.as_deref())?.merge(&args.overrides);
+    settings.directory_url.ok_or(Error::MissingDirectoryUrl)
+}
+
+fn default_threads() -> u64 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u64)
+        .unwrap_or(1)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var("HOME").ok().map(PathBuf::from)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+        default_profile = "main"
+        threads = 4
+        worker = "rig-1"
+
+        [profiles.main]
+        pool_url = "https://pool.example.com"
+
+        [profiles.backup]
+        pool_url = "https://pool.other.com"
+        worker = "rig-2"
+
+        [profiles.farm]
+        workers = [{ name = "gpu-a", threads = 2 }, { name = "gpu-b" }]
+    "#;
+
+    #[test]
+    fn profile_overrides_base() {
+        let file = ConfigFile::parse(FILE).unwrap();
+        assert_eq!(file.default_profile.as_deref(), Some("main"));
+        let backup = file.resolve(Some("backup")).unwrap();
+        assert_eq!(backup.pool_url.as_deref(), Some("https://pool.other.com"));
+        assert_eq!(backup.worker.as_deref(), Some("rig-2"));
+        assert_eq!(backup.threads, Some(4));
+        assert!(file.resolve(Some("missing")).is_err());
+        let farm = file.resolve(Some("farm")).unwrap();
+        let workers = farm.workers.unwrap();
+        assert_eq!(workers.len(), 2);
+        assert_eq!(workers[0].threads, Some(2));
+        assert_eq!(workers[1].name, "gpu-b");
+    }
+
+    #[test]
+    fn flags_override_profile() {
+        let args = ["mine", "--profile", "main", "--threads", "16"]
+            .into_iter()
+            .map(String::from);
+        let args = Args::parse(args).unwrap();
+        assert_eq!(args.command, vec!["mine".to_string()]);
+        let file = ConfigFile::parse(FILE).unwrap();
+        let settings = file
+            .resolve(args.profile.as_deref())
+            .unwrap()
+            .merge(&args.overrides);
+        assert_eq!(settings.threads, Some(16));
+        assert_eq!(settings.worker.as_deref(), Some("rig-1"));
+        assert!(Args::parse(["--threads".to_string()].into_iter()).is_err());
+        let args = [
+            "mine",
+            "--mirror-url",
+            "https://a",
+            "--mirror-url",
+            "https://b",
+        ]
+        .into_iter()
+        .map(String::from);
+        let args = Args::parse(args).unwrap();
+        assert_eq!(
+            args.overrides.mirror_urls,
+            Some(vec!["https://a".to_string(), "https://b".to_string()])
+        );
+    }
+}
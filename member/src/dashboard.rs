@@ -0,0 +1,275 @@
+use std::{
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ore_api::consts::TOKEN_DECIMALS;
+use ore_pool_types::{hex, PayoutStatement};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode},
+    layout::{Constraint, Layout},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame,
+};
+use solana_sdk::signer::Signer;
+
+use crate::{
+    client::Client,
+    config::Config,
+    error::Error,
+    mine::{self, Stats},
+};
+
+/// How often the dashboard redraws.
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// How often the earnings estimate is refreshed from the recent payouts.
+const ESTIMATE_REFRESH: Duration = Duration::from_secs(60);
+
+/// The number of recent payouts the earnings estimate is averaged over.
+const ESTIMATE_ROUNDS: usize = 20;
+
+/// Mines to the pool while drawing a terminal dashboard
+/// of the current challenge, hashrate, shares, estimated earnings, and balance.
+/// Press `q` to quit.
+pub async fn dashboard(config: &Config) -> Result<(), Error> {
+    let stats = Arc::new(Stats::new(config.total_threads(), false));
+    let authority = config.keypair()?.pubkey();
+    let estimate = Arc::new(Mutex::new(None));
+    // mine
+    let miner = tokio::task::spawn({
+        let config = config.clone();
+        let stats = stats.clone();
+        async move {
+            if let Err(err) = mine::run(&config, stats.clone()).await {
+                stats.log(format!("{:?}", err));
+            }
+        }
+    });
+    // follow the challenge stream
+    let follower = tokio::task::spawn({
+        let client = Client::new(config.pool_urls());
+        let stats = stats.clone();
+        async move {
+            loop {
+                if let Err(err) = client.follow_challenges(|c| stats.set_challenge(c)).await {
+                    stats.log(format!("{:?}", err));
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+    // estimate the earnings from the recent payouts
+    let estimator = tokio::task::spawn({
+        let client = Client::new(config.pool_urls());
+        let stats = stats.clone();
+        let estimate = estimate.clone();
+        async move {
+            loop {
+                match client.payouts(&authority).await {
+                    Ok(payouts) => {
+                        let statements: Vec<PayoutStatement> =
+                            payouts.into_iter().map(|signed| signed.statement).collect();
+                        if let Ok(mut estimate) = estimate.lock() {
+                            *estimate = estimate_hourly(statements.as_slice());
+                        }
+                    }
+                    Err(err) => stats.log(format!("{:?}", err)),
+                }
+                tokio::time::sleep(ESTIMATE_REFRESH).await;
+            }
+        }
+    });
+    // draw until quit
+    let res = tokio::task::spawn_blocking(move || draw(&stats, &estimate)).await?;
+    miner.abort();
+    follower.abort();
+    estimator.abort();
+    res
+}
+
+fn draw(stats: &Stats, estimate: &Mutex<Option<f64>>) -> Result<(), Error> {
+    let mut terminal = ratatui::init();
+    let mut last_counts = counts(stats);
+    let mut last_tick = Instant::now();
+    let mut hashrates = vec![0f64; last_counts.len()];
+    let res = loop {
+        let hourly = estimate.lock().ok().and_then(|estimate| *estimate);
+        if let Err(err) = terminal.draw(|frame| render(frame, stats, &hashrates, hourly)) {
+            break Err(err.into());
+        }
+        match event::poll(REFRESH) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.code == KeyCode::Char('q') => break Ok(()),
+                Ok(_) => {}
+                Err(err) => break Err(err.into()),
+            },
+            Ok(false) => {}
+            Err(err) => break Err(err.into()),
+        }
+        // recompute per-thread hashrate since the last tick
+        let elapsed = last_tick.elapsed().as_secs_f64();
+        if elapsed >= REFRESH.as_secs_f64() {
+            let counts = counts(stats);
+            hashrates = counts
+                .iter()
+                .zip(last_counts.iter())
+                .map(|(now, before)| now.saturating_sub(*before) as f64 / elapsed)
+                .collect();
+            last_counts = counts;
+            last_tick = Instant::now();
+        }
+    };
+    ratatui::restore();
+    res
+}
+
+fn render(frame: &mut Frame, stats: &Stats, hashrates: &[f64], hourly: Option<f64>) {
+    let [pool_area, threads_area, shares_area, logs_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Length(hashrates.len() as u16 + 4),
+        Constraint::Length(7),
+        Constraint::Min(3),
+    ])
+    .areas(frame.area());
+    // challenge
+    let challenge = stats.challenge.lock().ok().and_then(|c| *c);
+    let pool_lines = match challenge {
+        Some(c) => format!(
+            "challenge: {}\nmin difficulty: {}\ncutoff: {}s\nmembers: {}",
+            hex::encode(&c.challenge.challenge),
+            c.challenge.min_difficulty,
+            c.challenge.cutoff_time,
+            c.num_total_members,
+        ),
+        None => "waiting for challenge".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(pool_lines).block(Block::default().title("pool").borders(Borders::ALL)),
+        pool_area,
+    );
+    // hashrate per thread
+    let total: f64 = hashrates.iter().sum();
+    let rows = hashrates
+        .iter()
+        .enumerate()
+        .map(|(i, h)| Row::new(vec![format!("{}", i), format!("{:.2} H/s", h)]));
+    let table = Table::new(rows, [Constraint::Length(8), Constraint::Min(12)])
+        .header(Row::new(vec!["thread", "hashrate"]))
+        .block(
+            Block::default()
+                .title(format!("hashrate {:.2} H/s", total))
+                .borders(Borders::ALL),
+        );
+    frame.render_widget(table, threads_area);
+    // shares and balance
+    let total_balance = stats
+        .member
+        .lock()
+        .ok()
+        .and_then(|m| m.as_ref().map(|m| m.total_balance as u64))
+        .unwrap_or(0);
+    let estimate = match hourly {
+        Some(hourly) => format!(
+            "{:.6} ORE/h, {:.6} ORE/day",
+            hourly / 10f64.powi(TOKEN_DECIMALS as i32),
+            hourly * 24.0 / 10f64.powi(TOKEN_DECIMALS as i32),
+        ),
+        None => "waiting for payouts".to_string(),
+    };
+    let shares_lines = format!(
+        "accepted: {}\nrejected: {}\nestimated earnings: {}\nlast round reward: {} ORE\ntotal balance: {} ORE",
+        stats.accepted.load(Ordering::Relaxed),
+        stats.rejected.load(Ordering::Relaxed),
+        estimate,
+        amount_to_ui(stats.last_reward.load(Ordering::Relaxed)),
+        amount_to_ui(total_balance),
+    );
+    frame.render_widget(
+        Paragraph::new(shares_lines).block(Block::default().title("shares").borders(Borders::ALL)),
+        shares_area,
+    );
+    // most recent logs that fit
+    let logs = stats.logs();
+    let height = logs_area.height.saturating_sub(2) as usize;
+    let logs = logs[logs.len().saturating_sub(height)..].join("\n");
+    frame.render_widget(
+        Paragraph::new(logs).block(
+            Block::default()
+                .title("logs (q to quit)")
+                .borders(Borders::ALL),
+        ),
+        logs_area,
+    );
+}
+
+fn counts(stats: &Stats) -> Vec<u64> {
+    stats
+        .hashes
+        .iter()
+        .map(|h| h.load(Ordering::Relaxed))
+        .collect()
+}
+
+fn amount_to_ui(amount: u64) -> f64 {
+    amount as f64 / 10f64.powi(TOKEN_DECIMALS as i32)
+}
+
+/// The earnings per hour, in grains, estimated from the recent payouts of the member:
+/// its mean share of the rounds, times the miner rewards the pool pays per hour.
+/// None until two rounds were paid out.
+fn estimate_hourly(payouts: &[PayoutStatement]) -> Option<f64> {
+    // most recent first
+    let payouts = &payouts[..payouts.len().min(ESTIMATE_ROUNDS)];
+    let (newest, oldest) = (payouts.first()?, payouts.last()?);
+    let seconds = newest.distributed_at.checked_sub(oldest.distributed_at)?;
+    if seconds <= 0 {
+        return None;
+    }
+    let rounds = payouts.len() as f64;
+    let share = payouts.iter().map(|p| p.share / 100.0).sum::<f64>() / rounds;
+    let reward = payouts.iter().map(|p| p.total_amount as f64).sum::<f64>() / rounds;
+    let rounds_per_hour = (rounds - 1.0) * 3600.0 / seconds as f64;
+    Some(share * reward * rounds_per_hour)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{hash::Hash, pubkey::Pubkey};
+
+    use super::*;
+
+    fn payout(share: f64, total_amount: u64, distributed_at: i64) -> PayoutStatement {
+        PayoutStatement {
+            pool_address: Pubkey::new_unique(),
+            challenge: Hash::new_unique(),
+            last_hash_at: distributed_at,
+            member: Pubkey::new_unique(),
+            score: 0,
+            total_score: 0,
+            share,
+            amount: 0,
+            total_amount,
+            submission: None,
+            distributed_at,
+        }
+    }
+
+    #[test]
+    fn estimates_hourly_earnings() {
+        assert_eq!(estimate_hourly(&[]), None);
+        assert_eq!(estimate_hourly(&[payout(10.0, 1_000, 60)]), None);
+        // a 10% share of 1000 grains, one round a minute
+        let payouts = [
+            payout(10.0, 1_000, 180),
+            payout(10.0, 1_000, 120),
+            payout(10.0, 1_000, 60),
+        ];
+        let hourly = estimate_hourly(&payouts).unwrap();
+        assert!((hourly - 6_000.0).abs() < 1e-6);
+        // a larger share earns proportionally more
+        let payouts = [payout(20.0, 1_000, 120), payout(20.0, 1_000, 60)];
+        let hourly = estimate_hourly(&payouts).unwrap();
+        assert!((hourly - 12_000.0).abs() < 1e-6);
+    }
+}
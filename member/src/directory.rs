@@ -0,0 +1,42 @@
+use ore_pool_types::SignedPoolListing;
+
+use crate::{config, error::Error};
+
+/// The path listings are read from, relative to the directory url.
+const DIRECTORY_PATH: &str = "pools";
+
+/// Lists the pools published to the pool directory,
+/// skipping listings not signed by the authority of the pool.
+pub async fn list(args: &config::Args) -> Result<(), Error> {
+    let directory_url = config::directory_url(args)?;
+    let url = format!("{}/{}", directory_url.trim_end_matches('/'), DIRECTORY_PATH);
+    let resp = reqwest::get(url).await?;
+    let listings: Vec<SignedPoolListing> = resp.error_for_status()?.json().await?;
+    let total = listings.len();
+    let listings: Vec<SignedPoolListing> = listings.into_iter().filter(is_valid).collect();
+    println!(
+        "{:<48} {:>8} {:>8} {:>10} {:>14}",
+        "url", "op fee", "stk fee", "members", "hashrate"
+    );
+    for signed in listings.iter() {
+        let listing = &signed.listing;
+        println!(
+            "{:<48} {:>7}% {:>7}% {:>10} {:>10} H/s",
+            listing.url,
+            listing.operator_commission,
+            listing.staker_commission,
+            listing.num_members,
+            listing.hashrate
+        );
+    }
+    if listings.len() < total {
+        println!("skipped {} unverified listings", total - listings.len());
+    }
+    Ok(())
+}
+
+/// Whether the listing is signed by the authority its pool is derived from.
+fn is_valid(signed: &SignedPoolListing) -> bool {
+    let (pool_address, _) = ore_pool_api::state::pool_pda(signed.authority);
+    signed.listing.pool_address == pool_address && signed.verify()
+}
@@ -0,0 +1,45 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("bincode")]
+    Bincode(#[from] bincode::Error),
+    #[error("std io")]
+    StdIO(#[from] std::io::Error),
+    #[error("std parse int")]
+    StdParseInt(#[from] std::num::ParseIntError),
+    #[error("toml")]
+    Toml(#[from] toml::de::Error),
+    #[error("reqwest")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("serde json")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("solana client")]
+    SolanaClient(#[from] solana_client::client_error::ClientError),
+    #[error("solana program")]
+    SolanaProgram(#[from] solana_sdk::program_error::ProgramError),
+    #[error("solana signer")]
+    SolanaSigner(#[from] solana_sdk::signer::SignerError),
+    #[error("tokio join")]
+    TokioJoin(#[from] tokio::task::JoinError),
+    #[error("could not read keypair from provided path: {0}")]
+    KeypairRead(String),
+    #[error("could not read config file: {0}")]
+    ConfigRead(String),
+    #[error("missing profile in config file: {0}")]
+    MissingProfile(String),
+    #[error("missing pool url")]
+    MissingPoolUrl,
+    #[error("missing rpc url, required to claim")]
+    MissingRpcUrl,
+    #[error("missing pool directory url")]
+    MissingDirectoryUrl,
+    #[error("missing value for argument: {0}")]
+    MissingArgument(String),
+    #[error("unexpected argument: {0}")]
+    UnexpectedArgument(String),
+    #[error("member is not registered with the pool, join on-chain first")]
+    MemberNotRegistered,
+    #[error("payout statement of round {0} is not signed by the pool authority")]
+    InvalidPayoutStatement(String),
+    #[error("invalid command")]
+    InvalidCommand,
+}
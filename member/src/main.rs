@@ -0,0 +1,91 @@
+use ore_pool_types::NotificationChannel;
+use solana_sdk::signer::Signer;
+
+mod benchmark;
+mod bridge;
+mod claim;
+mod client;
+mod config;
+mod dashboard;
+mod directory;
+mod error;
+mod mine;
+mod notify;
+mod payouts;
+mod solo;
+
+#[tokio::main]
+async fn main() -> Result<(), error::Error> {
+    // parse resources
+    let args = config::Args::parse(std::env::args().skip(1))?;
+    let command: Vec<&str> = args.command.iter().map(String::as_str).collect();
+    // discovering pools doesn't need a pool url
+    if let ["pools", "list"] = command.as_slice() {
+        return directory::list(&args).await;
+    }
+    let config = config::Config::load(&args)?;
+    // run
+    match command.as_slice() {
+        ["mine"] => mine::mine(&config).await,
+        ["benchmark"] => benchmark::benchmark(&config, args.duration).await,
+        ["bridge"] => bridge::bridge(&config, args.listen.clone()).await,
+        ["dashboard"] => dashboard::dashboard(&config).await,
+        ["claim"] => claim::claim(&config).await,
+        ["auto-claim"] => claim::auto_claim(&config).await,
+        ["notify", "none"] => notify::notify(&config, NotificationChannel::None, None).await,
+        ["notify", "email", email] => {
+            notify::notify(&config, NotificationChannel::Email, Some(*email)).await
+        }
+        ["notify", "webhook", url] => {
+            notify::notify(&config, NotificationChannel::Webhook, Some(*url)).await
+        }
+        ["config"] => print_config(&config),
+        ["workers"] => print_workers(&config).await,
+        ["payouts"] => payouts::export(&config).await,
+        _ => Err(error::Error::InvalidCommand),
+    }
+}
+
+fn print_config(config: &config::Config) -> Result<(), error::Error> {
+    println!("profile: {}", config.profile.as_deref().unwrap_or("none"));
+    println!("pool url: {}", config.pool_url);
+    for mirror_url in config.mirror_urls.iter() {
+        println!("  mirror: {}", mirror_url);
+    }
+    println!("keypair path: {}", config.keypair_path.display());
+    println!("authority: {}", config.keypair()?.pubkey());
+    println!("threads: {}", config.threads);
+    println!("worker: {}", config.worker);
+    match config.rpc_url.as_ref() {
+        Some(rpc_url) => println!(
+            "solo fallback: {} after {} minutes",
+            rpc_url, config.solo_after
+        ),
+        None => println!("solo fallback: disabled"),
+    }
+    match config.claim_threshold {
+        Some(claim_threshold) => println!("auto-claim threshold: {} grains", claim_threshold),
+        None => println!("auto-claim threshold: pool min"),
+    }
+    for worker in config.workers.iter() {
+        println!("  {} ({} threads)", worker.name, worker.threads);
+    }
+    Ok(())
+}
+
+async fn print_workers(config: &config::Config) -> Result<(), error::Error> {
+    let client = client::Client::new(config.pool_urls());
+    let authority = config.keypair()?.pubkey();
+    let workers = client.workers(&authority).await?;
+    println!(
+        "{:<32} {:>10} {:>14} {:>12} {:>14}",
+        "worker", "shares", "total score", "last share", "hashrate"
+    );
+    for worker in workers {
+        println!(
+            "{:<32} {:>10} {:>14} {:>12} {:>12} H/s",
+            worker.name, worker.shares, worker.total_score, worker.last_share_at, worker.hashrate
+        );
+    }
+    Ok(())
+}
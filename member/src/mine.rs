@@ -0,0 +1,459 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use drillx::{equix::SolverMemory, Solution};
+use ore_pool_types::{ContributePayload, Member, MemberChallenge};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{client::Client, config::Config, error::Error, solo::Solo};
+
+/// The number of log lines kept for the dashboard.
+const MAX_LOG_LINES: usize = 100;
+
+/// The result of hashing across all threads until a deadline.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Best {
+    /// The solution with the highest difficulty and its difficulty, if any hash was found.
+    pub solution: Option<(Solution, u32)>,
+
+    /// The number of hashes computed.
+    pub hashes: u64,
+}
+
+/// Live mining stats, shared with the dashboard.
+pub struct Stats {
+    /// The number of hashes computed per thread.
+    pub hashes: Vec<AtomicU64>,
+
+    /// The number of contributions accepted by the pool.
+    pub accepted: AtomicU64,
+
+    /// The number of contributions rejected by the pool.
+    pub rejected: AtomicU64,
+
+    /// The latest challenge received from the pool.
+    pub challenge: Mutex<Option<MemberChallenge>>,
+
+    /// The latest member record received from the pool.
+    pub member: Mutex<Option<Member>>,
+
+    /// The balance attributed to the member for the last round.
+    pub last_reward: AtomicU64,
+
+    /// Whether every thread is lent to solo mining while the pool is unreachable.
+    solo: AtomicBool,
+
+    /// The number of workers hashing a pool round.
+    pool_rounds: AtomicUsize,
+
+    /// Whether log lines are printed, or kept for the dashboard.
+    print: bool,
+
+    /// The most recent log lines, if not printed.
+    logs: Mutex<VecDeque<String>>,
+}
+
+impl Best {
+    fn merge(self, other: Best) -> Best {
+        let solution = match (self.solution, other.solution) {
+            (Some(a), Some(b)) => Some(if b.1 > a.1 { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        Best {
+            solution,
+            hashes: self.hashes + other.hashes,
+        }
+    }
+}
+
+impl Stats {
+    pub fn new(threads: u64, print: bool) -> Self {
+        Self {
+            hashes: (0..threads.max(1)).map(|_| AtomicU64::new(0)).collect(),
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            challenge: Mutex::new(None),
+            member: Mutex::new(None),
+            last_reward: AtomicU64::new(0),
+            solo: AtomicBool::new(false),
+            pool_rounds: AtomicUsize::new(0),
+            print,
+            logs: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn log(&self, line: String) {
+        if self.print {
+            println!("{}", line);
+            return;
+        }
+        if let Ok(mut logs) = self.logs.lock() {
+            if logs.len() == MAX_LOG_LINES {
+                logs.pop_front();
+            }
+            logs.push_back(line);
+        }
+    }
+
+    pub fn logs(&self) -> Vec<String> {
+        self.logs
+            .lock()
+            .map(|logs| logs.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set_challenge(&self, member_challenge: MemberChallenge) {
+        if let Ok(mut challenge) = self.challenge.lock() {
+            *challenge = Some(member_challenge);
+        }
+    }
+
+    /// Claims the threads of a worker for a pool round,
+    /// false while every thread is lent to solo mining.
+    fn start_pool_round(&self) -> bool {
+        self.pool_rounds.fetch_add(1, Ordering::SeqCst);
+        if self.solo.load(Ordering::SeqCst) {
+            self.pool_rounds.fetch_sub(1, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    fn end_pool_round(&self) {
+        self.pool_rounds.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Lends every thread to solo mining, once the pool rounds in progress end,
+    /// so solo and pool mining never hash on the same threads.
+    async fn start_solo(&self) {
+        self.solo.store(true, Ordering::SeqCst);
+        while self.pool_rounds.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    fn end_solo(&self) {
+        self.solo.store(false, Ordering::SeqCst);
+    }
+
+    /// records the latest member record,
+    /// and the balance attributed since the previous one.
+    fn set_member(&self, new: Member) {
+        if let Ok(mut member) = self.member.lock() {
+            if let Some(old) = member.as_ref() {
+                if new.total_balance > old.total_balance {
+                    let reward = (new.total_balance - old.total_balance) as u64;
+                    self.last_reward.store(reward, Ordering::Relaxed);
+                }
+            }
+            *member = Some(new);
+        }
+    }
+}
+
+pub async fn mine(config: &Config) -> Result<(), Error> {
+    let stats = Arc::new(Stats::new(config.total_threads(), true));
+    run(config, stats).await
+}
+
+/// Mines to the pool with every configured worker, reporting progress into the stats.
+pub async fn run(config: &Config, stats: Arc<Stats>) -> Result<(), Error> {
+    let client = Arc::new(Client::new(config.pool_urls()));
+    let signer = Arc::new(config.keypair()?);
+    let member = get_or_register(&client, signer.pubkey()).await?;
+    stats.log(format!("member: {:?}", member));
+    let member_id = member.id as u64;
+    stats.set_member(member);
+    // the first worker falls back to solo mining with every thread,
+    // while the other workers pause their pool rounds
+    let mut solo = config.rpc_url.clone().map(|rpc_url| Fallback {
+        solo: Solo::new(rpc_url, signer.clone()),
+        after: Duration::from_secs(config.solo_after * 60),
+        counters: 0..stats.hashes.len(),
+    });
+    // one task per worker, each hashing with its own slice of the thread counters
+    let mut handles = vec![];
+    let mut offset = 0;
+    for (i, worker) in config.workers.iter().enumerate() {
+        let threads = worker.threads.max(1) as usize;
+        stats.log(format!("worker: {}, threads: {}", worker.name, threads));
+        let worker = Runner {
+            client: client.clone(),
+            signer: signer.clone(),
+            member_id,
+            name: worker.name.clone(),
+            counters: offset..offset + threads,
+            // only the first worker refreshes the member record
+            refresh_member: i == 0,
+            fallback: solo.take(),
+        };
+        offset += threads;
+        handles.push(tokio::task::spawn(worker.run(stats.clone())));
+    }
+    for handle in handles {
+        handle.await??;
+    }
+    Ok(())
+}
+
+/// A named worker mining to the pool.
+struct Runner {
+    client: Arc<Client>,
+    signer: Arc<Keypair>,
+    member_id: u64,
+    name: String,
+    counters: Range<usize>,
+    refresh_member: bool,
+    fallback: Option<Fallback>,
+}
+
+/// Solo mining while the pool is unreachable.
+struct Fallback {
+    solo: Solo,
+    after: Duration,
+    counters: Range<usize>,
+}
+
+impl Runner {
+    async fn run(self, stats: Arc<Stats>) -> Result<(), Error> {
+        let authority = self.signer.pubkey();
+        let mut last_challenge = [0; 32];
+        let mut pool_url = self.client.pool_url().to_string();
+        let mut unreachable_since: Option<Instant> = None;
+        loop {
+            // wait for the next challenge
+            let member_challenge = match self.client.challenge().await {
+                Ok(member_challenge) => {
+                    unreachable_since = None;
+                    member_challenge
+                }
+                Err(err) => {
+                    stats.log(format!("{}: {:?}", self.name, err));
+                    let since = *unreachable_since.get_or_insert_with(Instant::now);
+                    if let Some(fallback) = self.fallback.as_ref() {
+                        if since.elapsed() >= fallback.after {
+                            let counters = fallback.counters.clone();
+                            stats.start_solo().await;
+                            if let Err(err) = fallback
+                                .solo
+                                .run_until_pool_recovers(&self.client, &stats, counters)
+                                .await
+                            {
+                                stats.log(format!("solo: {:?}", err));
+                            }
+                            stats.end_solo();
+                            unreachable_since = None;
+                            continue;
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            // the instance failed over to drops the contributions of the round
+            // it takes over, so resync and mine the current challenge again
+            if self.client.pool_url() != pool_url {
+                pool_url = self.client.pool_url().to_string();
+                stats.log(format!("{}: failed over to {}", self.name, pool_url));
+                last_challenge = [0; 32];
+            }
+            let challenge = member_challenge.challenge;
+            if challenge.challenge == last_challenge {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            // the threads of every worker are lent to solo mining
+            if !stats.start_pool_round() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            last_challenge = challenge.challenge;
+            stats.set_challenge(member_challenge);
+            // refresh balance attributed in the previous round
+            if self.refresh_member {
+                match self.client.member(&authority).await {
+                    Ok(member) => stats.set_member(member),
+                    Err(err) => stats.log(format!("{:?}", err)),
+                }
+            }
+            // mine until the submission window opens
+            let seconds = challenge
+                .cutoff_time
+                .saturating_sub(member_challenge.buffer);
+            let deadline = Instant::now() + Duration::from_secs(seconds);
+            let nonces = worker_nonces(
+                nonce_range(self.member_id, member_challenge.num_total_members),
+                self.name.as_str(),
+            );
+            let best = tokio::task::spawn_blocking({
+                let stats = stats.clone();
+                let counters = self.counters.clone();
+                move || {
+                    find_best(
+                        challenge.challenge,
+                        nonces,
+                        &stats.hashes[counters],
+                        deadline,
+                    )
+                }
+            })
+            .await;
+            stats.end_pool_round();
+            let best = best?;
+            stats.log(format!("{}: hashes: {}", self.name, best.hashes));
+            let Some((solution, difficulty)) = best.solution else {
+                stats.log(format!("{}: no solution found", self.name));
+                continue;
+            };
+            stats.log(format!("{}: best difficulty: {}", self.name, difficulty));
+            if difficulty < challenge.min_difficulty as u32 {
+                stats.log(format!(
+                    "{}: best difficulty below pool min difficulty",
+                    self.name
+                ));
+                continue;
+            }
+            // sign and contribute
+            let signature = self.signer.sign_message(&solution.to_bytes());
+            let payload = ContributePayload {
+                authority,
+                solution,
+                signature,
+                worker: Some(self.name.clone()),
+            };
+            match self.client.contribute(&payload).await {
+                Ok(()) => {
+                    stats.accepted.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) => {
+                    stats.rejected.fetch_add(1, Ordering::Relaxed);
+                    stats.log(format!("{}: {:?}", self.name, err));
+                }
+            }
+        }
+    }
+}
+
+pub async fn get_or_register(client: &Client, authority: Pubkey) -> Result<Member, Error> {
+    match client.member(&authority).await {
+        Err(Error::MemberNotRegistered) => {
+            // solve the registration proof of work first, if the pool requires one
+            let challenge = client.register_challenge(&authority).await?;
+            let solution = match challenge.min_difficulty {
+                0 => None,
+                min_difficulty => Some(
+                    tokio::task::spawn_blocking(move || {
+                        solve(&challenge.challenge, min_difficulty as u32)
+                    })
+                    .await?,
+                ),
+            };
+            client.register(authority, solution).await
+        }
+        res => res,
+    }
+}
+
+/// hashes until a solution at or above the min difficulty is found.
+fn solve(challenge: &[u8; 32], min_difficulty: u32) -> Solution {
+    let mut memory = SolverMemory::new();
+    let mut nonce = 0u64;
+    loop {
+        let nonce_bytes = nonce.to_le_bytes();
+        if let Ok(hash) = drillx::hash_with_memory(&mut memory, challenge, &nonce_bytes) {
+            if hash.difficulty() >= min_difficulty {
+                return Solution::new(hash.d, nonce_bytes);
+            }
+        }
+        nonce += 1;
+    }
+}
+
+/// the slice of the nonce space assigned to this member,
+/// matching the bounds the pool server validates against.
+pub fn nonce_range(member_id: u64, num_total_members: u64) -> Range<u64> {
+    if num_total_members == 0 {
+        return 0..u64::MAX;
+    }
+    let unit = u64::MAX.saturating_div(num_total_members);
+    let left = unit.saturating_mul(member_id);
+    let right = unit.saturating_mul(member_id + 1);
+    left..right
+}
+
+/// the member nonce range starting at an offset derived from the worker name,
+/// so that workers of the same member on different machines
+/// don't search the same nonces.
+pub fn worker_nonces(nonces: Range<u64>, name: &str) -> Range<u64> {
+    let len = nonces.end - nonces.start;
+    if len == 0 {
+        return nonces;
+    }
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let start = nonces.start + hasher.finish() % len;
+    start..nonces.end
+}
+
+/// Hashes the challenge until the deadline with one thread per counter,
+/// each thread taking an equal slice of the nonce range
+/// and counting its hashes as it goes.
+pub fn find_best(
+    challenge: [u8; 32],
+    nonces: Range<u64>,
+    counters: &[AtomicU64],
+    deadline: Instant,
+) -> Best {
+    let threads = counters.len().max(1) as u64;
+    let unit = (nonces.end - nonces.start).saturating_div(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = counters
+            .iter()
+            .enumerate()
+            .map(|(i, counter)| {
+                let start = nonces.start + unit.saturating_mul(i as u64);
+                let end = start.saturating_add(unit);
+                scope.spawn(move || find_best_in(&challenge, start..end, counter, deadline))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .fold(Best::default(), Best::merge)
+    })
+}
+
+fn find_best_in(
+    challenge: &[u8; 32],
+    nonces: Range<u64>,
+    counter: &AtomicU64,
+    deadline: Instant,
+) -> Best {
+    let mut memory = SolverMemory::new();
+    let mut best = Best::default();
+    for nonce in nonces {
+        let nonce = nonce.to_le_bytes();
+        if let Ok(hash) = drillx::hash_with_memory(&mut memory, challenge, &nonce) {
+            let difficulty = hash.difficulty();
+            let contender = Best {
+                solution: Some((Solution::new(hash.d, nonce), difficulty)),
+                hashes: 0,
+            };
+            best = best.merge(contender);
+        }
+        best.hashes += 1;
+        counter.fetch_add(1, Ordering::Relaxed);
+        if Instant::now() >= deadline {
+            break;
+        }
+    }
+    best
+}
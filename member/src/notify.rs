@@ -0,0 +1,38 @@
+use ore_pool_types::{NotificationChannel, NotificationPrefs, NotificationPrefsPayload};
+use solana_sdk::{signature::Signature, signer::Signer};
+
+use crate::{client::Client, config::Config, error::Error};
+
+/// Sets how the pool sends the member its daily digest of earnings and settled claims,
+/// signed by the member authority.
+pub async fn notify(
+    config: &Config,
+    channel: NotificationChannel,
+    target: Option<&str>,
+) -> Result<(), Error> {
+    let client = Client::new(config.pool_urls());
+    let signer = config.keypair()?;
+    let mut payload = NotificationPrefsPayload {
+        authority: signer.pubkey(),
+        prefs: NotificationPrefs {
+            channel,
+            target: target.map(str::to_string),
+        },
+        signed_at: unix_timestamp(),
+        signature: Signature::default(),
+    };
+    payload.signature = signer.sign_message(payload.to_bytes().as_slice());
+    let prefs = client.notifications(&payload).await?;
+    match prefs.target {
+        Some(target) => println!("notifying by {}: {}", prefs.channel.as_str(), target),
+        None => println!("notifications disabled"),
+    }
+    Ok(())
+}
+
+fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
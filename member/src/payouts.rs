@@ -0,0 +1,27 @@
+use ore_pool_types::SignedPayoutStatement;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+use crate::{client::Client, config::Config, error::Error};
+
+/// Prints the signed payout statements of the member as json, for auditors,
+/// after checking each is signed by the authority of the pool.
+pub async fn export(config: &Config) -> Result<(), Error> {
+    let client = Client::new(config.pool_urls());
+    let authority = config.keypair()?.pubkey();
+    let payouts = client.payouts(&authority).await?;
+    if let Some(invalid) = payouts.iter().find(|signed| !is_valid(signed, &authority)) {
+        return Err(Error::InvalidPayoutStatement(
+            invalid.statement.challenge.to_string(),
+        ));
+    }
+    println!("{}", serde_json::to_string_pretty(&payouts)?);
+    Ok(())
+}
+
+/// Whether the statement is of the member, and signed by the authority its pool is derived from.
+fn is_valid(signed: &SignedPayoutStatement, member: &Pubkey) -> bool {
+    let (pool_address, _) = ore_pool_api::state::pool_pda(signed.authority);
+    signed.statement.pool_address == pool_address
+        && signed.statement.member == *member
+        && signed.verify()
+}
@@ -0,0 +1,176 @@
+use std::{
+    ops::Range,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use ore_api::{
+    consts::{BUS_ADDRESSES, BUS_COUNT, CONFIG_ADDRESS, ONE_MINUTE},
+    state::{proof_pda, Config, Proof},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    sysvar,
+    transaction::Transaction,
+};
+use steel::AccountDeserialize;
+
+use crate::{client::Client, error::Error, mine};
+
+/// The number of seconds before the proof cutoff to stop hashing and submit.
+const BUFFER: i64 = 5;
+
+/// The number of seconds to hash for if the proof is already past its cutoff.
+const MIN_SECONDS: i64 = 10;
+
+/// The compute unit limit of a mine transaction.
+const CU_LIMIT: u32 = 500_000;
+
+/// The compute unit price of a mine transaction, in micro lamports.
+const CU_PRICE: u64 = 10_000;
+
+/// Mines directly against the ORE program with the member's own proof,
+/// used while the pool is unreachable.
+pub struct Solo {
+    rpc_client: RpcClient,
+    signer: Arc<Keypair>,
+}
+
+impl Solo {
+    pub fn new(rpc_url: String, signer: Arc<Keypair>) -> Self {
+        let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+        Self { rpc_client, signer }
+    }
+
+    /// Mines solo round by round until the pool serves a challenge again.
+    pub async fn run_until_pool_recovers(
+        &self,
+        client: &Client,
+        stats: &Arc<mine::Stats>,
+        counters: Range<usize>,
+    ) -> Result<(), Error> {
+        stats.log("pool unreachable, mining solo".to_string());
+        self.open_proof_if_missing(stats).await?;
+        loop {
+            if let Err(err) = self.mine_round(stats, counters.clone()).await {
+                stats.log(format!("solo: {:?}", err));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            if client.challenge().await.is_ok() {
+                stats.log("pool recovered, mining to pool".to_string());
+                return Ok(());
+            }
+        }
+    }
+
+    async fn open_proof_if_missing(&self, stats: &mine::Stats) -> Result<(), Error> {
+        let authority = self.signer.pubkey();
+        let (proof_address, _) = proof_pda(authority);
+        if self
+            .rpc_client
+            .get_account_data(&proof_address)
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+        let ix = ore_api::sdk::open(authority, authority, authority);
+        let sig = self.submit(&[ix]).await?;
+        stats.log(format!("solo: opened proof {}", sig));
+        Ok(())
+    }
+
+    async fn mine_round(
+        &self,
+        stats: &Arc<mine::Stats>,
+        counters: Range<usize>,
+    ) -> Result<(), Error> {
+        let authority = self.signer.pubkey();
+        let (proof_address, _) = proof_pda(authority);
+        let proof = self.get_proof().await?;
+        let config = self.get_config().await?;
+        let clock = self.get_clock().await?;
+        // mine until just before the proof cutoff
+        let seconds = proof
+            .last_hash_at
+            .saturating_add(ONE_MINUTE)
+            .saturating_sub(BUFFER)
+            .saturating_sub(clock.unix_timestamp)
+            .max(MIN_SECONDS) as u64;
+        let deadline = Instant::now() + Duration::from_secs(seconds);
+        let best = tokio::task::spawn_blocking({
+            let stats = stats.clone();
+            move || {
+                mine::find_best(
+                    proof.challenge,
+                    0..u64::MAX,
+                    &stats.hashes[counters],
+                    deadline,
+                )
+            }
+        })
+        .await?;
+        let Some((solution, difficulty)) = best.solution else {
+            stats.log("solo: no solution found".to_string());
+            return Ok(());
+        };
+        stats.log(format!("solo: best difficulty: {}", difficulty));
+        if difficulty < config.min_difficulty as u32 {
+            stats.log("solo: best difficulty below program min difficulty".to_string());
+            return Ok(());
+        }
+        // resets the epoch first if due, which is a no-op otherwise
+        let bus = BUS_ADDRESSES[clock.unix_timestamp as usize % BUS_COUNT];
+        let ixs = [
+            ore_api::sdk::auth(proof_address),
+            ore_api::sdk::reset(authority),
+            ore_api::sdk::mine(authority, authority, bus, solution, vec![]),
+        ];
+        let sig = self.submit(&ixs).await?;
+        stats.accepted.fetch_add(1, Ordering::Relaxed);
+        stats.log(format!("solo: {}", sig));
+        Ok(())
+    }
+
+    async fn submit(&self, ixs: &[Instruction]) -> Result<Signature, Error> {
+        let budget_ixs = [
+            ComputeBudgetInstruction::set_compute_unit_limit(CU_LIMIT),
+            ComputeBudgetInstruction::set_compute_unit_price(CU_PRICE),
+        ];
+        let ixs = [budget_ixs.as_slice(), ixs].concat();
+        let hash = self.rpc_client.get_latest_blockhash().await?;
+        let signer = self.signer.as_ref();
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&signer.pubkey()), &[signer], hash);
+        self.rpc_client
+            .send_and_confirm_transaction(&tx)
+            .await
+            .map_err(From::from)
+    }
+
+    async fn get_proof(&self) -> Result<Proof, Error> {
+        let (proof_address, _) = proof_pda(self.signer.pubkey());
+        let data = self.rpc_client.get_account_data(&proof_address).await?;
+        let proof = Proof::try_from_bytes(data.as_slice())?;
+        Ok(*proof)
+    }
+
+    async fn get_config(&self) -> Result<Config, Error> {
+        let data = self.rpc_client.get_account_data(&CONFIG_ADDRESS).await?;
+        let config = Config::try_from_bytes(data.as_slice())?;
+        Ok(*config)
+    }
+
+    async fn get_clock(&self) -> Result<Clock, Error> {
+        let data = self
+            .rpc_client
+            .get_account_data(&sysvar::clock::id())
+            .await?;
+        bincode::deserialize(&data).map_err(From::from)
+    }
+}
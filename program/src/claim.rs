@@ -9,7 +9,7 @@ pub fn process_claim(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult
     let amount = u64::from_le_bytes(args.amount);
 
     // Load accounts.
-    let [signer_info, beneficiary_info, member_info, pool_info, proof_info, treasury_info, treasury_tokens_info, ore_program, token_program] =
+    let [signer_info, beneficiary_info, member_info, pool_info, proof_info, treasury_info, treasury_tokens_info, ore_program, token_program, config_info] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -26,6 +26,23 @@ pub fn process_claim(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult
     let pool = pool_info.to_account::<Pool>(&ore_pool_api::ID)?;
     ore_program.is_program(&ore_api::ID)?;
     token_program.is_program(&spl_token::ID)?;
+    if config_info.key.ne(&pool_config_pda(*pool_info.key).0) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Enforce the pool minimum, unless claiming the whole balance.
+    // Pools that never set a minimum have no config account.
+    let min_claim = if config_info.data_is_empty() {
+        0
+    } else {
+        config_info
+            .to_account::<PoolConfig>(&ore_pool_api::ID)?
+            .check(|c| c.pool == *pool_info.key)?
+            .min_claim
+    };
+    if amount < min_claim && amount != member.balance {
+        return Err(PoolError::ClaimBelowMinimum.into());
+    }
 
     // Update member balance
     member.balance = member.balance.checked_sub(amount).unwrap();
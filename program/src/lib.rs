@@ -5,6 +5,7 @@ mod join;
 mod launch;
 mod open_share;
 mod open_stake;
+mod set_min_claim;
 mod stake;
 mod submit;
 mod unstake;
@@ -16,6 +17,7 @@ use join::*;
 use launch::*;
 use open_share::*;
 use open_stake::*;
+use set_min_claim::*;
 use stake::*;
 use submit::*;
 use unstake::*;
@@ -44,6 +46,7 @@ pub fn process_instruction(
         PoolInstruction::Launch => process_launch(accounts, data)?,
         PoolInstruction::OpenStake => process_open_stake(accounts, data)?,
         PoolInstruction::Submit => process_submit(accounts, data)?,
+        PoolInstruction::SetMinClaim => process_set_min_claim(accounts, data)?,
     }
 
     Ok(())
@@ -0,0 +1,45 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// SetMinClaim updates the minimum amount members may claim at once.
+pub fn process_set_min_claim(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    let args = SetMinClaim::try_from_bytes(data)?;
+    let min_claim = u64::from_le_bytes(args.min_claim);
+
+    // Load accounts.
+    let [signer_info, pool_info, config_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    pool_info
+        .to_account::<Pool>(&ore_pool_api::ID)?
+        .check(|p| p.authority == *signer_info.key)?;
+    let (config_address, config_bump) = pool_config_pda(*pool_info.key);
+    config_info.is_writable()?;
+    if config_info.key.ne(&config_address) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    system_program.is_program(&system_program::ID)?;
+
+    // Create the config account on first use
+    if config_info.data_is_empty() {
+        create_account::<PoolConfig>(
+            config_info,
+            &ore_pool_api::ID,
+            &[POOL_CONFIG, pool_info.key.as_ref(), &[config_bump]],
+            system_program,
+            signer_info,
+        )?;
+        let config = config_info.to_account_mut::<PoolConfig>(&ore_pool_api::ID)?;
+        config.pool = *pool_info.key;
+    }
+
+    // Update min claim
+    let config = config_info
+        .to_account_mut::<PoolConfig>(&ore_pool_api::ID)?
+        .check_mut(|c| c.pool == *pool_info.key)?;
+    config.min_claim = min_claim;
+
+    Ok(())
+}
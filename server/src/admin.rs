@@ -0,0 +1,756 @@
+use std::{str::FromStr, sync::atomic::Ordering};
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use ore_pool_api::state::Member;
+use ore_pool_types::{
+    AdjustBalancePayload, AdminConfig, ApiKey, AuditLogQuery, Backup, DistributionPreviewQuery,
+    IssueApiKeyPayload, ReconcileReport, RegisterPayload, RestoreReport, TreasuryReport,
+    UpdateConfigPayload,
+};
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use steel::AccountDeserialize;
+
+use crate::{
+    aggregator::Aggregator,
+    api_keys::{self, ApiKeys},
+    client_ip, contributor, database,
+    error::Error,
+    operator::Operator,
+    rpc_usage, telemetry,
+    utils::unix_timestamp,
+};
+
+/// The max number of accounts fetched per rpc call when reconciling.
+const RECONCILE_BATCH_SIZE: usize = 100;
+
+/// The drift tolerated per round, in grains.
+/// Each share, staker, and commission payout floors away less than a grain.
+const DRIFT_TOLERANCE: i64 = 10_000;
+
+/// The max number of drifting rounds in the treasury report.
+const MAX_DRIFT_ROUNDS: i64 = 100;
+
+/// The header the admin cli identifies the actor in, for the audit log.
+const ACTOR_HEADER: &str = "x-admin-actor";
+
+/// The actor recorded if the admin cli doesn't identify one.
+const DEFAULT_ACTOR: &str = "admin";
+
+/// The max length of an actor name.
+const MAX_ACTOR_LEN: usize = 64;
+
+/// The number of audit log entries returned if the query doesn't limit them.
+const DEFAULT_AUDIT_LOG_LIMIT: i64 = 100;
+
+/// The max number of audit log entries returned per request.
+const MAX_AUDIT_LOG_LIMIT: i64 = 1000;
+
+/// The version of the backup format.
+const BACKUP_VERSION: u8 = 1;
+
+/// The max size of a backup accepted by the restore request, 64 MiB.
+pub const MAX_BACKUP_SIZE: usize = 64 * 1024 * 1024;
+
+/// How long a signed balance adjustment is accepted for, in seconds.
+const MAX_ADJUSTMENT_AGE: i64 = 300;
+
+/// handler for the operator admin api, called from the admin cli.
+/// every request must carry the admin token as a bearer token.
+/// every action is recorded in the audit log before it is applied,
+/// and is not applied if it can't be recorded.
+pub struct Handle {
+    /// the token expected in the auth header of admin requests.
+    /// the admin api is disabled if unset.
+    admin_token: Option<String>,
+    /// the keys balance adjustments must be signed by, from `ADMIN_PUBKEYS`.
+    /// adjustments are disabled if unset.
+    admin_pubkeys: Vec<Pubkey>,
+}
+
+impl Handle {
+    pub fn from_env() -> Result<Self, Error> {
+        let admin_token = std::env::var("ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        if admin_token.is_none() {
+            tracing::info!("ADMIN_TOKEN unset, admin api disabled");
+        }
+        let admin_pubkeys = match std::env::var("ADMIN_PUBKEYS") {
+            Ok(str) => str
+                .split(',')
+                .map(str::trim)
+                .filter(|pubkey| !pubkey.is_empty())
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()?,
+            // optional
+            Err(_) => vec![],
+        };
+        Ok(Self {
+            admin_token,
+            admin_pubkeys,
+        })
+    }
+
+    /// submits the best solution of the current round now,
+    /// rather than waiting for the cutoff time.
+    pub async fn submit(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+        submit_now: web::Data<tokio::sync::Notify>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let total_score = {
+            let aggregator = aggregator.read().await;
+            aggregator.shards.total_score()
+        };
+        if total_score == 0 {
+            return HttpResponse::BadRequest().body("no contributions to submit");
+        }
+        let new_value = serde_json::json!({ "total_score": total_score }).to_string();
+        if let Err(err) = audit(operator.as_ref(), &req, "submit", None, Some(new_value)).await {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(err);
+        }
+        tracing::info!("admin: force submit");
+        submit_now.notify_one();
+        HttpResponse::Ok().finish()
+    }
+
+    /// compares the db balance of every member against its on-chain account,
+    /// and marks members whose db balance is ahead as unsynced
+    /// so that the next attribution lands it.
+    pub async fn reconcile(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        match reconcile_members(operator.as_ref(), &req).await {
+            Ok(report) => HttpResponse::Ok().json(report),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                let http_response: HttpResponse = err.into();
+                http_response
+            }
+        }
+    }
+
+    /// reconciles the pool proof against the db balances.
+    /// reads only, so isn't recorded in the audit log.
+    pub async fn treasury(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        match treasury_report(operator.as_ref()).await {
+            Ok(report) => HttpResponse::Ok().json(report),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// the rpc requests and estimated credits of the current day, by call.
+    /// reads only, so isn't recorded in the audit log.
+    pub async fn rpc_usage(handle: web::Data<Self>, req: HttpRequest) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        HttpResponse::Ok().json(rpc_usage::report())
+    }
+
+    pub async fn config(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        HttpResponse::Ok().json(admin_config(operator.as_ref()))
+    }
+
+    /// the payout split of the current round if it were submitted now,
+    /// with the reward and commissions of the query, to sanity-check config changes.
+    /// reads only, so isn't recorded in the audit log.
+    pub async fn distribution_preview(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+        query: web::Query<DistributionPreviewQuery>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let (operator_commission, staker_commission) = operator.commissions();
+        let operator_commission = query.operator_commission.unwrap_or(operator_commission);
+        let staker_commission = query.staker_commission.unwrap_or(staker_commission);
+        if operator_commission.saturating_add(staker_commission) > 100 {
+            return HttpResponse::BadRequest().body("commissions must not exceed 100%");
+        }
+        let reward = match query.reward {
+            Some(reward) => reward,
+            None => match operator.get_pool().await {
+                Ok(pool) => pool.reward,
+                Err(err) => {
+                    tracing::error!("{:?}", err);
+                    return HttpResponse::from(err);
+                }
+            },
+        };
+        let preview = {
+            let aggregator = aggregator.read().await;
+            aggregator.preview_distribution(reward, operator_commission, staker_commission)
+        };
+        HttpResponse::Ok().json(preview)
+    }
+
+    /// registers a member with the db on behalf of its authority,
+    /// once its member account exists on-chain.
+    pub async fn register(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        payload: web::Json<RegisterPayload>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let operator = operator.as_ref();
+        let authority = payload.authority;
+        telemetry::record_member(&authority);
+        let new_value = serde_json::json!({ "authority": authority.to_string() }).to_string();
+        let res = async {
+            audit(operator, &req, "register_member", None, Some(new_value)).await?;
+            contributor::register_new_member(operator, payload.into_inner()).await
+        }
+        .await;
+        match res {
+            Ok(db_member) => HttpResponse::Ok().json(db_member),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// credits or debits the balance of a member with a reason,
+    /// recorded in the ledger the member sees its adjustments in,
+    /// rather than editing the db by hand to resolve a dispute or compensate an incident.
+    /// adjustments must be signed by an admin key.
+    pub async fn adjust_balance(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        payload: web::Json<AdjustBalancePayload>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        if let Err(err) = payload.validate() {
+            return HttpResponse::from(Error::from(err));
+        }
+        let payload = payload.into_inner();
+        if let Err(err) = handle.verify_adjustment(&payload, unix_timestamp()) {
+            return HttpResponse::from(err);
+        }
+        let operator = operator.as_ref();
+        let res = async {
+            let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+            let (address, _) = ore_pool_api::state::member_pda(payload.authority, pool_address);
+            let mut conn = operator.db_client.get().await?;
+            database::write_balance_adjustment(
+                &mut conn,
+                address.to_string().as_str(),
+                &payload,
+                actor(&req),
+                remote_addr(&req),
+            )
+            .await
+        }
+        .await;
+        match res {
+            Ok(adjustment) => {
+                tracing::info!("admin: adjusted balance: {:?}", adjustment);
+                HttpResponse::Ok().json(adjustment)
+            }
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// the recorded admin actions, most recent first.
+    pub async fn audit_log(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        query: web::Query<AuditLogQuery>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_AUDIT_LOG_LIMIT)
+            .clamp(1, MAX_AUDIT_LOG_LIMIT);
+        let res = async {
+            let conn = operator.db_client.get().await?;
+            database::read_audit_log(&conn, limit, query.before).await
+        }
+        .await;
+        match res {
+            Ok(entries) => HttpResponse::Ok().json(entries),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// adjusts the runtime config.
+    /// commissions apply from the next submission,
+    /// and the min difficulty from the next challenge.
+    pub async fn update_config(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        payload: web::Json<UpdateConfigPayload>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        if let Err(err) = payload.validate() {
+            return HttpResponse::from(Error::from(err));
+        }
+        let operator = operator.as_ref();
+        let payload = payload.into_inner();
+        let (operator_commission, staker_commission) = operator.commissions();
+        let operator_commission = payload.operator_commission.unwrap_or(operator_commission);
+        let staker_commission = payload.staker_commission.unwrap_or(staker_commission);
+        if operator_commission.saturating_add(staker_commission) > 100 {
+            return HttpResponse::BadRequest().body("commissions must not exceed 100%");
+        }
+        let old_config = admin_config(operator);
+        let new_config = AdminConfig {
+            operator_commission,
+            staker_commission,
+            min_difficulty: payload.min_difficulty.unwrap_or(old_config.min_difficulty),
+        };
+        let res = audit_change(operator, &req, "update_config", &old_config, &new_config).await;
+        if let Err(err) = res {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(err);
+        }
+        operator.set_commissions(operator_commission, staker_commission);
+        operator
+            .min_difficulty
+            .store(new_config.min_difficulty, Ordering::Relaxed);
+        tracing::info!("admin: updated config: {:?}", new_config);
+        HttpResponse::Ok().json(new_config)
+    }
+
+    /// snapshots the db and the round in flight, to migrate the pool to a fresh host.
+    /// taken under the aggregator lock, so that no submission lands mid-backup.
+    pub async fn backup(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let operator = operator.as_ref();
+        let res = async {
+            let aggregator = aggregator.read().await;
+            let conn = operator.db_client.get().await?;
+            let members = database::read_members(&conn).await?;
+            let stakers = database::read_stakers(&conn).await?;
+            let round = aggregator.backup_round();
+            drop(aggregator);
+            let new_value = serde_json::json!({
+                "members": members.len(),
+                "stakers": stakers.len(),
+                "contributions": round.contributions.len(),
+            })
+            .to_string();
+            audit(operator, &req, "backup", None, Some(new_value)).await?;
+            let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+            Ok::<_, Error>(Backup {
+                version: BACKUP_VERSION,
+                created_at: unix_timestamp(),
+                pool_address,
+                members,
+                stakers,
+                round,
+            })
+        }
+        .await;
+        match res {
+            Ok(backup) => HttpResponse::Ok().json(backup),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// restores a backup into the empty db of a fresh host,
+    /// and its round into the aggregator if its challenge is still current.
+    pub async fn restore(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+        payload: web::Json<Backup>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let operator = operator.as_ref();
+        let backup = payload.into_inner();
+        if backup.version != BACKUP_VERSION {
+            return HttpResponse::BadRequest().body("unsupported backup version");
+        }
+        let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+        if backup.pool_address != pool_address {
+            return HttpResponse::BadRequest().body("backup is of another pool");
+        }
+        let res = async {
+            let new_value = serde_json::json!({
+                "created_at": backup.created_at,
+                "members": backup.members.len(),
+                "stakers": backup.stakers.len(),
+                "contributions": backup.round.contributions.len(),
+            })
+            .to_string();
+            audit(operator, &req, "restore", None, Some(new_value)).await?;
+            // no contributions are aggregated until the round is restored
+            let mut aggregator = aggregator.write().await;
+            let mut conn = operator.db_client.get().await?;
+            database::write_backup(&mut conn, &backup.members, &backup.stakers).await?;
+            operator.member_ids.clear();
+            let contributions = aggregator.restore_round(&backup.round);
+            Ok::<_, Error>(RestoreReport {
+                members: backup.members.len() as u64,
+                stakers: backup.stakers.len() as u64,
+                contributions: contributions.unwrap_or(0),
+                round_restored: contributions.is_some(),
+            })
+        }
+        .await;
+        match res {
+            Ok(report) => {
+                tracing::info!("admin: restored backup: {:?}", report);
+                HttpResponse::Ok().json(report)
+            }
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// issues an api key for read access to a third-party consumer.
+    /// the key is returned once, and only its hash is stored.
+    pub async fn issue_api_key(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        payload: web::Json<IssueApiKeyPayload>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        if let Err(err) = payload.validate() {
+            return HttpResponse::from(Error::from(err));
+        }
+        let operator = operator.as_ref();
+        let name = payload.name.trim();
+        let rate_limit = payload.rate_limit.unwrap_or(api_keys::DEFAULT_RATE_LIMIT);
+        let res = async {
+            let new_value = serde_json::json!({ "name": name, "rate_limit": rate_limit });
+            audit(
+                operator,
+                &req,
+                "issue_api_key",
+                None,
+                Some(new_value.to_string()),
+            )
+            .await?;
+            let (key, key_hash) = ApiKeys::generate();
+            let conn = operator.db_client.get().await?;
+            let api_key = database::write_api_key(&conn, name, &key_hash, rate_limit).await?;
+            Ok::<_, Error>(ApiKey {
+                key: Some(key),
+                ..api_key
+            })
+        }
+        .await;
+        match res {
+            Ok(api_key) => {
+                tracing::info!("admin: issued api key {} to {}", api_key.id, api_key.name);
+                HttpResponse::Ok().json(api_key)
+            }
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// the issued api keys, without the keys themselves.
+    pub async fn api_keys(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let res = async {
+            let conn = operator.db_client.get().await?;
+            database::read_api_keys(&conn).await
+        }
+        .await;
+        match res {
+            Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// revokes an api key, effective immediately on this server
+    /// and within the key cache ttl on the others.
+    pub async fn revoke_api_key(
+        handle: web::Data<Self>,
+        req: HttpRequest,
+        operator: web::Data<Operator>,
+        api_keys: web::Data<ApiKeys>,
+        path: web::Path<i64>,
+    ) -> impl Responder {
+        if let Err(err) = handle.auth(&req) {
+            return HttpResponse::from(err);
+        }
+        let operator = operator.as_ref();
+        let id = path.into_inner();
+        let res = async {
+            let new_value = serde_json::json!({ "id": id }).to_string();
+            audit(operator, &req, "revoke_api_key", None, Some(new_value)).await?;
+            let conn = operator.db_client.get().await?;
+            if !database::write_api_key_revoked(&conn, id).await? {
+                return Err(Error::ApiKeyDoesNotExist);
+            }
+            api_keys.clear();
+            Ok(())
+        }
+        .await;
+        match res {
+            Ok(()) => {
+                tracing::info!("admin: revoked api key {}", id);
+                HttpResponse::Ok().finish()
+            }
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                HttpResponse::from(err)
+            }
+        }
+    }
+
+    /// checks the adjustment is freshly signed by an admin key.
+    fn verify_adjustment(&self, payload: &AdjustBalancePayload, now: i64) -> Result<(), Error> {
+        if !self.admin_pubkeys.contains(&payload.signer) {
+            return Err(Error::Unauthorized.context("adjustment not signed by an admin key"));
+        }
+        if !payload.verify() {
+            return Err(Error::Unauthorized.context("invalid adjustment signature"));
+        }
+        if (now - payload.signed_at).abs() > MAX_ADJUSTMENT_AGE {
+            return Err(Error::Validation(
+                "adjustment signature expired".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// parse and validate the bearer token
+    fn auth(&self, req: &HttpRequest) -> Result<(), Error> {
+        let expected = self.admin_token.as_ref().ok_or(Error::Unauthorized)?;
+        let header = req
+            .headers()
+            .get("Authorization")
+            .ok_or(Error::Unauthorized)?;
+        let token = header
+            .to_str()?
+            .strip_prefix("Bearer ")
+            .ok_or(Error::Unauthorized)?;
+        if !constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+            return Err(Error::Unauthorized);
+        }
+        Ok(())
+    }
+}
+
+fn admin_config(operator: &Operator) -> AdminConfig {
+    let (operator_commission, staker_commission) = operator.commissions();
+    AdminConfig {
+        operator_commission,
+        staker_commission,
+        min_difficulty: operator.min_difficulty.load(Ordering::Relaxed),
+    }
+}
+
+/// records an admin action in the audit log.
+async fn audit(
+    operator: &Operator,
+    req: &HttpRequest,
+    action: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) -> Result<(), Error> {
+    let actor = actor(req);
+    let remote_addr = remote_addr(req);
+    let conn = operator.db_client.get().await?;
+    database::write_audit_entry(&*conn, actor, remote_addr, action, old_value, new_value).await
+}
+
+/// the client address of the request, for the audit log.
+fn remote_addr(req: &HttpRequest) -> Option<String> {
+    client_ip::client_ip(req)
+        .or(req.peer_addr().map(|addr| addr.ip()))
+        .map(|ip| ip.to_string())
+}
+
+/// the actor the admin cli identifies in the request header.
+fn actor(req: &HttpRequest) -> &str {
+    req.headers()
+        .get(ACTOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|actor| !actor.is_empty() && actor.len() <= MAX_ACTOR_LEN)
+        .unwrap_or(DEFAULT_ACTOR)
+}
+
+/// records an admin action that changes a value in the audit log.
+async fn audit_change<T: serde::Serialize>(
+    operator: &Operator,
+    req: &HttpRequest,
+    action: &str,
+    old_value: &T,
+    new_value: &T,
+) -> Result<(), Error> {
+    let old_value = serde_json::to_string(old_value)?;
+    let new_value = serde_json::to_string(new_value)?;
+    audit(operator, req, action, Some(old_value), Some(new_value)).await
+}
+
+#[tracing::instrument(skip_all)]
+async fn treasury_report(operator: &Operator) -> Result<TreasuryReport, Error> {
+    let authority = operator.keypair.pubkey();
+    let (pool_address, _) = ore_pool_api::state::pool_pda(authority);
+    let (operator_address, _) = ore_pool_api::state::member_pda(authority, pool_address);
+    let proof = operator.get_proof().await?;
+    let pool = operator.get_pool().await?;
+    let conn = operator.db_client.get().await?;
+    let (total_balances, commission) =
+        database::read_total_balances(&conn, operator_address.to_string().as_str()).await?;
+    let adjustments = database::read_total_adjustments(&conn).await?;
+    let drift_rounds =
+        database::read_drift_rounds(&conn, DRIFT_TOLERANCE, MAX_DRIFT_ROUNDS).await?;
+    // manual adjustments aren't rewards, so don't count as drift
+    let drift = proof.total_rewards as i64 - (total_balances as i64 - adjustments);
+    // dust only ever leaves rewards unattributed
+    let max_dust = DRIFT_TOLERANCE.saturating_mul(pool.total_submissions as i64);
+    Ok(TreasuryReport {
+        proof_balance: proof.balance,
+        proof_total_rewards: proof.total_rewards,
+        claimed: proof.total_rewards.saturating_sub(proof.balance),
+        member_balances: total_balances.saturating_sub(commission),
+        commission,
+        drift,
+        adjustments,
+        is_drifting: drift < 0 || drift > max_dust,
+        drift_rounds,
+    })
+}
+
+async fn reconcile_members(
+    operator: &Operator,
+    req: &HttpRequest,
+) -> Result<ReconcileReport, Error> {
+    let conn = operator.db_client.get().await?;
+    let members = database::read_members(&conn).await?;
+    let mut report = ReconcileReport {
+        checked: members.len() as u64,
+        ..Default::default()
+    };
+    let mut unsynced = vec![];
+    let mut unsynced_balances = vec![];
+    for chunk in members.chunks(RECONCILE_BATCH_SIZE) {
+        let addresses = chunk
+            .iter()
+            .map(|member| Pubkey::from_str(member.address.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let accounts = telemetry::rpc(
+            "get_multiple_accounts",
+            operator.rpc_client.get_multiple_accounts(&addresses),
+        )
+        .await?;
+        for (db_member, account) in chunk.iter().zip(accounts) {
+            let Some(account) = account else {
+                report.missing_onchain.push(db_member.authority.clone());
+                continue;
+            };
+            let onchain_balance = Member::try_from_bytes(account.data.as_slice())?.total_balance;
+            let db_balance = db_member.total_balance as u64;
+            if db_balance > onchain_balance && db_member.is_synced {
+                unsynced.push(db_member.address.clone());
+                unsynced_balances.push(serde_json::json!({
+                    "authority": db_member.authority,
+                    "db_balance": db_balance,
+                    "onchain_balance": onchain_balance,
+                }));
+                report.resynced.push(db_member.authority.clone());
+            } else if onchain_balance > db_balance {
+                report.ahead_onchain.push(db_member.authority.clone());
+            }
+        }
+    }
+    let new_value = serde_json::json!({
+        "checked": report.checked,
+        "resynced": unsynced_balances,
+        "missing_onchain": report.missing_onchain.len(),
+        "ahead_onchain": report.ahead_onchain.len(),
+    })
+    .to_string();
+    audit(operator, req, "reconcile", None, Some(new_value)).await?;
+    if !unsynced.is_empty() {
+        database::write_unsynced_members(&conn, unsynced.as_slice()).await?;
+    }
+    tracing::info!(
+        checked = report.checked,
+        resynced = report.resynced.len(),
+        missing_onchain = report.missing_onchain.len(),
+        ahead_onchain = report.ahead_onchain.len(),
+        "admin: reconciled members"
+    );
+    Ok(report)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
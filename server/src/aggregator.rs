@@ -1,6 +1,7 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, VecDeque},
     hash::Hash,
+    sync::Arc,
 };
 
 use drillx::Solution;
@@ -8,51 +9,104 @@ use ore_api::{
     consts::{BUS_ADDRESSES, BUS_COUNT},
     state::Bus,
 };
-use ore_pool_types::Challenge;
+use ore_pool_types::{
+    ArchivedShare, Challenge, ContributionBackup, DifficultyHistogram, DifficultyStats,
+    DistributionPreview, MemberChallenge, PreviewShare, RoundBackup, WorkerStats,
+};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Sha3_256};
-use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
+    signature::Signature, signer::Signer,
+};
 use steel::AccountDeserialize;
+use tracing::Instrument;
 
 use crate::{
-    database,
     error::Error,
+    ha::Leadership,
+    hashrate::{self, MemberShares},
+    histogram::Histogram,
     operator::{Operator, BUFFER_OPERATOR},
-    tx,
-    webhook::{self, Rewards},
+    queue::ContributionQueue,
+    shards::{Round, Shards},
+    telemetry, tx,
+    utils::unix_timestamp,
+    webhook::{Rewards, RewardsBySignature},
 };
 
 /// The client submits slightly earlier
 /// than the operator's cutoff time to create a "submission window".
 pub const BUFFER_CLIENT: u64 = 2 + BUFFER_OPERATOR;
 
+/// The number of times a submission is sent before giving up on confirming it.
+const SUBMIT_RETRIES: usize = 5;
+
+/// How long to wait for the blockhash of a failed submission to expire,
+/// before leaving the round claimed for lack of a definitive failure.
+const BLOCKHASH_EXPIRY_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(180);
+
+/// How often to poll the blockhash of a failed submission.
+const BLOCKHASH_EXPIRY_POLL: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
+/// The maximum number of named workers tracked per member.
+pub const MAX_WORKERS_PER_MEMBER: usize = 32;
+
 /// Aggregates contributions from the pool members.
 pub struct Aggregator {
     /// The current challenge.
     pub challenge: Challenge,
 
-    /// The rewards channel receiver.
-    pub rewards_rx: tokio::sync::mpsc::Receiver<webhook::Rewards>,
+    /// The rewards of the submissions, by signature.
+    pub rewards: Arc<RewardsBySignature>,
 
-    /// The set of contributions aggregated for the current challenge.
-    pub contributions: HashSet<Contribution>,
-
-    /// The total difficulty score of all the contributions aggregated so far.
-    pub total_score: u64,
-
-    /// The best solution submitted.
-    pub winner: Option<Winner>,
+    /// The contributions and member scores, sharded by member across worker tasks.
+    pub shards: Arc<Shards>,
 
     /// The number of workers that have been approved for the current challenge.
     pub num_members: u64,
 
     /// The map of stake contributors for attribution.
     pub stake: Stakers,
+
+    /// Publishes the member challenge to streaming clients whenever it changes.
+    pub challenge_tx: tokio::sync::watch::Sender<MemberChallenge>,
+
+    /// The next round, prefetched while the current round is submitted.
+    pub next: Option<NextRound>,
+}
+
+/// The state of the next round, prepared in the background
+/// so that rotating rounds is a swap rather than a refetch under the lock.
+pub struct NextRound {
+    /// The challenge that follows the submitted one.
+    pub challenge: Challenge,
+
+    /// The number of members as of the next challenge.
+    pub num_members: u64,
 }
 
 pub type BoostMint = Pubkey;
 pub type StakerBalances = HashMap<Pubkey, u64>;
 pub type Stakers = HashMap<BoostMint, StakerBalances>;
+pub type Workers = HashMap<Pubkey, HashMap<String, Worker>>;
+
+/// The running stats of a member's named worker.
+#[derive(Debug, Default)]
+pub struct Worker {
+    /// The number of contributions accepted from this worker.
+    pub shares: u64,
+
+    /// The total difficulty score of the contributions accepted from this worker.
+    pub total_score: u64,
+
+    /// The unix timestamp of the last accepted contribution.
+    pub last_share_at: i64,
+
+    /// The timestamp and score of recent contributions, for estimating hashrate.
+    pub recent: VecDeque<(i64, u64)>,
+}
 
 // Best hash to be submitted for the current challenge.
 #[derive(Clone, Copy, Debug)]
@@ -64,19 +118,84 @@ pub struct Winner {
     pub difficulty: u32,
 }
 
+/// The share quality of a submitted round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoundSummary {
+    pub challenge: [u8; 32],
+
+    pub last_hash_at: i64,
+
+    pub total_score: u64,
+
+    /// The difficulties of the shares accepted in the round.
+    pub histogram: Histogram,
+
+    /// The base reward of the round.
+    pub reward: u64,
+
+    /// The lifetime rewards of the pool proof after the round landed,
+    /// none if it couldn't be fetched.
+    pub proof_total_rewards: Option<u64>,
+
+    /// The unix timestamp the round was submitted at.
+    #[serde(default)]
+    pub submitted_at: i64,
+
+    /// The shares of each member in the round.
+    #[serde(default)]
+    pub members: Vec<MemberShares>,
+
+    /// The accepted solutions of the round, archived for independent verification.
+    #[serde(default)]
+    pub shares: Vec<ArchivedShare>,
+
+    /// The rewards split among the members.
+    #[serde(default)]
+    pub miner_rewards: u64,
+
+    /// The miner rewards paid to each member, for the payout statements.
+    #[serde(default)]
+    pub payouts: Vec<RoundPayout>,
+}
+
+/// The miner rewards paid to a member in a submitted round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoundPayout {
+    pub authority: Pubkey,
+
+    /// The weighted score of the member, summed across its workers.
+    pub score: u64,
+
+    /// The miner rewards paid to the member.
+    pub amount: u64,
+}
+
 /// A recorded contribution from a particular member of the pool.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Contribution {
     /// The member who submitted this solution.
     pub member: Pubkey,
 
+    /// The named worker of the member who found this solution.
+    pub worker: String,
+
     /// The difficulty score of the solution.
     pub score: u64,
 
     /// The drillx solution submitted representing the member's best hash.
     pub solution: Solution,
+
+    /// The challenge the solution was validated against,
+    /// so solutions that race a reset are not aggregated into the next round.
+    pub challenge: [u8; 32],
+
+    /// The span of the request that submitted this solution,
+    /// so that aggregation is logged under the same request id.
+    pub span: tracing::Span,
 }
 
+// a member scores one contribution per round, its best across its workers,
+// as worker names are chosen by the client and only label the stats
 impl PartialEq for Contribution {
     fn eq(&self, other: &Self) -> bool {
         self.member == other.member
@@ -91,13 +210,23 @@ impl Hash for Contribution {
     }
 }
 
+/// Aggregates contributions until the cutoff time of each challenge, then submits.
+/// Submits early if `submit_now` is notified, as by the admin api.
+/// Returns once this instance is no longer the leader,
+/// which is only checked between rounds, so that a submission and its distribution always finish.
 pub async fn process_contributions(
     aggregator: &tokio::sync::RwLock<Aggregator>,
     operator: &Operator,
-    rx: &mut tokio::sync::mpsc::UnboundedReceiver<Contribution>,
+    queue: &ContributionQueue,
+    submit_now: &tokio::sync::Notify,
+    leadership: &Leadership,
 ) -> Result<(), Error> {
+    let shards = aggregator.read().await.shards.clone();
     // outer loop for new challenges
     loop {
+        if !leadership.is_leader() {
+            return Ok(());
+        }
         let timer = tokio::time::Instant::now();
         let cutoff_time = {
             let read = aggregator.read().await;
@@ -107,60 +236,62 @@ pub async fn process_contributions(
         // inner loop to process contributions until cutoff time
         while remaining_time > 0 {
             // race the next contribution against remaining time
-            match tokio::time::timeout(tokio::time::Duration::from_secs(remaining_time), rx.recv())
-                .await
-            {
-                Ok(Some(contribution)) => {
-                    {
-                        let mut aggregator = aggregator.write().await;
-                        aggregator.insert(&contribution);
-                    }
+            let next = tokio::select! {
+                next = tokio::time::timeout(
+                    tokio::time::Duration::from_secs(remaining_time),
+                    queue.recv(),
+                ) => next,
+                _ = submit_now.notified() => {
+                    tracing::info!("forced submit");
+                    break;
+                }
+                // nothing is in flight yet, so stop right away
+                _ = leadership.until_standby() => return Ok(()),
+            };
+            match next {
+                Ok(contribution) => {
+                    shards.dispatch(contribution).await;
                     // recalculate the remaining time after processing the contribution
                     remaining_time = cutoff_time.saturating_sub(timer.elapsed().as_secs());
                 }
-                Ok(None) => {
-                    // if the receiver is closed, exit server
-                    return Err(Error::Internal("contribution channel closed".to_string()));
-                }
                 Err(_) => {
                     // timeout expired, meaning cutoff time has been reached
                     break;
                 }
             }
         }
-        // at this point, the cutoff time has been reached
-        let total_score = {
-            let read = aggregator.read().await;
-            read.total_score
-        };
-        if total_score > 0 {
+        // at this point, the cutoff time has been reached,
+        // wait for the shards to aggregate the contributions dispatched before it
+        shards.flush().await;
+        if shards.total_score() > 0 {
             // submit if contributions exist
             let mut aggregator = aggregator.write().await;
             if let Err(err) = aggregator.submit_and_reset(operator).await {
-                log::error!("{:?}", err);
+                tracing::error!("{:?}", err);
+                telemetry::capture(&err);
             }
         } else {
             // no contributions yet, wait for the first one to submit
-            if let Some(contribution) = rx.recv().await {
-                let mut aggregator = aggregator.write().await;
-                aggregator.insert(&contribution);
-                if let Err(err) = aggregator.submit_and_reset(operator).await {
-                    log::error!("{:?}", err);
-                }
+            let contribution = tokio::select! {
+                contribution = queue.recv() => contribution,
+                _ = leadership.until_standby() => return Ok(()),
+            };
+            let mut aggregator = aggregator.write().await;
+            contribution.span.in_scope(|| shards.insert(&contribution));
+            if let Err(err) = aggregator.submit_and_reset(operator).await {
+                tracing::error!("{:?}", err);
+                telemetry::capture(&err);
             }
         }
     }
 }
 
 impl Aggregator {
-    pub async fn new(
-        operator: &Operator,
-        rewards_rx: tokio::sync::mpsc::Receiver<webhook::Rewards>,
-    ) -> Result<Self, Error> {
+    pub async fn new(operator: &Operator, rewards: Arc<RewardsBySignature>) -> Result<Self, Error> {
         // fetch accounts
         let pool = operator.get_pool().await?;
         let proof = operator.get_proof().await?;
-        log::info!("proof: {:?}", proof);
+        tracing::info!("proof: {:?}", proof);
         let cutoff_time = operator.get_cutoff(&proof).await?;
         let min_difficulty = operator.min_difficulty().await?;
         let challenge = Challenge {
@@ -177,56 +308,265 @@ impl Aggregator {
             stake.insert(ba.mint, stakers);
         }
         // build self
+        let (challenge_tx, _) = tokio::sync::watch::channel(MemberChallenge {
+            challenge,
+            buffer: BUFFER_CLIENT,
+            num_total_members: pool.last_total_members,
+        });
+        telemetry::set_round_context(&challenge, pool.last_total_members);
         let aggregator = Aggregator {
             challenge,
-            rewards_rx,
-            contributions: HashSet::new(),
-            total_score: 0,
-            winner: None,
+            rewards,
+            shards: Shards::from_env(challenge.challenge)?,
             num_members: pool.last_total_members,
             stake,
+            challenge_tx,
+            next: None,
         };
         Ok(aggregator)
     }
 
-    fn insert(&mut self, contribution: &Contribution) {
-        match self.contributions.insert(*contribution) {
-            true => {
-                let difficulty = contribution.solution.to_hash().difficulty();
-                let contender = Winner {
-                    solution: contribution.solution,
-                    difficulty,
-                };
-                self.total_score += contribution.score;
-                match self.winner {
-                    Some(winner) => {
-                        if difficulty > winner.difficulty {
-                            self.winner = Some(contender);
-                        }
-                    }
-                    None => self.winner = Some(contender),
-                }
-            }
-            false => {
-                log::error!("already received contribution: {:?}", contribution.member);
-            }
+    /// Replaces the round with the current challenge on-chain, dropping any contributions,
+    /// as when taking over from another instance.
+    #[tracing::instrument(skip_all)]
+    pub async fn refresh(&mut self, operator: &Operator) -> Result<(), Error> {
+        let pool = operator.get_pool().await?;
+        let proof = operator.get_proof().await?;
+        let cutoff_time = operator.get_cutoff(&proof).await?;
+        let min_difficulty = operator.min_difficulty().await?;
+        self.challenge = Challenge {
+            challenge: proof.challenge,
+            lash_hash_at: pool.last_hash_at,
+            min_difficulty,
+            cutoff_time,
+        };
+        self.shards.reset(proof.challenge);
+        self.shards.take_rejected();
+        self.num_members = pool.last_total_members;
+        self.next = None;
+        self.challenge_tx.send_replace(self.member_challenge());
+        telemetry::set_round_context(&self.challenge, self.num_members);
+        Ok(())
+    }
+
+    /// The challenge as served to members.
+    pub fn member_challenge(&self) -> MemberChallenge {
+        MemberChallenge {
+            challenge: self.challenge,
+            buffer: BUFFER_CLIENT,
+            num_total_members: self.num_members,
+        }
+    }
+
+    /// The stats of each named worker of the member.
+    pub fn worker_stats(&self, member: &Pubkey) -> Vec<WorkerStats> {
+        self.shards.worker_stats(member)
+    }
+
+    /// The difficulties of the shares of the current round,
+    /// and the members whose shares are suspiciously uniform.
+    pub fn difficulty_stats(&self) -> DifficultyStats {
+        DifficultyStats {
+            challenge: self.challenge.challenge,
+            min_difficulty: self.challenge.min_difficulty,
+            round: self.shards.histogram().to_stats(),
+            uniform_members: self.shards.uniform_members(),
+        }
+    }
+
+    /// The difficulties of the shares accepted from the member.
+    pub fn member_difficulty(&self, member: &Pubkey) -> DifficultyHistogram {
+        self.shards.member_difficulty(member)
+    }
+
+    /// The hashrate of the pool, estimated from the scores of recent contributions
+    /// of every worker, in hashes per second.
+    pub fn hashrate(&self) -> u64 {
+        self.shards.hashrate()
+    }
+
+    /// The contributions aggregated for the current challenge, for backups.
+    pub fn backup_round(&self) -> RoundBackup {
+        let contributions = self
+            .shards
+            .round()
+            .contributions
+            .iter()
+            .map(|contribution| ContributionBackup {
+                member: contribution.member,
+                worker: contribution.worker.clone(),
+                score: contribution.score,
+                solution: contribution.solution,
+            })
+            .collect();
+        RoundBackup {
+            challenge: self.challenge,
+            contributions,
+        }
+    }
+
+    /// Aggregates the contributions of a backed up round,
+    /// if it is for the current challenge.
+    /// Returns the number of contributions restored, or none if the round is stale.
+    pub fn restore_round(&mut self, round: &RoundBackup) -> Option<u64> {
+        if round.challenge.challenge != self.challenge.challenge {
+            return None;
+        }
+        let before = self.shards.num_contributions();
+        for contribution in round.contributions.iter() {
+            self.shards.insert(&Contribution {
+                member: contribution.member,
+                worker: contribution.worker.clone(),
+                score: contribution.score,
+                solution: contribution.solution,
+                challenge: round.challenge.challenge,
+                span: tracing::Span::current(),
+            });
         }
+        Some((self.shards.num_contributions() - before) as u64)
     }
 
     // TODO Publish block to S3
+    #[tracing::instrument(skip_all, fields(challenge = ?self.challenge.challenge))]
     async fn submit_and_reset(&mut self, operator: &Operator) -> Result<(), Error> {
         // check if reset is needed
         // this may happen if a solution is landed on chain
         // but a subsequent application error is thrown before resetting
         if self.check_for_reset(operator).await? {
-            log::error!("irregular reset");
+            tracing::error!("irregular reset");
             self.reset(operator).await?;
         };
-        // prepare best solution and attestation of hash-power
-        let winner = self.winner()?;
-        log::info!("winner: {:?}", winner);
+        // merge the shards, and prepare best solution and attestation of hash-power
+        let round = self.shards.round();
+        let winner = round
+            .winner
+            .ok_or(Error::Conflict("no solutions were submitted".to_string()))?;
+        tracing::info!("winner: {:?}", winner);
         let best_solution = winner.solution;
-        let attestation = self.attestation();
+        let attestation = Self::attestation(&round);
+        let (pool_pda, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+        // claim the round, so that a restarted or failed over server never submits it twice
+        let challenge = self.challenge.challenge;
+        let journal = operator.journal.clone();
+        let db_pool = operator.db_client.clone();
+        if !journal
+            .claim_round(&db_pool, &challenge, self.challenge.lash_hash_at)
+            .await?
+        {
+            tracing::warn!("round already submitted, waiting for the next challenge");
+            self.reset(operator).await?;
+            return Ok(());
+        }
+        let mut blockhash = None;
+        let rewards = match &operator.simulator {
+            Some(simulator) => simulator.submit(operator, &best_solution).await,
+            None => {
+                self.land_submission(operator, best_solution, attestation, &mut blockhash)
+                    .await
+            }
+        };
+        let rewards = match rewards {
+            Ok(rewards) => rewards,
+            Err(err) => {
+                // released only if no submission of the round landed or still can,
+                // otherwise the claim and its signature are kept
+                // and the round is left for the next challenge, so it's never submitted twice
+                match self.submission_landed(operator, blockhash).await {
+                    Ok(false) => {
+                        if let Err(err) = journal.release_round(&db_pool, &challenge).await {
+                            tracing::error!("failed to release round claim: {:?}", err);
+                        }
+                    }
+                    Ok(true) => {
+                        tracing::warn!("submission landed unconfirmed, keeping round claim")
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            "failed to check submission, keeping round claim: {:?}",
+                            err
+                        )
+                    }
+                }
+                return Err(err);
+            }
+        };
+        // compute attributions for miners
+        tracing::info!("reward: {:?}", rewards);
+        let (operator_commission, staker_commission) = operator.commissions();
+        tracing::info!("// miner ////////////////////////");
+        let miner_rewards = Self::miner_rewards(&rewards, operator_commission, staker_commission);
+        let rewards_distribution = Self::rewards_distribution(pool_pda, &round, miner_rewards);
+        tracing::info!("// staker ////////////////////////");
+        // compute attributions for stakers
+        let rewards_distribution_boost_1 =
+            self.rewards_distribution_boost(pool_pda, rewards.boost_1, staker_commission)?;
+        let rewards_distribution_boost_2 =
+            self.rewards_distribution_boost(pool_pda, rewards.boost_2, staker_commission)?;
+        let rewards_distribution_boost_3 =
+            self.rewards_distribution_boost(pool_pda, rewards.boost_3, staker_commission)?;
+        tracing::info!("// operator ////////////////////////");
+        // compute attribution for operator
+        let rewards_distribution_operator = self.rewards_distribution_operator(
+            pool_pda,
+            operator.keypair.pubkey(),
+            &rewards,
+            operator_commission,
+        );
+        // snapshot the lifetime rewards on-chain, for the treasury report
+        let proof_total_rewards = match operator.get_proof().await {
+            Ok(proof) => Some(proof.total_rewards),
+            Err(err) => {
+                tracing::warn!("failed to fetch proof for round summary: {:?}", err);
+                None
+            }
+        };
+        let mut rejected = operator.rejected_shares.take();
+        for (member, count) in self.shards.take_rejected() {
+            *rejected.entry(member).or_default() += count;
+        }
+        let summary = self.round_summary(
+            &round,
+            rewards.base,
+            miner_rewards,
+            proof_total_rewards,
+            rejected,
+        );
+        // write rewards to db
+        let increments = [
+            rewards_distribution,
+            rewards_distribution_boost_1,
+            rewards_distribution_boost_2,
+            rewards_distribution_boost_3,
+            vec![rewards_distribution_operator],
+        ]
+        .concat();
+        tokio::spawn(
+            async move {
+                let res = journal
+                    .distribute_round(&db_pool, &challenge, increments, summary)
+                    .await;
+                if let Err(err) = res {
+                    tracing::error!("failed to distribute round: {:?}", err);
+                    telemetry::capture(&err);
+                }
+            }
+            .in_current_span(),
+        );
+        // reset
+        self.reset(operator).await?;
+        Ok(())
+    }
+
+    /// lands the best solution on-chain and waits for the rewards webhook.
+    /// Sets the blockhash of the last submission sent, so a failure can be told apart
+    /// from a submission that may still land.
+    async fn land_submission(
+        &mut self,
+        operator: &Operator,
+        best_solution: Solution,
+        attestation: [u8; 32],
+        blockhash: &mut Option<solana_sdk::hash::Hash>,
+    ) -> Result<Rewards, Error> {
         // derive accounts for instructions
         let authority = &operator.keypair.pubkey();
         let (pool_pda, _) = ore_pool_api::state::pool_pda(*authority);
@@ -241,83 +581,108 @@ impl Aggregator {
             bus,
             operator.get_boost_mine_accounts(),
         );
+        let sig = self
+            .send_submission(operator, &[auth_ix, submit_ix], blockhash)
+            .await;
+        let result = if sig.is_ok() { "landed" } else { "failed" };
+        telemetry::metrics()
+            .submissions
+            .add(1, &[opentelemetry::KeyValue::new("result", result)]);
+        let sig = sig?;
+        tracing::info!("{:?}", sig);
+        // listen for rewards,
+        // while prefetching the next challenge and publishing it to members,
+        // who mine it as soon as it lands instead of once the round is distributed
+        let last_hash_at = self.challenge.lash_hash_at;
+        let challenge_tx = &self.challenge_tx;
+        let prefetch = async {
+            let next = NextRound::prefetch(operator, last_hash_at).await;
+            match &next {
+                Ok(next) => {
+                    challenge_tx
+                        .send_if_modified(|member_challenge| next.publish(member_challenge));
+                }
+                Err(err) => tracing::error!("failed to prefetch next challenge: {:?}", err),
+            }
+            next.ok()
+        };
+        let (rewards, next) = tokio::join!(self.rewards.wait(&sig), prefetch);
+        self.next = next;
+        Ok(rewards)
+    }
+
+    /// Sends the submission until it confirms,
+    /// recording each signature of the round as soon as it's sent,
+    /// so the claim of a submission that lands unconfirmed is never released.
+    async fn send_submission(
+        &self,
+        operator: &Operator,
+        ixs: &[Instruction],
+        blockhash: &mut Option<solana_sdk::hash::Hash>,
+    ) -> Result<Signature, Error> {
+        let challenge = &self.challenge.challenge;
         let rpc_client = &operator.rpc_client;
-        let sig = tx::submit::submit_and_confirm_instructions(
-            &operator.keypair,
-            rpc_client,
-            &[auth_ix, submit_ix],
-            1_500_000,
-            500_000,
-        )
-        .await?;
-        log::info!("{:?}", sig);
-        // listen for rewards
-        let rewards_rx = &mut self.rewards_rx;
-        let rewards = rewards_rx
-            .recv()
-            .await
-            .ok_or(Error::Internal("rewards channel closed".to_string()))?;
-        // compute attributions for miners
-        log::info!("reward: {:?}", rewards);
-        log::info!("// miner ////////////////////////");
-        let rewards_distribution = self.rewards_distribution(
-            pool_pda,
-            &rewards,
-            operator.operator_commission,
-            operator.staker_commission,
-        );
-        log::info!("// staker ////////////////////////");
-        // compute attributions for stakers
-        let rewards_distribution_boost_1 =
-            self.rewards_distribution_boost(pool_pda, rewards.boost_1, operator.staker_commission)?;
-        let rewards_distribution_boost_2 =
-            self.rewards_distribution_boost(pool_pda, rewards.boost_2, operator.staker_commission)?;
-        let rewards_distribution_boost_3 =
-            self.rewards_distribution_boost(pool_pda, rewards.boost_3, operator.staker_commission)?;
-        log::info!("// operator ////////////////////////");
-        // compute attribution for operator
-        let rewards_distribution_operator = self.rewards_distribution_operator(
-            pool_pda,
-            operator.keypair.pubkey(),
-            &rewards,
-            operator.operator_commission,
-        );
-        // write rewards to db
-        let mut db_client = operator.db_client.get().await?;
-        tokio::spawn(async move {
-            database::write_member_total_balances(&mut db_client, rewards_distribution).await?;
-            database::write_member_total_balances(&mut db_client, rewards_distribution_boost_1)
-                .await?;
-            database::write_member_total_balances(&mut db_client, rewards_distribution_boost_2)
+        for _ in 0..SUBMIT_RETRIES {
+            let tx = tx::submit::sign_instructions(operator, ixs, 1_500_000, 500_000).await?;
+            let sig = tx.signatures[0];
+            operator
+                .journal
+                .record_signature(&operator.db_client, challenge, &sig)
                 .await?;
-            database::write_member_total_balances(&mut db_client, rewards_distribution_boost_3)
-                .await?;
-            database::write_member_total_balances(
-                &mut db_client,
-                vec![rewards_distribution_operator],
-            )
-            .await
-        });
-        // reset
-        self.reset(operator).await?;
-        Ok(())
+            *blockhash = Some(tx.message.recent_blockhash);
+            telemetry::rpc("send_transaction", rpc_client.send_transaction(&tx)).await?;
+            match operator.confirmer.confirm(rpc_client, &sig).await {
+                Ok(()) => return Ok(sig),
+                Err(err) => tracing::info!("{:?}", err),
+            }
+        }
+        Err(Error::Rpc(
+            "failed to land transaction with confirmation".to_string(),
+        ))
     }
 
-    fn rewards_distribution(
+    /// Whether a submission of the round landed,
+    /// waiting for the blockhash of the last one sent to expire, after which none can land.
+    /// None were sent without a blockhash.
+    async fn submission_landed(
         &self,
-        pool: Pubkey,
-        rewards: &Rewards,
-        operator_commission: u64,
-        staker_commission: u64,
-    ) -> Vec<(String, u64)> {
-        // compute denominator
-        let denominator = self.total_score as u128;
-        log::info!("base reward denominator: {}", denominator);
+        operator: &Operator,
+        blockhash: Option<solana_sdk::hash::Hash>,
+    ) -> Result<bool, Error> {
+        let Some(blockhash) = blockhash else {
+            return Ok(false);
+        };
+        let rpc_client = &operator.rpc_client;
+        let started_at = tokio::time::Instant::now();
+        loop {
+            if self.check_for_reset(operator).await? {
+                return Ok(true);
+            }
+            // a transaction can't land once its blockhash expired as of the finalized bank
+            let is_valid = telemetry::rpc(
+                "is_blockhash_valid",
+                rpc_client.is_blockhash_valid(&blockhash, CommitmentConfig::finalized()),
+            )
+            .await?;
+            if !is_valid {
+                return self.check_for_reset(operator).await;
+            }
+            if started_at.elapsed() > BLOCKHASH_EXPIRY_TIMEOUT {
+                return Err(Error::Rpc(
+                    "blockhash of the submission did not expire".to_string(),
+                ));
+            }
+            tokio::time::sleep(BLOCKHASH_EXPIRY_POLL).await;
+        }
+    }
+
+    /// The rewards split among the miners, from the base reward and the stake rewards.
+    fn miner_rewards(rewards: &Rewards, operator_commission: u64, staker_commission: u64) -> u128 {
         // compute miner split
         let miner_commission = 100 - operator_commission;
-        log::info!("miner commission: {}", miner_commission);
+        tracing::info!("miner commission: {}", miner_commission);
         let miner_rewards = (rewards.base * miner_commission / 100) as u128;
-        log::info!("miner rewards as commission for miners: {}", miner_rewards);
+        tracing::info!("miner rewards as commission for miners: {}", miner_rewards);
         // compute miner split from stake rewards
         let miner_rewards_from_stake_1 = Self::split_stake_rewards_for_miners(
             rewards.boost_1,
@@ -338,16 +703,119 @@ impl Aggregator {
             + miner_rewards_from_stake_1
             + miner_rewards_from_stake_2
             + miner_rewards_from_stake_3;
-        log::info!("total rewards as commission for miners: {}", total_rewards);
-        let contributions = self.contributions.iter();
-        contributions
-            .map(|c| {
-                log::info!("raw base reward score: {}", c.score);
-                let score = (c.score as u128).saturating_mul(total_rewards);
-                let score = score.checked_div(denominator).unwrap_or(0);
-                log::info!("attributed base reward score: {}", score);
+        tracing::info!("total rewards as commission for miners: {}", total_rewards);
+        total_rewards
+    }
+
+    fn rewards_distribution(
+        pool: Pubkey,
+        round: &Round,
+        miner_rewards: u128,
+    ) -> Vec<(String, u64)> {
+        Self::miner_shares(round, miner_rewards)
+            .into_iter()
+            .map(|(c, _, score)| {
                 let (member_pda, _) = ore_pool_api::state::member_pda(c.member, pool);
-                (member_pda.to_string(), score as u64)
+                (member_pda.to_string(), score)
+            })
+            .collect()
+    }
+
+    /// The weight and miner rewards of each member, summed across its workers.
+    fn round_payouts(round: &Round, miner_rewards: u128) -> Vec<RoundPayout> {
+        let mut payouts: HashMap<Pubkey, RoundPayout> = HashMap::new();
+        for (c, weight, amount) in Self::miner_shares(round, miner_rewards) {
+            let payout = payouts.entry(c.member).or_insert(RoundPayout {
+                authority: c.member,
+                score: 0,
+                amount: 0,
+            });
+            payout.score = payout
+                .score
+                .saturating_add(weight.min(u64::MAX as u128) as u64);
+            payout.amount = payout.amount.saturating_add(amount);
+        }
+        payouts.into_values().collect()
+    }
+
+    /// The weight and share of the miner rewards of each contribution.
+    fn miner_shares(round: &Round, total_rewards: u128) -> Vec<(&Contribution, u128, u64)> {
+        // compute denominator
+        let weights = Self::weights(round);
+        let denominator: u128 = weights.iter().map(|(_, weight)| weight).sum();
+        tracing::info!("base reward denominator: {}", denominator);
+        weights
+            .into_iter()
+            .map(|(c, weight)| {
+                tracing::info!("raw base reward score: {} weight: {}", c.score, weight);
+                let score = weight.saturating_mul(total_rewards);
+                let score = score.checked_div(denominator).unwrap_or(0);
+                tracing::info!("attributed base reward score: {}", score);
+                (c, weight, score as u64)
+            })
+            .collect()
+    }
+
+    /// The base reward split of the current round if it were submitted now,
+    /// as computed by the distribution, without committing anything.
+    pub fn preview_distribution(
+        &self,
+        reward: u64,
+        operator_commission: u64,
+        staker_commission: u64,
+    ) -> DistributionPreview {
+        let miner_rewards = (reward as u128 * (100 - operator_commission) as u128 / 100) as u64;
+        let round = self.shards.round();
+        let shares: Vec<PreviewShare> = Self::miner_shares(&round, miner_rewards as u128)
+            .into_iter()
+            .map(|(c, weight, amount)| PreviewShare {
+                member: c.member,
+                worker: c.worker.clone(),
+                score: c.score,
+                weight: weight.min(u64::MAX as u128) as u64,
+                amount,
+            })
+            .collect();
+        let paid: u64 = shares.iter().map(|share| share.amount).sum();
+        let operator = (reward as u128 * operator_commission as u128 / 100) as u64;
+        DistributionPreview {
+            challenge: self.challenge.challenge,
+            reward,
+            operator_commission,
+            staker_commission,
+            total_score: round.total_score,
+            shares,
+            operator,
+            dust: miner_rewards.saturating_sub(paid),
+        }
+    }
+
+    /// The payout weight of each contribution, which is its score,
+    /// unless score decay is enabled.
+    /// Then the decayed score of the member is split across its workers by score.
+    fn weights(round: &Round) -> Vec<(&Contribution, u128)> {
+        let Some(decayed_scores) = &round.decayed_scores else {
+            return round
+                .contributions
+                .iter()
+                .map(|c| (c, c.score as u128))
+                .collect();
+        };
+        let mut member_scores: HashMap<Pubkey, u128> = HashMap::new();
+        for c in round.contributions.iter() {
+            *member_scores.entry(c.member).or_default() += c.score as u128;
+        }
+        round
+            .contributions
+            .iter()
+            .map(|c| {
+                let decayed = decayed_scores.get(&c.member).copied().unwrap_or(0.0) as u128;
+                let member_score = member_scores.get(&c.member).copied().unwrap_or(0);
+                let weight = decayed
+                    .saturating_mul(c.score as u128)
+                    .checked_div(member_score)
+                    .unwrap_or(0);
+                (c, weight)
             })
             .collect()
     }
@@ -359,16 +827,16 @@ impl Aggregator {
     ) -> u128 {
         let miner_rewards_from_stake: u128 = match boost_event {
             Some(boost_event) => {
-                log::info!("{:?}", boost_event);
+                tracing::info!("{:?}", boost_event);
                 let miner_commission_for_stake: u128 =
                     (100 - operator_commission - staker_commission) as u128;
-                log::info!("miner commission for stake: {}", miner_commission_for_stake);
+                tracing::info!("miner commission for stake: {}", miner_commission_for_stake);
                 let stake_rewards = boost_event.reward as u128;
                 stake_rewards * miner_commission_for_stake / 100
             }
             None => 0,
         };
-        log::info!(
+        tracing::info!(
             "stake rewards as commission for miners: {}",
             miner_rewards_from_stake
         );
@@ -384,36 +852,36 @@ impl Aggregator {
         match boost_event {
             None => Ok(vec![]),
             Some(boost_event) => {
-                log::info!("{:?}", boost_event);
+                tracing::info!("{:?}", boost_event);
                 let total_reward = boost_event.reward as u128;
                 let staker_commission: u128 = staker_commission as u128;
-                log::info!("staker commission: {}", staker_commission);
+                tracing::info!("staker commission: {}", staker_commission);
                 let staker_rewards = total_reward * staker_commission / 100;
-                log::info!("total rewards from stake: {}", total_reward);
-                log::info!(
+                tracing::info!("total rewards from stake: {}", total_reward);
+                tracing::info!(
                     "total rewards as commission for stakers: {}",
                     staker_rewards
                 );
                 let stakers = self
                     .stake
                     .get(&boost_event.mint)
-                    .ok_or(Error::Internal(format!(
-                        "missing staker balances: {:?}",
+                    .ok_or(Error::NotFound(format!(
+                        "staker balances of {:?}",
                         boost_event.mint,
                     )))?;
                 let denominator_iter = stakers.iter();
                 let distribution_iter = stakers.iter();
                 let denominator: u64 = denominator_iter.map(|(_, balance)| balance).sum();
                 let denominator = denominator as u128;
-                log::info!("staked reward denominator: {}", denominator);
+                tracing::info!("staked reward denominator: {}", denominator);
                 let res = distribution_iter
                     .map(|(stake_authority, balance)| {
-                        log::info!("staked balance: {:?}", (stake_authority, balance));
+                        tracing::info!("staked balance: {:?}", (stake_authority, balance));
                         let balance = *balance as u128;
                         let score = balance.saturating_mul(staker_rewards);
-                        log::info!("scaled score from stake: {}", score);
+                        tracing::info!("scaled score from stake: {}", score);
                         let score = score.checked_div(denominator).unwrap_or(0);
-                        log::info!("attributed reward from stake: {}", score);
+                        tracing::info!("attributed reward from stake: {}", score);
                         let (member_pda, _) =
                             ore_pool_api::state::member_pda(*stake_authority, pool);
                         (member_pda.to_string(), score as u64)
@@ -437,7 +905,7 @@ impl Aggregator {
         let mut stake_rewards = 0;
         if let Some(boost_event) = rewards.boost_1 {
             let r = boost_event.reward * operator_commission / 100;
-            log::info!(
+            tracing::info!(
                 "staker rewards for operator: {} from {:?}",
                 r,
                 boost_event.mint
@@ -446,7 +914,7 @@ impl Aggregator {
         }
         if let Some(boost_event) = rewards.boost_2 {
             let r = boost_event.reward * operator_commission / 100;
-            log::info!(
+            tracing::info!(
                 "staker rewards for operator: {} from {:?}",
                 r,
                 boost_event.mint
@@ -455,18 +923,18 @@ impl Aggregator {
         }
         if let Some(boost_event) = rewards.boost_3 {
             let r = boost_event.reward * operator_commission / 100;
-            log::info!(
+            tracing::info!(
                 "staker rewards for operator: {} from {:?}",
                 r,
                 boost_event.mint
             );
             stake_rewards += r;
         }
-        log::info!("operator commission: {}", operator_commission);
-        log::info!("mine rewards for operator: {}", mine_rewards);
-        log::info!("stake rewards for operator: {}", stake_rewards);
+        tracing::info!("operator commission: {}", operator_commission);
+        tracing::info!("mine rewards for operator: {}", mine_rewards);
+        tracing::info!("stake rewards for operator: {}", stake_rewards);
         let total_rewards = mine_rewards + stake_rewards;
-        log::info!("total rewards for operator: {}", total_rewards);
+        tracing::info!("total rewards for operator: {}", total_rewards);
         let (member_pda, _) = ore_pool_api::state::member_pda(pool_authority, pool);
         (member_pda.to_string(), total_rewards)
     }
@@ -489,11 +957,11 @@ impl Aggregator {
         Ok(top_bus)
     }
 
-    fn attestation(&self) -> [u8; 32] {
+    fn attestation(round: &Round) -> [u8; 32] {
         let mut hasher = Sha3_256::new();
-        let contributions = &self.contributions;
+        let contributions = &round.contributions;
         let num_contributions = contributions.len();
-        log::info!("num contributions: {}", num_contributions);
+        tracing::info!("num contributions: {}", num_contributions);
         for contribution in contributions.iter() {
             let hex_string: String =
                 contribution
@@ -517,19 +985,57 @@ impl Aggregator {
         attestation
     }
 
-    async fn reset(&mut self, operator: &Operator) -> Result<(), Error> {
-        self.update_challenge(operator).await?;
-        let pool = operator.get_pool().await?;
-        self.contributions = HashSet::new();
-        self.total_score = 0;
-        self.winner = None;
-        self.num_members = pool.last_total_members;
-        Ok(())
+    /// The share quality of the round, persisted for tuning the min difficulty,
+    /// the shares of each member, for the member hashrate,
+    /// and the accepted solutions, for independent verification.
+    fn round_summary(
+        &self,
+        round: &Round,
+        reward: u64,
+        miner_rewards: u128,
+        proof_total_rewards: Option<u64>,
+        rejected: HashMap<Pubkey, u64>,
+    ) -> RoundSummary {
+        RoundSummary {
+            challenge: self.challenge.challenge,
+            last_hash_at: self.challenge.lash_hash_at,
+            total_score: round.total_score,
+            histogram: round.histogram.clone(),
+            reward,
+            proof_total_rewards,
+            submitted_at: unix_timestamp(),
+            members: hashrate::member_shares(round.contributions.iter(), rejected),
+            shares: round
+                .contributions
+                .iter()
+                .map(|contribution| ArchivedShare {
+                    authority: contribution.member,
+                    worker: contribution.worker.clone(),
+                    digest: contribution.solution.d,
+                    nonce: contribution.solution.n,
+                    score: contribution.score,
+                })
+                .collect(),
+            miner_rewards: miner_rewards.min(u64::MAX as u128) as u64,
+            payouts: Self::round_payouts(round, miner_rewards),
+        }
     }
 
-    fn winner(&self) -> Result<Winner, Error> {
-        self.winner
-            .ok_or(Error::Internal("no solutions were submitted".to_string()))
+    /// Rotates to the next round, swapping in the prefetched round if there is one.
+    #[tracing::instrument(skip_all)]
+    async fn reset(&mut self, operator: &Operator) -> Result<(), Error> {
+        let last_hash_at = self.challenge.lash_hash_at;
+        let next = match self.next.take() {
+            Some(next) if next.challenge.lash_hash_at != last_hash_at => next,
+            _ => NextRound::prefetch(operator, last_hash_at).await?,
+        };
+        self.challenge_tx
+            .send_if_modified(|member_challenge| next.publish(member_challenge));
+        self.challenge = next.challenge;
+        self.num_members = next.num_members;
+        self.shards.reset(self.challenge.challenge);
+        telemetry::set_round_context(&self.challenge, self.num_members);
+        Ok(())
     }
 
     async fn check_for_reset(&self, operator: &Operator) -> Result<bool, Error> {
@@ -538,29 +1044,48 @@ impl Aggregator {
         let needs_reset = pool.last_hash_at != last_hash_at;
         Ok(needs_reset)
     }
+}
 
-    async fn update_challenge(&mut self, operator: &Operator) -> Result<(), Error> {
+impl NextRound {
+    /// Polls for the challenge that follows `last_hash_at`.
+    async fn prefetch(operator: &Operator, last_hash_at: i64) -> Result<Self, Error> {
         let max_retries = 10;
         let mut retries = 0;
-        let last_hash_at = self.challenge.lash_hash_at;
         loop {
             let proof = operator.get_proof().await?;
             let pool = operator.get_pool().await?;
             if pool.last_hash_at != last_hash_at {
                 let cutoff_time = operator.get_cutoff(&proof).await?;
                 let min_difficulty = operator.min_difficulty().await?;
-                self.challenge.challenge = proof.challenge;
-                self.challenge.lash_hash_at = pool.last_hash_at;
-                self.challenge.min_difficulty = min_difficulty;
-                self.challenge.cutoff_time = cutoff_time;
-                return Ok(());
+                return Ok(Self {
+                    challenge: Challenge {
+                        challenge: proof.challenge,
+                        lash_hash_at: pool.last_hash_at,
+                        min_difficulty,
+                        cutoff_time,
+                    },
+                    num_members: pool.last_total_members,
+                });
             } else {
                 retries += 1;
                 if retries == max_retries {
-                    return Err(Error::Internal("failed to fetch new challenge".to_string()));
+                    return Err(Error::Rpc("failed to fetch new challenge".to_string()));
                 }
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                operator.wait_for_pool().await;
             }
         }
     }
+
+    /// Replaces the member challenge with this round's,
+    /// returning whether it changed so streaming clients are notified once per challenge.
+    fn publish(&self, member_challenge: &mut MemberChallenge) -> bool {
+        let is_modified = member_challenge.challenge.challenge != self.challenge.challenge
+            || member_challenge.num_total_members != self.num_members;
+        *member_challenge = MemberChallenge {
+            challenge: self.challenge,
+            buffer: BUFFER_CLIENT,
+            num_total_members: self.num_members,
+        };
+        is_modified
+    }
 }
@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::IpAddr,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, HttpMessage, HttpResponse,
+};
+use ore_pool_types::hex;
+use sha3::{Digest, Sha3_256};
+
+use crate::{client_ip::ClientIp, database, error::Error, operator::Operator};
+
+/// The header third-party consumers pass their api key in.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// The prefix of issued keys, to tell them apart in configs and leaks.
+const KEY_PREFIX: &str = "orepool_";
+
+/// The rate limit of keys issued without one, in requests per minute.
+pub const DEFAULT_RATE_LIMIT: u32 = 600;
+
+/// How long a key lookup is cached, so revocations take effect within it.
+const KEY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// The number of tracked buckets above which the idle ones are pruned.
+const MAX_BUCKETS: usize = 10_000;
+
+/// The max key lookups cached, past which lookups aren't cached until stale ones are pruned.
+const MAX_CACHED_KEYS: usize = 10_000;
+
+/// The requests with an invalid key allowed per client ip, per minute,
+/// checked before the db lookup so that guessing keys can't load the db.
+const INVALID_KEY_RATE_LIMIT: u32 = 10;
+
+/// Api keys for third-party read access to the stats and member endpoints,
+/// issued from the admin api and stored hashed in the db.
+///
+/// Requests with a key are rate limited per key, by the limit it was issued with.
+/// Requests without one are rate limited per client ip by `READ_RATE_LIMIT`
+/// (requests per minute, unlimited if unset), so dashboards polling the read endpoints
+/// are held to their own budget instead of competing with the miner-facing routes.
+/// Member signature auth is unaffected.
+pub struct ApiKeys {
+    anonymous_limit: Option<u32>,
+    /// The id and rate limit by key hash, none if invalid, and when it was looked up.
+    keys: RwLock<HashMap<String, (Option<(i64, u32)>, Instant)>>,
+    limiter: RateLimiter<Bucket>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Bucket {
+    Key(i64),
+    Ip(IpAddr),
+    InvalidKey(IpAddr),
+}
+
+impl ApiKeys {
+    pub fn from_env() -> Result<Self, Error> {
+        let anonymous_limit = match std::env::var("READ_RATE_LIMIT") {
+            Ok(str) => Some(str.parse::<u32>()?.max(1)),
+            // optional
+            Err(_) => None,
+        };
+        Ok(Self {
+            anonymous_limit,
+            keys: RwLock::new(HashMap::new()),
+            limiter: RateLimiter::default(),
+        })
+    }
+
+    /// Generates a new key, returning it and its hash to store.
+    pub fn generate() -> (String, String) {
+        let bytes: [u8; 32] = rand::random();
+        let key = format!("{}{}", KEY_PREFIX, hex::encode(bytes.as_slice()));
+        let hash = hash(key.as_str());
+        (key, hash)
+    }
+
+    /// Forgets the cached lookups, for revocations to take effect immediately.
+    pub fn clear(&self) {
+        if let Ok(mut keys) = self.keys.write() {
+            keys.clear();
+        }
+    }
+
+    /// The id and rate limit of the key, from the cache if looked up recently.
+    async fn lookup(&self, operator: &Operator, key: &str) -> Result<Option<(i64, u32)>, Error> {
+        let key_hash = hash(key);
+        let cached = self
+            .keys
+            .read()
+            .ok()
+            .and_then(|keys| keys.get(&key_hash).copied())
+            .filter(|(_, looked_up_at)| looked_up_at.elapsed() < KEY_CACHE_TTL);
+        if let Some((key, _)) = cached {
+            return Ok(key);
+        }
+        let conn = operator.db_client.get().await?;
+        let key = database::read_api_key(&conn, key_hash.as_str()).await?;
+        self.cache(key_hash, key);
+        Ok(key)
+    }
+
+    fn cache(&self, key_hash: String, key: Option<(i64, u32)>) {
+        if let Ok(mut keys) = self.keys.write() {
+            if keys.len() >= MAX_CACHED_KEYS {
+                keys.retain(|_, (_, looked_up_at)| looked_up_at.elapsed() < KEY_CACHE_TTL);
+            }
+            if keys.len() < MAX_CACHED_KEYS || keys.contains_key(&key_hash) {
+                keys.insert(key_hash, (key, Instant::now()));
+            }
+        }
+    }
+}
+
+/// Authenticates the api key of a read request, if any, and applies its rate limit,
+/// or the anonymous rate limit of the client ip.
+/// Client ips sending invalid keys are rate limited before their keys are looked up.
+pub async fn read_access(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let (Some(api_keys), Some(operator)) = (
+        req.app_data::<web::Data<ApiKeys>>(),
+        req.app_data::<web::Data<Operator>>(),
+    ) else {
+        let res = next.call(req).await?;
+        return Ok(res.map_into_left_body());
+    };
+    let key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    let ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|client_ip| client_ip.0);
+    let (bucket, limit) = match key {
+        Some(key) => {
+            let invalid_key_bucket = ip.map(Bucket::InvalidKey);
+            if let Some(bucket) = invalid_key_bucket {
+                if let Err(retry_after) = api_keys.limiter.peek(&bucket, INVALID_KEY_RATE_LIMIT) {
+                    let res = too_many_requests(retry_after);
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+            }
+            match api_keys.lookup(operator, key.as_str()).await {
+                Ok(Some((id, limit))) => (Some(Bucket::Key(id)), limit),
+                Ok(None) => {
+                    if let Some(bucket) = invalid_key_bucket {
+                        let _ = api_keys.limiter.check(bucket, INVALID_KEY_RATE_LIMIT);
+                    }
+                    let res = HttpResponse::Unauthorized().body("invalid api key");
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+                Err(err) => {
+                    tracing::error!("failed to look up api key: {:?}", err);
+                    let res = HttpResponse::from(err);
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+            }
+        }
+        None => match (ip, api_keys.anonymous_limit) {
+            (Some(ip), Some(limit)) => (Some(Bucket::Ip(ip)), limit),
+            _ => (None, 0),
+        },
+    };
+    if let Some(bucket) = bucket {
+        if let Err(retry_after) = api_keys.limiter.check(bucket, limit) {
+            let res = too_many_requests(retry_after);
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    }
+    let res = next.call(req).await?;
+    Ok(res.map_into_left_body())
+}
+
+pub(crate) fn too_many_requests(retry_after: u64) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_after.to_string()))
+        .finish()
+}
+
+/// Token buckets refilled continuously to the limit per minute.
+pub(crate) struct RateLimiter<K> {
+    buckets: Mutex<HashMap<K, (f64, Instant)>>,
+}
+
+impl<K> Default for RateLimiter<K> {
+    fn default() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    /// Takes a token from the bucket,
+    /// or returns the seconds until one is available.
+    pub(crate) fn check(&self, key: K, limit_per_minute: u32) -> Result<(), u64> {
+        self.check_at(key, limit_per_minute, Instant::now())
+    }
+
+    fn check_at(&self, key: K, limit_per_minute: u32, now: Instant) -> Result<(), u64> {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        let capacity = limit_per_minute as f64;
+        let per_second = capacity / 60.0;
+        if buckets.len() > MAX_BUCKETS {
+            // idle for a minute, so full again
+            buckets.retain(|_, (_, updated_at)| now.duration_since(*updated_at).as_secs() < 60);
+        }
+        let (tokens, updated_at) = buckets.entry(key).or_insert((capacity, now));
+        let elapsed = now.duration_since(*updated_at).as_secs_f64();
+        *tokens = (*tokens + elapsed * per_second).min(capacity);
+        *updated_at = now;
+        if *tokens < 1.0 {
+            return Err(((1.0 - *tokens) / per_second).ceil() as u64);
+        }
+        *tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Returns the seconds until a token is available in the bucket,
+    /// without taking one.
+    fn peek(&self, key: &K, limit_per_minute: u32) -> Result<(), u64> {
+        self.peek_at(key, limit_per_minute, Instant::now())
+    }
+
+    fn peek_at(&self, key: &K, limit_per_minute: u32, now: Instant) -> Result<(), u64> {
+        let Ok(buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        let Some((tokens, updated_at)) = buckets.get(key) else {
+            return Ok(());
+        };
+        let capacity = limit_per_minute as f64;
+        let per_second = capacity / 60.0;
+        let elapsed = now.duration_since(*updated_at).as_secs_f64();
+        let tokens = (tokens + elapsed * per_second).min(capacity);
+        if tokens < 1.0 {
+            return Err(((1.0 - tokens) / per_second).ceil() as u64);
+        }
+        Ok(())
+    }
+}
+
+fn hash(key: &str) -> String {
+    hex::encode(Sha3_256::digest(key.as_bytes()).as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_hashed_keys() {
+        let (key, key_hash) = ApiKeys::generate();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_eq!(hash(key.as_str()), key_hash);
+        assert_ne!(ApiKeys::generate().0, key);
+    }
+
+    #[test]
+    fn limits_per_bucket() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..60 {
+            assert!(limiter.check_at(Bucket::Key(1), 60, now).is_ok());
+        }
+        assert_eq!(limiter.check_at(Bucket::Key(1), 60, now), Err(1));
+        // other buckets are independent
+        assert!(limiter.check_at(Bucket::Key(2), 60, now).is_ok());
+        // refilled at a token per second
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at(Bucket::Key(1), 60, later).is_ok());
+        assert!(limiter.check_at(Bucket::Key(1), 60, later).is_err());
+    }
+
+    #[test]
+    fn limits_invalid_keys_per_ip() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+        let bucket = Bucket::InvalidKey(IpAddr::from([1, 2, 3, 4]));
+        // peeking takes no tokens
+        for _ in 0..2 * INVALID_KEY_RATE_LIMIT {
+            assert!(limiter
+                .peek_at(&bucket, INVALID_KEY_RATE_LIMIT, now)
+                .is_ok());
+        }
+        for _ in 0..INVALID_KEY_RATE_LIMIT {
+            assert!(limiter
+                .check_at(bucket, INVALID_KEY_RATE_LIMIT, now)
+                .is_ok());
+        }
+        assert_eq!(
+            limiter.peek_at(&bucket, INVALID_KEY_RATE_LIMIT, now),
+            Err(6)
+        );
+        let later = now + Duration::from_secs(6);
+        assert!(limiter
+            .peek_at(&bucket, INVALID_KEY_RATE_LIMIT, later)
+            .is_ok());
+    }
+
+    #[test]
+    fn caps_cached_keys() {
+        let api_keys = ApiKeys {
+            anonymous_limit: None,
+            keys: RwLock::new(HashMap::new()),
+            limiter: RateLimiter::default(),
+        };
+        for i in 0..MAX_CACHED_KEYS + 10 {
+            api_keys.cache(i.to_string(), None);
+        }
+        assert_eq!(api_keys.keys.read().unwrap().len(), MAX_CACHED_KEYS);
+        // cached lookups are still refreshed
+        api_keys.cache("0".to_string(), Some((1, DEFAULT_RATE_LIMIT)));
+        assert_eq!(
+            api_keys.keys.read().unwrap().get("0").map(|(key, _)| *key),
+            Some(Some((1, DEFAULT_RATE_LIMIT)))
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use ore_pool_types::{PoolListing, SignedPoolListing};
+use solana_sdk::signer::Signer;
+
+use crate::{aggregator::Aggregator, error::Error, operator::Operator, utils::unix_timestamp};
+
+/// The seconds between listings if `BEACON_SECONDS` is unset.
+const DEFAULT_BEACON_SECONDS: u64 = 300;
+
+/// The path listings are posted to, relative to the directory url.
+const DIRECTORY_PATH: &str = "pools";
+
+/// Publishes the pool metadata to a pool directory, signed by the pool authority,
+/// so members can discover the pool and check the listing came from the operator.
+pub struct Beacon {
+    http_client: reqwest::Client,
+    /// the url of the pool directory
+    directory_url: String,
+    /// the time between listings
+    interval: Duration,
+}
+
+impl Beacon {
+    /// None unless `DIRECTORY_URL` is set.
+    pub fn from_env() -> Result<Option<Self>, Error> {
+        let Ok(directory_url) = std::env::var("DIRECTORY_URL") else {
+            return Ok(None);
+        };
+        let seconds = match std::env::var("BEACON_SECONDS") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => DEFAULT_BEACON_SECONDS,
+        };
+        Ok(Some(Self {
+            http_client: reqwest::Client::new(),
+            directory_url: directory_url.trim_end_matches('/').to_string(),
+            interval: Duration::from_secs(seconds),
+        }))
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Signs and posts the current listing of the pool.
+    #[tracing::instrument(skip_all)]
+    pub async fn publish(
+        &self,
+        operator: &Operator,
+        aggregator: &tokio::sync::RwLock<Aggregator>,
+    ) -> Result<(), Error> {
+        let listing = listing(operator, aggregator).await?;
+        let signed = SignedPoolListing {
+            signature: operator.keypair.sign_message(listing.to_bytes().as_slice()),
+            authority: operator.keypair.pubkey(),
+            listing,
+        };
+        let url = format!("{}/{}", self.directory_url, DIRECTORY_PATH);
+        self.http_client
+            .post(url)
+            .json(&signed)
+            .send()
+            .await?
+            .error_for_status()?;
+        tracing::info!("published pool listing: {:?}", signed.listing);
+        Ok(())
+    }
+}
+
+async fn listing(
+    operator: &Operator,
+    aggregator: &tokio::sync::RwLock<Aggregator>,
+) -> Result<PoolListing, Error> {
+    let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+    let pool = operator.get_pool().await?;
+    // the on-chain url is right padded with 0s
+    let url = pool.url.split(|byte| *byte == 0).next().unwrap_or_default();
+    let (operator_commission, staker_commission) = operator.commissions();
+    let hashrate = aggregator.read().await.hashrate();
+    Ok(PoolListing {
+        pool_address,
+        url: String::from_utf8_lossy(url).to_string(),
+        operator_commission,
+        staker_commission,
+        num_members: pool.total_members,
+        hashrate,
+        published_at: unix_timestamp(),
+    })
+}
@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+
+/// How often the background task refreshes the blockhash.
+pub const BLOCKHASH_REFRESH: Duration = Duration::from_secs(5);
+
+/// The age past which a cached blockhash is fetched again on use,
+/// well within the ~60 seconds a blockhash stays valid for.
+const BLOCKHASH_MAX_AGE: Duration = Duration::from_secs(20);
+
+/// The latest blockhash, refreshed in the background
+/// so transactions can be signed without waiting on the rpc.
+#[derive(Default)]
+pub struct BlockhashCache {
+    latest: RwLock<Option<(Hash, Instant)>>,
+}
+
+impl BlockhashCache {
+    /// The cached blockhash, unless too old to land with.
+    pub fn get(&self) -> Option<Hash> {
+        let latest = self.latest.read().ok()?;
+        let (hash, fetched_at) = (*latest)?;
+        (fetched_at.elapsed() < BLOCKHASH_MAX_AGE).then_some(hash)
+    }
+
+    pub fn set(&self, hash: Hash) {
+        if let Ok(mut latest) = self.latest.write() {
+            *latest = Some((hash, Instant::now()));
+        }
+    }
+}
+
+/// The ids of the members, which never change once registered,
+/// so contributions are validated without the db, and while it's unavailable.
+/// Cleared when a backup is restored, whose ids replace those read before.
+#[derive(Default)]
+pub struct MemberIdCache {
+    ids: RwLock<HashMap<Pubkey, i64>>,
+}
+
+impl MemberIdCache {
+    pub fn get(&self, authority: &Pubkey) -> Option<i64> {
+        self.ids.read().ok()?.get(authority).copied()
+    }
+
+    pub fn insert(&self, authority: Pubkey, id: i64) {
+        if let Ok(mut ids) = self.ids.write() {
+            ids.insert(authority, id);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut ids) = self.ids.write() {
+            ids.clear();
+        }
+    }
+}
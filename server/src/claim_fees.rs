@@ -0,0 +1,182 @@
+use ore_api::consts::{MINT_ADDRESS, ONE_ORE};
+use ore_pool_types::ClaimQuote;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+use crate::{error::Error, operator::Operator, telemetry};
+
+/// The compute unit limit of claims whose fee is paid by the operator.
+pub const CU_LIMIT: u32 = 100_000;
+
+/// The compute unit price of claims whose fee is paid by the operator, in micro lamports.
+pub const CU_PRICE: u64 = 10_000;
+
+/// The signatures of a claim whose fee is paid by the operator, the operator's and the member's.
+pub const NUM_SIGNATURES: u8 = 2;
+
+/// The base fee of each signature, in lamports.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// The size of a token account, for the rent of the member token account.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// The max service fee, in basis points of the amount claimed.
+const MAX_SERVICE_FEE_BPS: u64 = 10_000;
+
+/// The fee model of claims relayed by the server with the operator as fee payer,
+/// for members without SOL to pay for their own claims.
+///
+/// Enabled by setting `CLAIM_FEE_LAMPORTS_PER_ORE`, the rate the transaction fee is converted to ORE at.
+/// The transaction fee, plus a service fee of `CLAIM_SERVICE_FEE_BPS` basis points of the amount claimed,
+/// is deducted from the claim by a transfer to the operator's ORE token account in the same transaction,
+/// as quoted by `/claim/quote` before the member signs.
+/// Members paying for their own claims are unaffected.
+pub struct ClaimFees {
+    /// The lamports one ORE is converted at, none if disabled.
+    lamports_per_ore: Option<u64>,
+
+    /// The service fee in basis points of the amount claimed.
+    service_fee_bps: u64,
+}
+
+impl ClaimFees {
+    pub fn new(lamports_per_ore: Option<u64>, service_fee_bps: u64) -> Self {
+        Self {
+            lamports_per_ore: lamports_per_ore.filter(|rate| *rate > 0),
+            service_fee_bps: service_fee_bps.min(MAX_SERVICE_FEE_BPS),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let lamports_per_ore = match std::env::var("CLAIM_FEE_LAMPORTS_PER_ORE") {
+            Ok(str) => Some(str.parse()?),
+            // optional
+            Err(_) => None,
+        };
+        let service_fee_bps = match std::env::var("CLAIM_SERVICE_FEE_BPS") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => 0,
+        };
+        let fees = Self::new(lamports_per_ore, service_fee_bps);
+        if fees.is_enabled() {
+            tracing::info!(
+                "operator-paid claims enabled, service fee {} bps",
+                fees.service_fee_bps
+            );
+        }
+        Ok(fees)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lamports_per_ore.is_some()
+    }
+
+    /// The ORE token account of the operator the fees are transferred to.
+    pub fn fee_account(operator: &Operator) -> Pubkey {
+        spl_associated_token_account::get_associated_token_address(
+            &operator.keypair.pubkey(),
+            &MINT_ADDRESS,
+        )
+    }
+
+    /// Quotes the fees of claiming the amount,
+    /// including the rent of the member token account if it doesn't exist yet.
+    pub async fn quote(
+        &self,
+        operator: &Operator,
+        authority: &Pubkey,
+        amount: u64,
+    ) -> Result<ClaimQuote, Error> {
+        let beneficiary =
+            spl_associated_token_account::get_associated_token_address(authority, &MINT_ADDRESS);
+        let rpc_client = &operator.rpc_client;
+        let account = telemetry::rpc(
+            "get_account",
+            rpc_client.get_account_with_commitment(&beneficiary, rpc_client.commitment()),
+        )
+        .await?;
+        let rent = match account.value {
+            Some(_) => 0,
+            None => {
+                telemetry::rpc(
+                    "get_minimum_balance_for_rent_exemption",
+                    rpc_client.get_minimum_balance_for_rent_exemption(TOKEN_ACCOUNT_LEN),
+                )
+                .await?
+            }
+        };
+        self.quote_with_rent(
+            operator.keypair.pubkey(),
+            Self::fee_account(operator),
+            amount,
+            rent,
+        )
+    }
+
+    fn quote_with_rent(
+        &self,
+        fee_payer: Pubkey,
+        fee_account: Pubkey,
+        amount: u64,
+        rent: u64,
+    ) -> Result<ClaimQuote, Error> {
+        let lamports_per_ore = self.lamports_per_ore.ok_or(Error::ClaimFeesDisabled)?;
+        let priority_fee = (CU_LIMIT as u64 * CU_PRICE).div_ceil(1_000_000);
+        let network_fee = NUM_SIGNATURES as u64 * LAMPORTS_PER_SIGNATURE + priority_fee + rent;
+        let network_fee_grains = (network_fee as u128 * ONE_ORE as u128)
+            .div_ceil(lamports_per_ore as u128)
+            .min(u64::MAX as u128) as u64;
+        let service_fee = (amount as u128 * self.service_fee_bps as u128 / 10_000) as u64;
+        let fee = network_fee_grains.saturating_add(service_fee);
+        if fee >= amount {
+            return Err(Error::ClaimBelowFee(fee));
+        }
+        Ok(ClaimQuote {
+            amount,
+            network_fee,
+            network_fee_grains,
+            service_fee,
+            fee,
+            net_amount: amount - fee,
+            fee_payer,
+            fee_account,
+            compute_unit_limit: CU_LIMIT,
+            compute_unit_price: CU_PRICE,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_fees() {
+        let (fee_payer, fee_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // 1 ORE per SOL
+        let fees = ClaimFees::new(Some(1_000_000_000), 100);
+        let quote = fees
+            .quote_with_rent(fee_payer, fee_account, ONE_ORE, 0)
+            .unwrap();
+        // two signatures and 100k compute units at 10k micro lamports
+        assert_eq!(quote.network_fee, 11_000);
+        assert_eq!(quote.network_fee_grains, 1_100_000);
+        assert_eq!(quote.service_fee, ONE_ORE / 100);
+        assert_eq!(quote.fee, 1_100_000 + ONE_ORE / 100);
+        assert_eq!(quote.net_amount + quote.fee, ONE_ORE);
+        // the rent of a new token account is passed through
+        let quote = fees
+            .quote_with_rent(fee_payer, fee_account, ONE_ORE, 2_039_280)
+            .unwrap();
+        assert_eq!(quote.network_fee, 2_050_280);
+        // claims that don't cover the fee are rejected
+        assert!(matches!(
+            fees.quote_with_rent(fee_payer, fee_account, 1_000, 0),
+            Err(Error::ClaimBelowFee(_))
+        ));
+        assert!(matches!(
+            ClaimFees::new(None, 0).quote_with_rent(fee_payer, fee_account, ONE_ORE, 0),
+            Err(Error::ClaimFeesDisabled)
+        ));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Mutex,
+    time::Duration,
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, HttpMessage, HttpRequest,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::error::Error;
+
+/// The header load balancers append the client address to.
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The signature of a PROXY protocol v2 header.
+const PROXY_V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The max length of a PROXY protocol v1 header, including the CRLF.
+const PROXY_V1_MAX_LEN: usize = 107;
+
+/// The max length of a PROXY protocol v2 header accepted,
+/// large enough for the addresses and common TLVs.
+const PROXY_V2_MAX_LEN: usize = 16 + 520;
+
+/// The time a proxied connection has to send its PROXY header.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The load balancers trusted to report the client address,
+/// from `TRUSTED_PROXIES`, a comma separated list of ips and cidr ranges.
+/// Unset trusts none, and requests are keyed on the peer address.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    pub fn from_env() -> Result<Self, Error> {
+        match std::env::var("TRUSTED_PROXIES") {
+            Ok(str) => Self::parse(str.as_str()),
+            // optional
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(str: &str) -> Result<Self, Error> {
+        let mut ranges = vec![];
+        for range in str.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (ip, prefix) = match range.split_once('/') {
+                Some((ip, prefix)) => (ip.parse::<IpAddr>()?, Some(prefix.parse::<u8>()?)),
+                None => (range.parse::<IpAddr>()?, None),
+            };
+            let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+            let prefix = prefix.unwrap_or(max_prefix);
+            if prefix > max_prefix {
+                return Err(Error::Config(format!("invalid trusted proxy: {}", range)));
+            }
+            ranges.push((canonical(ip), prefix));
+        }
+        Ok(Self { ranges })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.ranges.iter().any(|(range, prefix)| match (range, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                masked(u32::from(*range) as u128, *prefix, 32)
+                    == masked(u32::from(ip) as u128, *prefix, 32)
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                masked(u128::from(*range), *prefix, 128) == masked(u128::from(ip), *prefix, 128)
+            }
+            _ => false,
+        })
+    }
+
+    /// The client address of a request from the peer.
+    /// If the peer is trusted, the forwarded-for chain is walked from the right,
+    /// skipping trusted proxies, to the first address appended by a trusted proxy.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let peer = canonical(peer);
+        if !self.contains(peer) {
+            return peer;
+        }
+        let Some(forwarded_for) = forwarded_for else {
+            return peer;
+        };
+        let mut client = peer;
+        for hop in forwarded_for.rsplit(',') {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                // unparseable hops are spoofable, stop at the last good one
+                break;
+            };
+            client = canonical(ip);
+            if !self.contains(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// The client addresses of the connections forwarded by the PROXY protocol listener,
+/// by the local address each was forwarded from, as actix sees the peer.
+#[derive(Default)]
+pub struct ProxiedAddrs(Mutex<HashMap<SocketAddr, SocketAddr>>);
+
+impl ProxiedAddrs {
+    fn get(&self, local: &SocketAddr) -> Option<SocketAddr> {
+        self.0.lock().ok()?.get(local).copied()
+    }
+
+    fn insert(&self, local: SocketAddr, source: SocketAddr) {
+        if let Ok(mut addrs) = self.0.lock() {
+            addrs.insert(local, source);
+        }
+    }
+
+    fn remove(&self, local: &SocketAddr) {
+        if let Ok(mut addrs) = self.0.lock() {
+            addrs.remove(local);
+        }
+    }
+}
+
+/// The real client address of a request, set by [`real_ip`].
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+/// Resolves the real client address of the request,
+/// through the PROXY protocol listener and trusted forwarded-for headers.
+pub async fn real_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(peer) = req.peer_addr() {
+        let peer_ip = req
+            .app_data::<web::Data<ProxiedAddrs>>()
+            .and_then(|proxied| proxied.get(&peer))
+            .map(|source| source.ip())
+            .unwrap_or(peer.ip());
+        let forwarded_for = req
+            .headers()
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let ip = match req.app_data::<web::Data<TrustedProxies>>() {
+            Some(trusted) => trusted.client_ip(peer_ip, forwarded_for),
+            None => canonical(peer_ip),
+        };
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.call(req).await
+}
+
+/// The real client address of the request, if resolved.
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    req.extensions()
+        .get::<ClientIp>()
+        .map(|client_ip| client_ip.0)
+}
+
+/// Whether connections start with a PROXY protocol header, `PROXY_PROTOCOL`,
+/// as sent by load balancers that pass tcp through (e.g. for tls termination upstream).
+/// The header is only accepted from trusted proxies, so they must be set,
+/// otherwise any client could claim any address.
+pub fn proxy_protocol_from_env(trusted: &TrustedProxies) -> Result<bool, Error> {
+    let enabled = match std::env::var("PROXY_PROTOCOL") {
+        Ok(str) => str.parse()?,
+        // optional
+        Err(_) => false,
+    };
+    if enabled && trusted.is_empty() {
+        return Err(Error::Config(
+            "PROXY_PROTOCOL requires TRUSTED_PROXIES".to_string(),
+        ));
+    }
+    Ok(enabled)
+}
+
+/// Accepts connections that start with a PROXY protocol (v1 or v2) header,
+/// and forwards the rest of each connection to the http server on `upstream`,
+/// recording the client address for [`real_ip`].
+/// Connections from peers outside the trusted proxies are dropped.
+pub async fn proxy_protocol(
+    listener: TcpListener,
+    upstream: SocketAddr,
+    trusted: TrustedProxies,
+    proxied: web::Data<ProxiedAddrs>,
+) -> Result<(), Error> {
+    loop {
+        let (inbound, peer) = listener.accept().await?;
+        if !trusted.contains(peer.ip()) {
+            tracing::warn!("dropped connection from untrusted proxy {}", peer);
+            continue;
+        }
+        let proxied = proxied.clone();
+        tokio::spawn(async move {
+            if let Err(err) = forward(inbound, upstream, proxied.get_ref()).await {
+                tracing::debug!("proxied connection from {} closed: {:?}", peer, err);
+            }
+        });
+    }
+}
+
+async fn forward(
+    mut inbound: TcpStream,
+    upstream: SocketAddr,
+    proxied: &ProxiedAddrs,
+) -> Result<(), Error> {
+    let mut buffer = Vec::with_capacity(PROXY_V1_MAX_LEN);
+    let read_header = async {
+        let mut chunk = [0u8; 256];
+        loop {
+            let n = inbound.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(Error::InvalidProxyHeader);
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+            if let Some(header) = parse_proxy_header(buffer.as_slice())? {
+                return Ok(header);
+            }
+        }
+    };
+    let (source, len) = tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_header)
+        .await
+        .map_err(|_| Error::InvalidProxyHeader)??;
+    let mut outbound = TcpStream::connect(upstream).await?;
+    let local = outbound.local_addr()?;
+    // recorded before any bytes reach the server
+    if let Some(source) = source {
+        proxied.insert(local, source);
+    }
+    let res = async {
+        outbound.write_all(&buffer[len..]).await?;
+        tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+    }
+    .await;
+    proxied.remove(&local);
+    res?;
+    Ok(())
+}
+
+/// Parses the PROXY protocol header at the start of the buffer,
+/// returning the source address (none for LOCAL and UNKNOWN connections)
+/// and the length of the header, or none if the header is incomplete.
+pub fn parse_proxy_header(buffer: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, Error> {
+    if buffer.len() < PROXY_V2_SIGNATURE.len() {
+        let is_prefix = PROXY_V2_SIGNATURE.starts_with(buffer)
+            || b"PROXY ".starts_with(buffer)
+            || buffer.starts_with(b"PROXY ");
+        return match is_prefix {
+            true => Ok(None),
+            false => Err(Error::InvalidProxyHeader),
+        };
+    }
+    if buffer.starts_with(&PROXY_V2_SIGNATURE) {
+        return parse_proxy_v2(buffer);
+    }
+    if buffer.starts_with(b"PROXY ") {
+        return parse_proxy_v1(buffer);
+    }
+    Err(Error::InvalidProxyHeader)
+}
+
+fn parse_proxy_v1(buffer: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, Error> {
+    let Some(end) = buffer.windows(2).position(|w| w == b"\r\n") else {
+        return match buffer.len() < PROXY_V1_MAX_LEN {
+            true => Ok(None),
+            false => Err(Error::InvalidProxyHeader),
+        };
+    };
+    let line = std::str::from_utf8(&buffer[..end]).map_err(|_| Error::InvalidProxyHeader)?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    let source = match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => None,
+        ["PROXY", "TCP4" | "TCP6", source, _, source_port, _] => {
+            let ip: IpAddr = source.parse().map_err(|_| Error::InvalidProxyHeader)?;
+            let port: u16 = source_port.parse().map_err(|_| Error::InvalidProxyHeader)?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(Error::InvalidProxyHeader),
+    };
+    Ok(Some((source, end + 2)))
+}
+
+fn parse_proxy_v2(buffer: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, Error> {
+    if buffer.len() < 16 {
+        return Ok(None);
+    }
+    let version_command = buffer[12];
+    let family = buffer[13];
+    let len = 16 + u16::from_be_bytes([buffer[14], buffer[15]]) as usize;
+    if version_command >> 4 != 2 || len > PROXY_V2_MAX_LEN {
+        return Err(Error::InvalidProxyHeader);
+    }
+    if buffer.len() < len {
+        return Ok(None);
+    }
+    let addresses = &buffer[16..len];
+    // LOCAL connections, e.g. health checks, carry no client
+    if version_command & 0x0f == 0 {
+        return Ok(Some((None, len)));
+    }
+    let source = match family {
+        // TCP over IPv4
+        0x11 if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Some(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        // TCP over IPv6
+        0x21 if addresses.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        // UNSPEC and unix sockets
+        0x00 | 0x31 | 0x32 => None,
+        _ => return Err(Error::InvalidProxyHeader),
+    };
+    Ok(Some((source, len)))
+}
+
+/// IPv4 clients of a dual-stack socket are seen as IPv4-mapped IPv6 addresses.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => ip,
+        },
+        ip => ip,
+    }
+}
+
+fn masked(bits: u128, prefix: u8, width: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        prefix => bits >> (width - prefix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trusts_ranges() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8, 192.168.1.1, fd00::/8").unwrap();
+        assert!(trusted.contains("10.1.2.3".parse().unwrap()));
+        assert!(trusted.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(trusted.contains("192.168.1.1".parse().unwrap()));
+        assert!(!trusted.contains("192.168.1.2".parse().unwrap()));
+        assert!(trusted.contains("fd12::1".parse().unwrap()));
+        assert!(!trusted.contains("fe80::1".parse().unwrap()));
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("not an ip").is_err());
+    }
+
+    #[test]
+    fn walks_forwarded_for() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        // untrusted peers can't spoof the header
+        assert_eq!(trusted.client_ip(client, Some("198.51.100.1")), client);
+        assert_eq!(trusted.client_ip(proxy, None), proxy);
+        assert_eq!(
+            trusted.client_ip(proxy, Some("198.51.100.1, 203.0.113.7, 10.0.0.2")),
+            client
+        );
+        assert_eq!(
+            trusted.client_ip(proxy, Some("203.0.113.7, garbage, 10.0.0.2")),
+            "10.0.0.2".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            TrustedProxies::default().client_ip(proxy, Some("203.0.113.7")),
+            proxy
+        );
+    }
+
+    #[test]
+    fn parses_proxy_v1() {
+        let header = b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 443\r\nGET / HTTP/1.1\r\n";
+        let (source, len) = parse_proxy_header(header).unwrap().unwrap();
+        assert_eq!(source, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(&header[len..len + 3], b"GET");
+        let (source, _) = parse_proxy_header(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+        assert_eq!(source, None);
+        assert!(parse_proxy_header(b"PROXY TCP4 203.0").unwrap().is_none());
+        assert!(parse_proxy_header(b"GET / HTTP/1.1\r\n").is_err());
+    }
+
+    #[test]
+    fn parses_proxy_v2() {
+        let mut header = PROXY_V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[203, 0, 113, 7, 10, 0, 0, 1]);
+        header.extend_from_slice(&51234u16.to_be_bytes());
+        header.extend_from_slice(&443u16.to_be_bytes());
+        assert!(parse_proxy_header(&header[..20]).unwrap().is_none());
+        header.extend_from_slice(b"GET");
+        let (source, len) = parse_proxy_header(&header).unwrap().unwrap();
+        assert_eq!(source, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(len, 28);
+        // LOCAL
+        header[12] = 0x20;
+        let (source, _) = parse_proxy_header(&header).unwrap().unwrap();
+        assert_eq!(source, None);
+    }
+}
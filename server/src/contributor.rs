@@ -1,31 +1,65 @@
-use actix_web::{web, HttpResponse, Responder};
+use std::{str::FromStr, time::Instant};
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use ore_pool_types::{
-    BalanceUpdate, ContributePayload, GetMemberPayload, MemberChallenge, PoolAddress,
-    RegisterPayload, RegisterStakerPayload, Staker, UpdateBalancePayload,
+    validate, BalanceUpdate, ClaimPayload, ClaimQuoteQuery, ClaimReceipt, ContributePayload,
+    GetMemberPayload, MemberChallenge, MemberInfo, NotificationChannel, NotificationPrefs,
+    NotificationPrefsPayload, PayoutStatement, PoolAddress, RegisterPayload, RegisterStakerPayload,
+    ShareVerification, SignedPayoutStatement, Staker, UpdateBalancePayload,
 };
-use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, signer::Signer};
 
 use crate::{
-    aggregator::{Aggregator, BUFFER_CLIENT},
-    database,
-    error::Error,
+    aggregator::Aggregator,
+    api_keys,
+    claim_fees::ClaimFees,
+    client_ip, database,
+    error::{Context, Error},
+    notify::{self, Notifier},
     operator::Operator,
-    tx, webhook, Contribution,
+    prescreen::Rejection,
+    queue::{self, ContributionQueue},
+    registration::Registration,
+    signatures::SignatureVerifier,
+    telemetry, tx,
+    utils::unix_timestamp,
+    webhook, Contribution,
 };
 
+/// The worker name of contributions that don't name one.
+const DEFAULT_WORKER: &str = "default";
+
+/// The compute unit limit of a join transaction paid by the operator.
+const JOIN_CU_LIMIT: u32 = 50_000;
+
+/// The compute unit price of a join transaction paid by the operator, in micro lamports.
+const JOIN_CU_PRICE: u64 = 10_000;
+
 ////////////////////////////////////////////////////////////////////////////////////
 /// HTTP HANDLERS //////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 pub async fn register(
+    req: HttpRequest,
     operator: web::Data<Operator>,
+    registration: web::Data<Registration>,
     payload: web::Json<RegisterPayload>,
 ) -> impl Responder {
+    telemetry::record_member(&payload.authority);
+    // verify the proof of work before touching the rpc or db
+    if let Err(err) = registration.verify(&payload.authority, payload.solution.as_ref()) {
+        tracing::error!("{:?}", err);
+        return HttpResponse::from(err);
+    }
+    if let Err(retry_after) = registration.throttle(client_ip::client_ip(&req)) {
+        tracing::warn!("registration rate limited: {}", payload.authority);
+        return api_keys::too_many_requests(retry_after);
+    }
     let operator = operator.as_ref();
     let res = register_new_member(operator, payload.into_inner()).await;
     match res {
         Ok(db_member) => HttpResponse::Ok().json(&db_member),
         Err(err) => {
-            log::error!("{:?}", err);
+            tracing::error!("{:?}", err);
             let http_response: HttpResponse = err.into();
             http_response
         }
@@ -38,6 +72,7 @@ pub async fn register_staker(
     webhook_client: web::Data<webhook::Client>,
     payload: web::Json<RegisterStakerPayload>,
 ) -> impl Responder {
+    telemetry::record_member(&payload.authority);
     let res = register_new_staker(
         operator.as_ref(),
         aggregator.as_ref(),
@@ -48,13 +83,29 @@ pub async fn register_staker(
     match res {
         Ok(staker) => HttpResponse::Ok().json(staker),
         Err(err) => {
-            log::error!("{:?}", err);
+            tracing::error!("{:?}", err);
             let http_response: HttpResponse = err.into();
             http_response
         }
     }
 }
 
+/// The proof-of-work challenge the authority must solve to register.
+pub async fn register_challenge(
+    registration: web::Data<Registration>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    let authority = match path.authority() {
+        Ok(authority) => authority,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(Error::from(err));
+        }
+    };
+    telemetry::record_member(&authority);
+    HttpResponse::Ok().json(registration.challenge(&authority))
+}
+
 pub async fn pool_address(operator: web::Data<Operator>) -> impl Responder {
     let operator = operator.as_ref();
     let (pool_pda, bump) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
@@ -68,101 +119,430 @@ pub async fn update_balance(
     operator: web::Data<Operator>,
     payload: web::Json<UpdateBalancePayload>,
 ) -> impl Responder {
+    telemetry::record_member(&payload.authority);
     match update_balance_onchain(operator.as_ref(), payload.into_inner()).await {
         Ok(balance_update) => HttpResponse::Ok().json(balance_update),
         Err(err) => {
-            log::error!("{:?}", err);
-            HttpResponse::InternalServerError().body(err.to_string())
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
         }
     }
 }
 
-pub async fn member(
+/// Relays a claim signed by the member,
+/// rejecting claims below the pool minimum before they cost the member a fee.
+/// Claims with the operator as fee payer are signed by the operator,
+/// if they transfer at least the quoted fee to the operator.
+pub async fn claim(
     operator: web::Data<Operator>,
-    path: web::Path<GetMemberPayload>,
+    claim_fees: web::Data<ClaimFees>,
+    payload: web::Json<ClaimPayload>,
+) -> impl Responder {
+    telemetry::record_member(&payload.authority);
+    match relay_claim(operator.as_ref(), claim_fees.as_ref(), payload.into_inner()).await {
+        Ok(receipt) => HttpResponse::Ok().json(receipt),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// Quotes the fees of claiming with the operator as fee payer,
+/// and the net amount the member receives.
+pub async fn claim_quote(
+    operator: web::Data<Operator>,
+    claim_fees: web::Data<ClaimFees>,
+    query: web::Query<ClaimQuoteQuery>,
 ) -> impl Responder {
-    match operator
-        .get_member_db(path.into_inner().authority.as_str())
+    let authority = match Pubkey::from_str(query.authority.as_str()) {
+        Ok(authority) => authority,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(Error::from(err));
+        }
+    };
+    telemetry::record_member(&authority);
+    match claim_fees
+        .quote(operator.as_ref(), &authority, query.amount)
         .await
     {
-        Ok(member) => HttpResponse::Ok().json(&member),
+        Ok(quote) => HttpResponse::Ok().json(quote),
         Err(err) => {
-            log::error!("{:?}", err);
-            HttpResponse::NotFound().finish()
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
         }
     }
 }
 
-// TODO: consider the need for auth on this get/read?
-pub async fn challenge(aggregator: web::Data<tokio::sync::RwLock<Aggregator>>) -> impl Responder {
-    // acquire read on aggregator for challenge
-    let (challenge, last_num_members) = {
+/// Stores the notification preferences of a member, signed by its authority.
+pub async fn notifications(
+    operator: web::Data<Operator>,
+    notifier: web::Data<Notifier>,
+    payload: web::Json<NotificationPrefsPayload>,
+) -> impl Responder {
+    telemetry::record_member(&payload.authority);
+    let res =
+        update_notification_prefs(operator.as_ref(), notifier.as_ref(), payload.into_inner()).await;
+    match res {
+        Ok(prefs) => HttpResponse::Ok().json(prefs),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// The member record, with its hashrate and acceptance ratio
+/// over the last 10 minutes, hour, and day.
+pub async fn member(
+    operator: web::Data<Operator>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    if let Err(err) = path.authority() {
+        tracing::error!("{:?}", err);
+        return HttpResponse::from(Error::from(err));
+    }
+    let authority = path.into_inner().authority;
+    let member = match operator.get_member_db(authority.as_str()).await {
+        Ok(member) => member,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(err);
+        }
+    };
+    let res = async {
+        let conn = operator.db_client.get().await?;
+        database::read_member_hashrate(&conn, authority.as_str(), unix_timestamp()).await
+    }
+    .await;
+    match res {
+        Ok(hashrate) => HttpResponse::Ok().json(MemberInfo { member, hashrate }),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+pub async fn workers(
+    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    let authority = match path.authority() {
+        Ok(authority) => authority,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(Error::from(err));
+        }
+    };
+    telemetry::record_member(&authority);
+    let workers = {
         let aggregator = aggregator.read().await;
-        (aggregator.challenge, aggregator.num_members)
+        aggregator.worker_stats(&authority)
     };
-    // build member challenge
-    let member_challenge = MemberChallenge {
-        challenge,
-        buffer: BUFFER_CLIENT,
-        num_total_members: last_num_members,
+    HttpResponse::Ok().json(workers)
+}
+
+/// The manual balance adjustments applied to the member by the operator, most recent first.
+pub async fn adjustments(
+    operator: web::Data<Operator>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    if let Err(err) = path.authority() {
+        tracing::error!("{:?}", err);
+        return HttpResponse::from(Error::from(err));
+    }
+    let res = async {
+        let conn = operator.db_client.get().await?;
+        database::read_balance_adjustments(&conn, path.authority.as_str()).await
+    }
+    .await;
+    match res {
+        Ok(adjustments) => HttpResponse::Ok().json(adjustments),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// The payouts of the member in the most recent distributed rounds,
+/// as statements signed by the pool authority that can be exported and verified offline.
+pub async fn payouts(
+    operator: web::Data<Operator>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    if let Err(err) = path.authority() {
+        tracing::error!("{:?}", err);
+        return HttpResponse::from(Error::from(err));
+    }
+    let res = async {
+        let conn = operator.db_client.get().await?;
+        let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+        database::read_round_payouts(&conn, &pool_address, path.authority.as_str(), None).await
+    }
+    .await;
+    match res {
+        Ok(statements) => HttpResponse::Ok().json(sign_payouts(&operator, statements)),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// The payout of the member in the round, as a statement signed by the pool authority.
+pub async fn payout(
+    operator: web::Data<Operator>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (member, round) = path.into_inner();
+    let res = async {
+        let authority = validate::pubkey(member.as_str())?;
+        let challenge = Hash::from_str(round.as_str())
+            .map_err(|_| Error::Validation(format!("invalid round: {}", round)))?;
+        let conn = operator.db_client.get().await?;
+        let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+        let statements = database::read_round_payouts(
+            &conn,
+            &pool_address,
+            authority.to_string().as_str(),
+            Some(challenge.to_string().as_str()),
+        )
+        .await?;
+        sign_payouts(&operator, statements)
+            .pop()
+            .ok_or(Error::NotFound(format!(
+                "no payout of {} in round {}",
+                authority, challenge
+            )))
+    }
+    .await;
+    match res {
+        Ok(statement) => HttpResponse::Ok().json(statement),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// The archived shares of the member in the round re-verified against the round challenge,
+/// so anyone can check the scores credited by the operator correspond to real work.
+pub async fn verify(
+    operator: web::Data<Operator>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (round, member) = path.into_inner();
+    let res = async {
+        let challenge = Hash::from_str(round.as_str())
+            .map_err(|_| Error::Validation(format!("invalid round: {}", round)))?;
+        let authority = validate::pubkey(member.as_str())?;
+        let conn = operator.db_client.get().await?;
+        let shares = database::read_round_shares(
+            &conn,
+            challenge.to_string().as_str(),
+            authority.to_string().as_str(),
+        )
+        .await?;
+        if shares.is_empty() {
+            return Err(Error::NotFound(format!(
+                "no archived shares of {} in round {}",
+                authority, challenge
+            )));
+        }
+        Ok(ShareVerification::new(&challenge.to_bytes(), shares))
+    }
+    .await;
+    match res {
+        Ok(verification) => HttpResponse::Ok().json(verification),
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            HttpResponse::from(err)
+        }
+    }
+}
+
+/// The difficulties of the shares accepted from the member since the server started.
+pub async fn member_difficulty(
+    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    let authority = match path.authority() {
+        Ok(authority) => authority,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return HttpResponse::from(Error::from(err));
+        }
+    };
+    let histogram = {
+        let aggregator = aggregator.read().await;
+        aggregator.member_difficulty(&authority)
+    };
+    HttpResponse::Ok().json(histogram)
+}
+
+/// The difficulties of the shares of the current round,
+/// and the members whose shares are suspiciously uniform.
+pub async fn difficulty_stats(
+    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+) -> impl Responder {
+    let stats = {
+        let aggregator = aggregator.read().await;
+        aggregator.difficulty_stats()
     };
-    HttpResponse::Ok().json(&member_challenge)
+    HttpResponse::Ok().json(stats)
+}
+
+// TODO: consider the need for auth on this get/read?
+/// Serves the published challenge, which never waits on the aggregator lock,
+/// so members get the next challenge while the last round is still being distributed.
+pub async fn challenge(
+    challenges: web::Data<tokio::sync::watch::Receiver<MemberChallenge>>,
+) -> impl Responder {
+    let member_challenge = *challenges.borrow();
+    HttpResponse::Ok().json(member_challenge)
+}
+
+/// Streams the member challenge as server-sent events,
+/// starting with the current challenge and then once per new challenge.
+pub async fn challenge_stream(
+    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+) -> impl Responder {
+    let rx = {
+        let aggregator = aggregator.read().await;
+        aggregator.challenge_tx.subscribe()
+    };
+    let stream = futures::stream::unfold((rx, true), |(mut rx, first)| async move {
+        if !first && rx.changed().await.is_err() {
+            // the aggregator dropped the sender, end the stream
+            return None;
+        }
+        let json = serde_json::to_string(&*rx.borrow_and_update());
+        let event = match json {
+            Ok(json) => Ok(web::Bytes::from(format!("data: {}\n\n", json))),
+            Err(err) => Err(actix_web::error::ErrorInternalServerError(err)),
+        };
+        Some((event, (rx, false)))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
 }
 
 /// Accepts solutions from pool members. If their solutions are valid, it
 /// aggregates the contributions into a list for publishing and submission.
 pub async fn contribute(
     operator: web::Data<Operator>,
-    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
-    tx: web::Data<tokio::sync::mpsc::UnboundedSender<Contribution>>,
+    challenges: web::Data<tokio::sync::watch::Receiver<MemberChallenge>>,
+    signature_verifier: web::Data<SignatureVerifier>,
+    queue: web::Data<ContributionQueue>,
     payload: web::Json<ContributePayload>,
+    phases: telemetry::Phases,
 ) -> impl Responder {
-    // acquire read on aggregator for challenge
-    let aggregator = aggregator.read().await;
-    let challenge = aggregator.challenge;
-    let num_members = aggregator.num_members;
-    drop(aggregator);
+    telemetry::record_member(&payload.authority);
+    // validate against a snapshot of the challenge,
+    // so invalid solutions never wait on the aggregator lock
+    let (challenge, num_members) = {
+        let member_challenge = challenges.borrow();
+        (
+            member_challenge.challenge,
+            member_challenge.num_total_members,
+        )
+    };
+    // error if worker name invalid
+    if let Err(err) = payload.validate() {
+        tracing::error!("{}: {:?}", err, payload.authority);
+        telemetry::record_contribution("invalid_worker");
+        return HttpResponse::from(Error::from(err));
+    }
     // decode solution difficulty
     let solution = &payload.solution;
     let difficulty = solution.to_hash().difficulty();
     // authenticate the sender signature
-    if !payload
-        .signature
-        .verify(&payload.authority.to_bytes(), &solution.to_bytes())
-    {
+    let is_valid_signature = phases
+        .time(
+            "verify_signature",
+            signature_verifier.verify(&payload.authority, &payload.signature, &solution.to_bytes()),
+        )
+        .await;
+    if !is_valid_signature {
+        telemetry::record_contribution("invalid_signature");
         return HttpResponse::Unauthorized().finish();
     }
     // error if solution below min difficulty
     if difficulty < (challenge.min_difficulty as u32) {
-        log::error!("solution below min difficulity: {:?}", payload.authority);
-        return HttpResponse::BadRequest().finish();
-    }
-    // error if digest is invalid
-    if !drillx::is_valid_digest(&challenge.challenge, &solution.n, &solution.d) {
-        log::error!("invalid solution");
-        return HttpResponse::BadRequest().finish();
+        tracing::error!("solution below min difficulity: {:?}", payload.authority);
+        return reject(&operator, &payload.authority, Rejection::BelowMinDifficulty);
     }
     // validate nonce
     let member_authority = &payload.authority;
     let nonce = solution.n;
     let nonce = u64::from_le_bytes(nonce);
-    if let Err(err) = validate_nonce(operator.as_ref(), member_authority, nonce, num_members).await
-    {
-        log::error!("{:?}", err);
-        return HttpResponse::Unauthorized().finish();
+    let res = phases
+        .time(
+            "validate_nonce",
+            validate_nonce(operator.as_ref(), member_authority, nonce, num_members),
+        )
+        .await;
+    if let Err(err) = res {
+        tracing::error!("{:?}", err);
+        return reject(&operator, member_authority, Rejection::InvalidNonce);
+    }
+    // error if nonce already received, before paying for the digest
+    if operator.seen_nonces.contains(&challenge.challenge, nonce) {
+        tracing::error!("already received solution: {:?}", member_authority);
+        return reject(&operator, member_authority, Rejection::DuplicateNonce);
+    }
+    // error if digest is invalid
+    let timer = Instant::now();
+    let is_valid_digest = drillx::is_valid_digest(&challenge.challenge, &solution.n, &solution.d);
+    phases.record("verify_digest", timer);
+    if !is_valid_digest {
+        tracing::error!("invalid solution");
+        return reject(&operator, member_authority, Rejection::InvalidDigest);
     }
     // calculate score
-    let score = 2u64.pow(difficulty);
+    let score = ore_pool_types::verify::score(difficulty);
     // update the aggegator
-    if let Err(err) = tx.send(Contribution {
+    let payload = payload.into_inner();
+    let worker = payload.worker.unwrap_or(DEFAULT_WORKER.to_string());
+    let res = queue.push(Contribution {
         member: payload.authority,
+        worker,
         score,
         solution: payload.solution,
-    }) {
-        log::error!("{:?}", err);
+        challenge: challenge.challenge,
+        span: tracing::Span::current(),
+    });
+    match res {
+        Ok(None) => {}
+        Ok(Some(dropped)) => {
+            tracing::warn!("contribution queue full, dropped: {:?}", dropped.member);
+            telemetry::record_contribution("dropped");
+        }
+        Err(_) => {
+            tracing::warn!("contribution queue full");
+            telemetry::record_contribution("queue_full");
+            return HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", queue::RETRY_AFTER_SECONDS.to_string()))
+                .finish();
+        }
     }
+    // only once queued, so contributions retried after a full queue aren't duplicates
+    operator.seen_nonces.insert(&challenge.challenge, nonce);
     HttpResponse::Ok().finish()
 }
+
+fn reject(operator: &Operator, authority: &Pubkey, rejection: Rejection) -> HttpResponse {
+    telemetry::record_contribution(rejection.code());
+    if rejection.is_rejected_share() {
+        operator.rejected_shares.add(authority);
+    }
+    rejection.response()
+}
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
@@ -179,11 +559,11 @@ async fn update_balance_onchain(
         .await?;
     // assert that the fee payer is someone else
     let tx = payload.transaction;
-    let fee_payer = tx.message.account_keys.first().ok_or(Error::Internal(
+    let fee_payer = tx.message.account_keys.first().ok_or(Error::Validation(
         "missing fee payer in update balance payload".to_string(),
     ))?;
     if fee_payer.eq(&keypair.pubkey()) {
-        return Err(Error::Internal(
+        return Err(Error::Validation(
             "fee payer must be client for update balance".to_string(),
         ));
     }
@@ -191,10 +571,11 @@ async fn update_balance_onchain(
     tx::validate::validate_attribution(&tx, member.total_balance)?;
     // sign transaction and submit
     let mut tx = tx;
-    let rpc_client = &operator.rpc_client;
     tx.partial_sign(&[keypair], hash);
-    let sig = tx::submit::submit_and_confirm_transaction(rpc_client, &tx).await?;
-    log::info!("on demand attribution sig: {:?}", sig);
+    let sig = tx::submit::submit_and_confirm_transaction(operator, &tx)
+        .await
+        .context("submitting balance update")?;
+    tracing::info!("on demand attribution sig: {:?}", sig);
     // set member as synced in db
     let db_client = &operator.db_client;
     let db_client = db_client.get().await?;
@@ -207,6 +588,108 @@ async fn update_balance_onchain(
     })
 }
 
+async fn relay_claim(
+    operator: &Operator,
+    claim_fees: &ClaimFees,
+    payload: ClaimPayload,
+) -> Result<ClaimReceipt, Error> {
+    let operator_address = operator.keypair.pubkey();
+    let (pool_address, _) = ore_pool_api::state::pool_pda(operator_address);
+    // the whole balance may always be claimed, even if below the pool minimum
+    let min_claim = operator
+        .get_min_claim()
+        .await
+        .context("reading min claim")?;
+    let balance = operator
+        .get_member_onchain(&payload.authority)
+        .await
+        .context("reading member for claim")?
+        .ok_or(Error::MemberDoesNotExist)?
+        .balance;
+    let min_claim = min_claim.min(balance);
+    let mut transaction = payload.transaction;
+    let is_fee_claim = transaction.message.account_keys.first() == Some(&operator_address);
+    let (amount, fee) = match is_fee_claim {
+        false => {
+            let amount = tx::validate::validate_claim(
+                &transaction,
+                &payload.authority,
+                &pool_address,
+                min_claim,
+            )?;
+            (amount, 0)
+        }
+        true => {
+            let (amount, fee) = tx::validate::validate_fee_claim(
+                &transaction,
+                &payload.authority,
+                &pool_address,
+                min_claim,
+                &operator_address,
+                &ClaimFees::fee_account(operator),
+            )?;
+            // quoted again, in case the member token account was created since
+            let quote = claim_fees
+                .quote(operator, &payload.authority, amount)
+                .await?;
+            if fee < quote.fee {
+                return Err(Error::ClaimBelowFee(quote.fee));
+            }
+            let recent_blockhash = transaction.message.recent_blockhash;
+            transaction
+                .try_partial_sign(&[&operator.keypair], recent_blockhash)
+                .map_err(|err| Error::Validation(err.to_string()))?;
+            (amount, fee)
+        }
+    };
+    let sig = tx::submit::submit_and_confirm_transaction(operator, &transaction)
+        .await
+        .context("relaying claim")?;
+    tracing::info!("claim sig: {:?}, fee: {}", sig, fee);
+    // the claim landed regardless, it's only missing from the digest
+    if let Err(err) = record_claim(operator, &payload.authority, amount, fee, &sig).await {
+        tracing::warn!("failed to record claim: {:?}", err);
+    }
+    Ok(ClaimReceipt {
+        amount,
+        signature: sig,
+        fee,
+    })
+}
+
+async fn record_claim(
+    operator: &Operator,
+    authority: &Pubkey,
+    amount: u64,
+    fee: u64,
+    signature: &Signature,
+) -> Result<(), Error> {
+    let conn = operator.db_client.get().await?;
+    database::write_claim(&conn, authority, amount, fee, signature).await
+}
+
+async fn update_notification_prefs(
+    operator: &Operator,
+    notifier: &Notifier,
+    payload: NotificationPrefsPayload,
+) -> Result<NotificationPrefs, Error> {
+    payload.prefs.validate()?;
+    let age = unix_timestamp().abs_diff(payload.signed_at);
+    if age > notify::MAX_SIGNATURE_AGE as u64 || !payload.verify() {
+        return Err(Error::Unauthorized);
+    }
+    if payload.prefs.channel == NotificationChannel::Email && !notifier.email_enabled() {
+        return Err(Error::EmailDisabled);
+    }
+    // only members are notified
+    operator
+        .get_member_db(payload.authority.to_string().as_str())
+        .await?;
+    let conn = operator.db_client.get().await?;
+    database::write_notification_prefs(&conn, &payload.authority, &payload.prefs).await?;
+    Ok(payload.prefs)
+}
+
 async fn register_new_staker(
     operator: &Operator,
     aggregator: &tokio::sync::RwLock<Aggregator>,
@@ -257,7 +740,7 @@ async fn register_new_staker(
         }
         Err(err) => {
             // staker doesn't exist yet on-chain
-            log::error!("{:?}", err);
+            tracing::error!("{:?}", err);
             // return error to http client
             // bc they should create the staker (share) account before hitting this path
             Err(Error::StakerDoesNotExist)
@@ -265,41 +748,48 @@ async fn register_new_staker(
     }
 }
 
-async fn register_new_member(
+pub async fn register_new_member(
     operator: &Operator,
     payload: RegisterPayload,
 ) -> Result<ore_pool_types::Member, Error> {
     let keypair = &operator.keypair;
     let member_authority = payload.authority;
     let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
-    // check if on-chain account already exists
-    let member = operator.get_member_onchain(&member_authority).await;
+    let (member_pda, _) = ore_pool_api::state::member_pda(member_authority, pool_pda);
     let db_client = operator.db_client.get().await?;
-    match member {
-        Ok(member) => {
-            // member already exists on-chain
-            // check if record is in db already
-            let (member_pda, _) = ore_pool_api::state::member_pda(member_authority, pool_pda);
-            let db_member = database::read_member(&db_client, &member_pda.to_string()).await;
-            match db_member {
-                Ok(db_member) => {
-                    // member already exists in db
-                    Ok(db_member)
-                }
-                Err(_) => {
-                    // write member to db
-                    let db_member = database::write_new_member(&db_client, &member, false).await?;
-                    Ok(db_member)
-                }
-            }
+    // the member pda is unique to this pool,
+    // so an authority registered with another pool of the operator isn't mistaken for a member,
+    // and one already registered with this pool gets its existing member back
+    if let Some(db_member) = database::find_member(&db_client, &member_pda.to_string()).await? {
+        return Ok(db_member);
+    }
+    // check if on-chain account already exists,
+    // erroring on rpc failures rather than reporting the member as missing,
+    // so clients don't pay to join again
+    match operator.get_member_onchain(&member_authority).await? {
+        Some(member) => {
+            // write member to db
+            let db_member = database::write_new_member(&db_client, &member, false).await?;
+            Ok(db_member)
         }
-        Err(err) => {
-            // member doesn't exist yet on-chain
-            // land tx to create new member account
-            log::error!("{:?}", err);
-            // return error to http client
-            // bc they should create the member account before hitting this path
-            Err(Error::MemberDoesNotExist)
+        None => {
+            // the operator pays for the member account,
+            // which is what the registration proof of work guards
+            let ix = ore_pool_api::sdk::join(member_authority, pool_pda, keypair.pubkey());
+            let sig = tx::submit::submit_and_confirm_instructions(
+                operator,
+                &[ix],
+                JOIN_CU_LIMIT,
+                JOIN_CU_PRICE,
+            )
+            .await?;
+            tracing::info!("joined member {}: {}", member_authority, sig);
+            let member = operator
+                .get_member_onchain(&member_authority)
+                .await?
+                .ok_or(Error::MemberDoesNotExist)?;
+            let db_member = database::write_new_member(&db_client, &member, false).await?;
+            Ok(db_member)
         }
     }
 }
@@ -314,18 +804,24 @@ async fn validate_nonce(
     if num_members.eq(&0) {
         return Ok(());
     }
-    let member = operator
-        .get_member_db(member_authority.to_string().as_str())
-        .await?;
-    let nonce_index = member.id as u64;
-    let u64_unit = u64::MAX.saturating_div(num_members);
-    let left_bound = u64_unit.saturating_mul(nonce_index);
-    let right_bound = u64_unit.saturating_mul(nonce_index + 1);
-    let ge_left = nonce >= left_bound;
-    let le_right = nonce <= right_bound;
-    if ge_left && le_right {
-        Ok(())
-    } else {
-        Err(Error::Internal("invalid nonce from client".to_string()))
-    }
+    let member_id = operator.get_member_id(member_authority).await?;
+    validate::nonce(nonce, member_id, num_members)?;
+    Ok(())
+}
+
+/// Signs each payout statement with the pool authority.
+fn sign_payouts(
+    operator: &Operator,
+    statements: Vec<PayoutStatement>,
+) -> Vec<SignedPayoutStatement> {
+    statements
+        .into_iter()
+        .map(|statement| SignedPayoutStatement {
+            signature: operator
+                .keypair
+                .sign_message(statement.to_bytes().as_slice()),
+            authority: operator.keypair.pubkey(),
+            statement,
+        })
+        .collect()
 }
@@ -1,12 +1,19 @@
 use std::{env, pin::Pin, str::FromStr, sync::Arc};
 
-use crate::{error::Error, operator::Operator, tx};
+use crate::{
+    aggregator::RoundSummary, error::Error, hashrate, operator::Operator, telemetry, tx,
+    utils::unix_timestamp,
+};
 use deadpool_postgres::{GenericClient, Object, Pool};
 use futures::{Stream, StreamExt, TryStreamExt};
 use futures_util::pin_mut;
 use ore_pool_api::state::{member_pda, share_pda};
-use ore_pool_types::Staker;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
+use ore_pool_types::{
+    ClaimReceipt, HashrateWindow, NotificationChannel, NotificationPrefs, Staker,
+};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Signature, signer::Signer,
+};
 use tokio_postgres::{NoTls, Row};
 
 pub fn create_pool() -> Pool {
@@ -19,42 +26,155 @@ pub fn create_pool() -> Pool {
 // also sets the is-synced field to false
 // so that in the attribution loop we know which accounts
 // have been incremented in the db but not yet on-chain
+//
+// the round is marked distributed in the same transaction,
+// so that a round is never distributed twice, even across restarts and failovers,
+// and its summary, shares and payouts are written with it, or not at all
 const BATCH_SIZE: usize = 500;
-pub async fn write_member_total_balances(
+#[tracing::instrument(skip_all, fields(count = increments.len()))]
+pub async fn write_round_distribution(
     conn: &mut Object,
+    challenge: &[u8; 32],
     increments: Vec<(String, u64)>,
+    summary: &RoundSummary,
 ) -> Result<(), Error> {
-    // process updates in batches
+    let challenge = Hash::new_from_array(*challenge).to_string();
+    let created_at = unix_timestamp();
     let transaction = conn.transaction().await?;
+    let marked = telemetry::db(
+        "mark_round_distributed",
+        transaction.execute(
+            "UPDATE round_submissions SET distributed_at = $1
+            WHERE challenge = $2 AND distributed_at IS NULL",
+            &[&created_at, &challenge],
+        ),
+    )
+    .await?;
+    if marked == 0 {
+        // rolled back on drop
+        return Err(Error::RoundAlreadyDistributed(challenge));
+    }
+    // before the increments recover the debits the payouts are net of
+    write_round_payouts(&transaction, summary).await?;
+    // process updates in batches
     for batch in increments.chunks(BATCH_SIZE) {
         for (address, increment) in batch {
-            log::info!("address:increment {}:{}", address, increment);
-            // perform an individual update for each record in the batch
-            transaction
-                .execute(
-                    "UPDATE members SET total_balance = total_balance + $1, is_synced = false WHERE address = $2",
+            tracing::info!("address:increment {}:{}", address, increment);
+            // perform an individual update for each record in the batch,
+            // recovering any outstanding debit from the increment first
+            telemetry::db(
+                "increment_member_balance",
+                transaction.execute(
+                    "UPDATE members SET
+                    total_balance = total_balance + GREATEST($1 - debit, 0),
+                    debit = GREATEST(debit - $1, 0),
+                    is_synced = false
+                    WHERE address = $2",
                     &[&(*increment as i64), address],
-                )
-                .await?;
+                ),
+            )
+            .await?;
+            // recorded for the daily digest of earnings
+            telemetry::db(
+                "write_member_earnings",
+                transaction.execute(
+                    "INSERT INTO member_earnings (address, amount, created_at) VALUES ($1, $2, $3)",
+                    &[address, &(*increment as i64), &created_at],
+                ),
+            )
+            .await?;
         }
     }
+    write_round_summary(&transaction, summary).await?;
+    write_round_shares(&transaction, summary).await?;
+    write_member_shares(&transaction, summary).await?;
     // commit the transaction to apply all updates
     transaction.commit().await?;
     Ok(())
 }
 
+// how long a round claimed for submission is held without a landed signature,
+// before another instance (or this one, restarted) may take it over,
+// longer than the submission retries take to confirm
+pub const ROUND_CLAIM_TIMEOUT: i64 = 120;
+
+// claims the round for submission, returning false if it was already submitted,
+// or is being submitted by another instance
+#[tracing::instrument(skip_all)]
+pub async fn write_round_claim(
+    conn: &Object,
+    challenge: &[u8; 32],
+    last_hash_at: i64,
+) -> Result<bool, Error> {
+    let claimed_at = unix_timestamp();
+    let claimed = telemetry::db(
+        "write_round_claim",
+        conn.execute(
+            "INSERT INTO round_submissions (challenge, last_hash_at, claimed_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (challenge) DO UPDATE SET claimed_at = EXCLUDED.claimed_at
+            WHERE round_submissions.signature IS NULL AND round_submissions.claimed_at < $4",
+            &[
+                &Hash::new_from_array(*challenge).to_string(),
+                &last_hash_at,
+                &claimed_at,
+                &(claimed_at - ROUND_CLAIM_TIMEOUT),
+            ],
+        ),
+    )
+    .await?;
+    Ok(claimed == 1)
+}
+
+// releases the claim of a round that failed to land, to be submitted again,
+// along with the signatures it was sent with, which can no longer land
+#[tracing::instrument(skip_all)]
+pub async fn delete_round_claim(conn: &Object, challenge: &[u8; 32]) -> Result<(), Error> {
+    telemetry::db(
+        "delete_round_claim",
+        conn.execute(
+            "DELETE FROM round_submissions WHERE challenge = $1 AND distributed_at IS NULL",
+            &[&Hash::new_from_array(*challenge).to_string()],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn write_round_signature(
+    conn: &Object,
+    challenge: &[u8; 32],
+    signature: &Signature,
+) -> Result<(), Error> {
+    telemetry::db(
+        "write_round_signature",
+        conn.execute(
+            "UPDATE round_submissions SET signature = $1, submitted_at = $2 WHERE challenge = $3",
+            &[
+                &signature.to_string(),
+                &unix_timestamp(),
+                &Hash::new_from_array(*challenge).to_string(),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
 // streams all records from db where is-synced is false
 // updates on-chain balances in batches and marks records in db as synced,
 // the on-chain attribution instruction is idempotent
 // so any failures here are recoverable
 const NUM_ATTRIBUTIONS_PER_TX: usize = 10;
+#[tracing::instrument(skip_all)]
 pub async fn stream_members_attribution(
     conn: Arc<Object>,
     operator: Arc<Operator>,
 ) -> Result<(), Error> {
     // fetch count(*) to determine min buffer size
     let count_query = "SELECT COUNT(*) FROM members WHERE is_synced = false";
-    let row = conn.query_one(count_query, &[]).await?;
+    let row = telemetry::db("count_unsynced_members", conn.query_one(count_query, &[])).await?;
     let record_count: i64 = row.try_get(0)?;
     // build stream of memebrs to be attributed
     let stmt = "SELECT address, authority, total_balance FROM members WHERE is_synced = false";
@@ -88,8 +208,7 @@ pub async fn stream_members_attribution(
                 async move {
                     // attribute
                     match tx::submit::submit_and_confirm_instructions(
-                        &operator.keypair,
-                        &operator.rpc_client,
+                        &operator,
                         ix_buffer.as_slice(),
                         1_500_000,
                         20_000,
@@ -97,16 +216,16 @@ pub async fn stream_members_attribution(
                     .await
                     {
                         Ok(sig) => {
-                            log::info!("attribution sig: {:?}", sig);
+                            tracing::info!("attribution sig: {:?}", sig);
                             // mark as synced
                             if let Err(err) =
                                 write_synced_members(conn.as_ref(), address_buffer.as_slice()).await
                             {
-                                log::error!("{:?}", err);
+                                tracing::error!("{:?}", err);
                             }
                         }
                         Err(err) => {
-                            log::error!("{:?}", err);
+                            tracing::error!("{:?}", err);
                         }
                     }
                 }
@@ -122,21 +241,32 @@ pub async fn stream_members_attribution(
     Ok(())
 }
 
+#[tracing::instrument(skip_all, fields(count = address_buffer.len()))]
 pub async fn write_synced_members(conn: &Object, address_buffer: &[String]) -> Result<(), Error> {
     let query = "UPDATE members SET is_synced = true WHERE address = ANY($1)";
-    conn.execute(query, &[&address_buffer]).await?;
+    telemetry::db(
+        "write_synced_members",
+        conn.execute(query, &[&address_buffer]),
+    )
+    .await?;
     Ok(())
 }
 
+#[tracing::instrument(skip(conn))]
 pub async fn write_webhook_staker(conn: &Object, share: &Pubkey) -> Result<(), Error> {
     let share = share.to_string();
     let address_buffer: &[String] = &[share];
     let query = "UPDATE stakers SET webhook = true WHERE address = ANY($1)";
-    conn.execute(query, &[&address_buffer]).await?;
+    telemetry::db(
+        "write_webhook_staker",
+        conn.execute(query, &[&address_buffer]),
+    )
+    .await?;
     Ok(())
 }
 
 pub type StakersStream = Pin<Box<dyn Stream<Item = Result<Staker, Error>> + Send>>;
+#[tracing::instrument(skip(conn))]
 pub async fn stream_stakers(conn: &Object, mint: &Pubkey) -> Result<StakersStream, Error> {
     let stmt = "SELECT address, member_id, mint, webhook FROM stakers WHERE mint = ANY($1)";
     let params: &[String] = &[mint.to_string()];
@@ -147,6 +277,7 @@ pub async fn stream_stakers(conn: &Object, mint: &Pubkey) -> Result<StakersStrea
     Ok(Box::pin(stream))
 }
 
+#[tracing::instrument(skip(conn))]
 pub async fn write_new_staker(
     conn: &Object,
     member_authority: &Pubkey,
@@ -156,16 +287,19 @@ pub async fn write_new_staker(
     let (member_pda, _) = member_pda(*member_authority, *pool);
     let member = read_member(conn, &member_pda.to_string()).await?;
     let (share_pda, _) = share_pda(*member_authority, *pool, *mint);
-    conn.execute(
-        "INSERT INTO stakers
-        (address, member_id, mint, webhook)
-        VALUES ($1, $2, $3, $4)",
-        &[
-            &share_pda.to_string(),
-            &member.id,
-            &mint.to_string(),
-            &false,
-        ],
+    telemetry::db(
+        "write_new_staker",
+        conn.execute(
+            "INSERT INTO stakers
+            (address, member_id, mint, webhook)
+            VALUES ($1, $2, $3, $4)",
+            &[
+                &share_pda.to_string(),
+                &member.id,
+                &mint.to_string(),
+                &false,
+            ],
+        ),
     )
     .await?;
     let staker = Staker {
@@ -177,6 +311,7 @@ pub async fn write_new_staker(
     Ok(staker)
 }
 
+#[tracing::instrument(skip_all, fields(authority = %member.authority))]
 pub async fn write_new_member(
     conn: &Object,
     member: &ore_pool_api::state::Member,
@@ -191,29 +326,35 @@ pub async fn write_new_member(
         is_approved: approved,
         is_kyc: false,
         is_synced: true,
+        debit: 0,
     };
-    conn.execute(
-        "INSERT INTO members
-        (address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-        &[
-            &member.address,
-            &member.id,
-            &member.authority,
-            &member.pool_address,
-            &member.total_balance,
-            &member.is_approved,
-            &member.is_kyc,
-            &member.is_synced,
-        ],
+    telemetry::db(
+        "write_new_member",
+        conn.execute(
+            "INSERT INTO members
+            (address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &member.address,
+                &member.id,
+                &member.authority,
+                &member.pool_address,
+                &member.total_balance,
+                &member.is_approved,
+                &member.is_kyc,
+                &member.is_synced,
+            ],
+        ),
     )
     .await?;
     Ok(member)
 }
 
+#[tracing::instrument(skip(conn))]
 pub async fn read_staker(conn: &Object, address: &String) -> Result<Staker, Error> {
-    let row = conn
-        .query_one(
+    let row = telemetry::db(
+        "read_staker",
+        conn.query_one(
             &format!(
                 "SELECT address, member_id, mint, webhook
                 FROM stakers
@@ -221,8 +362,9 @@ pub async fn read_staker(conn: &Object, address: &String) -> Result<Staker, Erro
                 address
             ),
             &[],
-        )
-        .await?;
+        ),
+    )
+    .await?;
     decode_staker(&row)
 }
 
@@ -243,18 +385,1091 @@ fn decode_staker(row: &Row) -> Result<Staker, Error> {
     Ok(staker)
 }
 
+#[tracing::instrument(skip(conn))]
 pub async fn read_member(conn: &Object, address: &String) -> Result<ore_pool_types::Member, Error> {
-    let row = conn
-        .query_one(
+    let row = telemetry::db(
+        "read_member",
+        conn.query_one(
             &format!(
-                "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced
+                "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced, debit
                 FROM members
                 WHERE address = '{}'",
                 address
             ),
             &[],
+        ),
+    )
+    .await?;
+    decode_member(&row)
+}
+
+// none if the member isn't registered with the db yet,
+// unlike read_member, which errors
+#[tracing::instrument(skip_all)]
+pub async fn find_member(
+    conn: &Object,
+    address: &str,
+) -> Result<Option<ore_pool_types::Member>, Error> {
+    let row = telemetry::db(
+        "find_member",
+        conn.query_opt(
+            "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced, debit
+            FROM members
+            WHERE address = $1",
+            &[&address],
+        ),
+    )
+    .await?;
+    row.as_ref().map(decode_member).transpose()
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn read_members(conn: &Object) -> Result<Vec<ore_pool_types::Member>, Error> {
+    let rows = telemetry::db(
+        "read_members",
+        conn.query(
+            "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced, debit
+            FROM members",
+            &[],
+        ),
+    )
+    .await?;
+    rows.iter().map(decode_member).collect()
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn read_stakers(conn: &Object) -> Result<Vec<Staker>, Error> {
+    let rows = telemetry::db(
+        "read_stakers",
+        conn.query("SELECT address, member_id, mint, webhook FROM stakers", &[]),
+    )
+    .await?;
+    rows.iter().map(decode_staker).collect()
+}
+
+// restores the members and stakers of a backup in one transaction,
+// only into an empty db so that live balances are never overwritten
+#[tracing::instrument(skip_all, fields(members = members.len(), stakers = stakers.len()))]
+pub async fn write_backup(
+    conn: &mut Object,
+    members: &[ore_pool_types::Member],
+    stakers: &[Staker],
+) -> Result<(), Error> {
+    let transaction = conn.transaction().await?;
+    let row = telemetry::db(
+        "count_members",
+        transaction.query_one("SELECT COUNT(*) FROM members", &[]),
+    )
+    .await?;
+    let count: i64 = row.try_get(0)?;
+    if count > 0 {
+        return Err(Error::DatabaseNotEmpty);
+    }
+    for member in members {
+        telemetry::db(
+            "write_backup_member",
+            transaction.execute(
+                "INSERT INTO members
+                (address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced, debit)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                &[
+                    &member.address,
+                    &member.id,
+                    &member.authority,
+                    &member.pool_address,
+                    &member.total_balance,
+                    &member.is_approved,
+                    &member.is_kyc,
+                    &member.is_synced,
+                    &member.debit,
+                ],
+            ),
+        )
+        .await?;
+    }
+    for staker in stakers {
+        telemetry::db(
+            "write_backup_staker",
+            transaction.execute(
+                "INSERT INTO stakers
+                (address, member_id, mint, webhook)
+                VALUES ($1, $2, $3, $4)",
+                &[
+                    &staker.address.to_string(),
+                    &(staker.member_id as i64),
+                    &staker.mint.to_string(),
+                    &staker.webhook,
+                ],
+            ),
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn read_next_member_id(conn: &Object) -> Result<i64, Error> {
+    let row = telemetry::db(
+        "read_next_member_id",
+        conn.query_one("SELECT COALESCE(MAX(id) + 1, 0) FROM members", &[]),
+    )
+    .await?;
+    let id: i64 = row.try_get(0)?;
+    Ok(id)
+}
+
+// credits or debits the balance of a member and records it in the ledger,
+// with its admin signature, and in the audit log, in one transaction.
+// a credit is landed on-chain by the attribution loop,
+// a debit is recovered from future earnings as the on-chain balance never decrements
+#[tracing::instrument(skip(conn, adjustment, remote_addr), fields(address = %address, amount = adjustment.amount))]
+pub async fn write_balance_adjustment(
+    conn: &mut Object,
+    address: &str,
+    adjustment: &ore_pool_types::AdjustBalancePayload,
+    actor: &str,
+    remote_addr: Option<String>,
+) -> Result<ore_pool_types::BalanceAdjustment, Error> {
+    let authority = adjustment.authority.to_string();
+    let amount = adjustment.amount;
+    let reason = adjustment.reason.trim();
+    let created_at = unix_timestamp();
+    let transaction = conn.transaction().await?;
+    let row = telemetry::db(
+        "adjust_member_balance",
+        transaction.query_opt(
+            "UPDATE members SET
+            total_balance = total_balance + GREATEST($1, 0),
+            debit = debit + GREATEST(-$1, 0),
+            is_synced = is_synced AND $1 < 0
+            WHERE address = $2
+            RETURNING total_balance, debit",
+            &[&amount, &address],
+        ),
+    )
+    .await?;
+    let Some(row) = row else {
+        // rolled back on drop
+        return Err(Error::NotFound(format!("member {}", authority)));
+    };
+    let total_balance: i64 = row.try_get(0)?;
+    let debit: i64 = row.try_get(1)?;
+    let row = telemetry::db(
+        "write_balance_adjustment",
+        transaction.query_opt(
+            "INSERT INTO member_adjustments
+            (address, authority, amount, reason, actor, created_at, signer, signature)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING
+            RETURNING id",
+            &[
+                &address,
+                &authority,
+                &amount,
+                &reason,
+                &actor,
+                &created_at,
+                &adjustment.signer.to_string(),
+                &adjustment.signature.to_string(),
+            ],
+        ),
+    )
+    .await?;
+    let Some(row) = row else {
+        return Err(Error::Validation("adjustment already applied".to_string()));
+    };
+    let old_value = serde_json::json!({
+        "total_balance": total_balance - amount.max(0),
+        "debit": debit - (-amount).max(0),
+    })
+    .to_string();
+    let new_value = serde_json::json!({
+        "authority": authority,
+        "amount": amount,
+        "reason": reason,
+        "signer": adjustment.signer.to_string(),
+    })
+    .to_string();
+    write_audit_entry(
+        &transaction,
+        actor,
+        remote_addr,
+        "adjust_balance",
+        Some(old_value),
+        Some(new_value),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(ore_pool_types::BalanceAdjustment {
+        id: row.try_get(0)?,
+        authority,
+        amount,
+        reason: reason.to_string(),
+        created_at,
+    })
+}
+
+// the manual adjustments of a member, most recent first
+#[tracing::instrument(skip(conn))]
+pub async fn read_balance_adjustments(
+    conn: &Object,
+    authority: &str,
+) -> Result<Vec<ore_pool_types::BalanceAdjustment>, Error> {
+    let rows = telemetry::db(
+        "read_balance_adjustments",
+        conn.query(
+            "SELECT id, authority, amount, reason, created_at
+            FROM member_adjustments
+            WHERE authority = $1
+            ORDER BY created_at DESC, id DESC",
+            &[&authority],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(ore_pool_types::BalanceAdjustment {
+                id: row.try_get(0)?,
+                authority: row.try_get(1)?,
+                amount: row.try_get(2)?,
+                reason: row.try_get(3)?,
+                created_at: row.try_get(4)?,
+            })
+        })
+        .collect()
+}
+
+// marks members as unsynced
+// so that the attribution loop lands their db balance on-chain
+#[tracing::instrument(skip_all, fields(count = address_buffer.len()))]
+pub async fn write_unsynced_members(conn: &Object, address_buffer: &[String]) -> Result<(), Error> {
+    let query = "UPDATE members SET is_synced = false WHERE address = ANY($1)";
+    telemetry::db(
+        "write_unsynced_members",
+        conn.execute(query, &[&address_buffer]),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(conn, old_value, new_value))]
+pub async fn write_audit_entry(
+    conn: &impl GenericClient,
+    actor: &str,
+    remote_addr: Option<String>,
+    action: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) -> Result<(), Error> {
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    telemetry::db(
+        "write_audit_entry",
+        conn.execute(
+            "INSERT INTO admin_audit_log
+            (actor, remote_addr, action, old_value, new_value, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+            &[
+                &actor,
+                &remote_addr,
+                &action,
+                &old_value,
+                &new_value,
+                &created_at,
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+// most recent first
+#[tracing::instrument(skip(conn))]
+pub async fn read_audit_log(
+    conn: &Object,
+    limit: i64,
+    before: Option<i64>,
+) -> Result<Vec<ore_pool_types::AuditEntry>, Error> {
+    let rows = telemetry::db(
+        "read_audit_log",
+        conn.query(
+            "SELECT id, actor, remote_addr, action, old_value, new_value, created_at
+            FROM admin_audit_log
+            WHERE ($2::BIGINT IS NULL OR id < $2)
+            ORDER BY id DESC
+            LIMIT $1",
+            &[&limit, &before],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(ore_pool_types::AuditEntry {
+                id: row.try_get(0)?,
+                actor: row.try_get(1)?,
+                remote_addr: row.try_get(2)?,
+                action: row.try_get(3)?,
+                old_value: row.try_get(4)?,
+                new_value: row.try_get(5)?,
+                created_at: row.try_get(6)?,
+            })
+        })
+        .collect()
+}
+
+#[tracing::instrument(skip(conn, key_hash))]
+pub async fn write_api_key(
+    conn: &Object,
+    name: &str,
+    key_hash: &str,
+    rate_limit: u32,
+) -> Result<ore_pool_types::ApiKey, Error> {
+    let created_at = unix_timestamp();
+    let row = telemetry::db(
+        "write_api_key",
+        conn.query_one(
+            "INSERT INTO api_keys (name, key_hash, rate_limit, created_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id",
+            &[
+                &name,
+                &key_hash,
+                &(rate_limit.min(i32::MAX as u32) as i32),
+                &created_at,
+            ],
+        ),
+    )
+    .await?;
+    Ok(ore_pool_types::ApiKey {
+        id: row.try_get(0)?,
+        name: name.to_string(),
+        rate_limit,
+        created_at,
+        revoked_at: None,
+        key: None,
+    })
+}
+
+// the id and rate limit of the key, unless revoked
+#[tracing::instrument(skip_all)]
+pub async fn read_api_key(conn: &Object, key_hash: &str) -> Result<Option<(i64, u32)>, Error> {
+    let row = telemetry::db(
+        "read_api_key",
+        conn.query_opt(
+            "SELECT id, rate_limit FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+            &[&key_hash],
+        ),
+    )
+    .await?;
+    match row {
+        Some(row) => {
+            let id: i64 = row.try_get(0)?;
+            let rate_limit: i32 = row.try_get(1)?;
+            Ok(Some((id, rate_limit.max(1) as u32)))
+        }
+        None => Ok(None),
+    }
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn read_api_keys(conn: &Object) -> Result<Vec<ore_pool_types::ApiKey>, Error> {
+    let rows = telemetry::db(
+        "read_api_keys",
+        conn.query(
+            "SELECT id, name, rate_limit, created_at, revoked_at FROM api_keys ORDER BY id",
+            &[],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let rate_limit: i32 = row.try_get(2)?;
+            Ok(ore_pool_types::ApiKey {
+                id: row.try_get(0)?,
+                name: row.try_get(1)?,
+                rate_limit: rate_limit.max(1) as u32,
+                created_at: row.try_get(3)?,
+                revoked_at: row.try_get(4)?,
+                key: None,
+            })
+        })
+        .collect()
+}
+
+// returns false if the key doesn't exist or was already revoked
+#[tracing::instrument(skip(conn))]
+pub async fn write_api_key_revoked(conn: &Object, id: i64) -> Result<bool, Error> {
+    let revoked = telemetry::db(
+        "write_api_key_revoked",
+        conn.execute(
+            "UPDATE api_keys SET revoked_at = $1 WHERE id = $2 AND revoked_at IS NULL",
+            &[&unix_timestamp(), &id],
+        ),
+    )
+    .await?;
+    Ok(revoked == 1)
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn write_claim(
+    conn: &Object,
+    authority: &Pubkey,
+    amount: u64,
+    fee: u64,
+    signature: &Signature,
+) -> Result<(), Error> {
+    telemetry::db(
+        "write_claim",
+        conn.execute(
+            "INSERT INTO member_claims (signature, authority, amount, fee, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (signature) DO NOTHING",
+            &[
+                &signature.to_string(),
+                &authority.to_string(),
+                &(amount as i64),
+                &(fee as i64),
+                &unix_timestamp(),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(conn, prefs))]
+pub async fn write_notification_prefs(
+    conn: &Object,
+    authority: &Pubkey,
+    prefs: &NotificationPrefs,
+) -> Result<(), Error> {
+    telemetry::db(
+        "write_notification_prefs",
+        conn.execute(
+            "INSERT INTO notification_prefs (authority, channel, target, updated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (authority)
+            DO UPDATE SET channel = $2, target = $3, updated_at = $4",
+            &[
+                &authority.to_string(),
+                &prefs.channel.as_str(),
+                &prefs.target,
+                &unix_timestamp(),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// A member to send a digest to,
+/// with its earnings over the digest period.
+pub struct DigestRecipient {
+    pub authority: String,
+    pub channel: NotificationChannel,
+    pub target: String,
+    pub earned: u64,
+    pub balance: u64,
+}
+
+// members that opted into notifications,
+// with the sum of their earnings in [start, end)
+#[tracing::instrument(skip(conn))]
+pub async fn read_digest_recipients(
+    conn: &Object,
+    start: i64,
+    end: i64,
+) -> Result<Vec<DigestRecipient>, Error> {
+    let rows = telemetry::db(
+        "read_digest_recipients",
+        conn.query(
+            "SELECT p.authority, p.channel, p.target, m.total_balance,
+            COALESCE(SUM(e.amount), 0)::BIGINT
+            FROM notification_prefs p
+            JOIN members m ON m.authority = p.authority
+            LEFT JOIN member_earnings e
+            ON e.address = m.address AND e.created_at >= $1 AND e.created_at < $2
+            WHERE p.channel <> 'none' AND p.target IS NOT NULL
+            GROUP BY p.authority, p.channel, p.target, m.total_balance",
+            &[&start, &end],
+        ),
+    )
+    .await?;
+    let mut recipients = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        let channel: String = row.try_get(1)?;
+        let Some(channel) = NotificationChannel::parse(channel.as_str()) else {
+            continue;
+        };
+        let balance: i64 = row.try_get(3)?;
+        let earned: i64 = row.try_get(4)?;
+        recipients.push(DigestRecipient {
+            authority: row.try_get(0)?,
+            channel,
+            target: row.try_get(2)?,
+            earned: earned as u64,
+            balance: balance as u64,
+        });
+    }
+    Ok(recipients)
+}
+
+// claims confirmed in [start, end), by authority
+#[tracing::instrument(skip(conn))]
+pub async fn read_claims(
+    conn: &Object,
+    start: i64,
+    end: i64,
+) -> Result<Vec<(String, ClaimReceipt)>, Error> {
+    let rows = telemetry::db(
+        "read_claims",
+        conn.query(
+            "SELECT authority, amount, signature, fee FROM member_claims
+            WHERE created_at >= $1 AND created_at < $2
+            ORDER BY created_at",
+            &[&start, &end],
+        ),
+    )
+    .await?;
+    let mut claims = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        let amount: i64 = row.try_get(1)?;
+        let signature: String = row.try_get(2)?;
+        let fee: i64 = row.try_get(3)?;
+        let signature = Signature::from_str(signature.as_str())
+            .map_err(|err| Error::Serialization(err.to_string()))?;
+        claims.push((
+            row.try_get(0)?,
+            ClaimReceipt {
+                amount: amount as u64,
+                signature,
+                fee: fee as u64,
+            },
+        ));
+    }
+    Ok(claims)
+}
+
+// earnings and claims are only kept as long as the digest needs them
+#[tracing::instrument(skip(conn))]
+pub async fn prune_notification_history(conn: &Object, before: i64) -> Result<(), Error> {
+    telemetry::db(
+        "prune_member_earnings",
+        conn.execute(
+            "DELETE FROM member_earnings WHERE created_at < $1",
+            &[&before],
+        ),
+    )
+    .await?;
+    telemetry::db(
+        "prune_member_claims",
+        conn.execute(
+            "DELETE FROM member_claims WHERE created_at < $1",
+            &[&before],
+        ),
+    )
+    .await?;
+    telemetry::db(
+        "prune_notification_outbox",
+        conn.execute(
+            "DELETE FROM notification_outbox WHERE created_at < $1 AND sent_at IS NOT NULL",
+            &[&before],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// A notification waiting in the outbox.
+pub struct OutboxEntry {
+    pub id: i64,
+    pub channel: NotificationChannel,
+    pub target: String,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+// the key dedupes the entry,
+// so enqueueing the same notification twice is a no-op
+#[tracing::instrument(skip(conn, target, payload))]
+pub async fn write_outbox_entry(
+    conn: &Object,
+    key: &str,
+    authority: &str,
+    channel: NotificationChannel,
+    target: &str,
+    payload: &str,
+) -> Result<(), Error> {
+    telemetry::db(
+        "write_outbox_entry",
+        conn.execute(
+            "INSERT INTO notification_outbox
+            (key, authority, channel, target, payload, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (key) DO NOTHING",
+            &[
+                &key,
+                &authority,
+                &channel.as_str(),
+                &target,
+                &payload,
+                &unix_timestamp(),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+// oldest first
+#[tracing::instrument(skip(conn))]
+pub async fn read_outbox_pending(
+    conn: &Object,
+    limit: i64,
+    max_attempts: i32,
+) -> Result<Vec<OutboxEntry>, Error> {
+    let rows = telemetry::db(
+        "read_outbox_pending",
+        conn.query(
+            "SELECT id, channel, target, payload, attempts
+            FROM notification_outbox
+            WHERE sent_at IS NULL AND attempts < $2
+            ORDER BY id
+            LIMIT $1",
+            &[&limit, &max_attempts],
+        ),
+    )
+    .await?;
+    let mut entries = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        let channel: String = row.try_get(1)?;
+        let Some(channel) = NotificationChannel::parse(channel.as_str()) else {
+            continue;
+        };
+        entries.push(OutboxEntry {
+            id: row.try_get(0)?,
+            channel,
+            target: row.try_get(2)?,
+            payload: row.try_get(3)?,
+            attempts: row.try_get(4)?,
+        });
+    }
+    Ok(entries)
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn write_outbox_sent(conn: &Object, id: i64) -> Result<(), Error> {
+    telemetry::db(
+        "write_outbox_sent",
+        conn.execute(
+            "UPDATE notification_outbox SET sent_at = $2, attempts = attempts + 1 WHERE id = $1",
+            &[&id, &unix_timestamp()],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(conn))]
+pub async fn write_outbox_failed(conn: &Object, id: i64, error: &str) -> Result<(), Error> {
+    telemetry::db(
+        "write_outbox_failed",
+        conn.execute(
+            "UPDATE notification_outbox SET attempts = attempts + 1, last_error = $2 WHERE id = $1",
+            &[&id, &error],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn write_round_summary(
+    conn: &impl GenericClient,
+    summary: &RoundSummary,
+) -> Result<(), Error> {
+    let histogram = &summary.histogram;
+    let buckets = serde_json::to_string(&histogram.to_stats().buckets)?;
+    telemetry::db(
+        "write_round_summary",
+        conn.execute(
+            "INSERT INTO round_summaries
+            (challenge, last_hash_at, shares, total_score, min_difficulty, max_difficulty,
+            mean_difficulty, histogram, reward, created_at, proof_total_rewards, db_total_balance)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
+            (SELECT COALESCE(SUM(total_balance), 0)::BIGINT FROM members))
+            ON CONFLICT (challenge) DO NOTHING",
+            &[
+                &Hash::new_from_array(summary.challenge).to_string(),
+                &summary.last_hash_at,
+                &(histogram.shares() as i64),
+                &(summary.total_score as i64),
+                &histogram.min().map(|d| d as i32),
+                &histogram.max().map(|d| d as i32),
+                &histogram.mean(),
+                &buckets,
+                &(summary.reward as i64),
+                &unix_timestamp(),
+                &summary.proof_total_rewards.map(|r| r as i64),
+            ],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+// the shares of each member in the round, for the member hashrate,
+// pruning the shares past the hashrate windows
+#[tracing::instrument(skip_all)]
+async fn write_member_shares(
+    conn: &impl GenericClient,
+    summary: &RoundSummary,
+) -> Result<(), Error> {
+    let challenge = Hash::new_from_array(summary.challenge).to_string();
+    let created_at = match summary.submitted_at {
+        // journaled before the submission time was recorded
+        0 => unix_timestamp(),
+        submitted_at => submitted_at,
+    };
+    for member in summary.members.iter() {
+        telemetry::db(
+            "write_member_shares",
+            conn.execute(
+                "INSERT INTO member_shares (challenge, authority, accepted, rejected, score, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (challenge, authority) DO NOTHING",
+                &[
+                    &challenge,
+                    &member.authority.to_string(),
+                    &(member.accepted as i64),
+                    &(member.rejected as i64),
+                    &(member.score.min(i64::MAX as u64) as i64),
+                    &created_at,
+                ],
+            ),
+        )
+        .await?;
+    }
+    telemetry::db(
+        "prune_member_shares",
+        conn.execute(
+            "DELETE FROM member_shares WHERE created_at < $1",
+            &[&(unix_timestamp() - hashrate::RETENTION)],
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+// the latest migration applied to the db, none if no migration recorded its version
+#[tracing::instrument(skip_all)]
+pub async fn read_schema_version(conn: &Object) -> Result<Option<i32>, Error> {
+    let row = telemetry::db(
+        "read_schema_version",
+        conn.query_one("SELECT MAX(version) FROM schema_version", &[]),
+    )
+    .await?;
+    let version: Option<i32> = row.try_get(0)?;
+    Ok(version)
+}
+
+// the accepted solutions of the round, kept for independent verification
+#[tracing::instrument(skip_all, fields(count = summary.shares.len()))]
+async fn write_round_shares(
+    conn: &impl GenericClient,
+    summary: &RoundSummary,
+) -> Result<(), Error> {
+    let challenge = Hash::new_from_array(summary.challenge).to_string();
+    let created_at = match summary.submitted_at {
+        // journaled before the submission time was recorded
+        0 => unix_timestamp(),
+        submitted_at => submitted_at,
+    };
+    for share in summary.shares.iter() {
+        telemetry::db(
+            "write_round_share",
+            conn.execute(
+                "INSERT INTO round_shares (challenge, authority, worker, digest, nonce, score, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (challenge, authority, worker) DO NOTHING",
+                &[
+                    &challenge,
+                    &share.authority.to_string(),
+                    &share.worker,
+                    &share.digest.as_slice(),
+                    &share.nonce.as_slice(),
+                    &(share.score.min(i64::MAX as u64) as i64),
+                    &created_at,
+                ],
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+// the miner rewards paid to each member in the round, for the payout statements,
+// net of the debit recovered from them, as the miner rewards are incremented first
+#[tracing::instrument(skip_all)]
+async fn write_round_payouts(
+    conn: &impl GenericClient,
+    summary: &RoundSummary,
+) -> Result<(), Error> {
+    let challenge = Hash::new_from_array(summary.challenge).to_string();
+    let created_at = match summary.submitted_at {
+        // journaled before the submission time was recorded
+        0 => unix_timestamp(),
+        submitted_at => submitted_at,
+    };
+    let total_score = summary
+        .payouts
+        .iter()
+        .fold(0u64, |sum, payout| sum.saturating_add(payout.score));
+    for payout in summary.payouts.iter() {
+        telemetry::db(
+            "write_round_payout",
+            conn.execute(
+                "INSERT INTO round_payouts
+                (challenge, authority, score, amount, total_score, total_amount, created_at)
+                VALUES ($1, $2, $3,
+                $4 - LEAST($4, COALESCE((SELECT debit FROM members WHERE authority = $2), 0)),
+                $5, $6, $7)
+                ON CONFLICT (challenge, authority) DO NOTHING",
+                &[
+                    &challenge,
+                    &payout.authority.to_string(),
+                    &(payout.score.min(i64::MAX as u64) as i64),
+                    &(payout.amount.min(i64::MAX as u64) as i64),
+                    &(total_score.min(i64::MAX as u64) as i64),
+                    &(summary.miner_rewards.min(i64::MAX as u64) as i64),
+                    &created_at,
+                ],
+            ),
         )
         .await?;
+    }
+    Ok(())
+}
+
+// the payouts of the member in distributed rounds, most recent first,
+// only of the round if given
+#[tracing::instrument(skip(conn))]
+pub async fn read_round_payouts(
+    conn: &Object,
+    pool_address: &Pubkey,
+    authority: &str,
+    challenge: Option<&str>,
+) -> Result<Vec<ore_pool_types::PayoutStatement>, Error> {
+    let rows = telemetry::db(
+        "read_round_payouts",
+        conn.query(
+            "SELECT p.challenge, s.last_hash_at, p.authority, p.score, p.total_score,
+            p.amount, p.total_amount, s.signature, s.distributed_at
+            FROM round_payouts p
+            JOIN round_submissions s ON s.challenge = p.challenge
+            WHERE p.authority = $1 AND ($2::VARCHAR IS NULL OR p.challenge = $2)
+            AND s.distributed_at IS NOT NULL
+            ORDER BY p.created_at DESC
+            LIMIT 100",
+            &[&authority, &challenge],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let challenge: String = row.try_get(0)?;
+            let authority: String = row.try_get(2)?;
+            let score: i64 = row.try_get(3)?;
+            let total_score: i64 = row.try_get(4)?;
+            let amount: i64 = row.try_get(5)?;
+            let total_amount: i64 = row.try_get(6)?;
+            let submission: Option<String> = row.try_get(7)?;
+            let submission = submission
+                .map(|signature| Signature::from_str(signature.as_str()))
+                .transpose()
+                .map_err(|err| Error::Serialization(err.to_string()))?;
+            let share = match total_score {
+                0 => 0.0,
+                total_score => score as f64 * 100.0 / total_score as f64,
+            };
+            Ok(ore_pool_types::PayoutStatement {
+                pool_address: *pool_address,
+                challenge: Hash::from_str(challenge.as_str())
+                    .map_err(|err| Error::Serialization(err.to_string()))?,
+                last_hash_at: row.try_get(1)?,
+                member: Pubkey::from_str(authority.as_str())?,
+                score: score as u64,
+                total_score: total_score as u64,
+                share,
+                amount: amount as u64,
+                total_amount: total_amount as u64,
+                submission,
+                distributed_at: row.try_get(8)?,
+            })
+        })
+        .collect()
+}
+
+// the archived solutions of the member in the round
+#[tracing::instrument(skip(conn))]
+pub async fn read_round_shares(
+    conn: &Object,
+    challenge: &str,
+    authority: &str,
+) -> Result<Vec<ore_pool_types::ArchivedShare>, Error> {
+    let rows = telemetry::db(
+        "read_round_shares",
+        conn.query(
+            "SELECT authority, worker, digest, nonce, score
+            FROM round_shares
+            WHERE challenge = $1 AND authority = $2
+            ORDER BY worker",
+            &[&challenge, &authority],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let authority: String = row.try_get(0)?;
+            let digest: Vec<u8> = row.try_get(2)?;
+            let nonce: Vec<u8> = row.try_get(3)?;
+            let score: i64 = row.try_get(4)?;
+            Ok(ore_pool_types::ArchivedShare {
+                authority: Pubkey::from_str(authority.as_str())?,
+                worker: row.try_get(1)?,
+                digest: digest
+                    .try_into()
+                    .map_err(|_| Error::Serialization("invalid archived digest".to_string()))?,
+                nonce: nonce
+                    .try_into()
+                    .map_err(|_| Error::Serialization("invalid archived nonce".to_string()))?,
+                score: score as u64,
+            })
+        })
+        .collect()
+}
+
+// the hashrate of the member over each of the hashrate windows
+#[tracing::instrument(skip(conn))]
+pub async fn read_member_hashrate(
+    conn: &Object,
+    authority: &str,
+    now: i64,
+) -> Result<Vec<HashrateWindow>, Error> {
+    let rows = telemetry::db(
+        "read_member_hashrate",
+        conn.query(
+            "SELECT w.seconds,
+            COALESCE(SUM(s.score), 0)::BIGINT,
+            COALESCE(SUM(s.accepted), 0)::BIGINT,
+            COALESCE(SUM(s.rejected), 0)::BIGINT
+            FROM UNNEST($2::BIGINT[]) AS w(seconds)
+            LEFT JOIN member_shares s ON s.authority = $1 AND s.created_at > $3 - w.seconds
+            GROUP BY w.seconds
+            ORDER BY w.seconds",
+            &[&authority, &hashrate::WINDOWS.to_vec(), &now],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let window: i64 = row.try_get(0)?;
+            let score: i64 = row.try_get(1)?;
+            let accepted: i64 = row.try_get(2)?;
+            let rejected: i64 = row.try_get(3)?;
+            Ok(hashrate::window(
+                window,
+                score as u64,
+                accepted as u64,
+                rejected as u64,
+            ))
+        })
+        .collect()
+}
+
+// the sum of the db balances of every member,
+// and of the member the operator commission is attributed to
+#[tracing::instrument(skip(conn))]
+pub async fn read_total_balances(
+    conn: &Object,
+    operator_address: &str,
+) -> Result<(u64, u64), Error> {
+    let row = telemetry::db(
+        "read_total_balances",
+        conn.query_one(
+            "SELECT COALESCE(SUM(total_balance), 0)::BIGINT,
+            COALESCE(SUM(total_balance) FILTER (WHERE address = $1), 0)::BIGINT
+            FROM members",
+            &[&operator_address],
+        ),
+    )
+    .await?;
+    let total: i64 = row.try_get(0)?;
+    let commission: i64 = row.try_get(1)?;
+    Ok((total as u64, commission as u64))
+}
+
+// the net manual adjustments applied to the db balances,
+// the debits count only once recovered from earnings
+#[tracing::instrument(skip(conn))]
+pub async fn read_total_adjustments(conn: &Object) -> Result<i64, Error> {
+    let row = telemetry::db(
+        "read_total_adjustments",
+        conn.query_one(
+            "SELECT (SELECT COALESCE(SUM(amount), 0) FROM member_adjustments)::BIGINT
+            + (SELECT COALESCE(SUM(debit), 0) FROM members)::BIGINT",
+            &[],
+        ),
+    )
+    .await?;
+    let total: i64 = row.try_get(0)?;
+    Ok(total)
+}
+
+// the rounds where the drift between the on-chain lifetime rewards
+// and the db balances changed by more than the tolerance, oldest first
+#[tracing::instrument(skip(conn))]
+pub async fn read_drift_rounds(
+    conn: &Object,
+    tolerance: i64,
+    limit: i64,
+) -> Result<Vec<ore_pool_types::RoundDrift>, Error> {
+    let rows = telemetry::db(
+        "read_drift_rounds",
+        conn.query(
+            "SELECT challenge, created_at, reward, drift, change FROM (
+                SELECT challenge, created_at, reward,
+                proof_total_rewards - db_total_balance AS drift,
+                proof_total_rewards - db_total_balance
+                - LAG(proof_total_rewards - db_total_balance) OVER (ORDER BY created_at) AS change
+                FROM round_summaries
+                WHERE proof_total_rewards IS NOT NULL AND db_total_balance IS NOT NULL
+            ) rounds
+            WHERE ABS(change) > $1
+            ORDER BY created_at
+            LIMIT $2",
+            &[&tolerance, &limit],
+        ),
+    )
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let reward: i64 = row.try_get(2)?;
+            Ok(ore_pool_types::RoundDrift {
+                challenge: row.try_get(0)?,
+                created_at: row.try_get(1)?,
+                reward: reward as u64,
+                drift: row.try_get(3)?,
+                change: row.try_get(4)?,
+            })
+        })
+        .collect()
+}
+
+fn decode_member(row: &Row) -> Result<ore_pool_types::Member, Error> {
     Ok(ore_pool_types::Member {
         address: row.try_get(0)?,
         id: row.try_get(1)?,
@@ -264,5 +1479,6 @@ pub async fn read_member(conn: &Object, address: &String) -> Result<ore_pool_typ
         is_approved: row.try_get(5)?,
         is_kyc: row.try_get(6)?,
         is_synced: row.try_get(7)?,
+        debit: row.try_get(8)?,
     })
 }
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error;
+
+/// Decayed scores below this are forgotten.
+const MIN_SCORE: f64 = 1.0;
+
+/// The running score of each member, decayed over time,
+/// used to weight the payout of each round when `SCORE_HALF_LIFE` is set.
+///
+/// A member's score halves every half-life and grows with each accepted contribution,
+/// so members who contribute round after round are weighted by their history,
+/// while a member who only shows up for one round is weighted by that round alone.
+/// This discourages dumping shares into a single round.
+/// Unset (the default) pays each round by its own scores only.
+#[derive(Clone, Debug, Default)]
+pub struct ScoreDecay {
+    /// The seconds for a score to halve, none if decay is disabled.
+    half_life: Option<f64>,
+
+    /// The decayed score of each member, and the unix timestamp it was last decayed at.
+    scores: HashMap<Pubkey, (f64, i64)>,
+}
+
+impl ScoreDecay {
+    pub fn new(half_life: Option<u64>) -> Self {
+        Self {
+            half_life: half_life.filter(|s| *s > 0).map(|s| s as f64),
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let half_life = match std::env::var("SCORE_HALF_LIFE") {
+            Ok(str) => Some(str.parse()?),
+            // optional
+            Err(_) => None,
+        };
+        Ok(Self::new(half_life))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.half_life.is_some()
+    }
+
+    /// Decays the score of the member to now, then adds the contribution score.
+    pub fn add(&mut self, member: Pubkey, score: u64, now: i64) {
+        let Some(half_life) = self.half_life else {
+            return;
+        };
+        let (decayed, updated_at) = self.scores.entry(member).or_insert((0.0, now));
+        *decayed = decay(*decayed, now - *updated_at, half_life) + score as f64;
+        *updated_at = now;
+    }
+
+    /// The decayed score of the member as of now.
+    pub fn score(&self, member: &Pubkey, now: i64) -> f64 {
+        match (self.half_life, self.scores.get(member)) {
+            (Some(half_life), Some((decayed, updated_at))) => {
+                decay(*decayed, now - updated_at, half_life)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Forgets the members whose score has decayed away.
+    pub fn prune(&mut self, now: i64) {
+        let Some(half_life) = self.half_life else {
+            return;
+        };
+        self.scores.retain(|_, (decayed, updated_at)| {
+            decay(*decayed, now - *updated_at, half_life) >= MIN_SCORE
+        });
+    }
+}
+
+fn decay(score: f64, elapsed: i64, half_life: f64) -> f64 {
+    score * 0.5f64.powf(elapsed.max(0) as f64 / half_life)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_halves_every_half_life() {
+        let member = Pubkey::new_unique();
+        let mut decay = ScoreDecay::new(Some(60));
+        decay.add(member, 1024, 0);
+        assert_eq!(decay.score(&member, 60), 512.0);
+        decay.add(member, 512, 120);
+        assert_eq!(decay.score(&member, 120), 768.0);
+        decay.prune(60 * 20);
+        assert_eq!(decay.score(&member, 60 * 20), 0.0);
+    }
+
+    #[test]
+    fn disabled_keeps_no_scores() {
+        let member = Pubkey::new_unique();
+        let mut decay = ScoreDecay::new(None);
+        decay.add(member, 1024, 0);
+        assert!(!decay.is_enabled());
+        assert_eq!(decay.score(&member, 0), 0.0);
+    }
+}
@@ -1,6 +1,12 @@
 use actix_web::{http::header::ToStrError, HttpResponse};
 
-use crate::webhook;
+use ore_pool_types::validate::ValidationError;
+use solana_client::client_error::ClientErrorKind;
+
+use crate::telemetry;
+
+/// The seconds clients are asked to wait before retrying a request that failed on the rpc or db.
+const RETRY_AFTER_SECONDS: u64 = 1;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -10,8 +16,6 @@ pub enum Error {
     Base64Decode(#[from] base64::DecodeError),
     #[error("try from slice")]
     TryFromSlice(#[from] std::array::TryFromSliceError),
-    #[error("rewards channel send")]
-    RewardsChannelSend(#[from] tokio::sync::mpsc::error::SendError<webhook::Rewards>),
     #[error("tokio postgres")]
     TokioPostgres(#[from] tokio_postgres::Error),
     #[error("deadpool postgress")]
@@ -28,32 +32,287 @@ pub enum Error {
     StdEnv(#[from] std::env::VarError),
     #[error("std parse int")]
     StdParseInt(#[from] std::num::ParseIntError),
+    #[error("std parse bool")]
+    StdParseBool(#[from] std::str::ParseBoolError),
+    #[error("std addr parse")]
+    StdAddrParse(#[from] std::net::AddrParseError),
+    #[error("opentelemetry trace")]
+    OpenTelemetryTrace(#[from] opentelemetry::trace::TraceError),
+    #[error("opentelemetry metrics")]
+    OpenTelemetryMetrics(#[from] opentelemetry::metrics::MetricsError),
+    #[error("sentry dsn")]
+    SentryDsn(#[from] sentry::types::ParseDsnError),
     #[error("solana client")]
     SolanaClient(#[from] solana_client::client_error::ClientError),
+    #[error("solana pubsub client")]
+    SolanaPubsubClient(#[from] solana_client::nonblocking::pubsub_client::PubsubClientError),
     #[error("solana program")]
     SolanaProgram(#[from] solana_sdk::program_error::ProgramError),
     #[error("solana pubkey")]
     SolanaPubkey(#[from] solana_sdk::pubkey::ParsePubkeyError),
+    #[error("geyser builder")]
+    GeyserBuilder(#[from] yellowstone_grpc_client::GeyserGrpcBuilderError),
+    #[error("geyser client")]
+    GeyserClient(#[from] yellowstone_grpc_client::GeyserGrpcClientError),
+    #[error("smtp")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("email address")]
+    EmailAddress(#[from] lettre::address::AddressError),
+    #[error("email message")]
+    EmailMessage(#[from] lettre::error::Error),
     #[error("member doesn't exist yet")]
     MemberDoesNotExist,
     #[error("staker doesn't exist yet")]
     StakerDoesNotExist,
+    #[error("api key doesn't exist or was revoked")]
+    ApiKeyDoesNotExist,
     #[error("share account received")]
     ShareAccountReceived,
     #[error("proof account received")]
     ProofAccountReceived,
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("invalid registration proof of work")]
+    InvalidRegistrationProof,
+    #[error("database is not empty")]
+    DatabaseNotEmpty,
+    #[error("email notifications are not enabled by the pool")]
+    EmailDisabled,
+    #[error("round {0} was already distributed")]
+    RoundAlreadyDistributed(String),
+    #[error("invalid proxy protocol header")]
+    InvalidProxyHeader,
+    #[error("claim below the pool minimum of {0}")]
+    ClaimBelowMinimum(u64),
+    #[error("claim doesn't cover the fee of {0}")]
+    ClaimBelowFee(u64),
+    #[error("operator-paid claims are not enabled by the pool")]
+    ClaimFeesDisabled,
+    #[error("invalid input: {0}")]
+    InvalidInput(#[from] ValidationError),
+    #[error("rpc: {0}")]
+    Rpc(String),
+    #[error("transaction failed: {0}")]
+    TransactionFailed(String),
+    #[error("database: {0}")]
+    Database(String),
+    #[error("serialization: {0}")]
+    Serialization(String),
     #[error("{0}")]
-    Internal(String),
+    Validation(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("config: {0}")]
+    Config(String),
+    #[error("{context}: {source}")]
+    Context { context: String, source: Box<Error> },
+    #[error("{0} lock poisoned")]
+    LockPoisoned(&'static str),
+    #[error("task join")]
+    TaskJoin(#[from] tokio::task::JoinError),
+}
+
+/// The category of an error, for the status it's answered with and whether to retry it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The rpc or another upstream failed.
+    Rpc,
+    /// The db failed or rejected the query.
+    Database,
+    /// Input couldn't be decoded.
+    Serialization,
+    /// Input was decoded but is invalid.
+    Validation,
+    NotFound,
+    Conflict,
+    Unauthorized,
+    /// The server is misconfigured.
+    Config,
+    Internal,
+}
+
+impl Error {
+    /// Wraps the error with what was being done when it occurred, keeping its kind.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without the context attached to it.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.root() {
+            Error::Rpc(_)
+            | Error::TransactionFailed(_)
+            | Error::Reqwest(_)
+            | Error::SolanaClient(_)
+            | Error::SolanaPubsubClient(_)
+            | Error::GeyserBuilder(_)
+            | Error::GeyserClient(_)
+            | Error::Smtp(_) => ErrorKind::Rpc,
+            Error::Database(_) | Error::TokioPostgres(_) | Error::DeadpoolPostgres(_) => {
+                ErrorKind::Database
+            }
+            Error::Serialization(_)
+            | Error::Bincode(_)
+            | Error::Base64Decode(_)
+            | Error::TryFromSlice(_)
+            | Error::SerdeJson(_)
+            | Error::HttpHeader(_)
+            | Error::SolanaPubkey(_)
+            | Error::InvalidProxyHeader => ErrorKind::Serialization,
+            Error::Validation(_)
+            | Error::InvalidInput(_)
+            | Error::EmailAddress(_)
+            | Error::ClaimBelowMinimum(_)
+            | Error::ClaimBelowFee(_)
+            | Error::ClaimFeesDisabled
+            | Error::EmailDisabled => ErrorKind::Validation,
+            Error::NotFound(_)
+            | Error::MemberDoesNotExist
+            | Error::StakerDoesNotExist
+            | Error::ApiKeyDoesNotExist => ErrorKind::NotFound,
+            Error::Conflict(_) | Error::DatabaseNotEmpty | Error::RoundAlreadyDistributed(_) => {
+                ErrorKind::Conflict
+            }
+            Error::Unauthorized | Error::InvalidRegistrationProof => ErrorKind::Unauthorized,
+            Error::Config(_)
+            | Error::StdEnv(_)
+            | Error::StdParseInt(_)
+            | Error::StdParseBool(_)
+            | Error::StdAddrParse(_)
+            | Error::SentryDsn(_)
+            | Error::OpenTelemetryTrace(_)
+            | Error::OpenTelemetryMetrics(_) => ErrorKind::Config,
+            _ => ErrorKind::Internal,
+        }
+    }
+
+    /// Whether the same request may succeed if retried later,
+    /// as the rpc or db were briefly unavailable.
+    pub fn is_retryable(&self) -> bool {
+        if self.is_db_unavailable() {
+            return true;
+        }
+        match self.root() {
+            Error::Rpc(_) => true,
+            Error::Reqwest(err) => err.is_timeout() || err.is_connect(),
+            Error::SolanaClient(err) => matches!(
+                err.kind(),
+                ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_)
+            ),
+            Error::SolanaPubsubClient(_) | Error::GeyserClient(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the db couldn't be reached, as opposed to rejecting the query.
+    pub fn is_db_unavailable(&self) -> bool {
+        match self.root() {
+            Error::DeadpoolPostgres(_) => true,
+            Error::TokioPostgres(err) => {
+                err.is_closed()
+                    || std::error::Error::source(err).is_some_and(|err| err.is::<std::io::Error>())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Attaches context to the error of a result, keeping its kind.
+pub trait Context<T> {
+    fn context(self, context: impl Into<String>) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, Error> {
+        self.map_err(|err| err.into().context(context))
+    }
 }
 
 impl From<Error> for HttpResponse {
     fn from(value: Error) -> Self {
-        match value {
-            Error::MemberDoesNotExist | Error::StakerDoesNotExist => {
-                HttpResponse::NotFound().finish()
+        match value.root() {
+            Error::ShareAccountReceived => return HttpResponse::Ok().finish(),
+            Error::InvalidRegistrationProof => {
+                return HttpResponse::Forbidden().body("invalid registration proof of work")
+            }
+            _ => {}
+        }
+        match value.kind() {
+            ErrorKind::NotFound => HttpResponse::NotFound().body(value.to_string()),
+            ErrorKind::Conflict => HttpResponse::Conflict().body(value.to_string()),
+            ErrorKind::Serialization | ErrorKind::Validation => {
+                HttpResponse::BadRequest().body(value.to_string())
+            }
+            ErrorKind::Unauthorized => HttpResponse::Unauthorized().finish(),
+            _ if value.is_retryable() => {
+                telemetry::capture(&value);
+                HttpResponse::ServiceUnavailable()
+                    .insert_header(("Retry-After", RETRY_AFTER_SECONDS.to_string()))
+                    .finish()
+            }
+            _ => {
+                telemetry::capture(&value);
+                HttpResponse::InternalServerError().finish()
             }
-            Error::ShareAccountReceived => HttpResponse::Ok().finish(),
-            _ => HttpResponse::InternalServerError().finish(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn maps_kinds_to_statuses() {
+        let status = |err: Error| HttpResponse::from(err).status();
+        assert_eq!(status(Error::MemberDoesNotExist), StatusCode::NOT_FOUND);
+        assert_eq!(
+            status(Error::Validation("invalid claim accounts".to_string())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(Error::Rpc("could not confirm transaction".to_string())),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(Error::TransactionFailed("custom program error".to_string())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(Error::LockPoisoned("journal")),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(Error::SolanaProgram(
+                solana_sdk::program_error::ProgramError::InvalidAccountData
+            )),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn context_keeps_the_kind() {
+        let res: Result<(), Error> = Err(Error::Unauthorized);
+        let err = res.context("invalid webhook auth header").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unauthorized);
+        assert_eq!(err.to_string(), "invalid webhook auth header: unauthorized");
+        assert!(matches!(err.root(), Error::Unauthorized));
+        let err = Error::Rpc("geyser stream closed".to_string()).context("geyser");
+        assert!(err.is_retryable());
+        assert!(!Error::TransactionFailed(String::new()).is_retryable());
+    }
+}
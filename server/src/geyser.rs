@@ -0,0 +1,337 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use futures::{SinkExt, StreamExt};
+use ore_api::state::Proof;
+use ore_pool_api::state::Pool;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use steel::AccountDeserialize;
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+    SubscribeUpdateAccount, SubscribeUpdateTransaction,
+};
+
+use crate::{
+    error::Error,
+    ha::Leadership,
+    webhook::{self, RewardsBySignature},
+};
+
+/// The max confirmed signatures kept, for confirmations awaited after the update arrived.
+const MAX_SIGNATURES: usize = 4096;
+
+/// How long to wait before reconnecting a failed stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// The max delay between reconnects, backing off from `RECONNECT_DELAY`.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Ingests the pool and proof accounts and the confirmed pool program transactions
+/// from a Yellowstone gRPC (Geyser) stream at `GEYSER_URL`, authenticated with `GEYSER_X_TOKEN`,
+/// for operators running their own validator.
+///
+/// While the stream is up, the accounts are read from it instead of the rpc,
+/// transactions are confirmed from it instead of the rpc websocket,
+/// and the rewards of each submission are decoded from its logs,
+/// ahead of the webhook, which posts them again for the same signature.
+/// Everything falls back to the rpc while it's reconnecting.
+pub struct Geyser {
+    url: String,
+    x_token: Option<String>,
+    pool_address: Pubkey,
+    proof_address: Pubkey,
+    connected: AtomicBool,
+    pool: tokio::sync::watch::Sender<Option<Pool>>,
+    proof: Mutex<Option<Proof>>,
+    signatures: Mutex<Signatures>,
+    /// Bumped on each confirmed signature.
+    confirmed: tokio::sync::watch::Sender<u64>,
+}
+
+/// The most recently confirmed signatures, and whether each failed.
+#[derive(Default)]
+struct Signatures {
+    by_signature: HashMap<Signature, Option<String>>,
+    order: VecDeque<Signature>,
+}
+
+impl Geyser {
+    /// None unless `GEYSER_URL` is set.
+    pub fn from_env(pool_authority: &Pubkey) -> Option<Self> {
+        let url = std::env::var("GEYSER_URL").ok()?;
+        // optional
+        let x_token = std::env::var("GEYSER_X_TOKEN").ok();
+        let (pool_address, _) = ore_pool_api::state::pool_pda(*pool_authority);
+        let (proof_address, _) = ore_pool_api::state::pool_proof_pda(pool_address);
+        tracing::info!("ingesting from geyser at {}", url);
+        Some(Self {
+            url,
+            x_token,
+            pool_address,
+            proof_address,
+            connected: AtomicBool::new(false),
+            pool: tokio::sync::watch::channel(None).0,
+            proof: Mutex::new(None),
+            signatures: Mutex::new(Signatures::default()),
+            confirmed: tokio::sync::watch::channel(0).0,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The latest pool account, none unless streamed since the last connect.
+    pub fn pool(&self) -> Option<Pool> {
+        if !self.is_connected() {
+            return None;
+        }
+        *self.pool.borrow()
+    }
+
+    /// The latest proof account, none unless streamed since the last connect.
+    pub fn proof(&self) -> Option<Proof> {
+        if !self.is_connected() {
+            return None;
+        }
+        *self.proof.lock().ok()?
+    }
+
+    /// Resolves on the next pool account update, or after the timeout.
+    pub async fn wait_for_pool(&self, timeout: Duration) {
+        let mut rx = self.pool.subscribe();
+        let _ = tokio::time::timeout(timeout, rx.changed()).await;
+    }
+
+    /// Resolves once the signature is confirmed on the stream,
+    /// or errors if it failed or timed out.
+    pub async fn confirm(&self, sig: &Signature, timeout: Duration) -> Result<(), Error> {
+        let mut rx = self.confirmed.subscribe();
+        let confirmed = async {
+            loop {
+                if let Some(err) = self.confirmation(sig) {
+                    return err;
+                }
+                if rx.changed().await.is_err() {
+                    return Some("geyser stream closed".to_string());
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, confirmed).await {
+            Ok(None) => Ok(()),
+            Ok(Some(err)) => Err(Error::TransactionFailed(err)),
+            Err(_) => Err(Error::Rpc("could not confirm transaction".to_string())),
+        }
+    }
+
+    /// Streams the accounts and transactions, reconnecting on failure,
+    /// recording the rewards of each submission for the aggregator, while leader.
+    pub async fn run(&self, rewards: &RewardsBySignature, leadership: &Leadership) {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            if let Err(err) = self.stream(rewards, leadership, &mut delay).await {
+                tracing::warn!("geyser stream failed, falling back to rpc: {:?}", err);
+            }
+            self.connected.store(false, Ordering::Relaxed);
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn stream(
+        &self,
+        rewards: &RewardsBySignature,
+        leadership: &Leadership,
+        delay: &mut Duration,
+    ) -> Result<(), Error> {
+        let mut client = GeyserGrpcClient::build_from_shared(self.url.clone())?
+            .x_token(self.x_token.clone())?
+            .connect_timeout(Duration::from_secs(10))
+            .connect()
+            .await?;
+        let (mut subscribe_tx, mut stream) = client
+            .subscribe_with_request(Some(self.subscribe_request()))
+            .await?;
+        // the accounts are streamed on change only, so they're read from the rpc until then
+        self.pool.send_replace(None);
+        if let Ok(mut proof) = self.proof.lock() {
+            *proof = None;
+        }
+        self.connected.store(true, Ordering::Relaxed);
+        tracing::info!("geyser stream connected");
+        while let Some(update) = stream.next().await {
+            let update = update.map_err(|status| Error::Rpc(status.to_string()))?;
+            *delay = RECONNECT_DELAY;
+            match update.update_oneof {
+                Some(UpdateOneof::Account(account)) => self.on_account(account),
+                Some(UpdateOneof::Transaction(transaction)) => {
+                    self.on_transaction(transaction, rewards, leadership)
+                }
+                Some(UpdateOneof::Ping(_)) => {
+                    // keeps load balancers in front of the validator from closing the stream
+                    let ping = SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    };
+                    subscribe_tx
+                        .send(ping)
+                        .await
+                        .map_err(|err| Error::Rpc(err.to_string()))?;
+                }
+                _ => {}
+            }
+        }
+        Err(Error::Rpc("geyser stream closed".to_string()))
+    }
+
+    fn subscribe_request(&self) -> SubscribeRequest {
+        let accounts = SubscribeRequestFilterAccounts {
+            account: vec![
+                self.pool_address.to_string(),
+                self.proof_address.to_string(),
+            ],
+            ..Default::default()
+        };
+        let transactions = SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            account_include: vec![ore_pool_api::ID.to_string()],
+            ..Default::default()
+        };
+        SubscribeRequest {
+            accounts: HashMap::from([("pool".to_string(), accounts)]),
+            transactions: HashMap::from([("pool".to_string(), transactions)]),
+            commitment: Some(CommitmentLevel::Confirmed as i32),
+            ..Default::default()
+        }
+    }
+
+    fn on_account(&self, update: SubscribeUpdateAccount) {
+        let Some(account) = update.account else {
+            return;
+        };
+        let Ok(address) = Pubkey::try_from(account.pubkey.as_slice()) else {
+            return;
+        };
+        if address == self.pool_address {
+            match Pool::try_from_bytes(account.data.as_slice()) {
+                Ok(pool) => {
+                    self.pool.send_replace(Some(*pool));
+                }
+                Err(err) => tracing::warn!("invalid pool account from geyser: {:?}", err),
+            }
+        } else if address == self.proof_address {
+            match Proof::try_from_bytes(account.data.as_slice()) {
+                Ok(proof) => {
+                    if let Ok(mut latest) = self.proof.lock() {
+                        *latest = Some(*proof);
+                    }
+                }
+                Err(err) => tracing::warn!("invalid proof account from geyser: {:?}", err),
+            }
+        }
+    }
+
+    fn on_transaction(
+        &self,
+        update: SubscribeUpdateTransaction,
+        rewards: &RewardsBySignature,
+        leadership: &Leadership,
+    ) {
+        let Some(info) = update.transaction else {
+            return;
+        };
+        let Ok(sig) = Signature::try_from(info.signature.as_slice()) else {
+            return;
+        };
+        let Some(meta) = info.meta else {
+            return;
+        };
+        let err = meta.err.map(|err| format!("{:?}", err.err));
+        let is_submission = err.is_none()
+            && info
+                .transaction
+                .and_then(|tx| tx.message)
+                .is_some_and(|message| {
+                    message
+                        .account_keys
+                        .iter()
+                        .any(|key| key.as_slice() == self.proof_address.as_ref())
+                });
+        self.record_confirmation(sig, err);
+        // only the leader submits, and awaits the rewards
+        if !is_submission || !leadership.is_leader() {
+            return;
+        }
+        // claims touch the proof too, but carry no rewards
+        match webhook::decode_rewards(meta.log_messages.as_slice()) {
+            Ok(submission_rewards) => {
+                tracing::info!("rewards of {} from geyser: {:?}", sig, submission_rewards);
+                rewards.insert(sig, submission_rewards);
+            }
+            Err(err) => tracing::debug!("no rewards in {}: {:?}", sig, err),
+        }
+    }
+
+    fn record_confirmation(&self, sig: Signature, err: Option<String>) {
+        if let Ok(mut signatures) = self.signatures.lock() {
+            if signatures.by_signature.insert(sig, err).is_none() {
+                signatures.order.push_back(sig);
+            }
+            while signatures.order.len() > MAX_SIGNATURES {
+                if let Some(evicted) = signatures.order.pop_front() {
+                    signatures.by_signature.remove(&evicted);
+                }
+            }
+        }
+        self.confirmed.send_modify(|n| *n = n.wrapping_add(1));
+    }
+
+    /// None if not confirmed yet, or whether it failed.
+    fn confirmation(&self, sig: &Signature) -> Option<Option<String>> {
+        self.signatures.lock().ok()?.by_signature.get(sig).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_confirmations() {
+        let (pool_address, _) = ore_pool_api::state::pool_pda(Pubkey::new_unique());
+        let geyser = Geyser {
+            url: String::new(),
+            x_token: None,
+            pool_address,
+            proof_address: Pubkey::new_unique(),
+            connected: AtomicBool::new(false),
+            pool: tokio::sync::watch::channel(None).0,
+            proof: Mutex::new(None),
+            signatures: Mutex::new(Signatures::default()),
+            confirmed: tokio::sync::watch::channel(0).0,
+        };
+        let landed = Signature::new_unique();
+        let failed = Signature::new_unique();
+        geyser.record_confirmation(landed, None);
+        geyser.record_confirmation(failed, Some("InstructionError".to_string()));
+        assert_eq!(geyser.confirmation(&landed), Some(None));
+        assert!(geyser
+            .confirmation(&failed)
+            .is_some_and(|err| err.is_some()));
+        assert_eq!(geyser.confirmation(&Signature::new_unique()), None);
+        for _ in 0..MAX_SIGNATURES {
+            geyser.record_confirmation(Signature::new_unique(), None);
+        }
+        assert_eq!(geyser.confirmation(&landed), None);
+        // unread until streamed
+        assert!(geyser.pool().is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, HttpResponse, Responder,
+};
+use solana_sdk::pubkey::Pubkey;
+use tokio_postgres::NoTls;
+
+use crate::error::Error;
+
+/// The seconds between attempts to take the lock, and heartbeats while holding it,
+/// if `HA_POLL_SECONDS` is unset.
+const DEFAULT_POLL_SECONDS: u64 = 5;
+
+/// Whether this instance manages the pool, in active/standby mode.
+///
+/// With `HA_ENABLED`, instances against the same db elect a leader by holding
+/// a postgres advisory lock keyed by the pool, on a connection of their own.
+/// The lock is released by postgres when the connection of the leader dies,
+/// and taken by a standby on its next poll.
+/// The leader also steps down if a heartbeat on the connection fails,
+/// so two leaders overlap for at most one poll.
+///
+/// Only the leader aggregates contributions, serves the challenge, submits, and attributes.
+/// Standbys serve the read endpoints.
+pub struct Leadership {
+    is_leader: tokio::sync::watch::Receiver<bool>,
+}
+
+impl Leadership {
+    /// Always the leader, unless `HA_ENABLED` is set.
+    pub fn from_env(pool: &Pubkey) -> Result<Self, Error> {
+        let enabled = match std::env::var("HA_ENABLED") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => false,
+        };
+        if !enabled {
+            let (_, is_leader) = tokio::sync::watch::channel(true);
+            return Ok(Self { is_leader });
+        }
+        let poll_seconds = match std::env::var("HA_POLL_SECONDS") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => DEFAULT_POLL_SECONDS,
+        };
+        let db_url = std::env::var("DB_URL")?;
+        let lock_key = lock_key(pool);
+        let (leader_tx, is_leader) = tokio::sync::watch::channel(false);
+        tracing::info!(lock_key, poll_seconds, "ha: starting as standby");
+        tokio::spawn(async move {
+            let poll = Duration::from_secs(poll_seconds);
+            loop {
+                if let Err(err) = elect(db_url.as_str(), lock_key, poll, &leader_tx).await {
+                    tracing::error!("ha: {:?}", err);
+                }
+                if *leader_tx.borrow() {
+                    tracing::warn!("ha: lost leadership, now standby");
+                }
+                leader_tx.send_replace(false);
+                tokio::time::sleep(poll).await;
+            }
+        });
+        Ok(Self { is_leader })
+    }
+
+    pub fn is_leader(&self) -> bool {
+        *self.is_leader.borrow()
+    }
+
+    /// Waits until this instance is the leader.
+    pub async fn until_leader(&self) {
+        let mut is_leader = self.is_leader.clone();
+        // the sender is never dropped while the server runs
+        let _ = is_leader.wait_for(|is_leader| *is_leader).await;
+    }
+
+    /// Waits until this instance is no longer the leader.
+    pub async fn until_standby(&self) {
+        let mut is_leader = self.is_leader.clone();
+        if is_leader.wait_for(|is_leader| !*is_leader).await.is_err() {
+            // without leader election, the single instance leads for good
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// 200 if this instance is the leader, 503 otherwise,
+    /// for load balancers to route the leader-only endpoints.
+    pub async fn health(leadership: web::Data<Self>) -> impl Responder {
+        match leadership.is_leader() {
+            true => HttpResponse::Ok().body("leader"),
+            false => HttpResponse::ServiceUnavailable().body("standby"),
+        }
+    }
+}
+
+/// Rejects requests to leader-only endpoints on a standby with a 503.
+pub async fn leader_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let is_leader = req
+        .app_data::<web::Data<Leadership>>()
+        .map(|leadership| leadership.is_leader())
+        // without leader election, the single instance leads
+        .unwrap_or(true);
+    if !is_leader {
+        let res = HttpResponse::ServiceUnavailable().body("standby");
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    let res = next.call(req).await?;
+    Ok(res.map_into_left_body())
+}
+
+/// Takes the lock on a new connection and holds it while the connection is healthy.
+/// Returns once the connection fails.
+async fn elect(
+    db_url: &str,
+    lock_key: i64,
+    poll: Duration,
+    leader_tx: &tokio::sync::watch::Sender<bool>,
+) -> Result<(), Error> {
+    let (client, connection) = tokio_postgres::connect(db_url, NoTls).await?;
+    let mut connection = tokio::spawn(connection);
+    loop {
+        let is_leader = *leader_tx.borrow();
+        let res = tokio::select! {
+            res = tokio::time::timeout(poll, heartbeat(&client, lock_key, is_leader)) => res,
+            _ = &mut connection => {
+                return Err(Error::Database("leader election connection closed".to_string()));
+            }
+        };
+        let locked =
+            res.map_err(|_| Error::Database("leader election timed out".to_string()))??;
+        if locked && !is_leader {
+            tracing::info!("ha: elected leader");
+            leader_tx.send_replace(true);
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
+
+/// Tries to take the lock if not yet held, or checks the connection if held.
+async fn heartbeat(
+    client: &tokio_postgres::Client,
+    lock_key: i64,
+    is_leader: bool,
+) -> Result<bool, Error> {
+    if is_leader {
+        client.query_one("SELECT 1", &[]).await?;
+        return Ok(true);
+    }
+    let row = client
+        .query_one("SELECT pg_try_advisory_lock($1)", &[&lock_key])
+        .await?;
+    Ok(row.try_get(0)?)
+}
+
+/// The advisory lock key of the pool, so pools sharing a db elect their own leaders.
+fn lock_key(pool: &Pubkey) -> i64 {
+    let bytes = pool.to_bytes();
+    let mut key = [0; 8];
+    key.copy_from_slice(&bytes[..8]);
+    i64::from_le_bytes(key)
+}
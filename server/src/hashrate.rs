@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use ore_pool_types::HashrateWindow;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::aggregator::Contribution;
+
+/// The trailing windows the member hashrate is averaged over, in seconds.
+pub const WINDOWS: [i64; 3] = [10 * 60, 60 * 60, 24 * 60 * 60];
+
+/// How long the shares of each round are kept for the hashrate windows.
+pub const RETENTION: i64 = 2 * 24 * 60 * 60;
+
+/// The shares of a member in a submitted round,
+/// persisted for the time-weighted hashrate of the member api.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberShares {
+    pub authority: Pubkey,
+
+    /// The number of shares accepted.
+    pub accepted: u64,
+
+    /// The number of shares rejected, as below the min difficulty, invalid, or duplicate.
+    pub rejected: u64,
+
+    /// The total difficulty score of the accepted shares.
+    pub score: u64,
+}
+
+/// The shares rejected from each member since the last round was submitted,
+/// counted by the contribute handler before they reach the aggregator.
+#[derive(Default)]
+pub struct RejectedShares {
+    counts: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl RejectedShares {
+    pub fn add(&self, member: &Pubkey) {
+        if let Ok(mut counts) = self.counts.lock() {
+            *counts.entry(*member).or_default() += 1;
+        }
+    }
+
+    /// The rejected shares counted so far, resetting the counts for the next round.
+    pub fn take(&self) -> HashMap<Pubkey, u64> {
+        self.counts
+            .lock()
+            .map(|mut counts| std::mem::take(&mut *counts))
+            .unwrap_or_default()
+    }
+}
+
+/// The shares of each member in the round, from the accepted contributions
+/// and the counts of rejected shares.
+pub fn member_shares<'a>(
+    contributions: impl Iterator<Item = &'a Contribution>,
+    rejected: HashMap<Pubkey, u64>,
+) -> Vec<MemberShares> {
+    let mut shares: HashMap<Pubkey, MemberShares> = HashMap::new();
+    fn entry(shares: &mut HashMap<Pubkey, MemberShares>, authority: Pubkey) -> &mut MemberShares {
+        shares.entry(authority).or_insert(MemberShares {
+            authority,
+            accepted: 0,
+            rejected: 0,
+            score: 0,
+        })
+    }
+    for contribution in contributions {
+        let member = entry(&mut shares, contribution.member);
+        member.accepted += 1;
+        member.score = member.score.saturating_add(contribution.score);
+    }
+    for (authority, count) in rejected {
+        entry(&mut shares, authority).rejected += count;
+    }
+    let mut shares: Vec<MemberShares> = shares.into_values().collect();
+    shares.sort_by_key(|member| member.authority);
+    shares
+}
+
+/// The hashrate over the window, from the score of the shares accepted within it,
+/// so that time without shares averages in as no hashrate.
+pub fn window(window: i64, score: u64, accepted: u64, rejected: u64) -> HashrateWindow {
+    let total = accepted.saturating_add(rejected);
+    HashrateWindow {
+        window,
+        hashrate: score / window.max(1) as u64,
+        accepted,
+        rejected,
+        acceptance_ratio: match total {
+            0 => None,
+            total => Some(accepted as f64 / total as f64),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+
+    use super::*;
+
+    fn contribution(member: Pubkey, worker: &str, score: u64) -> Contribution {
+        Contribution {
+            member,
+            worker: worker.to_string(),
+            score,
+            solution: Solution::new([0; 16], [0; 8]),
+            challenge: [0; 32],
+            span: tracing::Span::none(),
+        }
+    }
+
+    #[test]
+    fn sums_member_shares() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let contributions = [contribution(a, "rig-1", 256), contribution(a, "rig-2", 512)];
+        let rejected = RejectedShares::default();
+        rejected.add(&a);
+        rejected.add(&b);
+        rejected.add(&b);
+        let mut shares = member_shares(contributions.iter(), rejected.take());
+        shares.sort_by_key(|member| member.authority != a);
+        assert_eq!(
+            shares,
+            vec![
+                MemberShares {
+                    authority: a,
+                    accepted: 2,
+                    rejected: 1,
+                    score: 768,
+                },
+                MemberShares {
+                    authority: b,
+                    accepted: 0,
+                    rejected: 2,
+                    score: 0,
+                },
+            ]
+        );
+        // reset for the next round
+        assert!(rejected.take().is_empty());
+    }
+
+    #[test]
+    fn averages_over_window() {
+        let stats = window(600, 600 * 1_000, 3, 1);
+        assert_eq!(stats.hashrate, 1_000);
+        assert_eq!(stats.acceptance_ratio, Some(0.75));
+        assert_eq!(window(600, 0, 0, 0).acceptance_ratio, None);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use ore_pool_types::{DifficultyBucket, DifficultyHistogram};
+use serde::{Deserialize, Serialize};
+
+/// The min shares before a histogram may be flagged as uniform.
+const MIN_UNIFORM_SHARES: u64 = 50;
+
+/// The fraction of shares in a single bucket above which a histogram is uniform.
+/// The best hash of a steady rig spreads over a few difficulties,
+/// so nearly every share at one difficulty suggests shares aren't from honest mining.
+const UNIFORM_MODE_SHARE: f64 = 0.9;
+
+/// The counts of accepted shares by difficulty.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Histogram {
+    counts: BTreeMap<u32, u64>,
+    shares: u64,
+}
+
+impl Histogram {
+    pub fn add(&mut self, difficulty: u32) {
+        *self.counts.entry(difficulty).or_default() += 1;
+        self.shares += 1;
+    }
+
+    /// Adds the counts of the other histogram.
+    pub fn merge(&mut self, other: &Histogram) {
+        for (difficulty, count) in other.counts.iter() {
+            *self.counts.entry(*difficulty).or_default() += count;
+        }
+        self.shares += other.shares;
+    }
+
+    pub fn shares(&self) -> u64 {
+        self.shares
+    }
+
+    pub fn min(&self) -> Option<u32> {
+        self.counts.keys().next().copied()
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        self.counts.keys().next_back().copied()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.shares == 0 {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .counts
+            .iter()
+            .map(|(difficulty, count)| *difficulty as f64 * *count as f64)
+            .sum();
+        sum / self.shares as f64
+    }
+
+    /// The fraction of the shares in the most common bucket.
+    pub fn mode_share(&self) -> f64 {
+        match self.counts.values().max() {
+            Some(count) => *count as f64 / self.shares as f64,
+            None => 0.0,
+        }
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        self.shares >= MIN_UNIFORM_SHARES && self.mode_share() >= UNIFORM_MODE_SHARE
+    }
+
+    pub fn to_stats(&self) -> DifficultyHistogram {
+        DifficultyHistogram {
+            buckets: self
+                .counts
+                .iter()
+                .map(|(difficulty, count)| DifficultyBucket {
+                    difficulty: *difficulty,
+                    count: *count,
+                })
+                .collect(),
+            shares: self.shares,
+            mean: self.mean(),
+            mode_share: self.mode_share(),
+            is_uniform: self.is_uniform(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_shares() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.mean(), 0.0);
+        assert_eq!(histogram.mode_share(), 0.0);
+        for difficulty in [10, 12, 12, 14] {
+            histogram.add(difficulty);
+        }
+        assert_eq!(histogram.shares(), 4);
+        assert_eq!(histogram.min(), Some(10));
+        assert_eq!(histogram.max(), Some(14));
+        assert_eq!(histogram.mean(), 12.0);
+        assert_eq!(histogram.mode_share(), 0.5);
+        assert_eq!(histogram.to_stats().buckets.len(), 3);
+    }
+
+    #[test]
+    fn flags_uniform_shares() {
+        let mut histogram = Histogram::default();
+        for _ in 0..MIN_UNIFORM_SHARES - 1 {
+            histogram.add(16);
+        }
+        assert!(!histogram.is_uniform());
+        histogram.add(16);
+        assert!(histogram.is_uniform());
+        for difficulty in 0..MIN_UNIFORM_SHARES as u32 {
+            histogram.add(16 + difficulty % 4);
+        }
+        assert!(!histogram.is_uniform());
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Signature;
+
+use crate::{aggregator::RoundSummary, database, error::Error, utils::unix_timestamp};
+
+/// How often the journal is replayed into the db while it has entries.
+pub const REPLAY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The default file the journal is written to.
+const DEFAULT_PATH: &str = "ore-pool-journal.jsonl";
+
+/// A round write, in the order it was made.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Claim {
+        challenge: [u8; 32],
+        last_hash_at: i64,
+        claimed_at: i64,
+    },
+    Release {
+        challenge: [u8; 32],
+    },
+    Signature {
+        challenge: [u8; 32],
+        signature: Signature,
+    },
+    Distribution {
+        challenge: [u8; 32],
+        increments: Vec<(String, u64)>,
+        summary: RoundSummary,
+    },
+}
+
+impl Entry {
+    fn challenge(&self) -> &[u8; 32] {
+        match self {
+            Self::Claim { challenge, .. }
+            | Self::Release { challenge }
+            | Self::Signature { challenge, .. }
+            | Self::Distribution { challenge, .. } => challenge,
+        }
+    }
+}
+
+/// The write-ahead journal of the rounds submitted while the db was unavailable,
+/// appended to `JOURNAL_PATH` (defaults to `ore-pool-journal.jsonl`),
+/// and replayed into the db in order once it's back.
+///
+/// Once a write of a round is journaled, the rest of the round is journaled too,
+/// so that its writes reach the db in order.
+/// The journal survives restarts, so a round claimed while the db was down
+/// is still never submitted or distributed twice.
+pub struct Journal {
+    path: PathBuf,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Journal {
+    /// Under `HA_ENABLED`, the journal must be on storage shared by every instance,
+    /// so that a new leader replays and honors the rounds journaled by the last one.
+    pub fn from_env() -> Result<Self, Error> {
+        let ha_enabled = match std::env::var("HA_ENABLED") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => false,
+        };
+        let path = match std::env::var("JOURNAL_PATH") {
+            Ok(str) => PathBuf::from(str),
+            Err(_) if ha_enabled => {
+                return Err(Error::Config(
+                    "HA_ENABLED requires JOURNAL_PATH, on storage shared by every instance"
+                        .to_string(),
+                ));
+            }
+            // optional
+            Err(_) => PathBuf::from(DEFAULT_PATH),
+        };
+        Self::open(path)
+    }
+
+    /// Opens the journal, reading the entries not yet replayed.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let entries = Self::read(&path)?;
+        if !entries.is_empty() {
+            tracing::warn!("{} journaled round writes to replay", entries.len());
+        }
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Rereads the entries not yet replayed,
+    /// including those journaled by another instance since this one opened it,
+    /// as on taking over the leadership.
+    pub fn reload(&self) -> Result<(), Error> {
+        let mut entries = self.lock()?;
+        *entries = Self::read(&self.path)?;
+        if !entries.is_empty() {
+            tracing::warn!("{} journaled round writes to replay", entries.len());
+        }
+        Ok(())
+    }
+
+    fn read(path: &PathBuf) -> Result<Vec<Entry>, Error> {
+        let mut entries = vec![];
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                entries.push(serde_json::from_str(line.as_str())?);
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock()
+            .map(|entries| entries.is_empty())
+            .unwrap_or(true)
+    }
+
+    /// Claims the round for submission, returning false if it was already claimed.
+    pub async fn claim_round(
+        &self,
+        pool: &Pool,
+        challenge: &[u8; 32],
+        last_hash_at: i64,
+    ) -> Result<bool, Error> {
+        if !self.is_journaled(challenge)? {
+            let res = async {
+                let conn = pool.get().await?;
+                database::write_round_claim(&conn, challenge, last_hash_at).await
+            }
+            .await;
+            match res {
+                Err(err) if err.is_db_unavailable() => {
+                    tracing::warn!("database unavailable, journaling round claim: {:?}", err);
+                }
+                res => return res,
+            }
+        }
+        let claimed_at = unix_timestamp();
+        if self.is_claimed(challenge, claimed_at)? {
+            return Ok(false);
+        }
+        self.append(Entry::Claim {
+            challenge: *challenge,
+            last_hash_at,
+            claimed_at,
+        })?;
+        Ok(true)
+    }
+
+    /// Releases the claim of a round that failed to land, with any signature it was sent with.
+    pub async fn release_round(&self, pool: &Pool, challenge: &[u8; 32]) -> Result<(), Error> {
+        if !self.is_journaled(challenge)? {
+            let res = async {
+                let conn = pool.get().await?;
+                database::delete_round_claim(&conn, challenge).await
+            }
+            .await;
+            match res {
+                Err(err) if err.is_db_unavailable() => {}
+                res => return res,
+            }
+        }
+        self.append(Entry::Release {
+            challenge: *challenge,
+        })
+    }
+
+    /// Records the signature the round was sent with, before it's confirmed.
+    pub async fn record_signature(
+        &self,
+        pool: &Pool,
+        challenge: &[u8; 32],
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        if !self.is_journaled(challenge)? {
+            let res = async {
+                let conn = pool.get().await?;
+                database::write_round_signature(&conn, challenge, signature).await
+            }
+            .await;
+            match res {
+                Err(err) if err.is_db_unavailable() => {}
+                res => return res,
+            }
+        }
+        self.append(Entry::Signature {
+            challenge: *challenge,
+            signature: *signature,
+        })
+    }
+
+    /// Writes the rewards of the round to the member balances, and its summary.
+    pub async fn distribute_round(
+        &self,
+        pool: &Pool,
+        challenge: &[u8; 32],
+        increments: Vec<(String, u64)>,
+        summary: RoundSummary,
+    ) -> Result<(), Error> {
+        if !self.is_journaled(challenge)? {
+            let res = write_distribution(pool, challenge, increments.clone(), &summary).await;
+            match res {
+                Err(err) if err.is_db_unavailable() => {
+                    tracing::warn!("database unavailable, journaling distribution: {:?}", err);
+                }
+                res => return res,
+            }
+        }
+        self.append(Entry::Distribution {
+            challenge: *challenge,
+            increments,
+            summary,
+        })
+    }
+
+    /// Replays the journal into the db in order, up to the first write that fails,
+    /// returning the number of writes replayed.
+    pub async fn replay(&self, pool: &Pool) -> Result<usize, Error> {
+        let entries = self.lock()?.clone();
+        let mut replayed = 0;
+        let mut res = Ok(());
+        for entry in entries {
+            res = replay_entry(pool, entry).await;
+            if res.is_err() {
+                break;
+            }
+            replayed += 1;
+        }
+        if replayed > 0 {
+            // entries appended while replaying are kept
+            let mut entries = self.lock()?;
+            entries.drain(..replayed);
+            self.rewrite(entries.as_slice())?;
+        }
+        res.map(|_| replayed)
+    }
+
+    fn is_journaled(&self, challenge: &[u8; 32]) -> Result<bool, Error> {
+        Ok(self
+            .lock()?
+            .iter()
+            .any(|entry| entry.challenge() == challenge))
+    }
+
+    /// Whether the journal holds a live claim of the round,
+    /// landed or claimed within the claim timeout.
+    fn is_claimed(&self, challenge: &[u8; 32], now: i64) -> Result<bool, Error> {
+        let mut claimed = false;
+        for entry in self.lock()?.iter().filter(|e| e.challenge() == challenge) {
+            claimed = match entry {
+                Entry::Claim { claimed_at, .. } => {
+                    *claimed_at >= now - database::ROUND_CLAIM_TIMEOUT
+                }
+                Entry::Release { .. } => false,
+                // released if it failed to land after all
+                Entry::Signature { .. } => true,
+                Entry::Distribution { .. } => return Ok(true),
+            };
+        }
+        Ok(claimed)
+    }
+
+    fn append(&self, entry: Entry) -> Result<(), Error> {
+        let mut entries = self.lock()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.sync_all()?;
+        entries.push(entry);
+        Ok(())
+    }
+
+    /// Replaces the journal with the entries, atomically.
+    fn rewrite(&self, entries: &[Entry]) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        file.sync_all()?;
+        std::fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<Entry>>, Error> {
+        self.entries
+            .lock()
+            .map_err(|_| Error::LockPoisoned("journal"))
+    }
+}
+
+async fn replay_entry(pool: &Pool, entry: Entry) -> Result<(), Error> {
+    let conn = pool.get().await?;
+    match entry {
+        Entry::Claim {
+            challenge,
+            last_hash_at,
+            ..
+        } => {
+            database::write_round_claim(&conn, &challenge, last_hash_at).await?;
+        }
+        Entry::Release { challenge } => {
+            database::delete_round_claim(&conn, &challenge).await?;
+        }
+        Entry::Signature {
+            challenge,
+            signature,
+        } => {
+            database::write_round_signature(&conn, &challenge, &signature).await?;
+        }
+        Entry::Distribution {
+            challenge,
+            increments,
+            summary,
+        } => {
+            drop(conn);
+            match write_distribution(pool, &challenge, increments, &summary).await {
+                // replayed before the journal was rewritten
+                Err(Error::RoundAlreadyDistributed(challenge)) => {
+                    tracing::warn!("skipped replaying distributed round {}", challenge);
+                }
+                res => res?,
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn write_distribution(
+    pool: &Pool,
+    challenge: &[u8; 32],
+    increments: Vec<(String, u64)>,
+    summary: &RoundSummary,
+) -> Result<(), Error> {
+    let mut conn = pool.get().await?;
+    database::write_round_distribution(&mut conn, challenge, increments, summary).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_journaled_claims() {
+        let path = std::env::temp_dir().join(format!("journal-{}.jsonl", rand::random::<u64>()));
+        let journal = Journal::open(path.clone()).unwrap();
+        let challenge = [1; 32];
+        journal
+            .append(Entry::Claim {
+                challenge,
+                last_hash_at: 0,
+                claimed_at: 1_000,
+            })
+            .unwrap();
+        assert!(journal.is_claimed(&challenge, 1_000).unwrap());
+        // stale claims are taken over
+        assert!(!journal
+            .is_claimed(&challenge, 1_001 + database::ROUND_CLAIM_TIMEOUT)
+            .unwrap());
+        // sent submissions hold the claim until released
+        journal
+            .append(Entry::Signature {
+                challenge,
+                signature: Signature::new_unique(),
+            })
+            .unwrap();
+        assert!(journal
+            .is_claimed(&challenge, 1_001 + database::ROUND_CLAIM_TIMEOUT)
+            .unwrap());
+        journal.append(Entry::Release { challenge }).unwrap();
+        assert!(!journal.is_claimed(&challenge, 1_000).unwrap());
+        assert!(!journal.is_journaled(&[2; 32]).unwrap());
+        // reopened after a restart
+        let journal = Journal::open(path.clone()).unwrap();
+        assert!(journal.is_journaled(&challenge).unwrap());
+        // journaled by another instance, before this one takes over
+        let other = Journal::open(path.clone()).unwrap();
+        other.append(Entry::Release { challenge: [2; 32] }).unwrap();
+        journal.reload().unwrap();
+        assert!(journal.is_journaled(&[2; 32]).unwrap());
+        journal.rewrite(&[]).unwrap();
+        assert!(Journal::open(path.clone()).unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+pub mod admin;
+pub mod aggregator;
+pub mod api_keys;
+pub mod beacon;
+pub mod cache;
+pub mod claim_fees;
+pub mod client_ip;
+pub mod contributor;
+pub mod database;
+pub mod decay;
+pub mod error;
+pub mod geyser;
+pub mod ha;
+pub mod hashrate;
+pub mod histogram;
+pub mod journal;
+pub mod notify;
+pub mod operator;
+pub mod preflight;
+pub mod prescreen;
+pub mod queue;
+pub mod registration;
+pub mod rpc_usage;
+pub mod serve;
+pub mod shards;
+pub mod signatures;
+pub mod simulate;
+pub mod telemetry;
+pub mod tx;
+pub mod utils;
+pub mod webhook;
+
+use actix_web::{get, middleware, web, HttpResponse, Responder};
+use aggregator::Contribution;
+
+/// Registers the http routes of the server.
+/// The handlers expect the operator, aggregator, channels, api keys, and handlers as app data.
+/// Routes that manage the round, or read its in-memory state, are served by the leader only,
+/// see [`ha::Leadership`].
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    // read by third-party dashboards, so rate limited apart from the miner-facing routes
+    cfg.service(
+        web::resource("/member/{authority}")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .route(web::get().to(contributor::member)),
+    )
+    .service(
+        web::resource("/member/{authority}/workers")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .wrap(middleware::from_fn(ha::leader_only))
+            .route(web::get().to(contributor::workers)),
+    )
+    .service(
+        web::resource("/member/{authority}/adjustments")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .route(web::get().to(contributor::adjustments)),
+    )
+    .service(
+        web::resource("/member/{authority}/payouts")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .route(web::get().to(contributor::payouts)),
+    )
+    .service(
+        web::resource("/member/{authority}/payouts/{round}")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .route(web::get().to(contributor::payout)),
+    )
+    .service(
+        web::resource("/member/{authority}/difficulty")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .wrap(middleware::from_fn(ha::leader_only))
+            .route(web::get().to(contributor::member_difficulty)),
+    )
+    .service(
+        web::resource("/verify/{round}/{member}")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .route(web::get().to(contributor::verify)),
+    )
+    .service(
+        web::resource("/stats/difficulty")
+            .wrap(middleware::from_fn(api_keys::read_access))
+            .wrap(middleware::from_fn(ha::leader_only))
+            .route(web::get().to(contributor::difficulty_stats)),
+    )
+    .service(web::resource("/pool-address").route(web::get().to(contributor::pool_address)))
+    .service(health)
+    .service(web::resource("/health/leader").route(web::get().to(ha::Leadership::health)))
+    // leader only, registered last as the scope matches every path
+    .service(
+        web::scope("")
+            .wrap(middleware::from_fn(ha::leader_only))
+            .service(web::resource("/register").route(web::post().to(contributor::register)))
+            .service(
+                web::resource("/register/challenge/{authority}")
+                    .route(web::get().to(contributor::register_challenge)),
+            )
+            .service(
+                web::resource("/register-staker")
+                    .route(web::post().to(contributor::register_staker)),
+            )
+            .service(web::resource("/contribute").route(web::post().to(contributor::contribute)))
+            .service(web::resource("/challenge").route(web::get().to(contributor::challenge)))
+            .service(
+                web::resource("/challenge-stream")
+                    .route(web::get().to(contributor::challenge_stream)),
+            )
+            .service(
+                web::resource("/update-balance").route(web::post().to(contributor::update_balance)),
+            )
+            .service(web::resource("/claim").route(web::post().to(contributor::claim)))
+            .service(web::resource("/claim/quote").route(web::get().to(contributor::claim_quote)))
+            .service(
+                web::resource("/notifications").route(web::post().to(contributor::notifications)),
+            )
+            .service(
+                web::resource("/webhook/share-account")
+                    .route(web::post().to(webhook::Handle::share_account)),
+            )
+            .service(
+                web::resource("/webhook/rewards").route(web::post().to(webhook::Handle::rewards)),
+            )
+            .service(web::resource("/admin/submit").route(web::post().to(admin::Handle::submit)))
+            .service(
+                web::resource("/admin/reconcile").route(web::post().to(admin::Handle::reconcile)),
+            )
+            .service(
+                web::resource("/admin/config")
+                    .route(web::get().to(admin::Handle::config))
+                    .route(web::post().to(admin::Handle::update_config)),
+            )
+            .service(
+                web::resource("/admin/register").route(web::post().to(admin::Handle::register)),
+            )
+            .service(
+                web::resource("/admin/adjustments")
+                    .route(web::post().to(admin::Handle::adjust_balance)),
+            )
+            .service(web::resource("/admin/treasury").route(web::get().to(admin::Handle::treasury)))
+            .service(
+                web::resource("/admin/rpc-usage").route(web::get().to(admin::Handle::rpc_usage)),
+            )
+            .service(
+                web::resource("/admin/distribution/preview")
+                    .route(web::get().to(admin::Handle::distribution_preview)),
+            )
+            .service(
+                web::resource("/admin/audit-log").route(web::get().to(admin::Handle::audit_log)),
+            )
+            .service(web::resource("/admin/backup").route(web::get().to(admin::Handle::backup)))
+            .service(
+                web::resource("/admin/api-keys")
+                    .route(web::get().to(admin::Handle::api_keys))
+                    .route(web::post().to(admin::Handle::issue_api_key)),
+            )
+            .service(
+                web::resource("/admin/api-keys/{id}/revoke")
+                    .route(web::post().to(admin::Handle::revoke_api_key)),
+            )
+            .service(
+                web::resource("/admin/restore")
+                    .app_data(web::JsonConfig::default().limit(admin::MAX_BACKUP_SIZE))
+                    .route(web::post().to(admin::Handle::restore)),
+            ),
+    );
+}
+
+#[get("/health")]
+async fn health() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
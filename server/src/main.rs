@@ -1,51 +1,154 @@
-mod aggregator;
-mod contributor;
-mod database;
-mod error;
-mod operator;
-mod tx;
-mod utils;
-mod webhook;
-
 use core::panic;
 use std::{collections::HashMap, sync::Arc};
 
-use actix_web::{get, middleware, web, App, HttpResponse, HttpServer, Responder};
-use aggregator::{Aggregator, Contribution, Stakers};
-use operator::Operator;
-use utils::create_cors;
+use actix_web::{middleware, web, App, HttpServer};
+use ore_pool_server::{
+    admin,
+    aggregator::{self, Aggregator, Stakers},
+    api_keys::ApiKeys,
+    beacon::Beacon,
+    cache,
+    claim_fees::ClaimFees,
+    client_ip::{self, ProxiedAddrs, TrustedProxies},
+    error,
+    ha::Leadership,
+    journal,
+    notify::{self, Notifier},
+    operator::Operator,
+    preflight,
+    queue::ContributionQueue,
+    registration, routes, rpc_usage, serve,
+    signatures::SignatureVerifier,
+    simulate, telemetry,
+    utils::create_cors,
+    webhook,
+};
+use solana_sdk::signer::Signer;
+
+/// The delay before retrying a failed aggregation task.
+const AGGREGATION_RETRY_DELAY: tokio::time::Duration = tokio::time::Duration::from_secs(1);
+
+/// The max delay between retries of the aggregation task, backing off from `AGGREGATION_RETRY_DELAY`.
+const MAX_AGGREGATION_RETRY_DELAY: tokio::time::Duration = tokio::time::Duration::from_secs(60);
 
 // TODO: publish attestation to s3
 // write attestation url to db with last-hash-at as foreign key
 #[actix_web::main]
 async fn main() -> Result<(), error::Error> {
-    env_logger::init();
-    // rewards channel
-    let (rewards_tx, rewards_rx) = tokio::sync::mpsc::channel::<webhook::Rewards>(1);
-    let rewards_tx = web::Data::new(rewards_tx);
-    // contributions channel
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Contribution>();
-    let tx = web::Data::new(tx);
+    let telemetry = telemetry::init()?;
+    rpc_usage::init()?;
+    // rewards of the submissions, by signature
+    let rewards = web::Data::new(webhook::RewardsBySignature::new());
+    // contributions queue
+    let queue = web::Data::new(ContributionQueue::from_env()?);
     // operator and aggregator mutex
-    let operator = web::Data::new(Operator::new()?);
-    let aggregator = tokio::sync::RwLock::new(Aggregator::new(&operator, rewards_rx).await?);
+    let operator = web::Data::new(Operator::new(simulate::enabled())?);
+    let (pool_address, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+    telemetry::set_pool_context(&pool_address);
+    preflight::check(&operator).await?;
+    let aggregator = Aggregator::new(&operator, rewards.clone().into_inner()).await?;
+    let challenges = web::Data::new(aggregator.challenge_tx.subscribe());
+    let aggregator = tokio::sync::RwLock::new(aggregator);
     let aggregator = web::Data::new(aggregator);
     let webhook_handler = web::Data::new(webhook::Handle::new()?);
     let webhook_client = web::Data::new(webhook::Client::new_stake()?);
+    let admin_handler = web::Data::new(admin::Handle::from_env()?);
+    let registration = web::Data::new(registration::Registration::new(&operator.keypair)?);
+    let submit_now = web::Data::new(tokio::sync::Notify::new());
+    let signature_verifier = web::Data::new(SignatureVerifier::from_env()?);
+    let leadership = web::Data::new(Leadership::from_env(&pool_address)?);
+    let notifier = web::Data::new(Notifier::from_env()?);
+    let trusted_proxies = web::Data::new(TrustedProxies::from_env()?);
+    let proxied_addrs = web::Data::new(ProxiedAddrs::default());
+    let api_keys = web::Data::new(ApiKeys::from_env()?);
+    let claim_fees = web::Data::new(ClaimFees::from_env()?);
     // env vars
     let attribution_epoch = attribution_epoch()?;
     let stake_commit_epoch = stake_commit_epoch()?;
 
-    // aggregate contributions
+    // aggregate contributions, while leader
     tokio::task::spawn({
         let operator = operator.clone();
         let aggregator = aggregator.clone();
+        let submit_now = submit_now.clone();
+        let leadership = leadership.clone();
+        let queue = queue.clone();
         async move {
-            if let Err(err) =
-                aggregator::process_contributions(aggregator.as_ref(), operator.as_ref(), &mut rx)
+            let mut delay = AGGREGATION_RETRY_DELAY;
+            loop {
+                leadership.until_leader().await;
+                let res = async {
+                    // the last leader may have journaled rounds this instance hasn't seen
+                    operator.journal.reload()?;
+                    // the round may have moved on under another leader
+                    aggregator.write().await.refresh(operator.as_ref()).await?;
+                    aggregator::process_contributions(
+                        aggregator.as_ref(),
+                        operator.as_ref(),
+                        queue.as_ref(),
+                        submit_now.as_ref(),
+                        leadership.as_ref(),
+                    )
                     .await
-            {
-                log::error!("{:?}", err);
+                }
+                .await;
+                match res {
+                    Ok(()) => {
+                        tracing::warn!("stopped aggregating, lost leadership");
+                        delay = AGGREGATION_RETRY_DELAY;
+                    }
+                    // retried while leader, rather than holding the lock without aggregating
+                    Err(err) => {
+                        tracing::error!("aggregation failed, retrying in {:?}: {:?}", delay, err);
+                        telemetry::capture(&err);
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_AGGREGATION_RETRY_DELAY);
+                    }
+                }
+            }
+        }
+    });
+
+    // refresh the blockhash cache, while leader
+    if operator.simulator.is_none() {
+        tokio::task::spawn({
+            let operator = operator.clone();
+            let leadership = leadership.clone();
+            async move {
+                loop {
+                    leadership.until_leader().await;
+                    if let Err(err) = operator.refresh_blockhash().await {
+                        tracing::warn!("failed to refresh blockhash: {:?}", err);
+                    }
+                    tokio::time::sleep(cache::BLOCKHASH_REFRESH).await;
+                }
+            }
+        });
+    }
+
+    // stream the pool accounts and transactions from geyser, if configured
+    if let Some(geyser) = operator.geyser.clone() {
+        let rewards = rewards.clone();
+        let leadership = leadership.clone();
+        tokio::task::spawn(async move { geyser.run(&rewards, &leadership).await });
+    }
+
+    // replay the round writes journaled while the db was unavailable, while leader
+    tokio::task::spawn({
+        let operator = operator.clone();
+        let leadership = leadership.clone();
+        async move {
+            loop {
+                leadership.until_leader().await;
+                if !operator.journal.is_empty() {
+                    match operator.journal.replay(&operator.db_client).await {
+                        Ok(replayed) => {
+                            tracing::info!("replayed {} journaled round writes", replayed)
+                        }
+                        Err(err) => tracing::warn!("failed to replay journal: {:?}", err),
+                    }
+                }
+                tokio::time::sleep(journal::REPLAY_INTERVAL).await;
             }
         }
     });
@@ -53,8 +156,10 @@ async fn main() -> Result<(), error::Error> {
     // kick off attribution loop
     tokio::task::spawn({
         let operator = operator.clone();
+        let leadership = leadership.clone();
         async move {
             loop {
+                leadership.until_leader().await;
                 // submit attributions
                 let operator = operator.clone().into_inner();
                 if let Err(err) = operator.attribute_members().await {
@@ -70,8 +175,10 @@ async fn main() -> Result<(), error::Error> {
     tokio::task::spawn({
         let operator = operator.clone();
         let aggregator = aggregator.clone();
+        let leadership = leadership.clone();
         async move {
             loop {
+                leadership.until_leader().await;
                 let operator = operator.clone().into_inner();
                 let aggregator = aggregator.clone().into_inner();
                 // commit stake
@@ -84,45 +191,121 @@ async fn main() -> Result<(), error::Error> {
         }
     });
 
+    // publish the pool listing to the directory, while leader
+    if let Some(beacon) = Beacon::from_env()? {
+        tokio::task::spawn({
+            let operator = operator.clone();
+            let aggregator = aggregator.clone();
+            let leadership = leadership.clone();
+            async move {
+                loop {
+                    leadership.until_leader().await;
+                    if let Err(err) = beacon.publish(&operator, &aggregator).await {
+                        tracing::warn!("failed to publish pool listing: {:?}", err);
+                    }
+                    tokio::time::sleep(beacon.interval()).await;
+                }
+            }
+        });
+    }
+
+    // enqueue daily digests and deliver member notifications, while leader
+    tokio::task::spawn({
+        let operator = operator.clone();
+        let notifier = notifier.clone();
+        let leadership = leadership.clone();
+        async move {
+            let mut digested_day = None;
+            loop {
+                leadership.until_leader().await;
+                let day = notify::current_day();
+                if digested_day != Some(day) {
+                    match notifier.enqueue_digests(&operator, day).await {
+                        Ok(()) => digested_day = Some(day),
+                        Err(err) => tracing::warn!("failed to enqueue digests: {:?}", err),
+                    }
+                }
+                if let Err(err) = notifier.dispatch(&operator).await {
+                    tracing::warn!("failed to deliver notifications: {:?}", err);
+                }
+                tokio::time::sleep(notify::POLL_INTERVAL).await;
+            }
+        }
+    });
+
+    // worker sets share the listener, to be swapped on signals
+    let listener = serve::bind_from_env()?;
+    // behind a PROXY protocol load balancer, the server listens on loopback,
+    // and connections are forwarded to it with the proxy header stripped
+    let listener = if client_ip::proxy_protocol_from_env(trusted_proxies.get_ref())? {
+        let upstream = serve::bind(([127, 0, 0, 1], 0).into())?;
+        listener.set_nonblocking(true)?;
+        let public = tokio::net::TcpListener::from_std(listener)?;
+        tokio::task::spawn({
+            let upstream = upstream.local_addr()?;
+            let trusted_proxies = trusted_proxies.get_ref().clone();
+            let proxied_addrs = proxied_addrs.clone();
+            async move {
+                let res =
+                    client_ip::proxy_protocol(public, upstream, trusted_proxies, proxied_addrs)
+                        .await;
+                if let Err(err) = res {
+                    tracing::error!("proxy protocol listener stopped: {:?}", err);
+                    telemetry::capture(&err);
+                }
+            }
+        });
+        upstream
+    } else {
+        listener
+    };
+
     // launch server
-    HttpServer::new(move || {
-        log::info!("starting server");
+    let app = move || {
+        tracing::info!("starting server");
         App::new()
-            .wrap(middleware::Logger::default())
+            .wrap(middleware::from_fn(telemetry::request_span))
+            .wrap(middleware::from_fn(client_ip::real_ip))
             .wrap(create_cors())
-            .app_data(tx.clone())
+            .app_data(queue.clone())
             .app_data(operator.clone())
             .app_data(aggregator.clone())
+            .app_data(challenges.clone())
+            .app_data(signature_verifier.clone())
             .app_data(webhook_handler.clone())
             .app_data(webhook_client.clone())
-            .app_data(rewards_tx.clone())
-            .service(web::resource("/member/{authority}").route(web::get().to(contributor::member)))
-            .service(web::resource("/pool-address").route(web::get().to(contributor::pool_address)))
-            .service(web::resource("/register").route(web::post().to(contributor::register)))
-            .service(
-                web::resource("/register-staker")
-                    .route(web::post().to(contributor::register_staker)),
-            )
-            .service(web::resource("/contribute").route(web::post().to(contributor::contribute)))
-            .service(web::resource("/challenge").route(web::get().to(contributor::challenge)))
-            .service(
-                web::resource("/update-balance").route(web::post().to(contributor::update_balance)),
-            )
-            .service(
-                web::resource("/webhook/share-account")
-                    .route(web::post().to(webhook::Handle::share_account)),
-            )
-            .service(
-                web::resource("/webhook/rewards").route(web::post().to(webhook::Handle::rewards)),
-            )
-            .service(health)
+            .app_data(rewards.clone())
+            .app_data(admin_handler.clone())
+            .app_data(registration.clone())
+            .app_data(submit_now.clone())
+            .app_data(leadership.clone())
+            .app_data(notifier.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(proxied_addrs.clone())
+            .app_data(api_keys.clone())
+            .app_data(claim_fees.clone())
+            .configure(routes)
+    };
+    let certificates = serve::Certificates::from_env()?;
+    let tls_config = certificates
+        .as_ref()
+        .map(|certificates| certificates.server_config());
+    serve::run(serve::workers_from_env()?, certificates, |workers| {
+        let server = HttpServer::new(app.clone())
+            .workers(workers)
+            .disable_signals();
+        let server = match tls_config.clone() {
+            Some(tls_config) => server.listen_rustls_0_21(listener.try_clone()?, tls_config)?,
+            None => server.listen(listener.try_clone()?)?,
+        };
+        Ok(server.run())
     })
-    .bind("0.0.0.0:3000")?
-    .run()
-    .await
-    .map_err(From::from)
+    .await?;
+    telemetry.shutdown();
+    Ok(())
 }
 
+#[tracing::instrument(skip_all)]
 async fn commit_stake(
     operator: Arc<Operator>,
     aggregator: Arc<tokio::sync::RwLock<Aggregator>>,
@@ -156,8 +339,3 @@ fn attribution_epoch() -> Result<u64, error::Error> {
     let epoch: u64 = string.parse()?;
     Ok(epoch)
 }
-
-#[get("/health")]
-async fn health() -> impl Responder {
-    HttpResponse::Ok().body("ok")
-}
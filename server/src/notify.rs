@@ -0,0 +1,314 @@
+use std::{collections::HashMap, net::IpAddr, time::Duration};
+
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use ore_api::consts::TOKEN_DECIMALS;
+use ore_pool_types::{ClaimReceipt, NotificationChannel, NotificationDigest};
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+use crate::{database, error::Error, operator::Operator, utils::unix_timestamp};
+
+/// The time between outbox deliveries.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The seconds a signed notification prefs payload is accepted for.
+pub const MAX_SIGNATURE_AGE: i64 = 5 * 60;
+
+/// The period of a digest, a utc day.
+const DIGEST_PERIOD: i64 = 24 * 60 * 60;
+
+/// The earnings, claims, and delivered notifications older than this are pruned.
+const HISTORY_RETENTION: i64 = 7 * DIGEST_PERIOD;
+
+/// The deliveries of a notification attempted before it is given up on.
+const MAX_ATTEMPTS: i32 = 5;
+
+/// The max notifications delivered per poll.
+const BATCH_SIZE: i64 = 100;
+
+/// The header webhooks are signed in, by the pool authority.
+const SIGNATURE_HEADER: &str = "x-ore-pool-signature";
+
+/// The time a webhook is given to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends members the daily digest of their earnings and settled claims,
+/// through the channel of their notification prefs.
+///
+/// Digests are enqueued in the `notification_outbox` table, keyed by member and day,
+/// then delivered from there with retries,
+/// so a restart or a change of leader neither drops nor duplicates them.
+pub struct Notifier {
+    /// the smtp relay to email with, email is disabled if unset
+    mailer: Option<Mailer>,
+}
+
+struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Notifier {
+    /// Email is enabled if `SMTP_URL` is set, sent from `SMTP_FROM`.
+    pub fn from_env() -> Result<Self, Error> {
+        let mailer = match std::env::var("SMTP_URL") {
+            Ok(url) => Some(Mailer {
+                transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(url.as_str())?.build(),
+                from: std::env::var("SMTP_FROM")?.parse()?,
+            }),
+            // optional
+            Err(_) => None,
+        };
+        Ok(Self { mailer })
+    }
+
+    pub fn email_enabled(&self) -> bool {
+        self.mailer.is_some()
+    }
+
+    /// Enqueues the digest of the day before `day` (days since the unix epoch)
+    /// for every member with notifications and something to report.
+    #[tracing::instrument(skip(self, operator))]
+    pub async fn enqueue_digests(&self, operator: &Operator, day: i64) -> Result<(), Error> {
+        let conn = operator.db_client.get().await?;
+        let period_end = day * DIGEST_PERIOD;
+        let period_start = period_end - DIGEST_PERIOD;
+        let recipients = database::read_digest_recipients(&conn, period_start, period_end).await?;
+        let mut claims: HashMap<String, Vec<ClaimReceipt>> = HashMap::new();
+        for (authority, receipt) in database::read_claims(&conn, period_start, period_end).await? {
+            claims.entry(authority).or_default().push(receipt);
+        }
+        for recipient in recipients {
+            let authority: Pubkey = recipient.authority.parse()?;
+            let member_claims = claims.remove(&recipient.authority).unwrap_or_default();
+            if recipient.earned == 0 && member_claims.is_empty() {
+                continue;
+            }
+            let digest = NotificationDigest {
+                authority,
+                period_start,
+                period_end,
+                earned: recipient.earned,
+                balance: recipient.balance,
+                claims: member_claims,
+            };
+            let key = format!("digest:{}:{}", recipient.authority, day - 1);
+            database::write_outbox_entry(
+                &conn,
+                key.as_str(),
+                recipient.authority.as_str(),
+                recipient.channel,
+                recipient.target.as_str(),
+                serde_json::to_string(&digest)?.as_str(),
+            )
+            .await?;
+        }
+        database::prune_notification_history(&conn, period_end - HISTORY_RETENTION).await?;
+        Ok(())
+    }
+
+    /// Delivers the pending notifications of the outbox,
+    /// recording failures to retry on the next poll.
+    #[tracing::instrument(skip_all)]
+    pub async fn dispatch(&self, operator: &Operator) -> Result<(), Error> {
+        let conn = operator.db_client.get().await?;
+        let entries = database::read_outbox_pending(&conn, BATCH_SIZE, MAX_ATTEMPTS).await?;
+        for entry in entries {
+            let res = match entry.channel {
+                NotificationChannel::Webhook => {
+                    self.post(operator, entry.target.as_str(), entry.payload.as_str())
+                        .await
+                }
+                NotificationChannel::Email => {
+                    self.email(entry.target.as_str(), entry.payload.as_str())
+                        .await
+                }
+                // opted out after the digest was enqueued
+                NotificationChannel::None => Ok(()),
+            };
+            match res {
+                Ok(()) => database::write_outbox_sent(&conn, entry.id).await?,
+                Err(err) => {
+                    tracing::warn!(
+                        "failed to deliver notification {} (attempt {}): {:?}",
+                        entry.id,
+                        entry.attempts + 1,
+                        err
+                    );
+                    database::write_outbox_failed(&conn, entry.id, err.to_string().as_str())
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Posts the json payload, signed by the pool authority
+    /// so the member can check it came from the pool.
+    /// Only to https urls whose host resolves to public addresses,
+    /// so member urls can't reach the operator's internal network.
+    async fn post(&self, operator: &Operator, url: &str, payload: &str) -> Result<(), Error> {
+        let url = reqwest::Url::parse(url).map_err(|err| Error::Validation(err.to_string()))?;
+        if url.scheme() != "https" {
+            return Err(Error::Validation("webhook url must be https".to_string()));
+        }
+        let host = url
+            .host_str()
+            .ok_or(Error::Validation("webhook url has no host".to_string()))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addrs = tokio::net::lookup_host((host, port))
+            .await?
+            .collect::<Vec<_>>();
+        if addrs.is_empty() || !addrs.iter().all(|addr| is_public(&addr.ip())) {
+            return Err(Error::Validation(format!(
+                "webhook host {} doesn't resolve to a public address",
+                host
+            )));
+        }
+        // pinned to the checked addresses, so the host can't be rebound to a private one
+        let http_client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve_to_addrs(host, addrs.as_slice())
+            .build()?;
+        let signature = operator.keypair.sign_message(payload.as_bytes());
+        http_client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature.to_string())
+            .body(payload.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn email(&self, to: &str, payload: &str) -> Result<(), Error> {
+        let mailer = self.mailer.as_ref().ok_or(Error::EmailDisabled)?;
+        let digest: NotificationDigest = serde_json::from_str(payload)?;
+        let message = Message::builder()
+            .from(mailer.from.clone())
+            .to(to.parse()?)
+            .subject("Your daily ORE pool digest")
+            .header(ContentType::TEXT_PLAIN)
+            .body(render(&digest))?;
+        mailer.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Whether the address is reachable on the public internet,
+/// rather than loopback, private, link-local (as the cloud metadata service), or otherwise reserved.
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // shared address space, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // reserved, 240.0.0.0/4, and this network, 0.0.0.0/8
+                || a >= 240
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(&IpAddr::V4(ip));
+            }
+            let segment = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local, fc00::/7
+                || (segment & 0xfe00) == 0xfc00
+                // link local, fe80::/10
+                || (segment & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// The days since the unix epoch, in utc.
+pub fn current_day() -> i64 {
+    unix_timestamp() / DIGEST_PERIOD
+}
+
+/// The plain text body of a digest email.
+fn render(digest: &NotificationDigest) -> String {
+    let mut body = format!(
+        "Member {}\n\nEarned in the last day: {} ORE\nPool balance: {} ORE\n",
+        digest.authority,
+        ore(digest.earned),
+        ore(digest.balance)
+    );
+    if !digest.claims.is_empty() {
+        body.push_str("\nSettled claims:\n");
+        for claim in digest.claims.iter() {
+            body.push_str(format!("  {} ORE ({})\n", ore(claim.amount), claim.signature).as_str());
+        }
+    }
+    body
+}
+
+fn ore(grains: u64) -> String {
+    format!(
+        "{:.prec$}",
+        grains as f64 / 10f64.powi(TOKEN_DECIMALS as i32),
+        prec = TOKEN_DECIMALS as usize
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::Signature;
+
+    use super::*;
+
+    #[test]
+    fn webhooks_only_reach_public_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(&ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700::1111"] {
+            assert!(is_public(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn renders_earnings_and_claims() {
+        let digest = NotificationDigest {
+            authority: Pubkey::new_unique(),
+            period_start: 0,
+            period_end: DIGEST_PERIOD,
+            earned: 150_000_000_000,
+            balance: 200_000_000_000,
+            claims: vec![ClaimReceipt {
+                amount: 100_000_000_000,
+                signature: Signature::default(),
+                fee: 0,
+            }],
+        };
+        let body = render(&digest);
+        assert!(body.contains("Earned in the last day: 1.50000000000 ORE"));
+        assert!(body.contains("Pool balance: 2.00000000000 ORE"));
+        assert!(body.contains("Settled claims:\n  1.00000000000 ORE"));
+    }
+}
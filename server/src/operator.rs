@@ -1,14 +1,24 @@
-use std::{collections::HashMap, pin::Pin, str::FromStr, sync::Arc, vec};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    vec,
+};
 
 use futures::{Future, StreamExt, TryFutureExt, TryStreamExt};
 use ore_api::state::{Config, Proof};
-use ore_pool_api::state::{Member, Pool, Share};
+use ore_pool_api::state::{Member, Pool, PoolConfig, Share};
 use ore_pool_types::Staker;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     account::Account,
     clock::Clock,
     commitment_config::CommitmentConfig,
+    hash::Hash,
     pubkey::Pubkey,
     signature::Keypair,
     signer::{EncodableKey, Signer},
@@ -16,10 +26,23 @@ use solana_sdk::{
 };
 use steel::AccountDeserialize;
 
-use crate::{database, error::Error, tx};
+use crate::{
+    cache::{BlockhashCache, MemberIdCache},
+    database,
+    error::Error,
+    geyser::Geyser,
+    hashrate::RejectedShares,
+    journal::Journal,
+    prescreen::SeenNonces,
+    simulate::Simulator,
+    telemetry, tx,
+    tx::confirm::Confirmer,
+};
 
 pub const BUFFER_OPERATOR: u64 = 5;
-const MIN_DIFFICULTY: Option<u64> = None;
+
+/// The rpc of a local test validator, used in simulation mode if `RPC_URL` is unset.
+const LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
 
 pub struct Operator {
     /// The pool authority keypair.
@@ -28,19 +51,46 @@ pub struct Operator {
     /// Solana RPC client.
     pub rpc_client: RpcClient,
 
+    /// Confirms transactions over the rpc websocket.
+    pub confirmer: Confirmer,
+
+    /// The latest blockhash, refreshed in the background.
+    pub blockhashes: BlockhashCache,
+
+    /// The ids of the members that have contributed.
+    pub member_ids: MemberIdCache,
+
+    /// The shares rejected from each member in the current round.
+    pub rejected_shares: RejectedShares,
+
+    /// The nonces accepted for the current challenge, to reject duplicates early.
+    pub seen_nonces: SeenNonces,
+
+    /// The round writes waiting for the db to be available.
+    pub journal: Arc<Journal>,
+
+    /// Streams the accounts and transactions of the pool, if configured.
+    pub geyser: Option<Arc<Geyser>>,
+
     /// Postgres connection pool.
     pub db_client: deadpool_postgres::Pool,
 
     /// The boost accounts for mining multipliers.
     pub boost_accounts: Vec<BoostAccount>,
 
-    /// The operator commission in % percentage.
-    /// Applied to the miner and staker rewards.
-    pub operator_commission: u64,
+    /// The operator and staker commissions in % percentage,
+    /// packed into one word so that they're always read as a pair.
+    /// The operator commission is applied to the miner and staker rewards,
+    /// the rest of the staker commission is given to miners to incentize participation.
+    /// Adjustable at runtime from the admin api.
+    commissions: AtomicU64,
 
-    /// The staker commission in % percentage.
-    /// The rest is given to miners to incentize participation.
-    pub staker_commission: u64,
+    /// The operator min difficulty, zero to defer to the program min.
+    /// Adjustable at runtime from the admin api.
+    pub min_difficulty: AtomicU64,
+
+    /// Stubs the chain if started in simulation mode.
+    pub simulator: Option<Simulator>,
 }
 
 pub struct BoostAccount {
@@ -71,36 +121,98 @@ impl BoostAccount {
 }
 
 impl Operator {
-    pub fn new() -> Result<Operator, Error> {
-        let keypair = Self::keypair()?;
-        let rpc_client = Self::rpc_client()?;
+    pub fn new(simulate: bool) -> Result<Operator, Error> {
+        let simulator = match simulate {
+            true => Some(Simulator::from_env()?),
+            false => None,
+        };
+        let keypair = match (simulate, Self::keypair_path()) {
+            // an ephemeral pool authority is enough to simulate
+            (true, Err(_)) => Keypair::new(),
+            _ => Self::keypair()?,
+        };
+        let rpc_client = match (simulate, Self::rpc_url()) {
+            // a local test validator, unused unless members update balances on-chain
+            (true, Err(_)) => RpcClient::new_with_commitment(
+                LOCAL_RPC_URL.to_string(),
+                CommitmentConfig::confirmed(),
+            ),
+            _ => Self::rpc_client()?,
+        };
+        let geyser = match simulate {
+            true => None,
+            false => Geyser::from_env(&keypair.pubkey()).map(Arc::new),
+        };
+        let confirmer = Confirmer::new(
+            Confirmer::ws_url(rpc_client.url().as_str())?,
+            geyser.clone(),
+        );
         let db_client = database::create_pool();
         let boosts = Self::load_boosts()?;
-        log::info!("boosts: {:?}", boosts);
+        tracing::info!("boosts: {:?}", boosts);
         let boost_accounts = BoostAccount::new_from_vec(boosts, keypair.pubkey());
         let operator_commission = Self::operator_commission()?;
-        log::info!("operator commision: {}", operator_commission);
+        tracing::info!("operator commision: {}", operator_commission);
         let staker_commission = Self::staker_commission()?;
-        log::info!("staker commission: {}", staker_commission);
+        tracing::info!("staker commission: {}", staker_commission);
+        let min_difficulty = Self::operator_min_difficulty()?;
+        tracing::info!("operator min difficulty: {}", min_difficulty);
         Ok(Operator {
             keypair,
             rpc_client,
+            confirmer,
+            blockhashes: BlockhashCache::default(),
+            member_ids: MemberIdCache::default(),
+            rejected_shares: RejectedShares::default(),
+            seen_nonces: SeenNonces::default(),
+            journal: Arc::new(Journal::from_env()?),
+            geyser,
             db_client,
             boost_accounts,
-            operator_commission,
-            staker_commission,
+            commissions: AtomicU64::new(pack_commissions(operator_commission, staker_commission)),
+            min_difficulty: AtomicU64::new(min_difficulty),
+            simulator,
         })
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn get_pool(&self) -> Result<Pool, Error> {
+        if let Some(simulator) = &self.simulator {
+            return simulator.pool(self).await;
+        }
+        if let Some(pool) = self.geyser.as_ref().and_then(|geyser| geyser.pool()) {
+            return Ok(pool);
+        }
         let authority = self.keypair.pubkey();
         let rpc_client = &self.rpc_client;
         let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
-        let data = rpc_client.get_account_data(&pool_pda).await?;
+        let data =
+            telemetry::rpc("get_account_data", rpc_client.get_account_data(&pool_pda)).await?;
         let pool = Pool::try_from_bytes(data.as_slice())?;
         Ok(*pool)
     }
 
+    /// The minimum amount a member may claim at once,
+    /// zero if the pool never set one and so has no config account.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_min_claim(&self) -> Result<u64, Error> {
+        if self.simulator.is_some() {
+            return Ok(0);
+        }
+        let rpc_client = &self.rpc_client;
+        let (pool_pda, _) = ore_pool_api::state::pool_pda(self.keypair.pubkey());
+        let (config_pda, _) = ore_pool_api::state::pool_config_pda(pool_pda);
+        let account = telemetry::rpc(
+            "get_account",
+            rpc_client.get_account_with_commitment(&config_pda, rpc_client.commitment()),
+        )
+        .await?;
+        match account.value {
+            Some(account) => Ok(PoolConfig::try_from_bytes(account.data.as_slice())?.min_claim),
+            None => Ok(0),
+        }
+    }
+
     pub async fn get_staker_onchain(
         &self,
         member_authority: &Pubkey,
@@ -110,7 +222,8 @@ impl Operator {
         let rpc_client = &self.rpc_client;
         let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
         let (share_pda, _) = ore_pool_api::state::share_pda(*member_authority, pool_pda, *mint);
-        let data = rpc_client.get_account_data(&share_pda).await?;
+        let data =
+            telemetry::rpc("get_account_data", rpc_client.get_account_data(&share_pda)).await?;
         let share = ore_pool_api::state::Share::try_from_bytes(data.as_slice())?;
         Ok((*share, share_pda))
     }
@@ -149,6 +262,9 @@ impl Operator {
     }
 
     pub async fn get_stakers_onchain(&self, mint: &Pubkey) -> Result<HashMap<Pubkey, u64>, Error> {
+        if self.simulator.is_some() {
+            return Ok(HashMap::new());
+        }
         let rpc_client = &self.rpc_client;
         let vec = self.get_stakers_db(mint).await?;
         let mut queries: Vec<Pin<Box<dyn Future<Output = GetManyStakers> + Send>>> = vec![];
@@ -173,14 +289,28 @@ impl Operator {
         Ok(results)
     }
 
-    pub async fn get_member_onchain(&self, member_authority: &Pubkey) -> Result<Member, Error> {
+    #[tracing::instrument(skip_all)]
+    /// The member account of the authority in this pool, none if it hasn't joined yet.
+    pub async fn get_member_onchain(
+        &self,
+        member_authority: &Pubkey,
+    ) -> Result<Option<Member>, Error> {
+        if let Some(simulator) = &self.simulator {
+            return simulator.member(self, member_authority).await.map(Some);
+        }
         let authority = self.keypair.pubkey();
         let rpc_client = &self.rpc_client;
         let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
         let (member_pda, _) = ore_pool_api::state::member_pda(*member_authority, pool_pda);
-        let data = rpc_client.get_account_data(&member_pda).await?;
-        let member = Member::try_from_bytes(data.as_slice())?;
-        Ok(*member)
+        let account = telemetry::rpc(
+            "get_account",
+            rpc_client.get_account_with_commitment(&member_pda, rpc_client.commitment()),
+        )
+        .await?;
+        match account.value {
+            Some(account) => Ok(Some(*Member::try_from_bytes(account.data.as_slice())?)),
+            None => Ok(None),
+        }
     }
 
     pub async fn get_member_db(
@@ -195,16 +325,63 @@ impl Operator {
         database::read_member(&db_client, &member_pda.to_string()).await
     }
 
+    /// The id of the member, from the cache if read before.
+    pub async fn get_member_id(&self, member_authority: &Pubkey) -> Result<i64, Error> {
+        if let Some(id) = self.member_ids.get(member_authority) {
+            return Ok(id);
+        }
+        let member = self
+            .get_member_db(member_authority.to_string().as_str())
+            .await?;
+        self.member_ids.insert(*member_authority, member.id);
+        Ok(member.id)
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn get_proof(&self) -> Result<Proof, Error> {
         let authority = self.keypair.pubkey();
+        if let Some(simulator) = &self.simulator {
+            return simulator.proof(authority);
+        }
+        if let Some(proof) = self.geyser.as_ref().and_then(|geyser| geyser.proof()) {
+            return Ok(proof);
+        }
         let rpc_client = &self.rpc_client;
         let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
         let (proof_pda, _) = ore_pool_api::state::pool_proof_pda(pool_pda);
-        let data = rpc_client.get_account_data(&proof_pda).await?;
+        let data =
+            telemetry::rpc("get_account_data", rpc_client.get_account_data(&proof_pda)).await?;
         let proof = Proof::try_from_bytes(data.as_slice())?;
         Ok(*proof)
     }
 
+    /// The latest blockhash, from the cache unless stale.
+    pub async fn latest_blockhash(&self) -> Result<Hash, Error> {
+        if let Some(hash) = self.blockhashes.get() {
+            return Ok(hash);
+        }
+        self.refresh_blockhash().await
+    }
+
+    /// Fetches the latest blockhash into the cache.
+    pub async fn refresh_blockhash(&self) -> Result<Hash, Error> {
+        let rpc_client = &self.rpc_client;
+        let hash =
+            telemetry::rpc("get_latest_blockhash", rpc_client.get_latest_blockhash()).await?;
+        self.blockhashes.set(hash);
+        Ok(hash)
+    }
+
+    /// Waits for the pool account to change, streamed from geyser if configured,
+    /// or for the next poll of the rpc.
+    pub async fn wait_for_pool(&self) {
+        let interval = tokio::time::Duration::from_secs(1);
+        match &self.geyser {
+            Some(geyser) if geyser.is_connected() => geyser.wait_for_pool(interval).await,
+            _ => tokio::time::sleep(interval).await,
+        }
+    }
+
     pub async fn get_cutoff(&self, proof: &Proof) -> Result<u64, Error> {
         let clock = self.get_clock().await?;
         Ok(proof
@@ -218,25 +395,39 @@ impl Operator {
     pub async fn min_difficulty(&self) -> Result<u64, Error> {
         let config = self.get_config().await?;
         let program_min = config.min_difficulty;
-        match MIN_DIFFICULTY {
-            Some(operator_min) => {
-                let max = program_min.max(operator_min);
-                Ok(max)
-            }
-            None => Ok(program_min),
-        }
+        let operator_min = self.min_difficulty.load(Ordering::Relaxed);
+        Ok(program_min.max(operator_min))
     }
 
+    /// The operator and staker commissions, as of now.
+    pub fn commissions(&self) -> (u64, u64) {
+        unpack_commissions(self.commissions.load(Ordering::Relaxed))
+    }
+
+    /// Replaces the operator and staker commissions at once.
+    pub fn set_commissions(&self, operator_commission: u64, staker_commission: u64) {
+        let commissions = pack_commissions(operator_commission, staker_commission);
+        self.commissions.store(commissions, Ordering::Relaxed);
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn attribute_members(self: Arc<Self>) -> Result<(), Error> {
+        if self.simulator.is_some() {
+            tracing::info!("simulate: attribution not landed");
+            return Ok(());
+        }
         let db_client = self.db_client.get().await?;
         let db_client = Arc::new(db_client);
         database::stream_members_attribution(db_client, self).await?;
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn commit_stake(&self) -> Result<(), Error> {
+        if self.simulator.is_some() {
+            return Ok(());
+        }
         let authority = &self.keypair;
-        let rpc_client = &self.rpc_client;
         let boost_mints = self.get_boosts();
         if boost_mints.len().gt(&0) {
             let mut ixs = vec![];
@@ -245,14 +436,13 @@ impl Operator {
                 ixs.push(ix);
             }
             let sig = tx::submit::submit_and_confirm_instructions(
-                authority,
-                rpc_client,
+                self,
                 ixs.as_slice(),
                 1_000_000,
                 10_000,
             )
             .await?;
-            log::info!("commit stake sig: {:?}", sig);
+            tracing::info!("commit stake sig: {:?}", sig);
         }
         Ok(())
     }
@@ -273,15 +463,24 @@ impl Operator {
         boost_accounts.iter().map(|ba| ba.mint).collect()
     }
 
+    #[tracing::instrument(skip_all)]
     async fn get_config(&self) -> Result<Config, Error> {
+        if let Some(simulator) = &self.simulator {
+            return Ok(simulator.config());
+        }
         let config_pda = ore_api::consts::CONFIG_ADDRESS;
         let rpc_client = &self.rpc_client;
-        let data = rpc_client.get_account_data(&config_pda).await?;
+        let data =
+            telemetry::rpc("get_account_data", rpc_client.get_account_data(&config_pda)).await?;
         let config = Config::try_from_bytes(data.as_slice())?;
         Ok(*config)
     }
 
+    #[tracing::instrument(skip_all)]
     async fn get_clock(&self) -> Result<Clock, Error> {
+        if let Some(simulator) = &self.simulator {
+            return Ok(simulator.clock());
+        }
         let rpc_client = &self.rpc_client;
         let data = rpc_client.get_account_data(&sysvar::clock::id()).await?;
         bincode::deserialize(&data).map_err(From::from)
@@ -289,8 +488,8 @@ impl Operator {
 
     fn keypair() -> Result<Keypair, Error> {
         let keypair_path = Operator::keypair_path()?;
-        let keypair = Keypair::read_from_file(keypair_path)
-            .map_err(|err| Error::Internal(err.to_string()))?;
+        let keypair =
+            Keypair::read_from_file(keypair_path).map_err(|err| Error::Config(err.to_string()))?;
         Ok(keypair)
     }
 
@@ -355,10 +554,32 @@ impl Operator {
         let commission: u64 = str.parse()?;
         Ok(commission)
     }
+
+    fn operator_min_difficulty() -> Result<u64, Error> {
+        match std::env::var("MIN_DIFFICULTY") {
+            Ok(str) => {
+                let min_difficulty: u64 = str.parse()?;
+                Ok(min_difficulty)
+            }
+            // optional
+            Err(_) => Ok(0),
+        }
+    }
 }
 
 type GetManyStakers = Result<Vec<Option<Account>>, Error>;
 
+/// The operator commission in the high half of the word, the staker commission in the low half.
+fn pack_commissions(operator_commission: u64, staker_commission: u64) -> u64 {
+    let operator_commission = operator_commission.min(u32::MAX as u64);
+    let staker_commission = staker_commission.min(u32::MAX as u64);
+    (operator_commission << 32) | staker_commission
+}
+
+fn unpack_commissions(commissions: u64) -> (u64, u64) {
+    (commissions >> 32, commissions & u32::MAX as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use base64::{prelude::BASE64_STANDARD, Engine};
@@ -379,4 +600,12 @@ mod tests {
         let bytes = BASE64_STANDARD.decode(base64);
         println!("bytes: {:?}", bytes);
     }
+
+    #[test]
+    fn packs_commissions() {
+        let commissions = super::pack_commissions(5, 40);
+        assert_eq!(super::unpack_commissions(commissions), (5, 40));
+        let commissions = super::pack_commissions(100, 0);
+        assert_eq!(super::unpack_commissions(commissions), (100, 0));
+    }
 }
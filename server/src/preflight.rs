@@ -0,0 +1,175 @@
+use ore_api::state::Proof;
+use ore_pool_api::state::Pool;
+use solana_sdk::{commitment_config::CommitmentLevel, signer::Signer};
+use steel::AccountDeserialize;
+
+use crate::{database, error::Error, operator::Operator, telemetry};
+
+/// The number of the latest migration in `init-db`, the db schema this binary expects.
+pub const SCHEMA_VERSION: i32 = 14;
+
+/// Validates the operator setup before any round starts,
+/// so that a misconfigured pool aborts on boot instead of failing mid-round.
+/// Every failed check is reported, each with what to do about it.
+#[tracing::instrument(skip_all)]
+pub async fn check(operator: &Operator) -> Result<(), Error> {
+    let mut failures = vec![];
+    // the simulator fakes the chain, but not the db
+    if operator.simulator.is_none() {
+        check_rpc(operator, &mut failures).await;
+        check_accounts(operator, &mut failures).await;
+    }
+    check_schema(operator, &mut failures).await;
+    if failures.is_empty() {
+        tracing::info!("preflight ok");
+        return Ok(());
+    }
+    for failure in failures.iter() {
+        tracing::error!("preflight: {}", failure);
+    }
+    Err(Error::Config(format!(
+        "preflight failed: {}",
+        failures.join("; ")
+    )))
+}
+
+async fn check_rpc(operator: &Operator, failures: &mut Vec<String>) {
+    // processed state can be rolled back, and with it the submitted solutions
+    let commitment = operator.rpc_client.commitment().commitment;
+    if commitment == CommitmentLevel::Processed {
+        failures.push(
+            "rpc commitment is processed, which may be rolled back: use confirmed or finalized"
+                .to_string(),
+        );
+    }
+    if let Err(err) = telemetry::rpc("get_health", operator.rpc_client.get_health()).await {
+        failures.push(format!(
+            "rpc at {} is unhealthy: {:?}: check RPC_URL",
+            operator.rpc_client.url(),
+            err
+        ));
+    }
+}
+
+async fn check_accounts(operator: &Operator, failures: &mut Vec<String>) {
+    let authority = operator.keypair.pubkey();
+    let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
+    let (proof_pda, _) = ore_pool_api::state::pool_proof_pda(pool_pda);
+    let accounts = match telemetry::rpc(
+        "get_multiple_accounts",
+        operator
+            .rpc_client
+            .get_multiple_accounts(&[pool_pda, proof_pda]),
+    )
+    .await
+    {
+        Ok(accounts) => accounts,
+        Err(err) => {
+            failures.push(format!("could not fetch the pool accounts: {:?}", err));
+            return;
+        }
+    };
+    match accounts.first().cloned().flatten() {
+        None => failures.push(format!(
+            "pool account {} does not exist: run the admin cli with COMMAND=\"init\" and the same KEYPAIR_PATH",
+            pool_pda
+        )),
+        Some(account) if account.owner != ore_pool_api::ID => failures.push(format!(
+            "pool account {} is owned by {}, not the pool program {}",
+            pool_pda,
+            account.owner,
+            ore_pool_api::ID
+        )),
+        Some(account) => match Pool::try_from_bytes(account.data.as_slice()) {
+            Ok(pool) if pool.authority != authority => failures.push(format!(
+                "keypair {} is not the pool authority {}: check KEYPAIR_PATH",
+                authority, pool.authority
+            )),
+            Ok(_) => {}
+            Err(err) => failures.push(format!(
+                "pool account {} is not a pool: {:?}",
+                pool_pda, err
+            )),
+        },
+    }
+    match accounts.get(1).cloned().flatten() {
+        None => failures.push(format!(
+            "proof account {} does not exist: run the admin cli with COMMAND=\"init\" and the same KEYPAIR_PATH",
+            proof_pda
+        )),
+        Some(account) if account.owner != ore_api::ID => failures.push(format!(
+            "proof account {} is owned by {}, not the ore program {}",
+            proof_pda,
+            account.owner,
+            ore_api::ID
+        )),
+        Some(account) => {
+            if let Err(err) = Proof::try_from_bytes(account.data.as_slice()) {
+                failures.push(format!(
+                    "proof account {} is not a proof: {:?}",
+                    proof_pda, err
+                ));
+            }
+        }
+    }
+}
+
+async fn check_schema(operator: &Operator, failures: &mut Vec<String>) {
+    let version = match operator.db_client.get().await {
+        Ok(conn) => database::read_schema_version(&conn).await,
+        Err(err) => {
+            failures.push(format!(
+                "could not connect to the db: {:?}: check DB_URL",
+                err
+            ));
+            return;
+        }
+    };
+    match version {
+        Ok(version) => {
+            if let Some(failure) = schema_mismatch(version) {
+                failures.push(failure);
+            }
+        }
+        Err(err) => failures.push(format!(
+            "could not read the db schema version: {:?}: apply the migrations in init-db",
+            err
+        )),
+    }
+}
+
+/// What to do about the schema version found in the db, none if it matches the binary.
+fn schema_mismatch(version: Option<i32>) -> Option<String> {
+    match version {
+        Some(version) if version == SCHEMA_VERSION => None,
+        Some(version) if version > SCHEMA_VERSION => Some(format!(
+            "db schema is at version {}, newer than the {} of this server: upgrade the server",
+            version, SCHEMA_VERSION
+        )),
+        Some(version) => Some(format!(
+            "db schema is at version {}, but this server expects {}: apply the newer migrations in init-db",
+            version, SCHEMA_VERSION
+        )),
+        None => Some(format!(
+            "db schema version is unknown, but this server expects {}: apply the migrations in init-db",
+            SCHEMA_VERSION
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_versions() {
+        assert_eq!(schema_mismatch(Some(SCHEMA_VERSION)), None);
+        assert!(schema_mismatch(Some(SCHEMA_VERSION - 1))
+            .unwrap()
+            .contains("apply the newer migrations"));
+        assert!(schema_mismatch(Some(SCHEMA_VERSION + 1))
+            .unwrap()
+            .contains("upgrade the server"));
+        assert!(schema_mismatch(None).is_some());
+    }
+}
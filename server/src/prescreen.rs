@@ -0,0 +1,110 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use actix_web::HttpResponse;
+
+/// Why a contribution was rejected before it reached the aggregator,
+/// ordered from the cheapest check to the most expensive.
+///
+/// The difficulty, nonce range, and duplicate nonce checks run before the digest is verified,
+/// so obviously bad solutions never cost an equihash verification.
+/// Each is returned as a distinct status and code, so members can tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    BelowMinDifficulty,
+    InvalidNonce,
+    DuplicateNonce,
+    InvalidDigest,
+}
+
+impl Rejection {
+    /// The code in the response body, also the label of the contributions metric.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rejection::BelowMinDifficulty => "below_min_difficulty",
+            Rejection::InvalidNonce => "invalid_nonce",
+            Rejection::DuplicateNonce => "duplicate_nonce",
+            Rejection::InvalidDigest => "invalid_digest",
+        }
+    }
+
+    /// Whether the rejection counts against the acceptance ratio of the member.
+    pub fn is_rejected_share(&self) -> bool {
+        !matches!(self, Rejection::InvalidNonce)
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        let mut res = match self {
+            Rejection::BelowMinDifficulty | Rejection::InvalidDigest => HttpResponse::BadRequest(),
+            Rejection::InvalidNonce => HttpResponse::Unauthorized(),
+            Rejection::DuplicateNonce => HttpResponse::Conflict(),
+        };
+        res.body(self.code())
+    }
+}
+
+/// The nonces accepted by the contribute handler for the current challenge,
+/// to reject duplicates before verifying their digest, without waiting on the aggregator lock.
+/// The aggregator still rejects the duplicates that race past it.
+#[derive(Default)]
+pub struct SeenNonces {
+    inner: Mutex<Nonces>,
+}
+
+#[derive(Default)]
+struct Nonces {
+    challenge: [u8; 32],
+    nonces: HashSet<u64>,
+}
+
+impl SeenNonces {
+    pub fn contains(&self, challenge: &[u8; 32], nonce: u64) -> bool {
+        self.inner
+            .lock()
+            .is_ok_and(|inner| inner.challenge == *challenge && inner.nonces.contains(&nonce))
+    }
+
+    /// Records the nonce, forgetting the nonces of the previous challenge.
+    pub fn insert(&self, challenge: &[u8; 32], nonce: u64) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if inner.challenge != *challenge {
+                inner.challenge = *challenge;
+                inner.nonces.clear();
+            }
+            inner.nonces.insert(nonce);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forgets_nonces_of_past_challenges() {
+        let seen = SeenNonces::default();
+        let (a, b) = ([1; 32], [2; 32]);
+        assert!(!seen.contains(&a, 7));
+        seen.insert(&a, 7);
+        assert!(seen.contains(&a, 7));
+        assert!(!seen.contains(&b, 7));
+        seen.insert(&b, 8);
+        assert!(!seen.contains(&a, 7));
+        assert!(seen.contains(&b, 8));
+    }
+
+    #[test]
+    fn rejections_have_distinct_codes() {
+        let rejections = [
+            Rejection::BelowMinDifficulty,
+            Rejection::InvalidNonce,
+            Rejection::DuplicateNonce,
+            Rejection::InvalidDigest,
+        ];
+        let codes: HashSet<&str> = rejections.iter().map(Rejection::code).collect();
+        assert_eq!(codes.len(), rejections.len());
+        assert_eq!(
+            Rejection::DuplicateNonce.response().status(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+    }
+}
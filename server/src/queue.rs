@@ -0,0 +1,163 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::{aggregator::Contribution, error::Error};
+
+/// The max contributions queued for the aggregator if `CONTRIBUTION_QUEUE_SIZE` is unset.
+const DEFAULT_CAPACITY: usize = 10_000;
+
+/// The seconds members are asked to wait before retrying a rejected contribution.
+pub const RETRY_AFTER_SECONDS: u64 = 1;
+
+/// The queue was full, and the contribution was turned away.
+#[derive(Debug)]
+pub struct QueueFull;
+
+/// What to do with a contribution when the queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Reject the new contribution, for the member to retry.
+    Reject,
+
+    /// Drop the lowest score contribution, queued or new.
+    DropLowest,
+}
+
+/// The contributions accepted by the http handlers, waiting for the aggregator.
+///
+/// Bounded by `CONTRIBUTION_QUEUE_SIZE` (defaults to 10,000), so a burst of contributions
+/// can't grow memory without bound while the aggregator falls behind.
+/// When full, `CONTRIBUTION_OVERFLOW` decides which contribution is turned away,
+/// `reject` (the default) for the new one, or `drop-lowest` for the lowest score.
+pub struct ContributionQueue {
+    queue: Mutex<VecDeque<Contribution>>,
+    notify: tokio::sync::Notify,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl ContributionQueue {
+    pub fn new(capacity: usize, overflow: Overflow) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            notify: tokio::sync::Notify::new(),
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let capacity = match std::env::var("CONTRIBUTION_QUEUE_SIZE") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => DEFAULT_CAPACITY,
+        };
+        let overflow = match std::env::var("CONTRIBUTION_OVERFLOW").as_deref() {
+            Ok("reject") | Err(_) => Overflow::Reject,
+            Ok("drop-lowest") => Overflow::DropLowest,
+            Ok(str) => {
+                return Err(Error::Config(format!(
+                    "invalid CONTRIBUTION_OVERFLOW: {}",
+                    str
+                )))
+            }
+        };
+        tracing::info!(capacity, ?overflow, "contribution queue");
+        Ok(Self::new(capacity, overflow))
+    }
+
+    /// Queues the contribution for the aggregator,
+    /// returning the queued contribution dropped to make room, if any.
+    /// Errors if it was turned away.
+    pub fn push(&self, contribution: Contribution) -> Result<Option<Contribution>, QueueFull> {
+        let mut queue = self.queue.lock().map_err(|_| QueueFull)?;
+        if queue.len() < self.capacity {
+            queue.push_back(contribution);
+            drop(queue);
+            self.notify.notify_one();
+            return Ok(None);
+        }
+        match self.overflow {
+            Overflow::Reject => Err(QueueFull),
+            Overflow::DropLowest => {
+                let lowest = queue
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, queued)| queued.score)
+                    .map(|(i, queued)| (i, queued.score));
+                match lowest {
+                    Some((i, score)) if score < contribution.score => {
+                        let dropped = queue.remove(i);
+                        queue.push_back(contribution);
+                        drop(queue);
+                        self.notify.notify_one();
+                        Ok(dropped)
+                    }
+                    _ => Err(QueueFull),
+                }
+            }
+        }
+    }
+
+    /// Waits for the next contribution.
+    pub async fn recv(&self) -> Contribution {
+        loop {
+            if let Some(contribution) = self.pop() {
+                return contribution;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().map(|queue| queue.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pop(&self) -> Option<Contribution> {
+        self.queue.lock().ok()?.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    fn contribution(score: u64) -> Contribution {
+        Contribution {
+            member: Pubkey::new_unique(),
+            worker: "default".to_string(),
+            score,
+            solution: Solution::new([0; 16], [0; 8]),
+            challenge: [0; 32],
+            span: tracing::Span::none(),
+        }
+    }
+
+    #[test]
+    fn overflow_rejects_new() {
+        let queue = ContributionQueue::new(2, Overflow::Reject);
+        assert!(queue.push(contribution(1)).unwrap().is_none());
+        assert!(queue.push(contribution(2)).unwrap().is_none());
+        assert!(queue.push(contribution(4)).is_err());
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn overflow_drops_lowest() {
+        let queue = ContributionQueue::new(2, Overflow::DropLowest);
+        assert!(queue.push(contribution(2)).unwrap().is_none());
+        assert!(queue.push(contribution(1)).unwrap().is_none());
+        assert_eq!(queue.push(contribution(4)).unwrap().unwrap().score, 1);
+        assert!(queue.push(contribution(1)).is_err());
+        let scores = (0..queue.len())
+            .map(|_| queue.pop().unwrap().score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores, vec![2, 4]);
+    }
+}
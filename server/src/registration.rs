@@ -0,0 +1,220 @@
+use std::net::IpAddr;
+
+use drillx::Solution;
+use ore_pool_types::{hex, RegisterChallenge};
+use sha3::{Digest, Sha3_256};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+
+use crate::{api_keys::RateLimiter, error::Error, utils::unix_timestamp};
+
+/// The seconds a registration challenge is valid for.
+/// A challenge is accepted for one more window after it rotates,
+/// so a solution found just before the rotation still registers.
+const WINDOW: i64 = 600;
+
+/// The min difficulty of registration solutions if `REGISTER_DIFFICULTY` is unset.
+/// Takes about a million hashes on average, minutes of a cpu core,
+/// so registering in bulk costs work on the order of the member account rent it spends.
+const DEFAULT_MIN_DIFFICULTY: u64 = 20;
+
+/// The registrations per minute per client ip if `REGISTER_RATE_LIMIT` is unset.
+const DEFAULT_RATE_LIMIT: u32 = 2;
+
+/// Gates registration behind a proof-of-work and a rate limit per client ip,
+/// raising the cost of registering members in bulk,
+/// since the operator pays for the member account of each new registrant.
+///
+/// The challenge of each authority is derived from a secret shared by every replica,
+/// so no challenge state is kept, challenges survive restarts,
+/// and a solution can't be reused for another authority.
+pub struct Registration {
+    /// The secret the challenges are derived from.
+    secret: [u8; 32],
+
+    /// The min difficulty of registration solutions, zero to not require them.
+    min_difficulty: u64,
+
+    /// The registrations per minute per client ip.
+    rate_limit: u32,
+
+    limiter: RateLimiter<IpAddr>,
+}
+
+impl Registration {
+    pub fn new(keypair: &Keypair) -> Result<Self, Error> {
+        let min_difficulty = match std::env::var("REGISTER_DIFFICULTY") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => DEFAULT_MIN_DIFFICULTY,
+        };
+        let rate_limit = match std::env::var("REGISTER_RATE_LIMIT") {
+            Ok(str) => str.parse::<u32>()?.max(1),
+            // optional
+            Err(_) => DEFAULT_RATE_LIMIT,
+        };
+        tracing::info!(
+            "registration min difficulty: {}, rate limit: {}",
+            min_difficulty,
+            rate_limit
+        );
+        Ok(Self {
+            secret: Self::secret(keypair)?,
+            min_difficulty,
+            rate_limit,
+            limiter: RateLimiter::default(),
+        })
+    }
+
+    /// Takes a registration from the budget of the client ip,
+    /// or returns the seconds until it may register again.
+    /// Requests without a known client ip aren't limited.
+    pub fn throttle(&self, ip: Option<IpAddr>) -> Result<(), u64> {
+        match ip {
+            Some(ip) => self.limiter.check(ip, self.rate_limit),
+            None => Ok(()),
+        }
+    }
+
+    /// The secret from `REGISTER_SECRET`, as 32 hex encoded bytes,
+    /// or else derived from the operator keypair, which every replica of the pool shares.
+    fn secret(keypair: &Keypair) -> Result<[u8; 32], Error> {
+        match std::env::var("REGISTER_SECRET") {
+            Ok(str) => hex::decode(str.as_str())
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(Error::Config(
+                    "REGISTER_SECRET must be 32 hex encoded bytes".to_string(),
+                )),
+            // optional
+            Err(_) => Ok(Self::keypair_secret(keypair)),
+        }
+    }
+
+    fn keypair_secret(keypair: &Keypair) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"ore-pool registration");
+        hasher.update(keypair.to_bytes());
+        hasher.finalize().into()
+    }
+
+    /// The current registration challenge of the authority.
+    pub fn challenge(&self, authority: &Pubkey) -> RegisterChallenge {
+        let window = unix_timestamp() / WINDOW;
+        RegisterChallenge {
+            challenge: self.derive(authority, window),
+            min_difficulty: self.min_difficulty,
+            expires_at: (window + 2) * WINDOW,
+        }
+    }
+
+    /// Verifies the solution solves the current or previous challenge of the authority.
+    pub fn verify(&self, authority: &Pubkey, solution: Option<&Solution>) -> Result<(), Error> {
+        if self.min_difficulty == 0 {
+            return Ok(());
+        }
+        let solution = solution.ok_or(Error::InvalidRegistrationProof)?;
+        // cheap difficulty check before hashing
+        if (solution.to_hash().difficulty() as u64) < self.min_difficulty {
+            return Err(Error::InvalidRegistrationProof);
+        }
+        let window = unix_timestamp() / WINDOW;
+        let is_valid = [window, window - 1]
+            .iter()
+            .any(|window| solution.is_valid(&self.derive(authority, *window)));
+        match is_valid {
+            true => Ok(()),
+            false => Err(Error::InvalidRegistrationProof),
+        }
+    }
+
+    fn derive(&self, authority: &Pubkey, window: i64) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.secret);
+        hasher.update(authority.to_bytes());
+        hasher.update(window.to_le_bytes());
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_is_bound_to_authority() {
+        let registration = Registration {
+            secret: [1; 32],
+            min_difficulty: 1,
+            rate_limit: 1,
+            limiter: RateLimiter::default(),
+        };
+        let a = registration.challenge(&Pubkey::new_unique());
+        let b = registration.challenge(&Pubkey::new_unique());
+        assert_ne!(a.challenge, b.challenge);
+    }
+
+    #[test]
+    fn verify_requires_solution() {
+        let registration = Registration {
+            secret: [1; 32],
+            min_difficulty: 1,
+            rate_limit: 1,
+            limiter: RateLimiter::default(),
+        };
+        let authority = Pubkey::new_unique();
+        assert!(registration.verify(&authority, None).is_err());
+        let ungated = Registration {
+            secret: [1; 32],
+            min_difficulty: 0,
+            rate_limit: 1,
+            limiter: RateLimiter::default(),
+        };
+        assert!(ungated.verify(&authority, None).is_ok());
+    }
+
+    #[test]
+    fn verify_accepts_solution_for_own_authority_only() {
+        let registration = Registration {
+            secret: [1; 32],
+            min_difficulty: 1,
+            rate_limit: 1,
+            limiter: RateLimiter::default(),
+        };
+        let authority = Pubkey::new_unique();
+        let challenge = registration.challenge(&authority).challenge;
+        let solution = (0u64..)
+            .find_map(|nonce| {
+                let hash = drillx::hash(&challenge, &nonce.to_le_bytes()).ok()?;
+                (hash.difficulty() >= 1).then(|| Solution::new(hash.d, nonce.to_le_bytes()))
+            })
+            .unwrap();
+        assert!(registration.verify(&authority, Some(&solution)).is_ok());
+        assert!(registration
+            .verify(&Pubkey::new_unique(), Some(&solution))
+            .is_err());
+    }
+
+    #[test]
+    fn throttle_limits_each_ip() {
+        let registration = Registration {
+            secret: [1; 32],
+            min_difficulty: 0,
+            rate_limit: 1,
+            limiter: RateLimiter::default(),
+        };
+        let a = IpAddr::from([203, 0, 113, 7]);
+        let b = IpAddr::from([198, 51, 100, 1]);
+        assert!(registration.throttle(Some(a)).is_ok());
+        assert!(registration.throttle(Some(a)).is_err());
+        assert!(registration.throttle(Some(b)).is_ok());
+        assert!(registration.throttle(None).is_ok());
+    }
+
+    #[test]
+    fn secret_is_stable_per_keypair() {
+        let keypair = Keypair::new();
+        let a = Registration::keypair_secret(&keypair);
+        let b = Registration::keypair_secret(&keypair);
+        assert_eq!(a, b);
+        assert_ne!(a, Registration::keypair_secret(&Keypair::new()));
+    }
+}
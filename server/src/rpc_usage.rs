@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use ore_pool_types::{RpcCallUsage, RpcUsageReport};
+
+use crate::{error::Error, telemetry, utils::unix_timestamp};
+
+/// The usage of the current day, set from the env on startup.
+static RPC_USAGE: OnceLock<RpcUsage> = OnceLock::new();
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The percent of the daily budget alerted at if `RPC_BUDGET_ALERT_PERCENT` is unset.
+const DEFAULT_ALERT_PERCENT: u64 = 80;
+
+/// The rpc requests of the current utc day by call, and the credits they're estimated to cost,
+/// for operators on paid rpc plans to see what the pool spends and on what.
+///
+/// Each call costs a credit, or the credits of the call in `RPC_CREDITS`
+/// (e.g. `"get_program_accounts=10,send_transaction=2"`), to match the pricing of the provider.
+/// If `RPC_DAILY_BUDGET` is set, an alert is raised once a day when the credits used reach
+/// `RPC_BUDGET_ALERT_PERCENT` of it (defaults to 80), and again when it's exhausted.
+pub struct RpcUsage {
+    credits_by_call: HashMap<String, u64>,
+    daily_budget: Option<u64>,
+    alert_percent: u64,
+    inner: Mutex<Usage>,
+}
+
+#[derive(Default)]
+struct Usage {
+    /// The start of the day counted, in unix seconds.
+    day_start: i64,
+    /// The requests and credits by call.
+    calls: HashMap<&'static str, (u64, u64)>,
+    credits: u64,
+    /// Whether the threshold and the budget were alerted today.
+    alerted: (bool, bool),
+}
+
+/// Sets the usage from the env, to be called once on startup.
+pub fn init() -> Result<(), Error> {
+    let _ = RPC_USAGE.set(RpcUsage::from_env()?);
+    Ok(())
+}
+
+/// Counts a request of the call, returning the credits it cost.
+pub fn record(call: &'static str) -> u64 {
+    usage().record(call, unix_timestamp())
+}
+
+/// The usage of the current day.
+pub fn report() -> RpcUsageReport {
+    usage().report(unix_timestamp())
+}
+
+fn usage() -> &'static RpcUsage {
+    RPC_USAGE.get_or_init(|| RpcUsage::new(HashMap::new(), None, DEFAULT_ALERT_PERCENT))
+}
+
+impl RpcUsage {
+    fn new(
+        credits_by_call: HashMap<String, u64>,
+        daily_budget: Option<u64>,
+        alert_percent: u64,
+    ) -> Self {
+        Self {
+            credits_by_call,
+            daily_budget,
+            alert_percent: alert_percent.min(100),
+            inner: Mutex::new(Usage::default()),
+        }
+    }
+
+    fn from_env() -> Result<Self, Error> {
+        let mut credits_by_call = HashMap::new();
+        if let Ok(str) = std::env::var("RPC_CREDITS") {
+            for entry in str
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+            {
+                let (call, credits) = entry.split_once('=').ok_or(Error::Config(format!(
+                    "invalid RPC_CREDITS entry: {}",
+                    entry
+                )))?;
+                credits_by_call.insert(call.trim().to_string(), credits.trim().parse()?);
+            }
+        }
+        let daily_budget = match std::env::var("RPC_DAILY_BUDGET") {
+            Ok(str) => Some(str.parse()?),
+            // optional
+            Err(_) => None,
+        };
+        let alert_percent = match std::env::var("RPC_BUDGET_ALERT_PERCENT") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => DEFAULT_ALERT_PERCENT,
+        };
+        if let Some(budget) = daily_budget {
+            tracing::info!("rpc daily budget: {} credits", budget);
+        }
+        Ok(Self::new(credits_by_call, daily_budget, alert_percent))
+    }
+
+    fn record(&self, call: &'static str, now: i64) -> u64 {
+        let credits = self.credits_by_call.get(call).copied().unwrap_or(1);
+        let Ok(mut usage) = self.inner.lock() else {
+            return credits;
+        };
+        usage.roll_over(now);
+        let (requests, call_credits) = usage.calls.entry(call).or_default();
+        *requests += 1;
+        *call_credits = call_credits.saturating_add(credits);
+        usage.credits = usage.credits.saturating_add(credits);
+        if let Some(budget) = self.daily_budget {
+            let used = usage.credits;
+            if !usage.alerted.1 && used >= budget {
+                usage.alerted = (true, true);
+                telemetry::alert(
+                    format!("rpc daily budget exhausted: {} of {} credits", used, budget).as_str(),
+                );
+            } else if !usage.alerted.0
+                && used as u128 * 100 >= budget as u128 * self.alert_percent as u128
+            {
+                usage.alerted.0 = true;
+                telemetry::alert(
+                    format!(
+                        "rpc daily budget at {}%: {} of {} credits",
+                        self.alert_percent, used, budget
+                    )
+                    .as_str(),
+                );
+            }
+        }
+        credits
+    }
+
+    fn report(&self, now: i64) -> RpcUsageReport {
+        let mut report = RpcUsageReport {
+            day_start: now - now.rem_euclid(SECONDS_PER_DAY),
+            requests: 0,
+            credits: 0,
+            daily_budget: self.daily_budget,
+            calls: vec![],
+        };
+        let Ok(mut usage) = self.inner.lock() else {
+            return report;
+        };
+        usage.roll_over(now);
+        for (call, (requests, credits)) in usage.calls.iter() {
+            report.requests += requests;
+            report.calls.push(RpcCallUsage {
+                call: call.to_string(),
+                requests: *requests,
+                credits: *credits,
+            });
+        }
+        report.credits = usage.credits;
+        report
+            .calls
+            .sort_by(|a, b| b.credits.cmp(&a.credits).then(a.call.cmp(&b.call)));
+        report
+    }
+}
+
+impl Usage {
+    /// Resets the counts at the start of each utc day.
+    fn roll_over(&mut self, now: i64) {
+        let day_start = now - now.rem_euclid(SECONDS_PER_DAY);
+        if day_start != self.day_start {
+            *self = Usage {
+                day_start,
+                ..Default::default()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_credits_by_call_per_day() {
+        let credits = HashMap::from([("get_program_accounts".to_string(), 10)]);
+        let usage = RpcUsage::new(credits, Some(100), 80);
+        let now = 3 * SECONDS_PER_DAY + 60;
+        assert_eq!(usage.record("get_account_data", now), 1);
+        assert_eq!(usage.record("get_program_accounts", now), 10);
+        assert_eq!(usage.record("get_program_accounts", now), 10);
+        let report = usage.report(now);
+        assert_eq!(report.day_start, 3 * SECONDS_PER_DAY);
+        assert_eq!(report.requests, 3);
+        assert_eq!(report.credits, 21);
+        assert_eq!(report.calls[0].call, "get_program_accounts");
+        assert_eq!(report.calls[0].requests, 2);
+        // alerted once at the threshold, then once when exhausted
+        for _ in 0..6 {
+            usage.record("get_program_accounts", now);
+        }
+        assert_eq!(usage.inner.lock().unwrap().alerted, (true, false));
+        usage.record("get_program_accounts", now);
+        usage.record("get_program_accounts", now);
+        assert_eq!(usage.inner.lock().unwrap().alerted, (true, true));
+        // reset the next day
+        let report = usage.report(now + SECONDS_PER_DAY);
+        assert_eq!(report.credits, 0);
+        assert!(report.calls.is_empty());
+    }
+}
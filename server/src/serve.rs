@@ -0,0 +1,217 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, RwLock},
+};
+
+use actix_web::dev::{Server, ServerHandle};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    Certificate, PrivateKey, ServerConfig,
+};
+use socket2::{Domain, Socket, Type};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+};
+
+use crate::{error::Error, telemetry};
+
+/// The certificate chain and private key served over TLS,
+/// read from `TLS_CERT_PATH` and `TLS_KEY_PATH`, and reloaded in place.
+pub struct Certificates {
+    cert_path: String,
+    key_path: String,
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl Certificates {
+    /// None unless `TLS_CERT_PATH` and `TLS_KEY_PATH` are set, to serve plain http.
+    pub fn from_env() -> Result<Option<Arc<Self>>, Error> {
+        let (Ok(cert_path), Ok(key_path)) = (
+            std::env::var("TLS_CERT_PATH"),
+            std::env::var("TLS_KEY_PATH"),
+        ) else {
+            return Ok(None);
+        };
+        let key = load(cert_path.as_str(), key_path.as_str())?;
+        tracing::info!("serving tls with {}", cert_path);
+        Ok(Some(Arc::new(Self {
+            cert_path,
+            key_path,
+            key: RwLock::new(Arc::new(key)),
+        })))
+    }
+
+    /// The TLS config of the server, serving the current certificates on each handshake.
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone())
+    }
+
+    /// Rereads the certificates, for new connections.
+    /// The current certificates are kept if they fail to load.
+    pub fn reload(&self) -> Result<(), Error> {
+        let key = load(self.cert_path.as_str(), self.key_path.as_str())?;
+        let mut write = self
+            .key
+            .write()
+            .map_err(|_| Error::LockPoisoned("tls certificates"))?;
+        *write = Arc::new(key);
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.key.read().ok().map(|key| key.clone())
+    }
+}
+
+/// Binds the listener on `LISTEN_ADDR` if set,
+/// or all interfaces on port 3000, over both IPv6 and IPv4,
+/// falling back to `0.0.0.0:3000` on hosts without IPv6.
+pub fn bind_from_env() -> Result<TcpListener, Error> {
+    match std::env::var("LISTEN_ADDR") {
+        Ok(str) => bind(str.parse()?),
+        // optional
+        Err(_) => bind(SocketAddr::from(([0u16; 8], 3000))).or_else(|err| {
+            tracing::warn!("failed to bind [::]:3000, binding 0.0.0.0:3000: {:?}", err);
+            bind(SocketAddr::from(([0u8; 4], 3000)))
+        }),
+    }
+}
+
+/// Binds the listener, accepting IPv4 clients on IPv6 addresses too (dual-stack),
+/// as IPv4-mapped addresses, rather than whatever the os defaults to.
+pub fn bind(addr: SocketAddr) -> Result<TcpListener, Error> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(false)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    Ok(socket.into())
+}
+
+/// The number of workers to start with,
+/// `WORKERS` if set, or one per core as actix defaults to.
+pub fn workers_from_env() -> Result<usize, Error> {
+    match std::env::var("WORKERS") {
+        Ok(str) => Ok(str.parse::<usize>()?.max(1)),
+        // optional
+        Err(_) => Ok(std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)),
+    }
+}
+
+/// Runs the server, reconfiguring it on signals without downtime:
+/// - SIGHUP reopens the log file and reloads the TLS certificates.
+/// - SIGUSR1 adds a worker, and SIGUSR2 removes one.
+/// - SIGINT and SIGTERM shut down gracefully.
+///
+/// The worker count is changed by starting a new worker set with `start`
+/// on the same listener, then gracefully stopping the old one,
+/// which stops accepting connections but finishes the requests in flight.
+/// `start` must disable the actix signal handlers.
+pub async fn run(
+    mut workers: usize,
+    certificates: Option<Arc<Certificates>>,
+    start: impl Fn(usize) -> std::io::Result<Server>,
+) -> Result<(), Error> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut user_defined1 = signal(SignalKind::user_defined1())?;
+    let mut user_defined2 = signal(SignalKind::user_defined2())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let (mut handle, mut running) = spawn(start(workers)?);
+    tracing::info!(workers, "started workers");
+    loop {
+        let next_workers = tokio::select! {
+            res = &mut running => {
+                // stopped on its own
+                return res?.map_err(From::from);
+            }
+            _ = hangup.recv() => {
+                reload(certificates.as_deref());
+                continue;
+            }
+            _ = user_defined1.recv() => workers + 1,
+            _ = user_defined2.recv() => workers.saturating_sub(1).max(1),
+            _ = interrupt.recv() => break,
+            _ = terminate.recv() => break,
+        };
+        if next_workers == workers {
+            continue;
+        }
+        let server = match start(next_workers) {
+            Ok(server) => server,
+            Err(err) => {
+                tracing::error!("failed to start workers, keeping {}: {:?}", workers, err);
+                continue;
+            }
+        };
+        let (next_handle, next_running) = spawn(server);
+        let old_handle = std::mem::replace(&mut handle, next_handle);
+        let old_running = std::mem::replace(&mut running, next_running);
+        tracing::info!(workers = next_workers, "started workers, stopping previous");
+        workers = next_workers;
+        tokio::spawn(async move {
+            old_handle.stop(true).await;
+            let _ = old_running.await;
+            tracing::info!("stopped previous workers");
+        });
+    }
+    tracing::info!("shutting down");
+    handle.stop(true).await;
+    let _ = running.await;
+    Ok(())
+}
+
+fn spawn(server: Server) -> (ServerHandle, JoinHandle<std::io::Result<()>>) {
+    let handle = server.handle();
+    (handle, tokio::spawn(server))
+}
+
+fn reload(certificates: Option<&Certificates>) {
+    match telemetry::reopen_log_file() {
+        Ok(()) => tracing::info!("reopened log file"),
+        Err(err) => tracing::error!("failed to reopen log file: {:?}", err),
+    }
+    if let Some(certificates) = certificates {
+        match certificates.reload() {
+            Ok(()) => tracing::info!("reloaded tls certificates"),
+            Err(err) => tracing::error!("failed to reload tls certificates: {:?}", err),
+        }
+    }
+}
+
+/// Reads a PEM certificate chain, and the first PKCS#8, RSA, or EC private key in the key file.
+fn load(cert_path: &str, key_path: &str) -> Result<CertifiedKey, Error> {
+    let chain = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if chain.is_empty() {
+        return Err(Error::Config(format!("no certificates in {}", cert_path)));
+    }
+    let mut reader = BufReader::new(File::open(key_path)?);
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => break PrivateKey(key),
+            Some(_) => continue,
+            None => return Err(Error::Config(format!("no private key in {}", key_path))),
+        }
+    };
+    let key = rustls::sign::any_supported_type(&key)
+        .map_err(|_| Error::Config(format!("unsupported private key in {}", key_path)))?;
+    Ok(CertifiedKey::new(chain, key))
+}
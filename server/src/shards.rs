@@ -0,0 +1,535 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use ore_pool_types::{DifficultyHistogram, WorkerStats};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    aggregator::{Contribution, Winner, Workers, MAX_WORKERS_PER_MEMBER},
+    decay::ScoreDecay,
+    error::Error,
+    histogram::Histogram,
+    telemetry,
+    utils::unix_timestamp,
+};
+
+/// The window of recent contributions used to estimate worker hashrate, in seconds.
+const HASHRATE_WINDOW: i64 = 600;
+
+/// The contributions buffered per shard before the dispatcher waits on its worker.
+const CHANNEL_SIZE: usize = 1_000;
+
+/// How long a named worker without shares is kept in the stats, in seconds.
+const WORKER_IDLE_TIMEOUT: i64 = 86_400;
+
+/// The member scores of the aggregator, sharded by member authority across worker tasks
+/// fed from the contribution queue, so that score accumulation scales across cores
+/// for pools with thousands of concurrent miners.
+///
+/// Each member hashes to one shard, so its contributions, workers, histograms, and decayed score
+/// are only ever aggregated by one worker, and the shards are merged only to distribute a round.
+/// `AGGREGATOR_SHARDS` sets the number of shards, and defaults to the number of cores.
+pub struct Shards {
+    shards: Vec<Mutex<Shard>>,
+    senders: Vec<mpsc::Sender<Message>>,
+}
+
+enum Message {
+    Contribution(Contribution),
+
+    /// Acknowledged once every contribution dispatched before it is aggregated.
+    Flush(oneshot::Sender<()>),
+}
+
+/// The state of the members that hash to a shard.
+#[derive(Default)]
+struct Shard {
+    /// The challenge contributions are aggregated for.
+    challenge: [u8; 32],
+
+    /// The best contribution of each member for the current challenge, across its workers.
+    contributions: HashSet<Contribution>,
+
+    /// The nonces contributed for the current challenge, so one solution is only accepted once.
+    /// Nonce ranges are assigned per member, and each member hashes to one shard.
+    nonces: HashSet<u64>,
+
+    /// The total difficulty score of the best contribution of each member.
+    total_score: u64,
+
+    /// The best solution submitted.
+    winner: Option<Winner>,
+
+    /// The difficulties of the shares accepted for the current challenge.
+    histogram: Histogram,
+
+    /// The number of shares of each member rejected by the aggregator
+    /// since the last round was distributed.
+    rejected: HashMap<Pubkey, u64>,
+
+    /// The named workers of each member, kept across challenges until idle.
+    workers: Workers,
+
+    /// The difficulties of the shares accepted from each member, kept across challenges.
+    member_histograms: HashMap<Pubkey, Histogram>,
+
+    /// The decayed score of each member, kept across challenges.
+    decay: ScoreDecay,
+}
+
+/// The contributions of a round, merged across the shards.
+pub struct Round {
+    pub contributions: Vec<Contribution>,
+
+    /// The total difficulty score of the contributions.
+    pub total_score: u64,
+
+    /// The best solution submitted.
+    pub winner: Option<Winner>,
+
+    /// The difficulties of the shares accepted.
+    pub histogram: Histogram,
+
+    /// The decayed score of each contributing member, none if decay is disabled.
+    pub decayed_scores: Option<HashMap<Pubkey, f64>>,
+}
+
+impl Shards {
+    /// Builds the shards from the env, and spawns a worker for each.
+    pub fn from_env(challenge: [u8; 32]) -> Result<Arc<Self>, Error> {
+        let count = match std::env::var("AGGREGATOR_SHARDS") {
+            Ok(str) => str.parse()?,
+            // optional
+            Err(_) => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        };
+        tracing::info!("aggregator shards: {}", count);
+        let (shards, receivers) = Self::new(count, challenge, ScoreDecay::from_env()?);
+        let shards = Arc::new(shards);
+        for (index, rx) in receivers.into_iter().enumerate() {
+            tokio::task::spawn(Self::work(shards.clone(), index, rx));
+        }
+        Ok(shards)
+    }
+
+    fn new(
+        count: usize,
+        challenge: [u8; 32],
+        decay: ScoreDecay,
+    ) -> (Self, Vec<mpsc::Receiver<Message>>) {
+        let count = count.max(1);
+        let mut shards = Vec::with_capacity(count);
+        let mut senders = Vec::with_capacity(count);
+        let mut receivers = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+            shards.push(Mutex::new(Shard {
+                challenge,
+                decay: decay.clone(),
+                ..Default::default()
+            }));
+            senders.push(tx);
+            receivers.push(rx);
+        }
+        (Self { shards, senders }, receivers)
+    }
+
+    /// Aggregates the contributions dispatched to the shard, for the life of the server.
+    async fn work(shards: Arc<Self>, index: usize, mut rx: mpsc::Receiver<Message>) {
+        while let Some(message) = rx.recv().await {
+            match message {
+                Message::Contribution(contribution) => {
+                    contribution
+                        .span
+                        .in_scope(|| shards.lock(index).insert(&contribution));
+                }
+                Message::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    /// Queues the contribution for the worker of its member's shard,
+    /// waiting if the worker is behind.
+    pub async fn dispatch(&self, contribution: Contribution) {
+        let index = self.index(&contribution.member);
+        if self.senders[index]
+            .send(Message::Contribution(contribution))
+            .await
+            .is_err()
+        {
+            tracing::error!("aggregator shard {} stopped", index);
+        }
+    }
+
+    /// Waits for every contribution dispatched so far to be aggregated.
+    pub async fn flush(&self) {
+        let mut acks = Vec::with_capacity(self.senders.len());
+        for sender in self.senders.iter() {
+            let (tx, rx) = oneshot::channel();
+            if sender.send(Message::Flush(tx)).await.is_ok() {
+                acks.push(rx);
+            }
+        }
+        for ack in acks {
+            let _ = ack.await;
+        }
+    }
+
+    /// Aggregates the contribution now, rather than through the worker of its shard.
+    pub fn insert(&self, contribution: &Contribution) {
+        self.lock(self.index(&contribution.member))
+            .insert(contribution);
+    }
+
+    /// Starts aggregating the challenge, dropping the contributions of the previous one.
+    pub fn reset(&self, challenge: [u8; 32]) {
+        let now = unix_timestamp();
+        for index in 0..self.shards.len() {
+            let mut shard = self.lock(index);
+            let capacity = shard.contributions.len();
+            shard.challenge = challenge;
+            shard.contributions = HashSet::with_capacity(capacity);
+            shard.nonces = HashSet::with_capacity(capacity);
+            shard.total_score = 0;
+            shard.winner = None;
+            shard.histogram = Histogram::default();
+            shard.decay.prune(now);
+            shard.prune_workers(now);
+        }
+    }
+
+    /// The total difficulty score of the contributions aggregated so far.
+    pub fn total_score(&self) -> u64 {
+        (0..self.shards.len()).fold(0u64, |sum, index| {
+            sum.saturating_add(self.lock(index).total_score)
+        })
+    }
+
+    /// The contributions of the current challenge merged across the shards.
+    pub fn round(&self) -> Round {
+        let now = unix_timestamp();
+        let mut round = Round {
+            contributions: vec![],
+            total_score: 0,
+            winner: None,
+            histogram: Histogram::default(),
+            decayed_scores: None,
+        };
+        for index in 0..self.shards.len() {
+            let shard = self.lock(index);
+            round
+                .contributions
+                .extend(shard.contributions.iter().cloned());
+            round.total_score = round.total_score.saturating_add(shard.total_score);
+            round.histogram.merge(&shard.histogram);
+            match (round.winner, shard.winner) {
+                (Some(winner), Some(contender)) if contender.difficulty <= winner.difficulty => {}
+                (_, Some(contender)) => round.winner = Some(contender),
+                (_, None) => {}
+            }
+            if shard.decay.is_enabled() {
+                let scores = round.decayed_scores.get_or_insert_with(HashMap::new);
+                for contribution in shard.contributions.iter() {
+                    scores
+                        .entry(contribution.member)
+                        .or_insert_with(|| shard.decay.score(&contribution.member, now));
+                }
+            }
+        }
+        round
+    }
+
+    /// The number of contributions aggregated for the current challenge.
+    pub fn num_contributions(&self) -> usize {
+        (0..self.shards.len()).fold(0, |sum, index| sum + self.lock(index).contributions.len())
+    }
+
+    /// The difficulties of the shares accepted for the current challenge.
+    pub fn histogram(&self) -> Histogram {
+        let mut histogram = Histogram::default();
+        for index in 0..self.shards.len() {
+            histogram.merge(&self.lock(index).histogram);
+        }
+        histogram
+    }
+
+    /// The shares rejected by the aggregator since the last call.
+    pub fn take_rejected(&self) -> HashMap<Pubkey, u64> {
+        let mut rejected = HashMap::new();
+        for index in 0..self.shards.len() {
+            rejected.extend(self.lock(index).rejected.drain());
+        }
+        rejected
+    }
+
+    /// The stats of each named worker of the member.
+    pub fn worker_stats(&self, member: &Pubkey) -> Vec<WorkerStats> {
+        let now = unix_timestamp();
+        let shard = self.lock(self.index(member));
+        let Some(workers) = shard.workers.get(member) else {
+            return vec![];
+        };
+        workers
+            .iter()
+            .map(|(name, worker)| {
+                let recent_score: u64 = worker
+                    .recent
+                    .iter()
+                    .filter(|(at, _)| now - at <= HASHRATE_WINDOW)
+                    .map(|(_, score)| score)
+                    .sum();
+                WorkerStats {
+                    name: name.clone(),
+                    shares: worker.shares,
+                    total_score: worker.total_score,
+                    last_share_at: worker.last_share_at,
+                    hashrate: recent_score / HASHRATE_WINDOW as u64,
+                }
+            })
+            .collect()
+    }
+
+    /// The difficulties of the shares accepted from the member.
+    pub fn member_difficulty(&self, member: &Pubkey) -> DifficultyHistogram {
+        match self.lock(self.index(member)).member_histograms.get(member) {
+            Some(histogram) => histogram.to_stats(),
+            None => Histogram::default().to_stats(),
+        }
+    }
+
+    /// The members whose shares are suspiciously uniform.
+    pub fn uniform_members(&self) -> Vec<Pubkey> {
+        let mut uniform_members = vec![];
+        for index in 0..self.shards.len() {
+            let shard = self.lock(index);
+            uniform_members.extend(
+                shard
+                    .member_histograms
+                    .iter()
+                    .filter(|(_, histogram)| histogram.is_uniform())
+                    .map(|(member, _)| *member),
+            );
+        }
+        uniform_members.sort();
+        uniform_members
+    }
+
+    /// The hashrate of the pool, estimated from the scores of recent contributions
+    /// of every worker, in hashes per second.
+    pub fn hashrate(&self) -> u64 {
+        let now = unix_timestamp();
+        let mut recent_score = 0u64;
+        for index in 0..self.shards.len() {
+            let shard = self.lock(index);
+            recent_score = shard
+                .workers
+                .values()
+                .flat_map(|workers| workers.values())
+                .flat_map(|worker| worker.recent.iter())
+                .filter(|(at, _)| now - at <= HASHRATE_WINDOW)
+                .map(|(_, score)| score)
+                .fold(recent_score, |sum, score| sum.saturating_add(*score));
+        }
+        recent_score / HASHRATE_WINDOW as u64
+    }
+
+    /// The shard of the member.
+    /// Pubkeys are uniformly distributed, so their leading bytes serve as the hash.
+    fn index(&self, member: &Pubkey) -> usize {
+        let bytes = member.to_bytes();
+        let mut hash = [0u8; 8];
+        hash.copy_from_slice(&bytes[..8]);
+        (u64::from_le_bytes(hash) % self.shards.len() as u64) as usize
+    }
+
+    /// Locks the shard, recovering it if a worker panicked while holding it.
+    fn lock(&self, index: usize) -> MutexGuard<'_, Shard> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Shard {
+    fn insert(&mut self, contribution: &Contribution) {
+        if contribution.challenge != self.challenge {
+            tracing::error!("solution for a past challenge: {:?}", contribution.member);
+            telemetry::record_contribution("stale_challenge");
+            self.reject(&contribution.member);
+            return;
+        }
+        let nonce = u64::from_le_bytes(contribution.solution.n);
+        if self.nonces.contains(&nonce) {
+            tracing::error!("already received solution: {:?}", contribution.member);
+            telemetry::record_contribution("duplicate_nonce");
+            self.reject(&contribution.member);
+            return;
+        }
+        self.nonces.insert(nonce);
+        let now = unix_timestamp();
+        self.record_worker(contribution, now);
+        let difficulty = contribution.solution.to_hash().difficulty();
+        self.histogram.add(difficulty);
+        self.member_histograms
+            .entry(contribution.member)
+            .or_default()
+            .add(difficulty);
+        // any share may be the best hash of the pool
+        let contender = Winner {
+            solution: contribution.solution,
+            difficulty,
+        };
+        match self.winner {
+            Some(winner) if winner.difficulty >= difficulty => {}
+            _ => self.winner = Some(contender),
+        }
+        // but only the best share of each member is scored
+        let previous = self.contributions.get(contribution).map(|c| c.score);
+        match previous {
+            Some(previous) if previous >= contribution.score => {
+                tracing::info!(
+                    worker = contribution.worker.as_str(),
+                    score = contribution.score,
+                    difficulty,
+                    "contribution below the member's best"
+                );
+                telemetry::record_contribution("below_best");
+            }
+            previous => {
+                let increase = contribution.score - previous.unwrap_or(0);
+                self.contributions.replace(contribution.clone());
+                self.total_score = self.total_score.saturating_add(increase);
+                self.decay.add(contribution.member, increase, now);
+                tracing::info!(
+                    worker = contribution.worker.as_str(),
+                    score = contribution.score,
+                    difficulty,
+                    "aggregated contribution"
+                );
+                telemetry::record_contribution("accepted");
+            }
+        }
+    }
+
+    /// Records the share in the stats of its named worker,
+    /// making room by dropping the member's least recently active worker if needed.
+    fn record_worker(&mut self, contribution: &Contribution, now: i64) {
+        let workers = self.workers.entry(contribution.member).or_default();
+        if !workers.contains_key(&contribution.worker) && workers.len() >= MAX_WORKERS_PER_MEMBER {
+            let stalest = workers
+                .iter()
+                .min_by_key(|(_, worker)| worker.last_share_at)
+                .map(|(name, _)| name.clone());
+            if let Some(stalest) = stalest {
+                workers.remove(&stalest);
+            }
+        }
+        let worker = workers.entry(contribution.worker.clone()).or_default();
+        worker.shares += 1;
+        worker.total_score = worker.total_score.saturating_add(contribution.score);
+        worker.last_share_at = now;
+        worker.recent.push_back((now, contribution.score));
+        while let Some((at, _)) = worker.recent.front() {
+            if now - at <= HASHRATE_WINDOW {
+                break;
+            }
+            worker.recent.pop_front();
+        }
+    }
+
+    /// Drops the workers without shares for a day, and the members left without workers.
+    fn prune_workers(&mut self, now: i64) {
+        self.workers.retain(|_, workers| {
+            workers.retain(|_, worker| now - worker.last_share_at <= WORKER_IDLE_TIMEOUT);
+            !workers.is_empty()
+        });
+    }
+
+    fn reject(&mut self, member: &Pubkey) {
+        *self.rejected.entry(*member).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+
+    use super::*;
+
+    fn contribution(member: Pubkey, worker: &str, nonce: u64, score: u64) -> Contribution {
+        Contribution {
+            member,
+            worker: worker.to_string(),
+            score,
+            solution: Solution::new([0; 16], nonce.to_le_bytes()),
+            challenge: [1; 32],
+            span: tracing::Span::none(),
+        }
+    }
+
+    #[test]
+    fn merges_members_across_shards() {
+        let (shards, _receivers) = Shards::new(4, [1; 32], ScoreDecay::new(None));
+        let members: Vec<Pubkey> = (0..16).map(|_| Pubkey::new_unique()).collect();
+        for (i, member) in members.iter().enumerate() {
+            shards.insert(&contribution(*member, "rig", i as u64, 4));
+            assert_eq!(shards.index(member), shards.index(member));
+        }
+        // duplicate nonces are rejected by the shard of the member
+        shards.insert(&contribution(members[1], "other", 1, 4));
+        let round = shards.round();
+        assert_eq!(round.contributions.len(), members.len());
+        assert_eq!(round.total_score, 4 * members.len() as u64);
+        assert_eq!(round.histogram.shares(), members.len() as u64);
+        assert!(round.winner.is_some());
+        assert_eq!(shards.total_score(), round.total_score);
+        assert_eq!(shards.take_rejected().values().sum::<u64>(), 1);
+        assert_eq!(shards.worker_stats(&members[0])[0].shares, 1);
+        // a new challenge drops the round, but keeps the workers
+        shards.reset([2; 32]);
+        assert_eq!(shards.total_score(), 0);
+        assert!(shards.round().contributions.is_empty());
+        assert_eq!(shards.worker_stats(&members[0]).len(), 1);
+    }
+
+    #[test]
+    fn scores_the_best_contribution_of_each_member() {
+        let (shards, _receivers) = Shards::new(1, [1; 32], ScoreDecay::new(None));
+        let member = Pubkey::new_unique();
+        // made up worker names don't multiply the score
+        shards.insert(&contribution(member, "a", 1, 8));
+        shards.insert(&contribution(member, "b", 2, 4));
+        shards.insert(&contribution(member, "c", 3, 16));
+        let round = shards.round();
+        assert_eq!(round.contributions.len(), 1);
+        assert_eq!(round.contributions[0].worker, "c");
+        assert_eq!(round.total_score, 16);
+        // but every share counts in the worker stats
+        assert_eq!(shards.worker_stats(&member).len(), 3);
+        assert_eq!(round.histogram.shares(), 3);
+    }
+
+    #[test]
+    fn makes_room_for_new_workers() {
+        let (shards, _receivers) = Shards::new(1, [1; 32], ScoreDecay::new(None));
+        let member = Pubkey::new_unique();
+        for nonce in 0..=MAX_WORKERS_PER_MEMBER as u64 {
+            let worker = format!("rig-{}", nonce);
+            shards.insert(&contribution(member, worker.as_str(), nonce, 4));
+        }
+        let workers = shards.worker_stats(&member);
+        assert_eq!(workers.len(), MAX_WORKERS_PER_MEMBER);
+        let last = format!("rig-{}", MAX_WORKERS_PER_MEMBER);
+        assert!(workers.iter().any(|w| w.name == last));
+        // idle workers are dropped with the next round
+        let now = unix_timestamp();
+        shards.lock(0).prune_workers(now + WORKER_IDLE_TIMEOUT + 1);
+        assert!(shards.worker_stats(&member).is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::{mpsc, Arc, Mutex};
+
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::error::Error;
+
+/// The max signatures verified in one batch.
+const MAX_BATCH: usize = 64;
+
+/// Verifies the signatures of contributions in batches, on threads of its own.
+///
+/// Each thread takes whatever signatures are queued, up to [`MAX_BATCH`], and verifies them at once,
+/// so batches only grow under load and a lone signature isn't held back.
+/// If a batch fails, its signatures are verified one by one to find the invalid ones.
+///
+/// The number of threads is `SIGNATURE_THREADS` if set, or half the cores.
+pub struct SignatureVerifier {
+    tx: mpsc::Sender<Request>,
+}
+
+struct Request {
+    authority: Pubkey,
+    signature: Signature,
+    message: Vec<u8>,
+    res: tokio::sync::oneshot::Sender<bool>,
+}
+
+impl SignatureVerifier {
+    pub fn from_env() -> Result<Self, Error> {
+        let threads = match std::env::var("SIGNATURE_THREADS") {
+            Ok(str) => str.parse::<usize>()?.max(1),
+            // optional
+            Err(_) => std::thread::available_parallelism()
+                .map(|n| (n.get() / 2).max(1))
+                .unwrap_or(1),
+        };
+        let (tx, rx) = mpsc::channel::<Request>();
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..threads {
+            let rx = rx.clone();
+            std::thread::Builder::new()
+                .name(format!("signatures-{}", i))
+                .spawn(move || run(rx.as_ref()))?;
+        }
+        tracing::info!(threads, "verifying signatures in batches");
+        Ok(Self { tx })
+    }
+
+    /// Whether the authority signed the message.
+    pub async fn verify(&self, authority: &Pubkey, signature: &Signature, message: &[u8]) -> bool {
+        let (res_tx, res_rx) = tokio::sync::oneshot::channel();
+        let request = Request {
+            authority: *authority,
+            signature: *signature,
+            message: message.to_vec(),
+            res: res_tx,
+        };
+        match self.tx.send(request) {
+            Ok(()) => {
+                if let Ok(is_valid) = res_rx.await {
+                    return is_valid;
+                }
+                tracing::error!("signature verifier dropped request");
+            }
+            Err(_) => tracing::error!("signature verifier stopped"),
+        }
+        // verify inline if the threads are gone
+        signature.verify(&authority.to_bytes(), message)
+    }
+}
+
+fn run(rx: &Mutex<mpsc::Receiver<Request>>) {
+    loop {
+        let batch = {
+            let Ok(rx) = rx.lock() else {
+                return;
+            };
+            // wait for the first, then take whatever else is queued
+            let Ok(first) = rx.recv() else {
+                return;
+            };
+            let mut batch = vec![first];
+            while batch.len() < MAX_BATCH {
+                match rx.try_recv() {
+                    Ok(request) => batch.push(request),
+                    Err(_) => break,
+                }
+            }
+            batch
+        };
+        let results = verify_batch(batch.as_slice());
+        for (request, is_valid) in batch.into_iter().zip(results) {
+            // the request may have been dropped
+            let _ = request.res.send(is_valid);
+        }
+    }
+}
+
+/// Whether each request is validly signed.
+fn verify_batch(batch: &[Request]) -> Vec<bool> {
+    if batch.len() > 1 && batch_is_valid(batch) {
+        return vec![true; batch.len()];
+    }
+    batch
+        .iter()
+        .map(|request| {
+            request
+                .signature
+                .verify(&request.authority.to_bytes(), request.message.as_slice())
+        })
+        .collect()
+}
+
+fn batch_is_valid(batch: &[Request]) -> bool {
+    let mut messages = Vec::with_capacity(batch.len());
+    let mut signatures = Vec::with_capacity(batch.len());
+    let mut public_keys = Vec::with_capacity(batch.len());
+    for request in batch {
+        let Ok(public_key) = ed25519_dalek::PublicKey::from_bytes(request.authority.as_ref())
+        else {
+            return false;
+        };
+        let Ok(signature) = ed25519_dalek::Signature::try_from(request.signature.as_ref()) else {
+            return false;
+        };
+        messages.push(request.message.as_slice());
+        signatures.push(signature);
+        public_keys.push(public_key);
+    }
+    ed25519_dalek::verify_batch(
+        messages.as_slice(),
+        signatures.as_slice(),
+        public_keys.as_slice(),
+    )
+    .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{signature::Keypair, signer::Signer};
+
+    use super::*;
+
+    fn request(keypair: &Keypair, message: &[u8]) -> Request {
+        let (res, _) = tokio::sync::oneshot::channel();
+        Request {
+            authority: keypair.pubkey(),
+            signature: keypair.sign_message(message),
+            message: message.to_vec(),
+            res,
+        }
+    }
+
+    #[test]
+    fn batch_finds_invalid_signatures() {
+        let keypairs = (0..4).map(|_| Keypair::new()).collect::<Vec<_>>();
+        let mut batch = keypairs
+            .iter()
+            .map(|keypair| request(keypair, b"solution"))
+            .collect::<Vec<_>>();
+        assert_eq!(verify_batch(batch.as_slice()), vec![true; 4]);
+        // signed by another authority
+        batch[2].authority = keypairs[0].pubkey();
+        assert_eq!(
+            verify_batch(batch.as_slice()),
+            vec![true, true, false, true]
+        );
+    }
+}
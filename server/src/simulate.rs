@@ -0,0 +1,239 @@
+use std::sync::Mutex;
+
+use bytemuck::Zeroable;
+use ore_api::state::{Config, Proof};
+use ore_pool_api::state::{Member, Pool};
+use solana_sdk::{clock::Clock, pubkey::Pubkey, signer::Signer};
+
+use crate::{database, error::Error, operator::Operator, utils::unix_timestamp, webhook::Rewards};
+
+/// The seconds per round if `SIMULATE_ROUND_SECONDS` is unset.
+const DEFAULT_ROUND_SECONDS: i64 = 60;
+
+/// The program min difficulty if `SIMULATE_MIN_DIFFICULTY` is unset,
+/// low enough for any machine to find solutions.
+const DEFAULT_MIN_DIFFICULTY: u64 = 4;
+
+/// The base reward per round if `SIMULATE_REWARD` is unset, 0.01 ORE.
+const DEFAULT_REWARD: u64 = 1_000_000_000;
+
+/// The flag that starts the server in simulation mode.
+pub const FLAG: &str = "--simulate";
+
+/// Stubs the pool, proof, config, and member accounts of the chain, and the submissions to it,
+/// so the server and member client can be developed without mainnet ORE or SOL.
+///
+/// Challenges rotate on a timer: every submission starts a new round with a random challenge,
+/// and the cutoff of each round is `SIMULATE_ROUND_SECONDS` after it started.
+/// Submissions are logged instead of landed, and pay a fixed `SIMULATE_REWARD`.
+/// Members are still registered in, and paid to, the db.
+pub struct Simulator {
+    /// The current round.
+    round: Mutex<Round>,
+
+    /// The seconds per round.
+    round_seconds: i64,
+
+    /// The program min difficulty.
+    min_difficulty: u64,
+
+    /// The base reward per round.
+    reward: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Round {
+    /// The current challenge.
+    challenge: [u8; 32],
+
+    /// The unix timestamp the round started at.
+    started_at: i64,
+
+    /// The number of members at the start of the round,
+    /// read from the db on first use.
+    last_total_members: Option<u64>,
+
+    /// The number of submissions so far.
+    total_submissions: u64,
+
+    /// The rewards of the submissions so far.
+    total_rewards: u64,
+}
+
+/// Whether the server was started with the simulate flag.
+pub fn enabled() -> bool {
+    std::env::args().any(|arg| arg == FLAG)
+}
+
+impl Simulator {
+    pub fn from_env() -> Result<Self, Error> {
+        let round_seconds = env_or("SIMULATE_ROUND_SECONDS", DEFAULT_ROUND_SECONDS as u64)?;
+        let min_difficulty = env_or("SIMULATE_MIN_DIFFICULTY", DEFAULT_MIN_DIFFICULTY)?;
+        let reward = env_or("SIMULATE_REWARD", DEFAULT_REWARD)?;
+        tracing::info!(
+            round_seconds,
+            min_difficulty,
+            reward,
+            "simulating chain, submissions are not landed"
+        );
+        Ok(Self {
+            round: Mutex::new(Round {
+                challenge: rand::random(),
+                started_at: unix_timestamp(),
+                last_total_members: None,
+                total_submissions: 0,
+                total_rewards: 0,
+            }),
+            round_seconds: round_seconds as i64,
+            min_difficulty,
+            reward,
+        })
+    }
+
+    pub async fn pool(&self, operator: &Operator) -> Result<Pool, Error> {
+        let round = self.round()?;
+        let last_total_members = match round.last_total_members {
+            Some(last_total_members) => last_total_members,
+            None => {
+                let last_total_members = total_members(operator).await?;
+                self.round
+                    .lock()
+                    .map_err(|_| poisoned())?
+                    .last_total_members = Some(last_total_members);
+                last_total_members
+            }
+        };
+        let mut pool = Pool::zeroed();
+        pool.authority = operator.keypair.pubkey();
+        pool.last_hash_at = round.started_at;
+        pool.reward = self.reward;
+        pool.total_submissions = round.total_submissions;
+        pool.total_members = last_total_members;
+        pool.last_total_members = last_total_members;
+        Ok(pool)
+    }
+
+    pub fn proof(&self, authority: Pubkey) -> Result<Proof, Error> {
+        let round = self.round()?;
+        let mut proof = Proof::zeroed();
+        proof.authority = authority;
+        proof.miner = authority;
+        proof.challenge = round.challenge;
+        // the cutoff is one minute after the last hash,
+        // so shift it to land the cutoff at the end of the round
+        proof.last_hash_at = round.started_at + self.round_seconds - 60;
+        proof.total_hashes = round.total_submissions;
+        proof.total_rewards = round.total_rewards;
+        Ok(proof)
+    }
+
+    pub fn config(&self) -> Config {
+        let mut config = Config::zeroed();
+        config.min_difficulty = self.min_difficulty;
+        config.last_reset_at = unix_timestamp();
+        config
+    }
+
+    pub fn clock(&self) -> Clock {
+        Clock {
+            unix_timestamp: unix_timestamp(),
+            ..Default::default()
+        }
+    }
+
+    /// The member account of the authority,
+    /// as if every authority had already joined the pool.
+    /// New members are given the next id after those in the db.
+    pub async fn member(&self, operator: &Operator, authority: &Pubkey) -> Result<Member, Error> {
+        let (pool_pda, _) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+        let (member_pda, _) = ore_pool_api::state::member_pda(*authority, pool_pda);
+        let conn = operator.db_client.get().await?;
+        let mut member = Member::zeroed();
+        member.pool = pool_pda;
+        member.authority = *authority;
+        match database::read_member(&conn, &member_pda.to_string()).await {
+            Ok(db_member) => {
+                member.id = db_member.id as u64;
+                member.total_balance = db_member.total_balance as u64;
+                member.balance = db_member.total_balance as u64;
+            }
+            Err(_) => {
+                member.id = database::read_next_member_id(&conn).await? as u64;
+            }
+        }
+        Ok(member)
+    }
+
+    /// Logs the submission instead of landing it, and starts the next round.
+    /// Returns the rewards the webhook would have received.
+    pub async fn submit(
+        &self,
+        operator: &Operator,
+        solution: &drillx::Solution,
+    ) -> Result<Rewards, Error> {
+        tracing::info!(
+            difficulty = solution.to_hash().difficulty(),
+            "simulate: submission not landed"
+        );
+        let last_total_members = total_members(operator).await?;
+        let mut round = self.round.lock().map_err(|_| poisoned())?;
+        *round = Round {
+            challenge: rand::random(),
+            // the next round must have a new timestamp to be seen as a reset
+            started_at: unix_timestamp().max(round.started_at + 1),
+            last_total_members: Some(last_total_members),
+            total_submissions: round.total_submissions + 1,
+            total_rewards: round.total_rewards.saturating_add(self.reward),
+        };
+        Ok(Rewards {
+            base: self.reward,
+            boost_1: None,
+            boost_2: None,
+            boost_3: None,
+        })
+    }
+
+    fn round(&self) -> Result<Round, Error> {
+        self.round
+            .lock()
+            .map(|round| *round)
+            .map_err(|_| poisoned())
+    }
+}
+
+/// The total members of the pool as the program counts them, the next member id,
+/// so every member id in the db falls within the nonce partition.
+async fn total_members(operator: &Operator) -> Result<u64, Error> {
+    let conn = operator.db_client.get().await?;
+    let next_id = database::read_next_member_id(&conn).await?;
+    Ok(next_id as u64)
+}
+
+fn env_or(var: &str, default: u64) -> Result<u64, Error> {
+    match std::env::var(var) {
+        Ok(str) => Ok(str.parse()?),
+        // optional
+        Err(_) => Ok(default),
+    }
+}
+
+fn poisoned() -> Error {
+    Error::LockPoisoned("simulated round")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proof_cutoff_lands_at_end_of_round() {
+        let simulator = Simulator::from_env().unwrap();
+        let round = simulator.round().unwrap();
+        let proof = simulator.proof(Pubkey::new_unique()).unwrap();
+        assert_eq!(proof.challenge, round.challenge);
+        assert_eq!(
+            proof.last_hash_at + 60,
+            round.started_at + simulator.round_seconds
+        );
+    }
+}
@@ -0,0 +1,668 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    FromRequest, HttpMessage, HttpRequest,
+};
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram},
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{metrics::SdkMeterProvider, runtime, trace::TracerProvider, Resource};
+use ore_pool_types::{hex, Challenge};
+use sentry_tracing::EventFilter;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    future::{ready, Future, Ready},
+    io::Write,
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+    time::Instant,
+};
+use tracing::{Instrument, Level};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::{client_ip::ClientIp, error::Error, rpc_usage};
+
+/// The header a request id is read from, and echoed back in.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The max length of a request id passed by the caller.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// The service name reported to the collector if `OTEL_SERVICE_NAME` is unset.
+const DEFAULT_SERVICE_NAME: &str = "ore-pool-server";
+
+/// The latency above which a request is logged as slow, if `SLOW_REQUEST_MS` is unset.
+const DEFAULT_SLOW_REQUEST_MS: f64 = 1000.0;
+
+/// The slow request thresholds, set on init.
+static SLOW_REQUESTS: OnceLock<SlowRequests> = OnceLock::new();
+
+/// The log file, if `LOG_PATH` is set.
+static LOG_FILE: OnceLock<LogFile> = OnceLock::new();
+
+/// The pool and round attached to every error report.
+static REPORT_CONTEXT: Mutex<ReportContext> = Mutex::new(ReportContext {
+    pool: None,
+    round: None,
+});
+
+/// The OTLP exporters and error sink, flushed on shutdown.
+pub struct Telemetry {
+    tracer_provider: Option<TracerProvider>,
+    meter_provider: Option<SdkMeterProvider>,
+    sentry: Option<sentry::ClientInitGuard>,
+}
+
+/// The server metrics, exported over OTLP if configured.
+pub struct Metrics {
+    /// HTTP requests served, by method, path, and status.
+    pub requests: Counter<u64>,
+
+    /// HTTP request latency in milliseconds, by method, path, and status.
+    pub request_duration: Histogram<f64>,
+
+    /// HTTP requests that failed with a server error, by method, path, and status.
+    pub request_errors: Counter<u64>,
+
+    /// HTTP request latency in milliseconds spent in each phase of the handler, by path and phase.
+    pub request_phase_duration: Histogram<f64>,
+
+    /// Contributions received, by result.
+    pub contributions: Counter<u64>,
+
+    /// Pool submissions to the ORE program, by result.
+    pub submissions: Counter<u64>,
+
+    /// Solana RPC call latency in milliseconds, by call.
+    pub rpc_duration: Histogram<f64>,
+
+    /// Solana RPC requests, by call.
+    pub rpc_requests: Counter<u64>,
+
+    /// Estimated Solana RPC credits used, by call.
+    pub rpc_credits: Counter<u64>,
+
+    /// Database query latency in milliseconds, by query.
+    pub db_duration: Histogram<f64>,
+}
+
+/// Installs the global tracing subscriber,
+/// filtered by `RUST_LOG` (defaults to `info`).
+///
+/// If `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans and metrics are also
+/// exported over OTLP/HTTP to that collector, under `OTEL_SERVICE_NAME`.
+///
+/// If `SENTRY_DSN` is set, panics and reported errors are sent to that
+/// Sentry-compatible sink, with recent logs attached as breadcrumbs.
+///
+/// If `LOG_PATH` is set, logs are appended to that file instead of stdout,
+/// and the file is reopened on [`reopen_log_file`] for rotation.
+///
+/// Requests slower than `SLOW_REQUEST_MS` (defaults to 1000),
+/// or the threshold of their route in `SLOW_REQUEST_MS_BY_ROUTE`, are logged as warnings.
+pub fn init() -> Result<Telemetry, Error> {
+    let _ = SLOW_REQUESTS.set(SlowRequests::from_env()?);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    // error reporting
+    let sentry = init_sentry()?;
+    let sentry_layer = sentry.as_ref().map(|_| {
+        sentry_tracing::layer().event_filter(|metadata| match *metadata.level() {
+            Level::ERROR | Level::WARN | Level::INFO => EventFilter::Breadcrumb,
+            _ => EventFilter::Ignore,
+        })
+    });
+    // traces and metrics
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok();
+    let (tracer_provider, meter_provider) = match endpoint.as_deref() {
+        Some(endpoint) => {
+            let (tracer_provider, meter_provider) = init_otlp(endpoint)?;
+            (Some(tracer_provider), Some(meter_provider))
+        }
+        None => (None, None),
+    };
+    let otel_layer = tracer_provider.as_ref().map(|tracer_provider| {
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(DEFAULT_SERVICE_NAME))
+    });
+    // logs
+    let log_file = match std::env::var("LOG_PATH") {
+        Ok(path) => {
+            let file = LogFile::open(path.as_str())?;
+            let _ = LOG_FILE.set(LogFile {
+                path,
+                file: Mutex::new(file),
+            });
+            LOG_FILE.get()
+        }
+        // optional
+        Err(_) => None,
+    };
+    let file_layer = log_file.map(|log_file| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || log_file)
+    });
+    let stdout_layer = log_file.is_none().then(tracing_subscriber::fmt::layer);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stdout_layer)
+        .with(otel_layer)
+        .with(sentry_layer)
+        .init();
+    if let Some(endpoint) = endpoint {
+        tracing::info!("exporting telemetry to {}", endpoint);
+    }
+    if sentry.is_some() {
+        tracing::info!("reporting errors to sentry");
+    }
+    Ok(Telemetry {
+        tracer_provider,
+        meter_provider,
+        sentry,
+    })
+}
+
+/// Reopens the log file at `LOG_PATH`, after it was moved by logrotate or similar.
+pub fn reopen_log_file() -> Result<(), Error> {
+    let Some(log_file) = LOG_FILE.get() else {
+        return Ok(());
+    };
+    let file = LogFile::open(log_file.path.as_str())?;
+    *log_file.file.lock().map_err(|_| log_file_poisoned())? = file;
+    Ok(())
+}
+
+struct ReportContext {
+    pool: Option<String>,
+    round: Option<RoundContext>,
+}
+
+struct RoundContext {
+    last_hash_at: i64,
+    fields: BTreeMap<String, sentry::protocol::Value>,
+}
+
+struct LogFile {
+    path: String,
+    file: Mutex<File>,
+}
+
+impl LogFile {
+    fn open(path: &str) -> std::io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+}
+
+impl Write for &LogFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file
+            .lock()
+            .map_err(|_| std::io::Error::other(log_file_poisoned()))?
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file
+            .lock()
+            .map_err(|_| std::io::Error::other(log_file_poisoned()))?
+            .flush()
+    }
+}
+
+fn log_file_poisoned() -> Error {
+    Error::LockPoisoned("log file")
+}
+
+fn init_sentry() -> Result<Option<sentry::ClientInitGuard>, Error> {
+    let Ok(dsn) = std::env::var("SENTRY_DSN") else {
+        return Ok(None);
+    };
+    let dsn = sentry::types::Dsn::from_str(dsn.as_str())?;
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: Some(dsn),
+        release: sentry::release_name!(),
+        environment: std::env::var("SENTRY_ENVIRONMENT").ok().map(Into::into),
+        before_send: Some(Arc::new(attach_context)),
+        ..Default::default()
+    });
+    Ok(Some(guard))
+}
+
+fn init_otlp(endpoint: &str) -> Result<(TracerProvider, SdkMeterProvider), Error> {
+    let service_name =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or(DEFAULT_SERVICE_NAME.to_string());
+    let resource = Resource::new(vec![KeyValue::new("service.name", service_name)]);
+    // traces
+    let tracer_provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            opentelemetry_sdk::trace::Config::default().with_resource(resource.clone()),
+        )
+        .install_batch(runtime::Tokio)?;
+    // metrics
+    let meter_provider = opentelemetry_otlp::new_pipeline()
+        .metrics(runtime::Tokio)
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .with_resource(resource)
+        .build()?;
+    global::set_meter_provider(meter_provider.clone());
+    Ok((tracer_provider, meter_provider))
+}
+
+impl Telemetry {
+    /// Flushes any spans, metrics, and error reports not yet exported.
+    pub fn shutdown(self) {
+        if let Some(tracer_provider) = self.tracer_provider {
+            if let Err(err) = tracer_provider.shutdown() {
+                tracing::error!("{:?}", err);
+            }
+        }
+        if let Some(meter_provider) = self.meter_provider {
+            if let Err(err) = meter_provider.shutdown() {
+                tracing::error!("{:?}", err);
+            }
+        }
+        // flushes on drop
+        drop(self.sentry);
+    }
+}
+
+/// Reports an unexpected error to the error sink,
+/// with the pool and round context attached.
+/// A no-op if error reporting isn't configured.
+pub fn capture(err: &Error) {
+    sentry::capture_error(err);
+}
+
+/// Logs an operational alert and reports it to the error sink.
+pub fn alert(message: &str) {
+    tracing::warn!("{}", message);
+    sentry::capture_message(message, sentry::Level::Warning);
+}
+
+/// Attaches the pool to every error report.
+pub fn set_pool_context(pool: &Pubkey) {
+    if let Ok(mut context) = REPORT_CONTEXT.lock() {
+        context.pool = Some(pool.to_string());
+    }
+}
+
+/// Attaches the current round to every error report.
+pub fn set_round_context(challenge: &Challenge, num_members: u64) {
+    if let Ok(mut context) = REPORT_CONTEXT.lock() {
+        context.round = Some(RoundContext {
+            last_hash_at: challenge.lash_hash_at,
+            fields: BTreeMap::from([
+                (
+                    "challenge".to_string(),
+                    hex::encode(&challenge.challenge).into(),
+                ),
+                ("last_hash_at".to_string(), challenge.lash_hash_at.into()),
+                (
+                    "min_difficulty".to_string(),
+                    challenge.min_difficulty.into(),
+                ),
+                ("cutoff_time".to_string(), challenge.cutoff_time.into()),
+                ("num_members".to_string(), num_members.into()),
+            ]),
+        });
+    }
+}
+
+/// Adds the pool and round context to an error report before it's sent.
+/// Set per event rather than on a scope,
+/// because scopes are per thread and the round moves between runtime threads.
+fn attach_context(
+    mut event: sentry::protocol::Event<'static>,
+) -> Option<sentry::protocol::Event<'static>> {
+    if let Ok(context) = REPORT_CONTEXT.lock() {
+        if let Some(pool) = context.pool.as_ref() {
+            event.tags.insert("pool".to_string(), pool.clone());
+        }
+        if let Some(round) = context.round.as_ref() {
+            event
+                .tags
+                .insert("round".to_string(), round.last_hash_at.to_string());
+            event.contexts.insert(
+                "round".to_string(),
+                sentry::protocol::Context::Other(round.fields.clone()),
+            );
+        }
+    }
+    Some(event)
+}
+
+/// The server metrics.
+/// Must not be called before [`init`], or the instruments are never exported.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| {
+        let meter = global::meter(DEFAULT_SERVICE_NAME);
+        Metrics {
+            requests: meter
+                .u64_counter("http.server.requests")
+                .with_description("HTTP requests served")
+                .init(),
+            request_duration: meter
+                .f64_histogram("http.server.duration")
+                .with_unit("ms")
+                .with_description("HTTP request latency")
+                .init(),
+            request_errors: meter
+                .u64_counter("http.server.errors")
+                .with_description("HTTP requests failed with a server error")
+                .init(),
+            request_phase_duration: meter
+                .f64_histogram("http.server.phase.duration")
+                .with_unit("ms")
+                .with_description("HTTP request latency per handler phase")
+                .init(),
+            contributions: meter
+                .u64_counter("pool.contributions")
+                .with_description("Contributions received")
+                .init(),
+            submissions: meter
+                .u64_counter("pool.submissions")
+                .with_description("Pool submissions to the ORE program")
+                .init(),
+            rpc_duration: meter
+                .f64_histogram("pool.rpc.duration")
+                .with_unit("ms")
+                .with_description("Solana RPC call latency")
+                .init(),
+            rpc_requests: meter
+                .u64_counter("pool.rpc.requests")
+                .with_description("Solana RPC requests")
+                .init(),
+            rpc_credits: meter
+                .u64_counter("pool.rpc.credits")
+                .with_description("Estimated Solana RPC credits used")
+                .init(),
+            db_duration: meter
+                .f64_histogram("pool.db.duration")
+                .with_unit("ms")
+                .with_description("Database query latency")
+                .init(),
+        }
+    })
+}
+
+/// Counts a contribution by its result.
+pub fn record_contribution(result: &'static str) {
+    metrics()
+        .contributions
+        .add(1, &[KeyValue::new("result", result)]);
+}
+
+/// Awaits a solana rpc call, recording its latency and usage.
+pub async fn rpc<T>(call: &'static str, f: impl Future<Output = T>) -> T {
+    let credits = rpc_usage::record(call);
+    let attributes = [KeyValue::new("call", call)];
+    metrics().rpc_requests.add(1, &attributes);
+    metrics().rpc_credits.add(credits, &attributes);
+    timed(&metrics().rpc_duration, "call", call, f).await
+}
+
+/// Awaits a database query, recording its latency.
+pub async fn db<T>(query: &'static str, f: impl Future<Output = T>) -> T {
+    timed(&metrics().db_duration, "query", query, f).await
+}
+
+async fn timed<T>(
+    histogram: &Histogram<f64>,
+    key: &'static str,
+    value: &'static str,
+    f: impl Future<Output = T>,
+) -> T {
+    let timer = Instant::now();
+    let res = f.await;
+    histogram.record(elapsed_ms(timer), &[KeyValue::new(key, value)]);
+    res
+}
+
+fn elapsed_ms(timer: Instant) -> f64 {
+    timer.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Wraps every request in a span carrying a request id,
+/// so that one request can be followed through the logs.
+///
+/// The request id is taken from the `x-request-id` header if present,
+/// and generated otherwise. It is echoed back in the response.
+///
+/// Records the latency and status of every request, and the phases timed by its handler,
+/// and logs the request with its phases if slower than the threshold of its route.
+pub async fn request_span(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+        client_ip = tracing::field::Empty,
+        member = tracing::field::Empty,
+        status = tracing::field::Empty,
+    );
+    if let Some(client_ip) = req.extensions().get::<ClientIp>() {
+        span.record("client_ip", tracing::field::display(client_ip.0));
+    }
+    let method = req.method().to_string();
+    let phases = Phases::default();
+    req.extensions_mut().insert(phases.clone());
+    let timer = Instant::now();
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    let status = res.status();
+    span.record("status", status.as_u16());
+    let elapsed_ms = elapsed_ms(timer);
+    tracing::info!(parent: &span, elapsed_ms, "response");
+    // label by route pattern, so that metrics aren't labeled per member
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or("unmatched".to_string());
+    let phases = phases.take();
+    let threshold_ms = SLOW_REQUESTS
+        .get_or_init(Default::default)
+        .threshold_ms(&route);
+    if elapsed_ms > threshold_ms {
+        tracing::warn!(parent: &span, elapsed_ms, threshold_ms, phases = ?phases, "slow request");
+    }
+    let metrics = metrics();
+    for (phase, phase_ms) in phases {
+        metrics.request_phase_duration.record(
+            phase_ms,
+            &[
+                KeyValue::new("path", route.clone()),
+                KeyValue::new("phase", phase),
+            ],
+        );
+    }
+    let attributes = [
+        KeyValue::new("method", method),
+        KeyValue::new("path", route),
+        KeyValue::new("status", status.as_u16() as i64),
+    ];
+    metrics.requests.add(1, &attributes);
+    metrics.request_duration.record(elapsed_ms, &attributes);
+    if status.is_server_error() {
+        metrics.request_errors.add(1, &attributes);
+    }
+    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
+
+/// The time spent in each phase of a request handler,
+/// e.g. waiting on the aggregator lock, recorded per route and logged with slow requests.
+/// Extracted by the handlers of requests wrapped in [`request_span`].
+#[derive(Clone, Default)]
+pub struct Phases(Rc<RefCell<Vec<(&'static str, f64)>>>);
+
+impl Phases {
+    /// Records the time since the timer started as the phase.
+    pub fn record(&self, phase: &'static str, timer: Instant) {
+        self.0.borrow_mut().push((phase, elapsed_ms(timer)));
+    }
+
+    /// Awaits f, recording its time as the phase.
+    pub async fn time<T>(&self, phase: &'static str, f: impl Future<Output = T>) -> T {
+        let timer = Instant::now();
+        let res = f.await;
+        self.record(phase, timer);
+        res
+    }
+
+    fn take(&self) -> Vec<(&'static str, f64)> {
+        self.0.take()
+    }
+}
+
+impl FromRequest for Phases {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // phases of requests not wrapped in the span are dropped
+        let phases = req
+            .extensions()
+            .get::<Phases>()
+            .cloned()
+            .unwrap_or_default();
+        ready(Ok(phases))
+    }
+}
+
+/// The latency thresholds above which requests are logged as slow.
+#[derive(Debug, PartialEq)]
+struct SlowRequests {
+    /// The threshold of routes without their own.
+    default_ms: f64,
+
+    /// The thresholds by route pattern, e.g. `/contribute`.
+    by_route_ms: HashMap<String, f64>,
+}
+
+impl Default for SlowRequests {
+    fn default() -> Self {
+        Self {
+            default_ms: DEFAULT_SLOW_REQUEST_MS,
+            by_route_ms: HashMap::new(),
+        }
+    }
+}
+
+impl SlowRequests {
+    fn from_env() -> Result<Self, Error> {
+        let default_ms = std::env::var("SLOW_REQUEST_MS").ok();
+        let by_route_ms = std::env::var("SLOW_REQUEST_MS_BY_ROUTE").ok();
+        Self::parse(default_ms.as_deref(), by_route_ms.as_deref())
+    }
+
+    /// Parses the default threshold, and the comma separated `route=ms` thresholds.
+    fn parse(default_ms: Option<&str>, by_route_ms: Option<&str>) -> Result<Self, Error> {
+        let default_ms = match default_ms {
+            Some(ms) => ms.trim().parse::<u64>()? as f64,
+            None => DEFAULT_SLOW_REQUEST_MS,
+        };
+        let mut thresholds = HashMap::new();
+        for entry in by_route_ms.unwrap_or_default().split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let (route, ms) = entry.split_once('=').ok_or(Error::Config(format!(
+                "invalid slow request threshold: {}",
+                entry
+            )))?;
+            thresholds.insert(route.trim().to_string(), ms.trim().parse::<u64>()? as f64);
+        }
+        Ok(Self {
+            default_ms,
+            by_route_ms: thresholds,
+        })
+    }
+
+    fn threshold_ms(&self, route: &str) -> f64 {
+        self.by_route_ms
+            .get(route)
+            .copied()
+            .unwrap_or(self.default_ms)
+    }
+}
+
+/// Records the member authority on the current request span.
+pub fn record_member(authority: &Pubkey) {
+    tracing::Span::current().record("member", tracing::field::display(authority));
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_request_thresholds_by_route() {
+        let slow_requests =
+            SlowRequests::parse(Some("500"), Some("/contribute=50, /admin/backup=30000,")).unwrap();
+        assert_eq!(slow_requests.threshold_ms("/contribute"), 50.0);
+        assert_eq!(slow_requests.threshold_ms("/admin/backup"), 30000.0);
+        assert_eq!(slow_requests.threshold_ms("/challenge"), 500.0);
+        assert_eq!(
+            SlowRequests::parse(None, None).unwrap(),
+            SlowRequests::default()
+        );
+        assert!(SlowRequests::parse(None, Some("/contribute")).is_err());
+    }
+    #[test]
+    fn attaches_round_context_from_any_thread() {
+        let challenge = Challenge {
+            challenge: [1; 32],
+            lash_hash_at: 42,
+            min_difficulty: 18,
+            cutoff_time: 55,
+        };
+        std::thread::spawn(move || set_round_context(&challenge, 3))
+            .join()
+            .unwrap();
+        let event = attach_context(sentry::protocol::Event::default()).unwrap();
+        assert_eq!(event.tags.get("round").map(String::as_str), Some("42"));
+        assert!(event.contexts.contains_key("round"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::RpcSignatureSubscribeConfig,
+    rpc_response::RpcSignatureResult,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+
+use crate::{error::Error, geyser::Geyser, telemetry};
+
+/// How long to wait for the cluster to confirm a signature.
+const CONFIRM_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(20);
+
+/// Confirms transactions with `signatureSubscribe`,
+/// multiplexing every pending signature over one websocket to the rpc.
+///
+/// The websocket is opened on first use, and reopened on the next confirmation if it fails.
+/// Confirmations fall back to polling the rpc while it's unavailable.
+/// If geyser is connected, signatures are confirmed from its stream instead.
+pub struct Confirmer {
+    /// The websocket url of the rpc.
+    ws_url: String,
+
+    /// The open websocket.
+    client: tokio::sync::Mutex<Option<Arc<PubsubClient>>>,
+
+    /// The geyser stream, if configured.
+    geyser: Option<Arc<Geyser>>,
+}
+
+impl Confirmer {
+    pub fn new(ws_url: String, geyser: Option<Arc<Geyser>>) -> Self {
+        Self {
+            ws_url,
+            client: tokio::sync::Mutex::new(None),
+            geyser,
+        }
+    }
+
+    /// The websocket url of the rpc, `WS_URL` if set,
+    /// or else the rpc url on the ws scheme and next port, as solana validators serve it.
+    pub fn ws_url(rpc_url: &str) -> Result<String, Error> {
+        if let Ok(ws_url) = std::env::var("WS_URL") {
+            return Ok(ws_url);
+        }
+        let mut url = reqwest::Url::parse(rpc_url)
+            .map_err(|err| Error::Config(format!("invalid rpc url: {}", err)))?;
+        let scheme = match url.scheme() {
+            "https" => "wss",
+            _ => "ws",
+        };
+        let bad_url = |_| Error::Config(format!("invalid rpc url: {}", rpc_url));
+        url.set_scheme(scheme).map_err(bad_url)?;
+        if let Some(port) = url.port() {
+            url.set_port(Some(port + 1)).map_err(bad_url)?;
+        }
+        Ok(url.to_string())
+    }
+
+    /// Resolves once the signature is confirmed, or errors if it failed or timed out.
+    #[tracing::instrument(skip_all)]
+    pub async fn confirm(&self, rpc_client: &RpcClient, sig: &Signature) -> Result<(), Error> {
+        if let Some(geyser) = self.geyser.as_ref().filter(|geyser| geyser.is_connected()) {
+            return geyser.confirm(sig, CONFIRM_TIMEOUT).await;
+        }
+        let client = match self.client().await {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!("signature websocket unavailable, polling: {:?}", err);
+                return poll(rpc_client, sig).await;
+            }
+        };
+        let config = RpcSignatureSubscribeConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            enable_received_notification: Some(false),
+        };
+        let (mut notifications, unsubscribe) =
+            match client.signature_subscribe(sig, Some(config)).await {
+                Ok(subscription) => subscription,
+                Err(err) => {
+                    tracing::warn!("signature subscription failed, polling: {:?}", err);
+                    self.disconnect(&client).await;
+                    return poll(rpc_client, sig).await;
+                }
+            };
+        // it may have confirmed before the subscription
+        if let Ok(true) = is_confirmed(rpc_client, sig).await {
+            unsubscribe().await;
+            return Ok(());
+        }
+        let notification = tokio::time::timeout(CONFIRM_TIMEOUT, notifications.next()).await;
+        match notification {
+            Ok(Some(notification)) => match notification.value {
+                RpcSignatureResult::ProcessedSignature(result) => match result.err {
+                    None => Ok(()),
+                    Some(err) => Err(Error::TransactionFailed(err.to_string())),
+                },
+                RpcSignatureResult::ReceivedSignature(_) => {
+                    Err(Error::Rpc("unexpected received notification".to_string()))
+                }
+            },
+            Ok(None) => {
+                // the websocket closed
+                drop(notifications);
+                self.disconnect(&client).await;
+                poll(rpc_client, sig).await
+            }
+            Err(_) => {
+                drop(notifications);
+                unsubscribe().await;
+                Err(Error::Rpc("could not confirm transaction".to_string()))
+            }
+        }
+    }
+
+    async fn client(&self) -> Result<Arc<PubsubClient>, Error> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+        let new_client = Arc::new(PubsubClient::new(self.ws_url.as_str()).await?);
+        *client = Some(new_client.clone());
+        Ok(new_client)
+    }
+
+    /// Drops the websocket, to reopen it on the next confirmation,
+    /// unless it was already replaced.
+    async fn disconnect(&self, failed: &Arc<PubsubClient>) {
+        let mut client = self.client.lock().await;
+        if client
+            .as_ref()
+            .is_some_and(|client| Arc::ptr_eq(client, failed))
+        {
+            *client = None;
+        }
+    }
+}
+
+async fn is_confirmed(rpc_client: &RpcClient, sig: &Signature) -> Result<bool, Error> {
+    let confirmed = telemetry::rpc(
+        "confirm_transaction",
+        rpc_client.confirm_transaction_with_commitment(sig, CommitmentConfig::confirmed()),
+    )
+    .await?;
+    Ok(confirmed.value)
+}
+
+/// Confirms the signature by polling the rpc.
+async fn poll(rpc_client: &RpcClient, sig: &Signature) -> Result<(), Error> {
+    let max_retries = 10;
+    let mut retries = 0;
+    while retries < max_retries {
+        if let Ok(true) = is_confirmed(rpc_client, sig).await {
+            return Ok(());
+        }
+        retries += 1;
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    }
+    Err(Error::Rpc("could not confirm transaction".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_url_from_rpc_url() {
+        assert_eq!(
+            Confirmer::ws_url("http://127.0.0.1:8899").unwrap(),
+            "ws://127.0.0.1:8900/"
+        );
+        assert_eq!(
+            Confirmer::ws_url("https://rpc.example.com/?api-key=abc").unwrap(),
+            "wss://rpc.example.com/?api-key=abc"
+        );
+    }
+}
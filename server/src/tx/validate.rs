@@ -1,15 +1,21 @@
-use ore_pool_api::instruction::Attribute;
-use solana_sdk::{program_error::ProgramError, transaction::Transaction};
+use ore_api::consts::MINT_ADDRESS;
+use ore_pool_api::instruction::{Attribute, Claim, PoolInstruction};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::CompiledInstruction, message::Message,
+    program_error::ProgramError, pubkey::Pubkey, transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::instruction::TokenInstruction;
 
-use crate::error::Error;
+use crate::{claim_fees, error::Error};
 
 pub fn validate_attribution(transaction: &Transaction, total_balance: i64) -> Result<(), Error> {
     let instructions = &transaction.message.instructions;
     // validate that all but the last instruction are compute budget
     let n = instructions.len();
-    let num_compute_budget_instructions = n - 1;
+    let num_compute_budget_instructions = n.saturating_sub(1);
     if num_compute_budget_instructions <= 1 {
-        return Err(Error::Internal(
+        return Err(Error::Validation(
             "attribution transactions must contain at least two compute budget instructions"
                 .to_string(),
         ));
@@ -20,24 +26,24 @@ pub fn validate_attribution(transaction: &Transaction, total_balance: i64) -> Re
             .message
             .account_keys
             .get(ix.program_id_index as usize)
-            .ok_or(Error::Internal("missing program id".to_string()))?;
+            .ok_or(Error::Validation("missing program id".to_string()))?;
         if program_id.ne(&solana_sdk::compute_budget::id()) {
-            return Err(Error::Internal(
+            return Err(Error::Validation(
                 "expected instruction to be compute budget".to_string(),
             ));
         }
     }
     // validate that last instruction is the attribution
-    let last = instructions
-        .last()
-        .ok_or(Error::Internal("empty attribution transaction".to_string()))?;
+    let last = instructions.last().ok_or(Error::Validation(
+        "empty attribution transaction".to_string(),
+    ))?;
     let last_program_id = transaction
         .message
         .account_keys
         .get(last.program_id_index as usize)
-        .ok_or(Error::Internal("missing program id".to_string()))?;
+        .ok_or(Error::Validation("missing program id".to_string()))?;
     if last_program_id.ne(&ore_pool_api::id()) {
-        return Err(Error::Internal(
+        return Err(Error::Validation(
             "expected instruction to be pool program".to_string(),
         ));
     }
@@ -49,7 +55,260 @@ pub fn validate_attribution(transaction: &Transaction, total_balance: i64) -> Re
     let args = Attribute::try_from_bytes(data)?;
     let args_total_balance = u64::from_le_bytes(args.total_balance);
     if args_total_balance.ne(&(total_balance as u64)) {
-        return Err(Error::Internal("invalid total balance arg".to_string()));
+        return Err(Error::Validation("invalid total balance arg".to_string()));
     }
     Ok(())
 }
+
+/// Validates a member claim before relaying it,
+/// returning the amount claimed.
+/// The min claim is capped at the member balance, since the whole balance may always be claimed.
+/// The claim must be the last instruction, after compute budget
+/// and associated token account instructions for the beneficiary.
+pub fn validate_claim(
+    transaction: &Transaction,
+    authority: &Pubkey,
+    pool: &Pubkey,
+    min_claim: u64,
+) -> Result<u64, Error> {
+    let message = &transaction.message;
+    let (last, rest) = message
+        .instructions
+        .split_last()
+        .ok_or(Error::Validation("empty claim transaction".to_string()))?;
+    for ix in rest {
+        let program_id = program_id(message, ix)?;
+        if program_id.ne(&solana_sdk::compute_budget::id())
+            && program_id.ne(&spl_associated_token_account::id())
+        {
+            return Err(Error::Validation(
+                "expected instruction to be compute budget or associated token".to_string(),
+            ));
+        }
+    }
+    validate_claim_instruction(message, last, authority, pool, min_claim)
+}
+
+/// Validates a member claim paid for by the operator before signing and relaying it,
+/// returning the amount claimed and the fee transferred to the operator.
+/// The operator must be the fee payer, and the member the only other signer.
+/// The compute budget must be the quoted one, any associated token account created must be
+/// the member's ORE account, and the fee must be transferred by the member
+/// from that account to the fee account in the last instruction, after the claim.
+pub fn validate_fee_claim(
+    transaction: &Transaction,
+    authority: &Pubkey,
+    pool: &Pubkey,
+    min_claim: u64,
+    fee_payer: &Pubkey,
+    fee_account: &Pubkey,
+) -> Result<(u64, u64), Error> {
+    let message = &transaction.message;
+    if message.header.num_required_signatures != claim_fees::NUM_SIGNATURES
+        || message.account_keys.first() != Some(fee_payer)
+        || message.account_keys.get(1) != Some(authority)
+    {
+        return Err(Error::Validation("invalid claim signers".to_string()));
+    }
+    let (transfer, rest) = message
+        .instructions
+        .split_last()
+        .ok_or(Error::Validation("empty claim transaction".to_string()))?;
+    let (claim, rest) = rest
+        .split_last()
+        .ok_or(Error::Validation("missing claim instruction".to_string()))?;
+    let beneficiary = get_associated_token_address(authority, &MINT_ADDRESS);
+    let compute_budget = [
+        ComputeBudgetInstruction::set_compute_unit_limit(claim_fees::CU_LIMIT).data,
+        ComputeBudgetInstruction::set_compute_unit_price(claim_fees::CU_PRICE).data,
+    ];
+    for ix in rest {
+        let program_id = program_id(message, ix)?;
+        let is_valid = if program_id.eq(&solana_sdk::compute_budget::id()) {
+            compute_budget.contains(&ix.data)
+        } else if program_id.eq(&spl_associated_token_account::id()) {
+            // the member's ORE account, created with the rent quoted
+            account(message, ix, 1)?.eq(&beneficiary)
+                && account(message, ix, 2)?.eq(authority)
+                && account(message, ix, 3)?.eq(&MINT_ADDRESS)
+        } else {
+            false
+        };
+        if !is_valid {
+            return Err(Error::Validation(
+                "expected instruction to be the quoted compute budget or member token account"
+                    .to_string(),
+            ));
+        }
+    }
+    let amount = validate_claim_instruction(message, claim, authority, pool, min_claim)?;
+    if account(message, claim, 1)?.ne(&beneficiary) {
+        return Err(Error::Validation("invalid claim beneficiary".to_string()));
+    }
+    // validate the fee transfer from the member
+    if program_id(message, transfer)?.ne(&spl_token::id()) {
+        return Err(Error::Validation(
+            "expected fee transfer instruction".to_string(),
+        ));
+    }
+    let (fee, destination, owner) = match TokenInstruction::unpack(&transfer.data)? {
+        TokenInstruction::Transfer { amount } => (
+            amount,
+            account(message, transfer, 1)?,
+            account(message, transfer, 2)?,
+        ),
+        TokenInstruction::TransferChecked { amount, .. } => {
+            if account(message, transfer, 1)?.ne(&MINT_ADDRESS) {
+                return Err(Error::Validation("invalid fee mint".to_string()));
+            }
+            (
+                amount,
+                account(message, transfer, 2)?,
+                account(message, transfer, 3)?,
+            )
+        }
+        _ => {
+            return Err(Error::Validation(
+                "expected fee transfer instruction".to_string(),
+            ))
+        }
+    };
+    if account(message, transfer, 0)?.ne(&beneficiary)
+        || destination.ne(fee_account)
+        || owner.ne(authority)
+    {
+        return Err(Error::Validation(
+            "invalid fee transfer accounts".to_string(),
+        ));
+    }
+    Ok((amount, fee))
+}
+
+/// Validates the claim instruction, returning the amount claimed.
+fn validate_claim_instruction(
+    message: &Message,
+    ix: &CompiledInstruction,
+    authority: &Pubkey,
+    pool: &Pubkey,
+    min_claim: u64,
+) -> Result<u64, Error> {
+    if program_id(message, ix)?.ne(&ore_pool_api::id()) {
+        return Err(Error::Validation(
+            "expected instruction to be pool program".to_string(),
+        ));
+    }
+    let (tag, data) = ix
+        .data
+        .split_first()
+        .ok_or(Error::Validation("empty claim instruction".to_string()))?;
+    if *tag != PoolInstruction::Claim as u8 {
+        return Err(Error::Validation("expected claim instruction".to_string()));
+    }
+    // validate signer and pool accounts
+    if account(message, ix, 0)?.ne(authority) || account(message, ix, 3)?.ne(pool) {
+        return Err(Error::Validation("invalid claim accounts".to_string()));
+    }
+    // validate claim amount
+    let args = Claim::try_from_bytes(data)
+        .map_err(|_| Error::Validation("invalid claim instruction data".to_string()))?;
+    let amount = u64::from_le_bytes(args.amount);
+    if amount < min_claim {
+        return Err(Error::ClaimBelowMinimum(min_claim));
+    }
+    Ok(amount)
+}
+
+fn program_id<'a>(message: &'a Message, ix: &CompiledInstruction) -> Result<&'a Pubkey, Error> {
+    message
+        .account_keys
+        .get(ix.program_id_index as usize)
+        .ok_or(Error::Validation("missing program id".to_string()))
+}
+
+fn account<'a>(
+    message: &'a Message,
+    ix: &CompiledInstruction,
+    index: usize,
+) -> Result<&'a Pubkey, Error> {
+    ix.accounts
+        .get(index)
+        .and_then(|index| message.account_keys.get(*index as usize))
+        .ok_or(Error::Validation("missing instruction account".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{message::Message, transaction::Transaction};
+
+    use super::*;
+
+    #[test]
+    fn empty_attribution_is_rejected() {
+        let tx = Transaction::new_unsigned(Message::new(&[], None));
+        assert!(validate_attribution(&tx, 0).is_err());
+    }
+
+    #[test]
+    fn claims_below_minimum_are_rejected() {
+        let authority = Pubkey::new_unique();
+        let operator = Pubkey::new_unique();
+        let (pool, bump) = ore_pool_api::state::pool_pda(operator);
+        let claim = |amount| {
+            let ix = ore_pool_api::sdk::claim(authority, authority, pool, bump, amount);
+            Transaction::new_unsigned(Message::new(&[ix], Some(&authority)))
+        };
+        assert_eq!(
+            validate_claim(&claim(100), &authority, &pool, 100).unwrap(),
+            100
+        );
+        assert!(matches!(
+            validate_claim(&claim(99), &authority, &pool, 100),
+            Err(Error::ClaimBelowMinimum(100))
+        ));
+        assert!(validate_claim(&claim(100), &operator, &pool, 0).is_err());
+    }
+
+    #[test]
+    fn fee_claims_pay_the_fee_to_the_operator() {
+        let authority = Pubkey::new_unique();
+        let operator = Pubkey::new_unique();
+        let (pool, bump) = ore_pool_api::state::pool_pda(operator);
+        let beneficiary = get_associated_token_address(&authority, &MINT_ADDRESS);
+        let fee_account = get_associated_token_address(&operator, &MINT_ADDRESS);
+        let claim = |fee_payer: Pubkey, destination: Pubkey, owner: Pubkey| {
+            let ixs = [
+                ComputeBudgetInstruction::set_compute_unit_limit(claim_fees::CU_LIMIT),
+                ComputeBudgetInstruction::set_compute_unit_price(claim_fees::CU_PRICE),
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &fee_payer,
+                    &authority,
+                    &MINT_ADDRESS,
+                    &spl_token::id(),
+                ),
+                ore_pool_api::sdk::claim(authority, beneficiary, pool, bump, 100),
+                spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    &beneficiary,
+                    &destination,
+                    &owner,
+                    &[],
+                    10,
+                )
+                .unwrap(),
+            ];
+            Transaction::new_unsigned(Message::new(&ixs, Some(&fee_payer)))
+        };
+        let validate = |tx: &Transaction| {
+            validate_fee_claim(tx, &authority, &pool, 0, &operator, &fee_account)
+        };
+        assert_eq!(
+            validate(&claim(operator, fee_account, authority)).unwrap(),
+            (100, 10)
+        );
+        // the fee must be paid by the member to the fee account
+        assert!(validate(&claim(operator, beneficiary, authority)).is_err());
+        assert!(validate(&claim(operator, fee_account, operator)).is_err());
+        // and the operator must be the fee payer
+        assert!(validate(&claim(authority, fee_account, authority)).is_err());
+    }
+}
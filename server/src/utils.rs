@@ -13,3 +13,10 @@ pub fn create_cors() -> Cors {
         .allowed_header(header::CONTENT_TYPE)
         .max_age(3600)
 }
+
+pub fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -1,9 +1,23 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::Mutex,
+};
+
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use base64::{prelude::BASE64_STANDARD, Engine};
 use ore_pool_api::event::UnstakeEvent;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    aggregator::Aggregator,
+    database,
+    error::{Context, Error},
+    operator::Operator,
+};
 
-use crate::{aggregator::Aggregator, database, error::Error, operator::Operator};
+/// The max submissions whose rewards are kept, for rewards received before they're awaited.
+const MAX_REWARDS: usize = 64;
 
 const HELIUS_URL: &str = "https://api.helius.xyz";
 const HELIUS_WEBHOOK_API_PATH: &str = "v0/webhooks";
@@ -62,6 +76,8 @@ struct ClientEditSuccess {
 #[derive(serde::Deserialize, Debug)]
 pub struct Event {
     pub meta: EventMeta,
+    #[serde(default)]
+    pub transaction: EventTransaction,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -70,7 +86,12 @@ pub struct EventMeta {
     pub log_messages: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct EventTransaction {
+    pub signatures: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
 pub struct Rewards {
     pub base: u64,
     pub boost_1: Option<ore_api::event::BoostEvent>,
@@ -78,6 +99,72 @@ pub struct Rewards {
     pub boost_3: Option<ore_api::event::BoostEvent>,
 }
 
+/// The rewards of the recent submissions by signature,
+/// as posted by the webhook or streamed from geyser, whichever arrives first,
+/// so that a submission is only ever distributed its own rewards, once.
+pub struct RewardsBySignature {
+    rewards: Mutex<RecentRewards>,
+    /// Bumped on each new rewards.
+    received: tokio::sync::watch::Sender<u64>,
+}
+
+#[derive(Default)]
+struct RecentRewards {
+    by_signature: HashMap<Signature, Rewards>,
+    order: VecDeque<Signature>,
+}
+
+impl RewardsBySignature {
+    pub fn new() -> Self {
+        Self {
+            rewards: Mutex::new(RecentRewards::default()),
+            received: tokio::sync::watch::channel(0).0,
+        }
+    }
+
+    /// Records the rewards of the submission, returning false if already received.
+    pub fn insert(&self, sig: Signature, rewards: Rewards) -> bool {
+        let Ok(mut recent) = self.rewards.lock() else {
+            return false;
+        };
+        if recent.by_signature.contains_key(&sig) {
+            return false;
+        }
+        recent.by_signature.insert(sig, rewards);
+        recent.order.push_back(sig);
+        while recent.order.len() > MAX_REWARDS {
+            if let Some(evicted) = recent.order.pop_front() {
+                recent.by_signature.remove(&evicted);
+            }
+        }
+        drop(recent);
+        self.received.send_modify(|n| *n = n.wrapping_add(1));
+        true
+    }
+
+    /// Resolves with the rewards of the submission, once received.
+    pub async fn wait(&self, sig: &Signature) -> Rewards {
+        let mut rx = self.received.subscribe();
+        loop {
+            if let Some(rewards) = self.get(sig) {
+                return rewards;
+            }
+            // the sender lives as long as self
+            let _ = rx.changed().await;
+        }
+    }
+
+    fn get(&self, sig: &Signature) -> Option<Rewards> {
+        self.rewards.lock().ok()?.by_signature.get(sig).cloned()
+    }
+}
+
+impl Default for RewardsBySignature {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Handle {
     pub fn new() -> Result<Self, Error> {
         let helius_auth_token = helius_auth_token()?;
@@ -98,7 +185,7 @@ impl Handle {
         {
             Ok(_event) => HttpResponse::Ok().finish(),
             Err(err) => {
-                log::error!("{:?}", err);
+                tracing::error!("{:?}", err);
                 let resp: HttpResponse = err.into();
                 resp
             }
@@ -107,15 +194,15 @@ impl Handle {
 
     pub async fn rewards(
         handle: web::Data<Handle>,
-        tx: web::Data<tokio::sync::mpsc::Sender<Rewards>>,
+        rewards: web::Data<RewardsBySignature>,
         req: HttpRequest,
         bytes: web::Bytes,
     ) -> impl Responder {
         let handle = handle.into_inner();
-        match handle.handle_rewards_event(&req, &bytes, tx.as_ref()).await {
+        match handle.handle_rewards_event(&req, &bytes, rewards.as_ref()) {
             Ok(_event) => HttpResponse::Ok().finish(),
             Err(err) => {
-                log::error!("{:?}", err);
+                tracing::error!("{:?}", err);
                 let resp: HttpResponse = err.into();
                 resp
             }
@@ -145,8 +232,8 @@ impl Handle {
     ) -> Result<(), Error> {
         let mut write = aggregator.write().await;
         let stake = &mut write.stake;
-        let stakers = stake.get_mut(&event.mint).ok_or(Error::Internal(format!(
-            "missing staker balances: {}",
+        let stakers = stake.get_mut(&event.mint).ok_or(Error::NotFound(format!(
+            "staker balances of {}",
             event.mint
         )))?;
         if let std::collections::hash_map::Entry::Occupied(ref mut occupied) =
@@ -179,106 +266,78 @@ impl Handle {
         // which sits in the 3rd to last line
         let event = event
             .first()
-            .ok_or(Error::Internal("empty webhook event".to_string()))?;
+            .ok_or(Error::Serialization("empty webhook event".to_string()))?;
         let log_messages = &event.meta.log_messages;
-        let index = log_messages.len().checked_sub(3).ok_or(Error::Internal(
-            "invalid webhook event message index".to_string(),
+        let index = log_messages
+            .len()
+            .checked_sub(3)
+            .ok_or(Error::Serialization(
+                "invalid webhook event message index".to_string(),
+            ))?;
+        let stake_event = log_messages.get(index).ok_or(Error::Serialization(
+            "missing webhook event message".to_string(),
         ))?;
-        let stake_event = log_messages
-            .get(index)
-            .ok_or(Error::Internal("missing webhook event message".to_string()))?;
         let stake_event = stake_event.trim_start_matches("Program data: ");
         let stake_event = BASE64_STANDARD
             .decode(stake_event)
             .map_err(|_| Error::ShareAccountReceived)?;
         let stake_event: &UnstakeEvent = bytemuck::try_from_bytes(stake_event.as_slice())
             .map_err(|_| Error::ShareAccountReceived)?;
-        log::info!("share account webhook event: {:?}", stake_event);
+        tracing::info!("share account webhook event: {:?}", stake_event);
         Ok(*stake_event)
     }
 
-    async fn handle_rewards_event(
+    /// the rewards may have been streamed from geyser already, and are received once.
+    fn handle_rewards_event(
         &self,
         req: &HttpRequest,
         bytes: &web::Bytes,
-        tx: &tokio::sync::mpsc::Sender<Rewards>,
+        rewards_by_signature: &RewardsBySignature,
     ) -> Result<(), Error> {
-        let rewards = self.decode_rewards_event(req, bytes)?;
-        tx.send(rewards).await?;
+        let (sig, rewards) = self.decode_rewards_event(req, bytes)?;
+        if !rewards_by_signature.insert(sig, rewards) {
+            tracing::debug!("rewards of {} already received", sig);
+        }
         Ok(())
     }
 
     /// decodes event that is sent on mine instructions (by listening to the proof account).
-    /// parses logs for base and boost rewards.
+    /// parses logs for base and boost rewards, and the signature of the transaction.
     fn decode_rewards_event(
         &self,
         req: &HttpRequest,
         bytes: &web::Bytes,
-    ) -> Result<Rewards, Error> {
+    ) -> Result<(Signature, Rewards), Error> {
         self.auth(req)?;
         let bytes = bytes.to_vec();
         let event = serde_json::from_slice::<Vec<Event>>(bytes.as_slice())?;
-        log::info!("proof account event: {:?}", event);
+        tracing::info!("proof account event: {:?}", event);
         let event = event
             .first()
-            .ok_or(Error::Internal("empty webhook event".to_string()))?;
-        let log_messages = event.meta.log_messages.as_slice();
-        let base_reward = Self::decode_base_reward(log_messages)?;
-        let boost_reward_1 = Self::decode_boost_reward(log_messages, 8).ok();
-        let boost_reward_2 = Self::decode_boost_reward(log_messages, 9).ok();
-        let boost_reward_3 = Self::decode_boost_reward(log_messages, 10).ok();
-        Ok(Rewards {
-            base: base_reward,
-            boost_1: boost_reward_1,
-            boost_2: boost_reward_2,
-            boost_3: boost_reward_3,
-        })
-    }
-
-    fn decode_base_reward(log_messages: &[String]) -> Result<u64, Error> {
-        let index = log_messages.len().checked_sub(7).ok_or(Error::Internal(
-            "invalid webhook event message index".to_string(),
-        ))?;
-        let base_reward_event = log_messages
-            .get(index)
-            .ok_or(Error::Internal("missing webhook event message".to_string()))?;
-        let base_reward_event = base_reward_event.trim_start_matches("Program log: Base: ");
-        let base_reward_event: u64 = base_reward_event.to_string().parse()?;
-        Ok(base_reward_event)
-    }
-
-    fn decode_boost_reward(
-        log_messages: &[String],
-        index: usize,
-    ) -> Result<ore_api::event::BoostEvent, Error> {
-        let index = log_messages
-            .len()
-            .checked_sub(index)
-            .ok_or(Error::Internal(
-                "invalid webhook event message index".to_string(),
+            .ok_or(Error::Serialization("empty webhook event".to_string()))?;
+        let sig = event
+            .transaction
+            .signatures
+            .first()
+            .ok_or(Error::Serialization(
+                "missing webhook event signature".to_string(),
             ))?;
-        let boost_event = log_messages
-            .get(index)
-            .ok_or(Error::Internal("missing webhook event message".to_string()))?;
-        let boost_event = boost_event.trim_start_matches("Program data: ");
-        let boost_event = BASE64_STANDARD.decode(boost_event)?;
-        let boost_event: &ore_api::event::BoostEvent =
-            bytemuck::try_from_bytes(boost_event.as_slice())
-                .map_err(|err| Error::Internal(err.to_string()))?;
-        log::info!("boost rewards webhook event: {:?}", boost_event);
-        Ok(*boost_event)
+        let sig = Signature::from_str(sig.as_str())
+            .map_err(|err| Error::Serialization(err.to_string()))?;
+        let rewards = decode_rewards(event.meta.log_messages.as_slice())?;
+        Ok((sig, rewards))
     }
 
     /// parse and validate the auth header
     fn auth(&self, req: &HttpRequest) -> Result<(), Error> {
-        let header = req.headers().get("Authorization").ok_or(Error::Internal(
-            "missing auth header in webhook event".to_string(),
-        ))?;
+        let header = req
+            .headers()
+            .get("Authorization")
+            .ok_or(Error::Unauthorized)
+            .context("missing auth header in webhook event")?;
         let header = header.to_str()?;
         if header.to_string().ne(&self.helius_auth_token) {
-            return Err(Error::Internal(
-                "invalid auth header in webhook event".to_string(),
-            ));
+            return Err(Error::Unauthorized.context("invalid auth header in webhook event"));
         }
         Ok(())
     }
@@ -319,15 +378,15 @@ impl Client {
         }
         // edit webhook
         let edit = self.edit(db_stakers).await?;
-        log::info!("edit: {:?}", edit.webhook_id);
+        tracing::info!("edit: {:?}", edit.webhook_id);
         // mark in db
         let db_client = &operator.db_client;
         let conn = db_client.get().await?;
         database::write_webhook_staker(&conn, &entry.share).await?;
         // insert into staker balancers
         let stake = &mut write.stake;
-        let stakers = stake.get_mut(&entry.mint).ok_or(Error::Internal(format!(
-            "missing staker balances: {}",
+        let stakers = stake.get_mut(&entry.mint).ok_or(Error::NotFound(format!(
+            "staker balances of {}",
             entry.mint
         )))?;
         if let std::collections::hash_map::Entry::Vacant(vacant) = stakers.entry(entry.authority) {
@@ -364,6 +423,57 @@ impl Client {
     }
 }
 
+/// parses the logs of a mine transaction for base and boost rewards,
+/// as posted by the webhook or streamed from geyser.
+pub fn decode_rewards(log_messages: &[String]) -> Result<Rewards, Error> {
+    let base_reward = decode_base_reward(log_messages)?;
+    let boost_reward_1 = decode_boost_reward(log_messages, 8).ok();
+    let boost_reward_2 = decode_boost_reward(log_messages, 9).ok();
+    let boost_reward_3 = decode_boost_reward(log_messages, 10).ok();
+    Ok(Rewards {
+        base: base_reward,
+        boost_1: boost_reward_1,
+        boost_2: boost_reward_2,
+        boost_3: boost_reward_3,
+    })
+}
+
+fn decode_base_reward(log_messages: &[String]) -> Result<u64, Error> {
+    let index = log_messages
+        .len()
+        .checked_sub(7)
+        .ok_or(Error::Serialization(
+            "invalid rewards log index".to_string(),
+        ))?;
+    let base_reward_event = log_messages
+        .get(index)
+        .ok_or(Error::Serialization("missing rewards log".to_string()))?;
+    let base_reward_event = base_reward_event.trim_start_matches("Program log: Base: ");
+    let base_reward_event: u64 = base_reward_event.to_string().parse()?;
+    Ok(base_reward_event)
+}
+
+fn decode_boost_reward(
+    log_messages: &[String],
+    index: usize,
+) -> Result<ore_api::event::BoostEvent, Error> {
+    let index = log_messages
+        .len()
+        .checked_sub(index)
+        .ok_or(Error::Serialization(
+            "invalid rewards log index".to_string(),
+        ))?;
+    let boost_event = log_messages
+        .get(index)
+        .ok_or(Error::Serialization("missing rewards log".to_string()))?;
+    let boost_event = boost_event.trim_start_matches("Program data: ");
+    let boost_event = BASE64_STANDARD.decode(boost_event)?;
+    let boost_event: &ore_api::event::BoostEvent = bytemuck::try_from_bytes(boost_event.as_slice())
+        .map_err(|err| Error::Serialization(err.to_string()))?;
+    tracing::info!("boost rewards event: {:?}", boost_event);
+    Ok(*boost_event)
+}
+
 /// this the /webhook path that your server exposes to helius.
 fn helius_webhook_url() -> Result<String, Error> {
     std::env::var("HELIUS_WEBHOOK_URL").map_err(From::from)
@@ -380,3 +490,32 @@ fn helius_auth_token() -> Result<String, Error> {
 fn helius_webhook_id() -> Result<String, Error> {
     std::env::var("HELIUS_WEBHOOK_ID").map_err(From::from)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receives_rewards_once_per_submission() {
+        let rewards = RewardsBySignature::new();
+        let submission = Signature::new_unique();
+        let reward = |base| Rewards {
+            base,
+            boost_1: None,
+            boost_2: None,
+            boost_3: None,
+        };
+        assert!(rewards.get(&submission).is_none());
+        assert!(rewards.insert(submission, reward(1)));
+        // posted by the webhook after being streamed from geyser
+        assert!(!rewards.insert(submission, reward(2)));
+        assert_eq!(rewards.get(&submission).map(|r| r.base), Some(1));
+        // another submission's rewards are never taken for this one
+        assert!(rewards.insert(Signature::new_unique(), reward(3)));
+        assert_eq!(rewards.get(&submission).map(|r| r.base), Some(1));
+        for _ in 0..MAX_REWARDS {
+            rewards.insert(Signature::new_unique(), reward(0));
+        }
+        assert!(rewards.get(&submission).is_none());
+    }
+}
@@ -0,0 +1,34 @@
+/// Lowercase hex of the bytes.
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut acc, byte| {
+        acc.push_str(&format!("{:02x}", byte));
+        acc
+    })
+}
+
+/// The bytes of the hex string, none if it isn't hex.
+pub fn decode(string: &str) -> Option<Vec<u8>> {
+    if string.len() % 2 != 0 {
+        return None;
+    }
+    (0..string.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(string.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let bytes = [0x00, 0x0f, 0xa5, 0xff];
+        assert_eq!(encode(&bytes), "000fa5ff");
+        assert_eq!(decode("000fa5ff"), Some(bytes.to_vec()));
+        assert_eq!(decode("000FA5FF"), Some(bytes.to_vec()));
+        assert_eq!(decode("abc"), None);
+        assert_eq!(decode("zz"), None);
+        assert_eq!(decode("é0"), None);
+    }
+}
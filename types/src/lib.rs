@@ -2,6 +2,10 @@ use drillx::Solution;
 use serde::{Deserialize, Serialize};
 use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction};
 
+pub mod hex;
+pub mod validate;
+pub mod verify;
+
 ///////////////////////////////////////////////////////////////////////////
 /// Request ///////////////////////////////////////////////////////////////
 ///////////////////////////////////////////////////////////////////////////
@@ -9,6 +13,12 @@ use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, transaction::
 pub struct RegisterPayload {
     /// The authority of the member account sending the payload.
     pub authority: Pubkey,
+
+    /// A solution to the registration challenge of the authority,
+    /// at or above its min difficulty.
+    /// Deters mass registration, and may be omitted if the pool doesn't require it.
+    #[serde(default)]
+    pub solution: Option<Solution>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +37,11 @@ pub struct ContributePayload {
 
     /// Must be a valid signature of the solution
     pub signature: Signature,
+
+    /// The name of the worker that found the solution.
+    /// Lets one member run many rigs, each contributing once per challenge.
+    #[serde(default)]
+    pub worker: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -42,6 +57,16 @@ pub struct UpdateBalancePayload {
     pub hash: Hash,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimPayload {
+    /// The authority of the member account sending the payload.
+    pub authority: Pubkey,
+
+    /// The transaction containing the claim instruction,
+    /// signed by the member as fee payer. Relayed by the server as is.
+    pub transaction: Transaction,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RegisterStakerPayload {
     /// The authority of the member account sending the payload.
@@ -51,6 +76,93 @@ pub struct RegisterStakerPayload {
     pub mint: Pubkey,
 }
 
+/// The request to set the notification preferences of a member.
+/// Signed by the member so no one else can redirect its digests.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NotificationPrefsPayload {
+    /// The authority of the member account sending the payload.
+    pub authority: Pubkey,
+
+    /// The preferences to store.
+    pub prefs: NotificationPrefs,
+
+    /// The unix timestamp the payload was signed at,
+    /// stale payloads are rejected so they can't be replayed.
+    pub signed_at: i64,
+
+    /// The signature of the payload bytes by the authority.
+    pub signature: Signature,
+}
+
+impl NotificationPrefsPayload {
+    /// The bytes signed by the member authority.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let target = self.prefs.target.as_deref().unwrap_or_default();
+        let mut bytes = Vec::with_capacity(49 + target.len());
+        bytes.extend_from_slice(self.authority.as_ref());
+        bytes.push(self.prefs.channel as u8);
+        bytes.extend_from_slice(&(target.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(target.as_bytes());
+        bytes.extend_from_slice(&self.signed_at.to_le_bytes());
+        bytes
+    }
+
+    /// Whether the payload is signed by the authority.
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(self.authority.as_ref(), self.to_bytes().as_slice())
+    }
+}
+
+/// The request to adjust the runtime config of the server, from the admin cli.
+/// Fields left unset are unchanged.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateConfigPayload {
+    /// The operator commission in % percentage.
+    pub operator_commission: Option<u64>,
+
+    /// The staker commission in % percentage.
+    pub staker_commission: Option<u64>,
+
+    /// The minimum difficulty accepted by the pool,
+    /// raised to the program min if lower. Zero defers to the program min.
+    pub min_difficulty: Option<u64>,
+}
+
+/// The query of the /admin/audit-log request.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditLogQuery {
+    /// The max number of entries returned, most recent first.
+    pub limit: Option<i64>,
+
+    /// Only entries older than this id, for paging.
+    pub before: Option<i64>,
+}
+
+/// The query of the /admin/distribution/preview request.
+/// Fields left unset default to the last on-chain reward and the runtime config.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DistributionPreviewQuery {
+    /// The base reward to split, in grains.
+    pub reward: Option<u64>,
+
+    /// The operator commission in % percentage.
+    pub operator_commission: Option<u64>,
+
+    /// The staker commission in % percentage.
+    pub staker_commission: Option<u64>,
+}
+
+/// The query of the /claim/quote request.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimQuoteQuery {
+    /// The authority of the member claiming.
+    pub authority: String,
+
+    /// The amount to claim, in grains.
+    pub amount: u64,
+}
+
 ///////////////////////////////////////////////////////////////////////////
 /// Response //////////////////////////////////////////////////////////////
 ///////////////////////////////////////////////////////////////////////////
@@ -105,6 +217,11 @@ pub struct Member {
 
     /// Whether or not this member's on-chain balance is in sync with the operator db balance.
     pub is_synced: bool,
+
+    /// The manual debits not yet recovered from the earnings of this member.
+    /// Recovered from future earnings, as the on-chain balance never decrements.
+    #[serde(default)]
+    pub debit: i64,
 }
 
 /// The staker record that sits in the operator database
@@ -124,7 +241,8 @@ pub struct Staker {
 }
 
 /// The response from the /challenge request.
-#[derive(Debug, Serialize, Deserialize)]
+/// Also the data of each event from the /challenge-stream request.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct MemberChallenge {
     /// The challenge to mine for.
     pub challenge: Challenge,
@@ -137,6 +255,57 @@ pub struct MemberChallenge {
     pub num_total_members: u64,
 }
 
+/// The stats of a member's worker, from the /member/{authority}/workers request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkerStats {
+    /// The name the worker contributes with.
+    pub name: String,
+
+    /// The number of contributions accepted from this worker.
+    pub shares: u64,
+
+    /// The total difficulty score of the contributions accepted from this worker.
+    pub total_score: u64,
+
+    /// The unix timestamp of the last accepted contribution.
+    pub last_share_at: i64,
+
+    /// The hashrate estimated from the scores of recent contributions, in hashes per second.
+    pub hashrate: u64,
+}
+
+/// The member record with its recent hashrate, from the /member/{authority} request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemberInfo {
+    #[serde(flatten)]
+    pub member: Member,
+
+    /// The time-weighted hashrate and acceptance of the member
+    /// over the last 10 minutes, hour, and day.
+    pub hashrate: Vec<HashrateWindow>,
+}
+
+/// The hashrate of a member over a trailing window,
+/// from the shares of the rounds submitted within it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HashrateWindow {
+    /// The length of the window in seconds.
+    pub window: i64,
+
+    /// The hashrate averaged over the window, in hashes per second.
+    pub hashrate: u64,
+
+    /// The number of shares accepted in the window.
+    pub accepted: u64,
+
+    /// The number of shares rejected in the window,
+    /// as below the min difficulty, invalid, or duplicate.
+    pub rejected: u64,
+
+    /// The ratio of the shares accepted, none if there were none.
+    pub acceptance_ratio: Option<f64>,
+}
+
 /// The response from the update-balance request.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BalanceUpdate {
@@ -146,3 +315,656 @@ pub struct BalanceUpdate {
     /// The transaction signature.
     pub signature: Signature,
 }
+
+/// The reconciliation of the pool proof against the db balances,
+/// from the /admin/treasury request.
+///
+/// Every reward lands in the proof and is attributed to the db balances of members,
+/// the operator commission included, so the lifetime rewards of the proof
+/// should match the sum of the db balances, less the rounding dust of each round.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TreasuryReport {
+    /// The unclaimed balance of the pool proof.
+    pub proof_balance: u64,
+
+    /// The lifetime rewards of the pool proof.
+    pub proof_total_rewards: u64,
+
+    /// The lifetime rewards claimed from the proof.
+    pub claimed: u64,
+
+    /// The lifetime balances of members in the db, excluding the operator commission.
+    pub member_balances: u64,
+
+    /// The lifetime operator commission in the db.
+    pub commission: u64,
+
+    /// The proof lifetime rewards less the db balances.
+    /// Positive if rewards are unattributed, negative if members are owed more than was earned.
+    pub drift: i64,
+
+    /// The net manual adjustments applied to the db balances, excluded from the drift.
+    #[serde(default)]
+    pub adjustments: i64,
+
+    /// Whether the drift is beyond the rounding dust tolerated.
+    pub is_drifting: bool,
+
+    /// The rounds where the drift changed beyond the tolerance, oldest first.
+    /// The first is where the divergence began.
+    pub drift_rounds: Vec<RoundDrift>,
+}
+
+/// The rpc usage of the server over the current utc day, from the /admin/rpc-usage request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcUsageReport {
+    /// The unix timestamp the day started at.
+    pub day_start: i64,
+
+    /// The rpc requests sent.
+    pub requests: u64,
+
+    /// The estimated credits used.
+    pub credits: u64,
+
+    /// The daily budget of credits, if configured.
+    pub daily_budget: Option<u64>,
+
+    /// The usage by call, the most credits first.
+    pub calls: Vec<RpcCallUsage>,
+}
+
+/// The rpc usage of one call.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcCallUsage {
+    /// The rpc call, e.g. get_account_data.
+    pub call: String,
+
+    /// The requests sent.
+    pub requests: u64,
+
+    /// The estimated credits used.
+    pub credits: u64,
+}
+
+/// A round where the drift between the proof and the db balances changed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoundDrift {
+    /// The base58 challenge of the round.
+    pub challenge: String,
+
+    /// The unix timestamp the round was recorded at.
+    pub created_at: i64,
+
+    /// The base reward of the round.
+    pub reward: u64,
+
+    /// The drift after the round.
+    pub drift: i64,
+
+    /// The change in drift since the previous round.
+    pub change: i64,
+}
+
+/// The number of accepted shares at a difficulty.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DifficultyBucket {
+    pub difficulty: u32,
+
+    pub count: u64,
+}
+
+/// The distribution of the difficulties of accepted shares.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DifficultyHistogram {
+    /// The non-empty buckets, by ascending difficulty.
+    pub buckets: Vec<DifficultyBucket>,
+
+    /// The number of shares across all buckets.
+    pub shares: u64,
+
+    /// The mean difficulty of the shares.
+    pub mean: f64,
+
+    /// The fraction of the shares in the most common bucket.
+    pub mode_share: f64,
+
+    /// Whether the shares are suspiciously uniform,
+    /// as the best hash of honest mining varies from round to round.
+    pub is_uniform: bool,
+}
+
+/// The share quality of the pool, from the /stats/difficulty request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DifficultyStats {
+    /// The challenge of the current round.
+    pub challenge: [u8; 32],
+
+    /// The min difficulty accepted in the current round.
+    pub min_difficulty: u64,
+
+    /// The difficulties of the shares accepted in the current round.
+    pub round: DifficultyHistogram,
+
+    /// The members whose shares since the server started are suspiciously uniform.
+    pub uniform_members: Vec<Pubkey>,
+}
+
+/// The response from the claim request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimReceipt {
+    /// The amount claimed.
+    pub amount: u64,
+
+    /// The transaction signature.
+    pub signature: Signature,
+
+    /// The fee deducted from the amount claimed, in grains,
+    /// zero unless the operator paid the transaction fee.
+    #[serde(default)]
+    pub fee: u64,
+}
+
+/// The fees of a claim whose transaction fee is paid by the operator,
+/// and how to build its transaction, from the /claim/quote request.
+///
+/// The transaction is paid for by the fee payer, and must set exactly the compute unit limit and price.
+/// The member transfers the fee in ORE from its token account to the fee account,
+/// in an instruction after the claim.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimQuote {
+    /// The amount claimed, in grains.
+    pub amount: u64,
+
+    /// The transaction fee paid by the operator, in lamports,
+    /// including the rent of the member token account if it's created by the claim.
+    pub network_fee: u64,
+
+    /// The network fee in grains, at the rate the pool converts at.
+    pub network_fee_grains: u64,
+
+    /// The service fee of the pool, in grains.
+    pub service_fee: u64,
+
+    /// The total fee transferred to the fee account, in grains.
+    pub fee: u64,
+
+    /// The amount the member receives, net of the fee, in grains.
+    pub net_amount: u64,
+
+    /// The operator, who pays for the transaction.
+    pub fee_payer: Pubkey,
+
+    /// The ORE token account of the operator the fee is transferred to.
+    pub fee_account: Pubkey,
+
+    /// The compute unit limit the transaction must set.
+    pub compute_unit_limit: u32,
+
+    /// The compute unit price the transaction must set, in micro lamports.
+    pub compute_unit_price: u64,
+}
+
+/// How a member is sent its daily digest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum NotificationChannel {
+    /// Not notified.
+    #[default]
+    None = 0,
+
+    /// Posted as json to a url of the member.
+    Webhook = 1,
+
+    /// Emailed through the smtp relay of the pool.
+    Email = 2,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::None => "none",
+            NotificationChannel::Webhook => "webhook",
+            NotificationChannel::Email => "email",
+        }
+    }
+
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "none" => Some(NotificationChannel::None),
+            "webhook" => Some(NotificationChannel::Webhook),
+            "email" => Some(NotificationChannel::Email),
+            _ => None,
+        }
+    }
+}
+
+/// The notification preferences of a member.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPrefs {
+    /// How the member is notified.
+    pub channel: NotificationChannel,
+
+    /// The webhook url or email address, unset for no notifications.
+    pub target: Option<String>,
+}
+
+/// The daily summary of a member's earnings and settled claims,
+/// posted to its webhook or rendered to its email.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationDigest {
+    /// The authority of the member.
+    pub authority: Pubkey,
+
+    /// The unix timestamp the period starts at, inclusive.
+    pub period_start: i64,
+
+    /// The unix timestamp the period ends at, exclusive.
+    pub period_end: i64,
+
+    /// The grains attributed to the member in the period.
+    pub earned: u64,
+
+    /// The member balance in the db, as of the digest.
+    pub balance: u64,
+
+    /// The claims relayed by the pool and confirmed in the period.
+    pub claims: Vec<ClaimReceipt>,
+}
+
+/// The runtime config of the server, from the /admin/config request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// The operator commission in % percentage.
+    pub operator_commission: u64,
+
+    /// The staker commission in % percentage.
+    pub staker_commission: u64,
+
+    /// The minimum difficulty accepted by the pool,
+    /// zero if the program min is used.
+    pub min_difficulty: u64,
+}
+
+/// The result of reconciling the operator db against on-chain member accounts,
+/// from the /admin/reconcile request.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// The number of db members checked.
+    pub checked: u64,
+
+    /// The members whose db balance was ahead of the chain but marked as synced,
+    /// now marked as unsynced so the next attribution lands their balance.
+    pub resynced: Vec<String>,
+
+    /// The members in the db without an on-chain account.
+    pub missing_onchain: Vec<String>,
+
+    /// The members whose on-chain balance is ahead of the db,
+    /// which should never happen and needs manual review.
+    pub ahead_onchain: Vec<String>,
+}
+
+/// A recorded admin action, from the /admin/audit-log request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+
+    /// The name the admin cli identified as.
+    pub actor: String,
+
+    /// The address the admin request came from.
+    pub remote_addr: Option<String>,
+
+    /// The admin action, e.g. `update_config`.
+    pub action: String,
+
+    /// The json of the value before the action, if any.
+    pub old_value: Option<String>,
+
+    /// The json of the value after the action, if any.
+    pub new_value: Option<String>,
+
+    /// The unix timestamp of the action.
+    pub created_at: i64,
+}
+
+/// The request to issue an api key for read access, from the admin cli.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IssueApiKeyPayload {
+    /// Who the key is issued to, e.g. the name of a dashboard.
+    pub name: String,
+
+    /// The max requests per minute, the server default if unset.
+    pub rate_limit: Option<u32>,
+}
+
+/// The request to manually credit or debit the balance of a member, from the admin cli,
+/// signed by an admin key.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdjustBalancePayload {
+    /// The authority of the member.
+    pub authority: Pubkey,
+
+    /// The amount in grains, positive to credit, negative to debit.
+    pub amount: i64,
+
+    /// Why the balance is adjusted, shown to the member, e.g. the incident compensated.
+    pub reason: String,
+
+    /// The unix timestamp the adjustment was signed at.
+    pub signed_at: i64,
+
+    /// The admin key that signed the adjustment.
+    pub signer: Pubkey,
+
+    /// The signature of the adjustment bytes by the admin key.
+    pub signature: Signature,
+}
+
+/// A manual adjustment of the balance of a member,
+/// from the /admin/adjustments and /member/{authority}/adjustments requests.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceAdjustment {
+    pub id: i64,
+
+    /// The authority of the member.
+    pub authority: String,
+
+    /// The amount in grains, positive for a credit, negative for a debit.
+    pub amount: i64,
+
+    /// Why the balance was adjusted.
+    pub reason: String,
+
+    /// The unix timestamp the adjustment was applied at.
+    pub created_at: i64,
+}
+
+/// An api key for read access, from the /admin/api-keys requests.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: i64,
+
+    /// Who the key is issued to.
+    pub name: String,
+
+    /// The max requests per minute.
+    pub rate_limit: u32,
+
+    /// The unix timestamp the key was issued at.
+    pub created_at: i64,
+
+    /// The unix timestamp the key was revoked at, if revoked.
+    pub revoked_at: Option<i64>,
+
+    /// The key itself, only returned when issued, as only its hash is stored.
+    pub key: Option<String>,
+}
+
+/// The proof-of-work challenge a new member must solve to register,
+/// from the /register/challenge/{authority} request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterChallenge {
+    /// The challenge to hash with drillx, derived from the authority.
+    pub challenge: [u8; 32],
+
+    /// The min difficulty of the solution, zero if registration isn't gated.
+    pub min_difficulty: u64,
+
+    /// The unix timestamp after which the challenge is no longer accepted.
+    pub expires_at: i64,
+}
+
+/// A share accepted in a submitted round, archived with its solution
+/// so that anyone can re-verify it against the round challenge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedShare {
+    /// The authority of the member who submitted the share.
+    pub authority: Pubkey,
+
+    /// The named worker of the member who found the share.
+    pub worker: String,
+
+    /// The drillx digest of the solution.
+    pub digest: [u8; 16],
+
+    /// The nonce of the solution, little-endian.
+    pub nonce: [u8; 8],
+
+    /// The difficulty score the operator credited the share with.
+    pub score: u64,
+}
+
+/// An archived share re-verified against the round challenge.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifiedShare {
+    #[serde(flatten)]
+    pub share: ArchivedShare,
+
+    /// The difficulty of the solution, none if its digest is invalid.
+    pub difficulty: Option<u32>,
+
+    /// The score recomputed from the difficulty, zero if the digest is invalid.
+    pub verified_score: u64,
+}
+
+/// The archived shares of a member in a round re-verified against the round challenge,
+/// from the /verify/{round}/{member} request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareVerification {
+    /// The challenge of the round.
+    pub challenge: Hash,
+
+    pub shares: Vec<VerifiedShare>,
+
+    /// The score the operator credited the member with in the round.
+    pub reported_score: u64,
+
+    /// The score recomputed from the shares with a valid digest.
+    pub verified_score: u64,
+
+    /// Whether every share has a valid digest and was credited the score of its difficulty.
+    pub is_valid: bool,
+}
+
+/// The metadata a pool publishes to a pool directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolListing {
+    /// The pubkey address of the pool pda.
+    pub pool_address: Pubkey,
+
+    /// The url members connect to.
+    pub url: String,
+
+    /// The operator commission in % percentage.
+    pub operator_commission: u64,
+
+    /// The staker commission in % percentage.
+    pub staker_commission: u64,
+
+    /// The total number of members of the pool.
+    pub num_members: u64,
+
+    /// The hashrate estimated from the scores of recent contributions, in hashes per second.
+    pub hashrate: u64,
+
+    /// The unix timestamp the listing was published at.
+    pub published_at: i64,
+}
+
+/// A pool listing signed by the pool authority,
+/// so directories and members can check it was published by the operator.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedPoolListing {
+    pub listing: PoolListing,
+
+    /// The authority of the pool.
+    pub authority: Pubkey,
+
+    /// The signature of the listing bytes by the authority.
+    pub signature: Signature,
+}
+
+/// The payout of a member in a distributed round, from the /member/{authority}/payouts request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PayoutStatement {
+    /// The pubkey address of the pool pda.
+    pub pool_address: Pubkey,
+
+    /// The challenge of the round, which identifies it.
+    pub challenge: Hash,
+
+    /// The last hash at of the pool during the round.
+    pub last_hash_at: i64,
+
+    /// The authority of the member.
+    pub member: Pubkey,
+
+    /// The score the member was credited with, weighted as in the distribution.
+    pub score: u64,
+
+    /// The total weighted score of the members in the round.
+    pub total_score: u64,
+
+    /// The share of the member of the miner rewards in % percentage.
+    pub share: f64,
+
+    /// The miner rewards paid to the member, net of any outstanding debit recovered from them.
+    pub amount: u64,
+
+    /// The miner rewards of the round split among the members.
+    pub total_amount: u64,
+
+    /// The signature of the landed submission, none if it wasn't recorded.
+    pub submission: Option<Signature>,
+
+    /// The unix timestamp the round was distributed at.
+    pub distributed_at: i64,
+}
+
+/// A payout statement signed by the pool authority,
+/// so auditors can check the payouts of a pool from the exported statements alone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedPayoutStatement {
+    pub statement: PayoutStatement,
+
+    /// The authority of the pool.
+    pub authority: Pubkey,
+
+    /// The signature of the statement bytes by the authority.
+    pub signature: Signature,
+}
+
+/// The payout split of the current round if it were submitted now,
+/// from the /admin/distribution/preview request. Nothing is committed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DistributionPreview {
+    /// The challenge of the current round.
+    pub challenge: [u8; 32],
+
+    /// The base reward split, in grains.
+    pub reward: u64,
+
+    /// The operator commission in % percentage.
+    pub operator_commission: u64,
+
+    /// The staker commission in % percentage.
+    pub staker_commission: u64,
+
+    /// The total difficulty score of the round.
+    pub total_score: u64,
+
+    /// The share of each contribution.
+    pub shares: Vec<PreviewShare>,
+
+    /// The operator commission of the reward, in grains.
+    pub operator: u64,
+
+    /// The grains of the miner rewards lost to rounding.
+    pub dust: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreviewShare {
+    /// The authority of the member who contributed.
+    pub member: Pubkey,
+
+    /// The named worker of the member who found the solution.
+    pub worker: String,
+
+    /// The difficulty score of the solution.
+    pub score: u64,
+
+    /// The payout weight of the solution, its score unless score decay is enabled.
+    pub weight: u64,
+
+    /// The grains paid to the member for the solution.
+    pub amount: u64,
+}
+
+/// A portable snapshot of the pool state, from the /admin/backup request.
+/// Also the payload of the /admin/restore request, to migrate the pool to a fresh host.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    /// The version of the backup format.
+    pub version: u8,
+
+    /// The unix timestamp the backup was taken at.
+    pub created_at: i64,
+
+    /// The pool the backup was taken from.
+    pub pool_address: Pubkey,
+
+    /// The member records, including balances not yet attributed on-chain.
+    pub members: Vec<Member>,
+
+    /// The staker records.
+    pub stakers: Vec<Staker>,
+
+    /// The round in flight when the backup was taken.
+    pub round: RoundBackup,
+}
+
+/// The contributions aggregated for a challenge, not yet submitted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoundBackup {
+    /// The challenge the contributions are for.
+    pub challenge: Challenge,
+
+    /// The contributions aggregated so far.
+    pub contributions: Vec<ContributionBackup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContributionBackup {
+    /// The authority of the member who contributed.
+    pub member: Pubkey,
+
+    /// The named worker of the member who found the solution.
+    pub worker: String,
+
+    /// The difficulty score of the solution.
+    pub score: u64,
+
+    /// The solution contributed.
+    pub solution: Solution,
+}
+
+/// The result of restoring a backup, from the /admin/restore request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// The number of members restored.
+    pub members: u64,
+
+    /// The number of stakers restored.
+    pub stakers: u64,
+
+    /// The number of contributions restored into the current round,
+    /// zero if the backed up round is no longer current.
+    pub contributions: u64,
+
+    /// Whether the backed up round is still current, and was restored.
+    pub round_restored: bool,
+}
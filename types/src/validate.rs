@@ -0,0 +1,409 @@
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    AdjustBalancePayload, ContributePayload, GetMemberPayload, IssueApiKeyPayload,
+    NotificationChannel, NotificationPrefs, UpdateConfigPayload,
+};
+
+/// The maximum length of a worker name in bytes.
+pub const MAX_WORKER_NAME_LEN: usize = 32;
+
+/// The maximum length of a base58 encoded pubkey.
+const MAX_PUBKEY_LEN: usize = 44;
+
+/// The maximum length of a webhook url or email address.
+const MAX_NOTIFICATION_TARGET_LEN: usize = 254;
+
+/// The maximum length of the name an api key is issued to.
+const MAX_API_KEY_NAME_LEN: usize = 64;
+
+/// The maximum length of the reason for a balance adjustment.
+const MAX_ADJUSTMENT_REASON_LEN: usize = 256;
+
+/// The maximum commission in % percentage.
+const MAX_COMMISSION: u64 = 100;
+
+/// Why a decoded payload was rejected.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("worker name is empty")]
+    WorkerNameEmpty,
+    #[error("worker name longer than {MAX_WORKER_NAME_LEN} bytes")]
+    WorkerNameTooLong,
+    #[error("worker name contains control characters")]
+    WorkerNameInvalid,
+    #[error("invalid pubkey")]
+    InvalidPubkey,
+    #[error("commission above {MAX_COMMISSION}%")]
+    CommissionTooHigh,
+    #[error("invalid member id")]
+    InvalidMemberId,
+    #[error("nonce out of range")]
+    NonceOutOfRange,
+    #[error("notification target is set without a channel")]
+    UnexpectedNotificationTarget,
+    #[error("invalid webhook url")]
+    InvalidWebhookUrl,
+    #[error("invalid email address")]
+    InvalidEmail,
+    #[error("api key name is empty, too long, or contains control characters")]
+    InvalidApiKeyName,
+    #[error("rate limit must be at least one request per minute")]
+    InvalidRateLimit,
+    #[error("adjustment amount is zero")]
+    InvalidAdjustmentAmount,
+    #[error("adjustment reason is empty, too long, or contains control characters")]
+    InvalidAdjustmentReason,
+}
+
+impl ContributePayload {
+    /// Validates the fields serde can't, the solution and signature are fixed length.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match &self.worker {
+            Some(worker) => worker_name(worker),
+            None => Ok(()),
+        }
+    }
+}
+
+impl GetMemberPayload {
+    /// The authority parsed from its base58 string.
+    pub fn authority(&self) -> Result<Pubkey, ValidationError> {
+        pubkey(self.authority.as_str())
+    }
+}
+
+impl UpdateConfigPayload {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let commissions = [self.operator_commission, self.staker_commission];
+        if commissions
+            .into_iter()
+            .flatten()
+            .any(|c| c > MAX_COMMISSION)
+        {
+            return Err(ValidationError::CommissionTooHigh);
+        }
+        Ok(())
+    }
+}
+
+impl IssueApiKeyPayload {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let name = self.name.as_str();
+        if name.trim().is_empty()
+            || name.len() > MAX_API_KEY_NAME_LEN
+            || name.chars().any(char::is_control)
+        {
+            return Err(ValidationError::InvalidApiKeyName);
+        }
+        if self.rate_limit == Some(0) {
+            return Err(ValidationError::InvalidRateLimit);
+        }
+        Ok(())
+    }
+}
+
+impl AdjustBalancePayload {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.amount == 0 || self.amount == i64::MIN {
+            return Err(ValidationError::InvalidAdjustmentAmount);
+        }
+        let reason = self.reason.as_str();
+        if reason.trim().is_empty()
+            || reason.len() > MAX_ADJUSTMENT_REASON_LEN
+            || reason.chars().any(char::is_control)
+        {
+            return Err(ValidationError::InvalidAdjustmentReason);
+        }
+        Ok(())
+    }
+}
+
+impl NotificationPrefs {
+    /// Validates the target matches the channel.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let target = self.target.as_deref();
+        if target.is_some_and(|t| {
+            t.len() > MAX_NOTIFICATION_TARGET_LEN || t.chars().any(|c| c.is_whitespace())
+        }) {
+            return match self.channel {
+                NotificationChannel::Email => Err(ValidationError::InvalidEmail),
+                _ => Err(ValidationError::InvalidWebhookUrl),
+            };
+        }
+        match (self.channel, target) {
+            (NotificationChannel::None, None) => Ok(()),
+            (NotificationChannel::None, Some(_)) => {
+                Err(ValidationError::UnexpectedNotificationTarget)
+            }
+            // https only, the server also checks the host resolves to a public address
+            (NotificationChannel::Webhook, Some(url)) if url.starts_with("https://") => Ok(()),
+            (NotificationChannel::Webhook, _) => Err(ValidationError::InvalidWebhookUrl),
+            (NotificationChannel::Email, Some(email)) => match email.split_once('@') {
+                Some((local, domain))
+                    if !local.is_empty() && domain.contains('.') && !domain.contains('@') =>
+                {
+                    Ok(())
+                }
+                _ => Err(ValidationError::InvalidEmail),
+            },
+            (NotificationChannel::Email, None) => Err(ValidationError::InvalidEmail),
+        }
+    }
+}
+
+/// Validates the name a worker contributes with.
+pub fn worker_name(name: &str) -> Result<(), ValidationError> {
+    if name.is_empty() {
+        return Err(ValidationError::WorkerNameEmpty);
+    }
+    if name.len() > MAX_WORKER_NAME_LEN {
+        return Err(ValidationError::WorkerNameTooLong);
+    }
+    if name.chars().any(char::is_control) {
+        return Err(ValidationError::WorkerNameInvalid);
+    }
+    Ok(())
+}
+
+/// Parses a base58 pubkey, rejecting oversized strings before decoding.
+pub fn pubkey(str: &str) -> Result<Pubkey, ValidationError> {
+    if str.len() > MAX_PUBKEY_LEN {
+        return Err(ValidationError::InvalidPubkey);
+    }
+    Pubkey::from_str(str).map_err(|_| ValidationError::InvalidPubkey)
+}
+
+/// Validates the nonce is in the slice of the nonce space assigned to the member.
+/// Any nonce is valid if the member count is unknown.
+pub fn nonce(nonce: u64, member_id: i64, num_members: u64) -> Result<(), ValidationError> {
+    if num_members == 0 {
+        return Ok(());
+    }
+    let index = u64::try_from(member_id).map_err(|_| ValidationError::InvalidMemberId)?;
+    let unit = u64::MAX / num_members;
+    let left_bound = unit.saturating_mul(index);
+    let right_bound = unit.saturating_mul(index.saturating_add(1));
+    if (left_bound..=right_bound).contains(&nonce) {
+        Ok(())
+    } else {
+        Err(ValidationError::NonceOutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+    use proptest::prelude::*;
+    use solana_sdk::signature::Signature;
+
+    use super::*;
+    use crate::{
+        AuditLogQuery, Backup, RegisterPayload, RegisterStakerPayload, UpdateBalancePayload,
+    };
+
+    fn contribute_payload(worker: Option<String>) -> ContributePayload {
+        ContributePayload {
+            authority: Pubkey::new_unique(),
+            solution: Solution::new([1; 16], [2; 8]),
+            signature: Signature::default(),
+            worker,
+        }
+    }
+
+    #[test]
+    fn worker_names() {
+        assert!(worker_name("rig-2").is_ok());
+        assert!(worker_name(&"a".repeat(MAX_WORKER_NAME_LEN)).is_ok());
+        assert_eq!(worker_name(""), Err(ValidationError::WorkerNameEmpty));
+        assert_eq!(
+            worker_name(&"a".repeat(MAX_WORKER_NAME_LEN + 1)),
+            Err(ValidationError::WorkerNameTooLong)
+        );
+        assert_eq!(
+            worker_name("rig\n2"),
+            Err(ValidationError::WorkerNameInvalid)
+        );
+        assert!(contribute_payload(None).validate().is_ok());
+    }
+
+    #[test]
+    fn api_key_payloads() {
+        let payload = |name: &str, rate_limit| IssueApiKeyPayload {
+            name: name.to_string(),
+            rate_limit,
+        };
+        assert!(payload("dashboard", None).validate().is_ok());
+        assert!(payload("dashboard", Some(60)).validate().is_ok());
+        assert_eq!(
+            payload(" ", None).validate(),
+            Err(ValidationError::InvalidApiKeyName)
+        );
+        assert_eq!(
+            payload(&"a".repeat(MAX_API_KEY_NAME_LEN + 1), None).validate(),
+            Err(ValidationError::InvalidApiKeyName)
+        );
+        assert_eq!(
+            payload("dashboard", Some(0)).validate(),
+            Err(ValidationError::InvalidRateLimit)
+        );
+    }
+
+    #[test]
+    fn adjustment_payloads() {
+        let payload = |amount: i64, reason: &str| AdjustBalancePayload {
+            authority: Pubkey::new_unique(),
+            amount,
+            reason: reason.to_string(),
+            signed_at: 0,
+            signer: Pubkey::new_unique(),
+            signature: Signature::default(),
+        };
+        assert!(payload(1_000, "outage compensation").validate().is_ok());
+        assert!(payload(-1_000, "double payout").validate().is_ok());
+        assert_eq!(
+            payload(0, "outage compensation").validate(),
+            Err(ValidationError::InvalidAdjustmentAmount)
+        );
+        assert_eq!(
+            payload(1_000, " ").validate(),
+            Err(ValidationError::InvalidAdjustmentReason)
+        );
+        assert_eq!(
+            payload(1_000, &"a".repeat(MAX_ADJUSTMENT_REASON_LEN + 1)).validate(),
+            Err(ValidationError::InvalidAdjustmentReason)
+        );
+    }
+
+    #[test]
+    fn commissions() {
+        let payload = UpdateConfigPayload {
+            operator_commission: Some(100),
+            ..Default::default()
+        };
+        assert!(payload.validate().is_ok());
+        let payload = UpdateConfigPayload {
+            staker_commission: Some(101),
+            ..Default::default()
+        };
+        assert_eq!(payload.validate(), Err(ValidationError::CommissionTooHigh));
+    }
+
+    #[test]
+    fn notification_prefs() {
+        let prefs = |channel, target: Option<&str>| NotificationPrefs {
+            channel,
+            target: target.map(str::to_string),
+        };
+        assert!(prefs(NotificationChannel::None, None).validate().is_ok());
+        assert!(prefs(NotificationChannel::Email, Some("miner@example.com"))
+            .validate()
+            .is_ok());
+        assert!(prefs(
+            NotificationChannel::Webhook,
+            Some("https://example.com/ore")
+        )
+        .validate()
+        .is_ok());
+        assert_eq!(
+            prefs(NotificationChannel::None, Some("miner@example.com")).validate(),
+            Err(ValidationError::UnexpectedNotificationTarget)
+        );
+        assert_eq!(
+            prefs(NotificationChannel::Email, Some("miner@localhost")).validate(),
+            Err(ValidationError::InvalidEmail)
+        );
+        assert_eq!(
+            prefs(NotificationChannel::Email, Some("miner @example.com")).validate(),
+            Err(ValidationError::InvalidEmail)
+        );
+        assert_eq!(
+            prefs(NotificationChannel::Webhook, Some("ftp://example.com")).validate(),
+            Err(ValidationError::InvalidWebhookUrl)
+        );
+        assert_eq!(
+            prefs(NotificationChannel::Webhook, Some("http://example.com/ore")).validate(),
+            Err(ValidationError::InvalidWebhookUrl)
+        );
+        assert_eq!(
+            prefs(NotificationChannel::Webhook, None).validate(),
+            Err(ValidationError::InvalidWebhookUrl)
+        );
+    }
+
+    #[test]
+    fn nonce_bounds() {
+        let unit = u64::MAX / 4;
+        assert!(nonce(0, 0, 4).is_ok());
+        assert!(nonce(unit, 0, 4).is_ok());
+        assert!(nonce(unit + 1, 1, 4).is_ok());
+        assert_eq!(
+            nonce(unit * 2 + 1, 0, 4),
+            Err(ValidationError::NonceOutOfRange)
+        );
+        assert_eq!(nonce(0, -1, 4), Err(ValidationError::InvalidMemberId));
+        assert!(nonce(u64::MAX, i64::MAX, 4).is_ok());
+        assert!(nonce(u64::MAX, 0, 0).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn decoding_arbitrary_bytes_never_panics(
+            bytes in proptest::collection::vec(any::<u8>(), 0..512),
+        ) {
+            let _ = serde_json::from_slice::<RegisterPayload>(&bytes);
+            let _ = serde_json::from_slice::<RegisterStakerPayload>(&bytes);
+            let _ = serde_json::from_slice::<UpdateBalancePayload>(&bytes);
+            let _ = serde_json::from_slice::<UpdateConfigPayload>(&bytes);
+            let _ = serde_json::from_slice::<AuditLogQuery>(&bytes);
+            let _ = serde_json::from_slice::<Backup>(&bytes);
+            if let Ok(payload) = serde_json::from_slice::<ContributePayload>(&bytes) {
+                let _ = payload.validate();
+            }
+        }
+
+        #[test]
+        fn contribute_payload_roundtrips(
+            d in any::<[u8; 16]>(),
+            n in any::<[u8; 8]>(),
+            worker in proptest::option::of(".{0,40}"),
+        ) {
+            let mut payload = contribute_payload(worker);
+            payload.solution = Solution::new(d, n);
+            let json = serde_json::to_vec(&payload).unwrap();
+            let decoded: ContributePayload = serde_json::from_slice(&json).unwrap();
+            prop_assert_eq!(decoded.solution.d, d);
+            prop_assert_eq!(decoded.solution.n, n);
+            prop_assert_eq!(decoded.validate(), payload.validate());
+        }
+
+        #[test]
+        fn solution_lengths_are_enforced(d in proptest::collection::vec(any::<u8>(), 0..32)) {
+            let json = serde_json::json!({
+                "authority": Pubkey::new_unique(),
+                "solution": { "d": d, "n": [0u8; 8] },
+                "signature": Signature::default(),
+            });
+            let res = serde_json::from_value::<ContributePayload>(json);
+            prop_assert_eq!(res.is_ok(), d.len() == 16);
+        }
+
+        #[test]
+        fn pubkey_strings_never_panic(str in ".{0,64}") {
+            let payload = GetMemberPayload { authority: str.clone() };
+            prop_assert_eq!(payload.authority().ok(), Pubkey::from_str(&str).ok());
+        }
+
+        #[test]
+        fn worker_names_never_panic(name in ".{0,64}") {
+            let _ = worker_name(&name);
+        }
+
+        #[test]
+        fn nonces_never_panic(n in any::<u64>(), id in any::<i64>(), num in any::<u64>()) {
+            let _ = nonce(n, id, num);
+        }
+    }
+}
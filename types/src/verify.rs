@@ -0,0 +1,299 @@
+use drillx::Solution;
+use solana_sdk::hash::Hash;
+
+use crate::{
+    AdjustBalancePayload, ArchivedShare, PayoutStatement, PoolListing, ShareVerification,
+    SignedPayoutStatement, SignedPoolListing, VerifiedShare,
+};
+
+/// Prefixed to the signed bytes of a payout statement,
+/// so the signature can't be mistaken for one over a transaction or another message.
+const PAYOUT_STATEMENT_DOMAIN: &[u8] = b"ore-pool payout statement";
+
+/// Prefixed to the signed bytes of a pool listing, as for payout statements.
+const POOL_LISTING_DOMAIN: &[u8] = b"ore-pool pool listing";
+
+/// Prefixed to the signed bytes of a balance adjustment, as for payout statements.
+const BALANCE_ADJUSTMENT_DOMAIN: &[u8] = b"ore-pool balance adjustment";
+
+/// The score of a share of the difficulty, doubling with each leading zero.
+pub fn score(difficulty: u32) -> u64 {
+    2u64.checked_pow(difficulty).unwrap_or(u64::MAX)
+}
+
+impl ArchivedShare {
+    /// Re-runs the digest verification against the round challenge,
+    /// returning the difficulty of the solution if valid.
+    pub fn verify(&self, challenge: &[u8; 32]) -> Option<u32> {
+        if !drillx::is_valid_digest(challenge, &self.nonce, &self.digest) {
+            return None;
+        }
+        Some(
+            Solution::new(self.digest, self.nonce)
+                .to_hash()
+                .difficulty(),
+        )
+    }
+}
+
+impl ShareVerification {
+    /// Re-verifies the archived shares of a member against the round challenge,
+    /// recomputing the score of each from its difficulty.
+    pub fn new(challenge: &[u8; 32], shares: Vec<ArchivedShare>) -> Self {
+        let shares: Vec<VerifiedShare> = shares
+            .into_iter()
+            .map(|share| {
+                let difficulty = share.verify(challenge);
+                VerifiedShare {
+                    verified_score: difficulty.map(score).unwrap_or(0),
+                    difficulty,
+                    share,
+                }
+            })
+            .collect();
+        let reported_score = shares
+            .iter()
+            .fold(0u64, |sum, s| sum.saturating_add(s.share.score));
+        let verified_score = shares
+            .iter()
+            .fold(0u64, |sum, s| sum.saturating_add(s.verified_score));
+        let is_valid = shares
+            .iter()
+            .all(|s| s.difficulty.is_some() && s.verified_score == s.share.score);
+        Self {
+            challenge: Hash::new_from_array(*challenge),
+            shares,
+            reported_score,
+            verified_score,
+            is_valid,
+        }
+    }
+}
+
+impl PoolListing {
+    /// The bytes signed by the pool authority.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(POOL_LISTING_DOMAIN.len() + 80 + self.url.len());
+        bytes.extend_from_slice(POOL_LISTING_DOMAIN);
+        bytes.extend_from_slice(self.pool_address.as_ref());
+        bytes.extend_from_slice(&(self.url.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(self.url.as_bytes());
+        bytes.extend_from_slice(&self.operator_commission.to_le_bytes());
+        bytes.extend_from_slice(&self.staker_commission.to_le_bytes());
+        bytes.extend_from_slice(&self.num_members.to_le_bytes());
+        bytes.extend_from_slice(&self.hashrate.to_le_bytes());
+        bytes.extend_from_slice(&self.published_at.to_le_bytes());
+        bytes
+    }
+}
+
+impl SignedPoolListing {
+    /// Whether the listing is signed by the authority.
+    /// Callers should also check the pool address is derived from the authority.
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(self.authority.as_ref(), self.listing.to_bytes().as_slice())
+    }
+}
+
+impl AdjustBalancePayload {
+    /// The bytes signed by the admin key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(BALANCE_ADJUSTMENT_DOMAIN.len() + 88 + self.reason.len());
+        bytes.extend_from_slice(BALANCE_ADJUSTMENT_DOMAIN);
+        bytes.extend_from_slice(self.authority.as_ref());
+        bytes.extend_from_slice(&self.amount.to_le_bytes());
+        bytes.extend_from_slice(&(self.reason.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(self.reason.as_bytes());
+        bytes.extend_from_slice(&self.signed_at.to_le_bytes());
+        bytes.extend_from_slice(self.signer.as_ref());
+        bytes
+    }
+
+    /// Whether the adjustment is signed by its signer.
+    /// Callers should also check the signer is an admin key.
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(self.signer.as_ref(), self.to_bytes().as_slice())
+    }
+}
+
+impl PayoutStatement {
+    /// The bytes signed by the pool authority.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PAYOUT_STATEMENT_DOMAIN.len() + 217);
+        bytes.extend_from_slice(PAYOUT_STATEMENT_DOMAIN);
+        bytes.extend_from_slice(self.pool_address.as_ref());
+        bytes.extend_from_slice(self.challenge.as_ref());
+        bytes.extend_from_slice(&self.last_hash_at.to_le_bytes());
+        bytes.extend_from_slice(self.member.as_ref());
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&self.total_score.to_le_bytes());
+        bytes.extend_from_slice(&self.share.to_bits().to_le_bytes());
+        bytes.extend_from_slice(&self.amount.to_le_bytes());
+        bytes.extend_from_slice(&self.total_amount.to_le_bytes());
+        match &self.submission {
+            Some(submission) => {
+                bytes.push(1);
+                bytes.extend_from_slice(submission.as_ref());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&self.distributed_at.to_le_bytes());
+        bytes
+    }
+}
+
+impl SignedPayoutStatement {
+    /// Whether the statement is signed by the authority.
+    /// Callers should also check the pool address is derived from the authority,
+    /// and the submission landed on-chain.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(
+            self.authority.as_ref(),
+            self.statement.to_bytes().as_slice(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+    };
+
+    use super::*;
+
+    fn share(challenge: &[u8; 32]) -> ArchivedShare {
+        let (digest, nonce, difficulty) = (0u64..)
+            .find_map(|nonce| {
+                let hash = drillx::hash(challenge, &nonce.to_le_bytes()).ok()?;
+                Some((hash.d, nonce.to_le_bytes(), hash.difficulty()))
+            })
+            .unwrap();
+        ArchivedShare {
+            authority: Pubkey::new_unique(),
+            worker: "default".to_string(),
+            digest,
+            nonce,
+            score: score(difficulty),
+        }
+    }
+
+    #[test]
+    fn reverifies_archived_shares() {
+        let challenge = [7; 32];
+        let valid = share(&challenge);
+        let verification = ShareVerification::new(&challenge, vec![valid.clone()]);
+        assert!(verification.is_valid);
+        assert_eq!(verification.verified_score, valid.score);
+        // inflated score
+        let mut inflated = valid.clone();
+        inflated.score *= 2;
+        let verification = ShareVerification::new(&challenge, vec![valid.clone(), inflated]);
+        assert!(!verification.is_valid);
+        assert_eq!(verification.reported_score, valid.score * 3);
+        // solution of another challenge
+        let verification = ShareVerification::new(&[8; 32], vec![valid]);
+        assert!(!verification.is_valid);
+        assert_eq!(verification.verified_score, 0);
+    }
+
+    #[test]
+    fn verifies_signed_payout_statements() {
+        let keypair = Keypair::new();
+        let statement = PayoutStatement {
+            pool_address: Pubkey::new_unique(),
+            challenge: Hash::new_from_array([7; 32]),
+            last_hash_at: 1_000,
+            member: Pubkey::new_unique(),
+            score: 256,
+            total_score: 1024,
+            share: 25.0,
+            amount: 250,
+            total_amount: 1000,
+            submission: Some(Signature::new_unique()),
+            distributed_at: 1_060,
+        };
+        let mut signed = SignedPayoutStatement {
+            signature: keypair.sign_message(statement.to_bytes().as_slice()),
+            authority: keypair.pubkey(),
+            statement,
+        };
+        assert!(signed.verify());
+        // inflated amount
+        signed.statement.amount += 1;
+        assert!(!signed.verify());
+        signed.statement.amount -= 1;
+        // signed by another key
+        signed.authority = Pubkey::new_unique();
+        assert!(!signed.verify());
+    }
+    #[test]
+    fn verifies_signed_pool_listings() {
+        let keypair = Keypair::new();
+        let listing = PoolListing {
+            pool_address: Pubkey::new_unique(),
+            url: "https://pool.example".to_string(),
+            operator_commission: 5,
+            staker_commission: 10,
+            num_members: 42,
+            hashrate: 1_000,
+            published_at: 1_060,
+        };
+        let signed = SignedPoolListing {
+            signature: keypair.sign_message(listing.to_bytes().as_slice()),
+            authority: keypair.pubkey(),
+            listing,
+        };
+        assert!(signed.verify());
+        // as fetched from a directory
+        let json = serde_json::to_string(&signed).unwrap();
+        let mut signed: SignedPoolListing = serde_json::from_str(json.as_str()).unwrap();
+        assert!(signed.verify());
+        // lowered commission
+        signed.listing.operator_commission = 0;
+        assert!(!signed.verify());
+        signed.listing.operator_commission = 5;
+        // redirected url
+        signed.listing.url = "https://evil.example".to_string();
+        assert!(!signed.verify());
+        signed.listing.url = "https://pool.example".to_string();
+        assert!(signed.verify());
+        // the bare listing bytes, without the domain, aren't signed
+        let undomained = &signed.listing.to_bytes()[POOL_LISTING_DOMAIN.len()..];
+        assert!(!signed
+            .signature
+            .verify(signed.authority.as_ref(), undomained));
+    }
+
+    #[test]
+    fn verifies_signed_adjustments() {
+        let keypair = Keypair::new();
+        let mut adjustment = AdjustBalancePayload {
+            authority: Pubkey::new_unique(),
+            amount: 1_000,
+            reason: "outage compensation".to_string(),
+            signed_at: 1_060,
+            signer: keypair.pubkey(),
+            signature: Signature::default(),
+        };
+        adjustment.signature = keypair.sign_message(adjustment.to_bytes().as_slice());
+        assert!(adjustment.verify());
+        // inflated credit
+        adjustment.amount += 1;
+        assert!(!adjustment.verify());
+        adjustment.amount -= 1;
+        // signed for another time
+        adjustment.signed_at += 1;
+        assert!(!adjustment.verify());
+        adjustment.signed_at -= 1;
+        assert!(adjustment.verify());
+        // claimed by another key
+        adjustment.signer = Pubkey::new_unique();
+        assert!(!adjustment.verify());
+    }
+}